@@ -1,12 +1,24 @@
-use std::{cmp::min, net::SocketAddr, time::Duration};
+use std::{cmp::min, future::Future, time::Duration};
 
 use thiserror::Error;
 
-use crate::connection::{Connection, ConnectionError, ListenerHandles};
+use chacha20poly1305::ChaCha20Poly1305;
+use network::{transport::SplitTransport, Message, ServerAddress};
+
+use tokio::task::JoinError;
+use tokio_util::sync::CancellationToken;
+
+use crate::{
+    connection::{Connection, ConnectionError, ListenerHandles, TransportKind},
+    listeners::special_event::SpecialEventProcessorError,
+};
 
 const INITIAL_RETRY_SECONDS: u64 = 1;
 const MAX_RETRY_SECONDS: u64 = 180;
 const RETRY_MUTLIPLIER: u64 = 2;
+// a restarting server needs a moment before it accepts connections again
+const SERVER_SHUTDOWN_RETRY_SECONDS: u64 = 5;
+const GOODBYE_TIMEOUT: Duration = Duration::from_secs(1);
 
 #[derive(Debug, Error)]
 pub enum ClientError {
@@ -14,58 +26,174 @@ pub enum ClientError {
     ConnectionError(#[from] ConnectionError),
 }
 
-pub async fn run(server_addr: SocketAddr, client_addr: SocketAddr) -> Result<(), ClientError> {
-    let mut connection: Connection = Connection::default();
+/// How a session with the server came to an end.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SessionEnd {
+    /// No session could be established.
+    NotConnected,
+    /// The connection was lost or timed out.
+    Lost,
+    /// The server announced that it is shutting down.
+    ServerShutdown,
+    /// The user asked the client to quit.
+    Quit,
+}
+
+/// Connects to the server at `server_addr` until the user quits. A Unix socket address carries
+/// input over Unix datagrams whatever the connection's transport kind.
+pub async fn run(
+    server_addr: ServerAddress,
+    mut connection: Connection,
+) -> Result<(), ClientError> {
     let mut retry_seconds = INITIAL_RETRY_SECONDS;
 
+    let quit_token = CancellationToken::new();
+    let cloned_token = quit_token.clone();
+    tokio::spawn(async move {
+        if tokio::signal::ctrl_c().await.is_ok() {
+            cloned_token.cancel();
+        }
+    });
+
     println!("Beginning main loop");
     loop {
-        let transport = connection
-            .connect(client_addr, server_addr)
-            .await
-            .inspect_err(|err| eprintln!("Could not connect to server: {}", err));
-
-        if connection.is_connected && transport.is_ok() {
-            retry_seconds = INITIAL_RETRY_SECONDS;
-            // process events
-            let ListenerHandles {
-                input_event: input_event_processor,
-                special_event: special_event_processor,
-                cancellation_token,
-            } = connection
-                .spawn_listeners(transport.unwrap(), server_addr, client_addr)
-                .await?;
-
-            tokio::select! {
-                result = input_event_processor => {
-                    match result {
-                        Ok(Ok(())) => {},
-                        Ok(Err(err)) => eprintln!("Input event processor exited with error: {}", err),
-                        Err(err) => {
-                            eprintln!("Input event processor panicked: {}", err);
-                            panic!();
-                        },
+        let session = match (&server_addr, connection.transport_kind) {
+            (ServerAddress::Unix(path), _) => {
+                let Some(transport) = until_quit(&quit_token, connection.connect_unix(path)).await
+                else {
+                    return Ok(());
+                };
+                run_session(&connection, transport, &server_addr, &quit_token).await?
+            }
+            (ServerAddress::Inet(addr), TransportKind::Tcp) => {
+                let Some(transport) = until_quit(&quit_token, connection.connect(*addr)).await
+                else {
+                    return Ok(());
+                };
+                run_session(&connection, transport, &server_addr, &quit_token).await?
+            }
+            #[cfg(feature = "websocket")]
+            (ServerAddress::Inet(addr), TransportKind::WebSocket) => {
+                let Some(transport) =
+                    until_quit(&quit_token, connection.connect_websocket(*addr)).await
+                else {
+                    return Ok(());
+                };
+                run_session(&connection, transport, &server_addr, &quit_token).await?
+            }
+        };
+
+        match session {
+            SessionEnd::Quit => {
+                println!("Disconnected from server");
+                return Ok(());
+            }
+            SessionEnd::Lost => {
+                retry_seconds = INITIAL_RETRY_SECONDS;
+            }
+            SessionEnd::ServerShutdown => {
+                retry_seconds = INITIAL_RETRY_SECONDS;
+                println!(
+                    "Server shut down. Reconnecting in {} seconds",
+                    SERVER_SHUTDOWN_RETRY_SECONDS
+                );
+                let delay = tokio::time::sleep(Duration::from_secs(SERVER_SHUTDOWN_RETRY_SECONDS));
+                if until_quit(&quit_token, delay).await.is_none() {
+                    return Ok(());
+                }
+            }
+            SessionEnd::NotConnected => {
+                let delay = tokio::time::sleep(Duration::from_secs(retry_seconds));
+                if until_quit(&quit_token, delay).await.is_none() {
+                    return Ok(());
+                }
+                retry_seconds = min(retry_seconds * RETRY_MUTLIPLIER, MAX_RETRY_SECONDS);
+                println!(
+                    "Could not connect to server. Retrying in {} seconds",
+                    retry_seconds
+                );
+            }
+        }
+    }
+}
+
+/// Runs `future` to completion unless the user quits first.
+async fn until_quit<F: Future>(quit_token: &CancellationToken, future: F) -> Option<F::Output> {
+    tokio::select! {
+        output = future => Some(output),
+        _ = quit_token.cancelled() => None,
+    }
+}
+
+/// Processes events until the session ends.
+async fn run_session<Tr: SplitTransport<ChaCha20Poly1305> + 'static>(
+    connection: &Connection,
+    transport: Result<Tr, ConnectionError>,
+    server_addr: &ServerAddress,
+    quit_token: &CancellationToken,
+) -> Result<SessionEnd, ClientError> {
+    let transport = transport.inspect_err(|err| eprintln!("Could not connect to server: {}", err));
+
+    if let (true, Ok(transport)) = (connection.is_connected, transport) {
+        // process events
+        let ListenerHandles {
+            input_event: mut input_event_processor,
+            special_event: mut special_event_processor,
+            message_sender,
+            cancellation_token,
+        } = connection
+            .spawn_listeners(transport, server_addr.clone())
+            .await?;
+
+        let session_end = tokio::select! {
+            result = &mut input_event_processor => {
+                log_exit("Input event processor", result);
+                SessionEnd::Lost
+            }
+            result = &mut special_event_processor => {
+                match result {
+                    Ok(Err(SpecialEventProcessorError::ServerShuttingDown(_))) => {
+                        SessionEnd::ServerShutdown
+                    }
+                    result => {
+                        log_exit("Special event processor", result);
+                        SessionEnd::Lost
                     }
                 }
-                result = special_event_processor => {
-                    match result {
-                        Ok(Ok(())) => {},
-                        Ok(Err(err)) => eprintln!("Special event processor exited with error: {}", err),
-                        Err(err) => {
-                            eprintln!("Special event processor panicked: {}", err);
-                            panic!();
-                        },
+            }
+            _ = quit_token.cancelled() => {
+                // the sender stops once the goodbye is written, so wait for that before cancelling
+                let goodbye = Message::Goodbye {
+                    reason: "Client quit".to_string(),
+                };
+                if message_sender.send(goodbye).await.is_ok() {
+                    let sent = tokio::time::timeout(GOODBYE_TIMEOUT, &mut special_event_processor);
+                    if let Ok(result) = sent.await {
+                        log_exit("Special event processor", result);
                     }
                 }
+                SessionEnd::Quit
             }
-            cancellation_token.cancel();
-        } else {
-            tokio::time::sleep(Duration::from_secs(retry_seconds)).await;
-            retry_seconds = min(retry_seconds * RETRY_MUTLIPLIER, MAX_RETRY_SECONDS);
-            println!(
-                "Could not connect to server. Retrying in {} seconds",
-                retry_seconds
-            );
+        };
+        cancellation_token.cancel();
+
+        // the input listener releases held keys as it exits, which must happen before reconnecting
+        if !input_event_processor.is_finished() {
+            log_exit("Input event processor", input_event_processor.await);
+        }
+        Ok(session_end)
+    } else {
+        Ok(SessionEnd::NotConnected)
+    }
+}
+
+fn log_exit<E: std::fmt::Display>(name: &str, result: Result<Result<(), E>, JoinError>) {
+    match result {
+        Ok(Ok(())) => {}
+        Ok(Err(err)) => eprintln!("{} exited with error: {}", name, err),
+        Err(err) => {
+            eprintln!("{} panicked: {}", name, err);
+            panic!();
         }
     }
 }
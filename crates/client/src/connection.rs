@@ -1,17 +1,52 @@
-use std::net::SocketAddr;
+use std::{
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
 
-use chacha20poly1305::{aead::OsRng, ChaCha20Poly1305, KeyInit};
-use network::{tcp::TokioTcpTransport, transport::Transport, Message, TransportError};
+use chacha20poly1305::{
+    aead::{rand_core::RngCore, OsRng},
+    ChaCha20Poly1305, KeyInit,
+};
+use crypto::kdf;
+use input_simulator::InputSimulator;
+#[cfg(feature = "websocket")]
+use network::websocket::ClientWebSocketTransport;
+use network::{
+    link_stats::{ConnectionQuality, LinkStats},
+    tcp::TokioTcpTransport,
+    transport::SplitTransport,
+    unix::UnixSocketTransport,
+    Message, ResumeRequest, ServerAddress, TransportError,
+};
 use thiserror::Error;
-use tokio::{net::TcpStream, sync::mpsc, task::JoinHandle};
+use tokio::{
+    net::{TcpStream, UnixStream},
+    sync::mpsc,
+    task::JoinHandle,
+};
 use tokio_util::sync::CancellationToken;
+use uuid::Uuid;
 use x25519_dalek::{EphemeralSecret, PublicKey};
 
-use crate::listeners::{
-    input_event::InputEventListenerError, special_event::SpecialEventProcessorError,
+use crate::{
+    listeners::{input_event::InputEventListenerError, special_event::SpecialEventProcessorError},
+    session::Session,
 };
 
-use super::listeners::{input_event::input_event_listener, special_event::special_event_processor};
+use super::listeners::{
+    input_event::{input_event_listener, tunnelled_input_event_listener},
+    special_event::special_event_processor,
+};
+
+const INPUT_TUNNEL_BUF_LEN: usize = 256;
+const CONTROL_MESSAGE_BUF_LEN: usize = 8;
+/// Comfortably more than the server's 3 second idle heartbeat interval.
+pub const DEFAULT_PEER_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Ping statistics shared between a connection and the listeners measuring its links.
+pub type SharedLinkStats = Arc<Mutex<LinkStats>>;
 
 #[derive(Debug, Error)]
 pub enum ConnectionError {
@@ -25,89 +60,198 @@ pub enum ConnectionError {
     DHContributionError,
 }
 
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TransportKind {
+    /// Control messages over TCP and input events over UDP, or Unix stream and datagram
+    /// sockets for a server at a Unix socket address.
+    #[default]
+    Tcp,
+    /// Control messages and input events tunnelled through a single WebSocket.
+    #[cfg(feature = "websocket")]
+    WebSocket,
+}
+
+/// Shared access to a connection's round trip statistics.
+#[derive(Clone)]
+pub struct LinkQualityHandle {
+    control: SharedLinkStats,
+    input: SharedLinkStats,
+}
+
+impl LinkQualityHandle {
+    pub fn quality(&self) -> ConnectionQuality {
+        ConnectionQuality {
+            control: self.control.lock().unwrap().quality(),
+            input: self.input.lock().unwrap().quality(),
+        }
+    }
+}
+
 pub struct Connection {
     pub is_connected: bool,
+    pub transport_kind: TransportKind,
+    pub peer_timeout: Duration,
     symmetric_key: Option<ChaCha20Poly1305>,
+    session: Option<Session>,
+    /// Where the session is kept between runs of the client, if anywhere.
+    session_path: Option<PathBuf>,
+    control_link: SharedLinkStats,
+    input_link: SharedLinkStats,
 }
 
 impl Default for Connection {
     fn default() -> Self {
+        Connection::new(TransportKind::default())
+    }
+}
+
+impl Connection {
+    pub fn new(transport_kind: TransportKind) -> Self {
         let symmetric_key = None;
         let is_connected = false;
 
         Connection {
             is_connected,
+            transport_kind,
+            peer_timeout: DEFAULT_PEER_TIMEOUT,
             symmetric_key,
+            session: None,
+            session_path: None,
+            control_link: SharedLinkStats::default(),
+            input_link: SharedLinkStats::default(),
+        }
+    }
+
+    /// Sets how long the control connection may stay silent before the server is assumed dead.
+    pub fn with_peer_timeout(mut self, peer_timeout: Duration) -> Self {
+        self.peer_timeout = peer_timeout;
+        self
+    }
+
+    /// Keeps the session in the file at `path`, so that a restarted client resumes the session
+    /// saved there rather than joining the server as a new client.
+    pub fn with_session_file(mut self, path: PathBuf) -> Self {
+        self.session = Session::load(&path);
+        self.session_path = Some(path);
+        self
+    }
+
+    /// The id the server knows this client by, which stays the same across resumed sessions.
+    pub fn client_id(&self) -> Option<Uuid> {
+        self.session.as_ref().map(|session| session.client_id)
+    }
+
+    /// Round trip statistics for the current session.
+    pub fn link_quality(&self) -> ConnectionQuality {
+        self.link_quality_handle().quality()
+    }
+
+    /// Reads the round trip statistics of every later session too, for display while the
+    /// connection is owned by the client loop.
+    pub fn link_quality_handle(&self) -> LinkQualityHandle {
+        LinkQualityHandle {
+            control: self.control_link.clone(),
+            input: self.input_link.clone(),
         }
     }
-}
 
-impl Connection {
     pub async fn connect(
         &mut self,
-        client_addr: SocketAddr,
         server_addr: SocketAddr,
     ) -> Result<TokioTcpTransport<ChaCha20Poly1305>, ConnectionError> {
-        // TODO: add a server secret + client secret to ensure sessions are uniqiue
-        // as in TCP 1.3
         println!("Retrying connection to server");
 
         let socket = TcpStream::connect(server_addr).await?;
         let mut transport: TokioTcpTransport<ChaCha20Poly1305> = TokioTcpTransport::new(socket);
+        self.handshake(&mut transport, &server_addr.into()).await?;
+
+        Ok(transport)
+    }
+
+    /// Connects to a server on the same machine through its Unix socket at `server_path`.
+    pub async fn connect_unix(
+        &mut self,
+        server_path: &Path,
+    ) -> Result<UnixSocketTransport<ChaCha20Poly1305>, ConnectionError> {
+        println!("Retrying Unix socket connection to server");
+
+        let socket = UnixStream::connect(server_path).await?;
+        let mut transport: UnixSocketTransport<ChaCha20Poly1305> = UnixSocketTransport::new(socket);
+        let server_addr = ServerAddress::Unix(server_path.to_path_buf());
+        self.handshake(&mut transport, &server_addr).await?;
+
+        Ok(transport)
+    }
+
+    #[cfg(feature = "websocket")]
+    pub async fn connect_websocket(
+        &mut self,
+        server_addr: SocketAddr,
+    ) -> Result<ClientWebSocketTransport<ChaCha20Poly1305>, ConnectionError> {
+        println!("Retrying WebSocket connection to server");
+
+        let mut transport = ClientWebSocketTransport::connect(server_addr).await?;
+        self.handshake(&mut transport, &server_addr.into()).await?;
+
+        Ok(transport)
+    }
+
+    /// Whether input events arrive over the control connection instead of datagrams.
+    pub fn is_tunnelled(&self) -> bool {
+        self.transport_kind != TransportKind::Tcp
+    }
+
+    async fn handshake<Tr: SplitTransport<ChaCha20Poly1305>>(
+        &mut self,
+        transport: &mut Tr,
+        server_addr: &ServerAddress,
+    ) -> Result<(), ConnectionError> {
+        // TODO: add a server secret + client secret to ensure sessions are uniqiue
+        // as in TCP 1.3
+        let mut client_nonce = [0; 32];
+        OsRng.fill_bytes(&mut client_nonce);
+        let resume = self.session.as_ref().map(|session| ResumeRequest {
+            ticket: session.ticket.clone(),
+            nonce: client_nonce,
+        });
 
         println!("Sending ClientInit message to server");
         transport
-            .send_message(Message::ClientInit { addr: client_addr })
+            .send_message(Message::ClientInit {
+                tunnel_input: self.is_tunnelled(),
+                resume,
+                screen: InputSimulator::screen_geometry(),
+            })
             .await?;
 
-        let server_pub_key =
-            if let Message::ExchangePubKey { pub_key } = transport.receive_message().await? {
+        let cipher = match transport.receive_message().await? {
+            Message::ResumeAccepted { nonce } => {
+                let session = self.session.as_ref().ok_or_else(|| {
+                    ConnectionError::InvalidMessage("Resumption accepted but not requested".into())
+                })?;
+                println!("Server accepted session resumption");
+                let key = kdf::resumption_key(&session.secret, &client_nonce, &nonce);
+                ChaCha20Poly1305::new_from_slice(&key)
+                    .expect("Could not generate cipher from resumption secret")
+            }
+            Message::ExchangePubKey { pub_key } => {
                 println!("Received pub key from server");
-                pub_key
-            } else {
+                if self.session.take().is_some() {
+                    println!("Server declined session resumption");
+                }
+                exchange_keys(transport, pub_key).await?
+            }
+            _ => {
                 return Err(ConnectionError::InvalidMessage(
                     "Expected public key exchange".into(),
                 ));
-            };
-
-        // generate public key
-        let secret = EphemeralSecret::random_from_rng(OsRng);
-        let public_key = PublicKey::from(&secret);
-
-        // send pub key to server
-        // TODO: sign this message
-        println!("Sending pub key to server");
-        transport
-            .send_message(Message::ExchangePubKey {
-                pub_key: public_key,
-            })
-            .await?;
-
-        // wait for ack
-        println!("Waiting for server ack");
-        if let Message::ExchangePubKeyResponse = transport.receive_message().await? {
-            println!("Received ack from server");
-        } else {
-            return Err(ConnectionError::InvalidMessage(
-                "Server did not acknowledge client public key".into(),
-            ));
-        };
-
-        // generate cipher
-        let cipher = {
-            // extract this into a trait method if supporting different types of keys
-            let shared_secret = secret.diffie_hellman(&server_pub_key);
-            if !shared_secret.was_contributory() {
-                return Err(ConnectionError::DHContributionError);
             }
-
-            Ok::<_, ConnectionError>(
-                ChaCha20Poly1305::new_from_slice(&shared_secret.to_bytes())
-                    .expect("Could not generate cipher from shared secret"),
-            )
-        }?;
+        };
 
         self.symmetric_key = Some(cipher.clone());
+        // reset in place so that handles to the statistics follow the new session
+        *self.control_link.lock().unwrap() = LinkStats::default();
+        *self.input_link.lock().unwrap() = LinkStats::default();
         transport.set_key(cipher);
         self.is_connected = true;
 
@@ -123,50 +267,144 @@ impl Connection {
             ));
         };
 
+        if let Message::SessionTicket {
+            client_id,
+            ticket,
+            secret,
+        } = transport.receive_message().await?
+        {
+            let session = Session {
+                client_id,
+                ticket,
+                secret,
+            };
+            if let Some(path) = &self.session_path {
+                if let Err(err) = session.save(path) {
+                    eprintln!("Could not save session to {}: {}", path.display(), err);
+                }
+            }
+            self.session = Some(session);
+        } else {
+            return Err(ConnectionError::InvalidMessage(
+                "Server did not issue a session ticket".into(),
+            ));
+        };
+
         println!(
             "Successfully connected to server at address {}",
             server_addr
         );
 
-        Ok(transport)
+        Ok(())
     }
 
-    pub async fn spawn_listeners(
+    pub async fn spawn_listeners<Tr: SplitTransport<ChaCha20Poly1305> + 'static>(
         &self,
-        transport: TokioTcpTransport<ChaCha20Poly1305>,
-        server_addr: SocketAddr,
-        client_addr: SocketAddr,
+        transport: Tr,
+        server_addr: ServerAddress,
     ) -> Result<ListenerHandles, ConnectionError> {
         let key = self.symmetric_key.clone();
+        let control_link = self.control_link.clone();
+        let input_link = self.input_link.clone();
+        let peer_timeout = self.peer_timeout;
         let (release_request_sender, release_request_receiver) = mpsc::channel(8);
+        let (message_sender, message_receiver) = mpsc::channel(CONTROL_MESSAGE_BUF_LEN);
+        let cloned_message_sender = message_sender.clone();
         let cancellation_token = CancellationToken::new();
         let cloned_token = cancellation_token.clone();
 
+        // tunnelled input events are handed from the control connection to the input listener
+        let (tunnel_sender, tunnel_receiver) = if self.is_tunnelled() {
+            let (sender, receiver) = mpsc::channel(INPUT_TUNNEL_BUF_LEN);
+            (Some(sender), Some(receiver))
+        } else {
+            (None, None)
+        };
+
         let input_event = tokio::spawn(async move {
-            input_event_listener(
-                key,
-                client_addr,
-                server_addr,
-                release_request_receiver,
-                cloned_token,
-            )
-            .await
+            match tunnel_receiver {
+                Some(receiver) => {
+                    tunnelled_input_event_listener(receiver, release_request_receiver, cloned_token)
+                        .await
+                }
+                None => {
+                    input_event_listener(
+                        key,
+                        server_addr,
+                        release_request_receiver,
+                        input_link,
+                        cloned_token,
+                    )
+                    .await
+                }
+            }
         });
         let cloned_token = cancellation_token.clone();
         let special_event = tokio::spawn(async move {
-            special_event_processor(transport, release_request_sender, cloned_token).await
+            special_event_processor(
+                transport,
+                cloned_message_sender,
+                message_receiver,
+                release_request_sender,
+                tunnel_sender,
+                control_link,
+                peer_timeout,
+                cloned_token,
+            )
+            .await
         });
 
         Ok(ListenerHandles {
             input_event,
             special_event,
+            message_sender,
             cancellation_token,
         })
     }
 }
 
+/// Performs the full Diffie-Hellman key exchange, answering the server's public key.
+async fn exchange_keys<Tr: SplitTransport<ChaCha20Poly1305>>(
+    transport: &mut Tr,
+    server_pub_key: PublicKey,
+) -> Result<ChaCha20Poly1305, ConnectionError> {
+    // generate public key
+    let secret = EphemeralSecret::random_from_rng(OsRng);
+    let public_key = PublicKey::from(&secret);
+
+    // send pub key to server
+    // TODO: sign this message
+    println!("Sending pub key to server");
+    transport
+        .send_message(Message::ExchangePubKey {
+            pub_key: public_key,
+        })
+        .await?;
+
+    // wait for ack
+    println!("Waiting for server ack");
+    if let Message::ExchangePubKeyResponse = transport.receive_message().await? {
+        println!("Received ack from server");
+    } else {
+        return Err(ConnectionError::InvalidMessage(
+            "Server did not acknowledge client public key".into(),
+        ));
+    };
+
+    // extract this into a trait method if supporting different types of keys
+    let shared_secret = secret.diffie_hellman(&server_pub_key);
+    if !shared_secret.was_contributory() {
+        return Err(ConnectionError::DHContributionError);
+    }
+
+    Ok(ChaCha20Poly1305::new_from_slice(&shared_secret.to_bytes())
+        .expect("Could not generate cipher from shared secret"))
+}
+
 pub struct ListenerHandles {
     pub input_event: JoinHandle<Result<(), InputEventListenerError>>,
     pub special_event: JoinHandle<Result<(), SpecialEventProcessorError>>,
+    /// Queues a control message to the server.
+    pub message_sender: mpsc::Sender<Message>,
     pub cancellation_token: CancellationToken,
 }
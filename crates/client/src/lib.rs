@@ -1,3 +1,4 @@
 pub mod client_loop;
 pub mod connection;
 pub mod listeners;
+pub mod session;
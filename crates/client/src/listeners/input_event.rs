@@ -1,11 +1,24 @@
-use std::net::SocketAddr;
+use std::{
+    net::{Ipv4Addr, Ipv6Addr, SocketAddr},
+    time::Duration,
+};
 
 use chacha20poly1305::ChaCha20Poly1305;
 use input_simulator::{DeviceOutputError, InputSimulator};
-use network::{transport::Transport, udp::TokioUdpTransport, Message, TransportError};
+use network::{
+    transport::Transport,
+    udp::TokioUdpTransport,
+    unix::{input_socket_path, UnixDatagramTransport},
+    Message, ServerAddress, TransportError,
+};
 use thiserror::Error;
 use tokio::{net::UdpSocket, sync::mpsc::Receiver};
 use tokio_util::sync::CancellationToken;
+use uuid::Uuid;
+
+use crate::connection::SharedLinkStats;
+
+use super::LINK_QUALITY_LOG_INTERVAL;
 
 #[derive(Debug, Error)]
 pub enum InputEventListenerError {
@@ -17,35 +30,122 @@ pub enum InputEventListenerError {
     DeviceOutputError(#[from] DeviceOutputError),
 }
 
+// the hello doubles as a NAT keepalive, so it should be well below typical UDP mapping timeouts
+const UDP_HELLO_INTERVAL: u64 = 3;
+
 pub async fn input_event_listener(
     key: Option<ChaCha20Poly1305>,
-    client_addr: SocketAddr,
-    server_addr: SocketAddr,
+    server_addr: ServerAddress,
     release_request_receiver: Receiver<()>,
+    input_link: SharedLinkStats,
     cancellation_token: CancellationToken,
 ) -> Result<(), InputEventListenerError> {
-    let simulator = InputSimulator::new();
+    let mut simulator = InputSimulator::new();
+
+    let result = match server_addr {
+        ServerAddress::Inet(server_addr) => {
+            println!("Creating UDP transport for server at {}", server_addr);
+            let bind_addr = if server_addr.is_ipv4() {
+                SocketAddr::new(Ipv4Addr::UNSPECIFIED.into(), 0)
+            } else {
+                SocketAddr::new(Ipv6Addr::UNSPECIFIED.into(), 0)
+            };
+            let udp_socket = UdpSocket::bind(bind_addr).await?;
+            let udp_transport: TokioUdpTransport<ChaCha20Poly1305> =
+                TokioUdpTransport::new(udp_socket, server_addr, key);
+
+            input_event_processor(
+                udp_transport,
+                &mut simulator,
+                release_request_receiver,
+                input_link,
+                cancellation_token,
+            )
+            .await
+        }
+        ServerAddress::Unix(path) => {
+            let server_path = input_socket_path(&path);
+            println!(
+                "Creating Unix datagram transport for server at {}",
+                server_path.display()
+            );
+            // the server answers to this path, so it must be unique to the session
+            let local_path =
+                std::env::temp_dir().join(format!("kvm-input-{}.sock", Uuid::new_v4()));
+            let unix_transport: UnixDatagramTransport<ChaCha20Poly1305> =
+                UnixDatagramTransport::connect(&local_path, &server_path, key)?;
+
+            input_event_processor(
+                unix_transport,
+                &mut simulator,
+                release_request_receiver,
+                input_link,
+                cancellation_token,
+            )
+            .await
+        }
+    };
+    // keys must not stay held once the session is over, however it ended
+    simulator.release_all()?;
+    result
+}
 
-    println!("Creating UDP transport for server at {}", server_addr);
-    let udp_socket = UdpSocket::bind(client_addr).await?;
-    let udp_transport: TokioUdpTransport<ChaCha20Poly1305> =
-        TokioUdpTransport::new(udp_socket, server_addr, key);
+/// Emits input events that the server tunnels over the control connection.
+pub async fn tunnelled_input_event_listener(
+    input_event_receiver: Receiver<Message>,
+    release_request_receiver: Receiver<()>,
+    cancellation_token: CancellationToken,
+) -> Result<(), InputEventListenerError> {
+    let mut simulator = InputSimulator::new();
 
-    input_event_processor(
-        udp_transport,
-        simulator,
+    let result = tunnelled_input_event_processor(
+        &mut simulator,
+        input_event_receiver,
         release_request_receiver,
         cancellation_token,
     )
-    .await
+    .await;
+    simulator.release_all()?;
+    result
 }
 
-async fn input_event_processor(
-    mut transport: TokioUdpTransport<ChaCha20Poly1305>,
-    mut simulator: InputSimulator,
+async fn tunnelled_input_event_processor(
+    simulator: &mut InputSimulator,
+    mut input_event_receiver: Receiver<Message>,
     mut release_request_receiver: Receiver<()>,
     cancellation_token: CancellationToken,
 ) -> Result<(), InputEventListenerError> {
+    loop {
+        tokio::select! {
+            Some(message) = input_event_receiver.recv() => {
+                if let Message::InputEvent { event } = message {
+                    simulator.emit(event)?;
+                }
+            },
+            _ = release_request_receiver.recv() => {
+                simulator.release_all()?;
+            },
+            _ = cancellation_token.cancelled() => {
+                return Ok(())
+            },
+        }
+    }
+}
+
+async fn input_event_processor<Tr: Transport>(
+    mut transport: Tr,
+    simulator: &mut InputSimulator,
+    mut release_request_receiver: Receiver<()>,
+    input_link: SharedLinkStats,
+    cancellation_token: CancellationToken,
+) -> Result<(), InputEventListenerError> {
+    let mut link_quality_log_interval =
+        tokio::time::interval(Duration::from_secs(LINK_QUALITY_LOG_INTERVAL));
+    link_quality_log_interval.tick().await;
+    // the server learns which address to send input events to from the source of this datagram
+    let mut hello_interval = tokio::time::interval(Duration::from_secs(UDP_HELLO_INTERVAL));
+    let mut hello_seq = 0;
+
     loop {
         tokio::select! {
             message = transport.receive_message() => {
@@ -54,24 +154,38 @@ async fn input_event_processor(
                         Message::InputEvent { event } => {
                             simulator.emit(event)?;
                         }
+                        Message::Ping { seq, sent_at } => {
+                            transport.send_message(Message::Pong { seq, sent_at }).await?;
+                        }
+                        Message::Pong { seq, sent_at } => {
+                            input_link.lock().unwrap().record_pong(seq, sent_at);
+                        }
                         _ => {
                             eprintln!("Event is not an input event: {:?}", event);
                         }
                     },
                     Err(err) => {
                         eprintln!(
-                            "An error has occured when listening to UDP messages: {:?}",
+                            "An error has occured when listening to input messages: {:?}",
                             err
                         );
                         return Err(err.into())
                     }
                 }
             },
+            _ = hello_interval.tick() => {
+                transport.send_message(Message::UdpHello { seq: hello_seq }).await?;
+                hello_seq += 1;
+                let ping = input_link.lock().unwrap().ping();
+                transport.send_message(ping).await?;
+            },
+            _ = link_quality_log_interval.tick() => {
+                println!("Input link quality: {}", input_link.lock().unwrap().quality());
+            },
             _ = release_request_receiver.recv() => {
                 simulator.release_all()?;
             },
             _ = cancellation_token.cancelled() => {
-                simulator.release_all()?;
                 return Ok(())
             },
         }
@@ -1,2 +1,5 @@
 pub mod input_event;
 pub mod special_event;
+
+// how often each listener logs the round trip statistics of its link
+const LINK_QUALITY_LOG_INTERVAL: u64 = 60;
@@ -3,8 +3,7 @@ use std::time::Duration;
 use chacha20poly1305::ChaCha20Poly1305;
 use input_simulator::DeviceOutputError;
 use network::{
-    tcp::{TokioTcpTransport, TokioTcpTransportReader, TokioTcpTransportWriter},
-    transport::{TransportReader, TransportWriter},
+    transport::{SplitTransport, TransportReader, TransportWriter},
     Message, TransportError,
 };
 use thiserror::Error;
@@ -14,6 +13,10 @@ use tokio::{
 };
 use tokio_util::sync::CancellationToken;
 
+use crate::connection::SharedLinkStats;
+
+use super::LINK_QUALITY_LOG_INTERVAL;
+
 #[derive(Debug, Error)]
 pub enum SpecialEventProcessorError {
     // TODO: check a join error implies a panic occured
@@ -27,28 +30,47 @@ pub enum SpecialEventProcessorError {
     ReleaseRequesetSendError(#[from] SendError<()>),
     #[error("Transport error")]
     TransportError(#[from] TransportError),
+    #[error("No message from server in {0:?}, assuming it is gone")]
+    PeerTimeout(Duration),
+    #[error("Server is shutting down: {0}")]
+    ServerShuttingDown(String),
 }
 
-pub async fn special_event_processor(
-    transport: TokioTcpTransport<ChaCha20Poly1305>,
+#[allow(clippy::too_many_arguments)]
+pub async fn special_event_processor<Tr: SplitTransport<ChaCha20Poly1305>>(
+    transport: Tr,
+    message_sender: Sender<Message>,
+    message_receiver: mpsc::Receiver<Message>,
     release_request_sender: Sender<()>,
+    input_event_sender: Option<Sender<Message>>,
+    control_link: SharedLinkStats,
+    peer_timeout: Duration,
     cancellation_token: CancellationToken,
 ) -> Result<(), SpecialEventProcessorError> {
     let (read_transport, write_transport) = transport.into_split();
-    let (message_sender, message_receiver) = mpsc::channel(8);
 
     let cloned_token = cancellation_token.clone();
+    let cloned_link = control_link.clone();
     let listener = tokio::spawn(async move {
         special_event_listener(
             read_transport,
             message_sender,
             release_request_sender,
+            input_event_sender,
+            cloned_link,
+            peer_timeout,
             cloned_token,
         )
         .await
     });
     let sender = tokio::spawn(async move {
-        special_event_sender(write_transport, message_receiver, cancellation_token).await
+        special_event_sender(
+            write_transport,
+            message_receiver,
+            control_link,
+            cancellation_token,
+        )
+        .await
     });
 
     tokio::select! {
@@ -62,29 +84,51 @@ pub async fn special_event_processor(
     Ok(())
 }
 
-pub async fn special_event_listener(
-    mut reader: TokioTcpTransportReader<ChaCha20Poly1305>,
+pub async fn special_event_listener<R: TransportReader>(
+    mut reader: R,
     message_sender: mpsc::Sender<Message>,
     release_request_sender: Sender<()>,
+    input_event_sender: Option<Sender<Message>>,
+    control_link: SharedLinkStats,
+    peer_timeout: Duration,
     cancellation_token: CancellationToken,
 ) -> Result<(), SpecialEventProcessorError> {
     loop {
         tokio::select! {
-            message = reader.receive_message() => {
+            // the server sends a heartbeat whenever it is idle, so silence means it is gone
+            message = tokio::time::timeout(peer_timeout, reader.receive_message()) => {
+                let Ok(message) = message else {
+                    eprintln!("No message from server in {:?}", peer_timeout);
+                    return Err(SpecialEventProcessorError::PeerTimeout(peer_timeout));
+                };
                 if let Ok(event) = message {
                         match event {
                             Message::ClipboardChanged { content } => {
                                 println!("New clipboard item: [{:?}]", content);
                                 message_sender.send(Message::ExchangePubKeyResponse).await?; // TODO: temporary response
                             }
-                            Message::TargetChangeNotification => {
+                            Message::TargetChangeNotification { seq } => {
                                 println!("Releasing all keys");
                                 release_request_sender.send(()).await?;
-                                message_sender.send(Message::TargetChangeResponse).await?;
+                                message_sender.send(Message::TargetChangeResponse { seq }).await?;
                             }
                             Message::Heartbeat => {}
-                            _ => {
-                                unimplemented!("Received unimplemented special event")
+                            Message::ServerShuttingDown { reason } => {
+                                println!("Server is shutting down: {}", reason);
+                                return Err(SpecialEventProcessorError::ServerShuttingDown(reason));
+                            }
+                            Message::Ping { seq, sent_at } => {
+                                message_sender.send(Message::Pong { seq, sent_at }).await?;
+                            }
+                            Message::Pong { seq, sent_at } => {
+                                control_link.lock().unwrap().record_pong(seq, sent_at);
+                            }
+                            Message::InputEvent { .. } => match &input_event_sender {
+                                Some(sender) => sender.send(event).await?,
+                                None => eprintln!("Input event received outside of a tunnel"),
+                            }
+                            message => {
+                                eprintln!("Ignoring unexpected message from server: {}", message);
                             }
                         }
                 } else {
@@ -102,19 +146,33 @@ pub async fn special_event_listener(
     }
 }
 
-pub async fn special_event_sender(
-    mut writer: TokioTcpTransportWriter<ChaCha20Poly1305>,
+pub async fn special_event_sender<W: TransportWriter>(
+    mut writer: W,
     mut message_receiver: mpsc::Receiver<Message>,
+    control_link: SharedLinkStats,
     cancellation_token: CancellationToken,
 ) -> Result<(), SpecialEventProcessorError> {
     let timeout = Duration::from_secs(3);
+    let mut link_quality_log_interval =
+        tokio::time::interval(Duration::from_secs(LINK_QUALITY_LOG_INTERVAL));
+    link_quality_log_interval.tick().await;
     loop {
         tokio::select! {
             Some(message) = message_receiver.recv() => {
+                let goodbye = matches!(message, Message::Goodbye { .. });
                 writer.send_message(message).await?;
+                // nothing may follow a goodbye, so the session ends once it is written
+                if goodbye {
+                    return Ok(())
+                }
             },
             _ = tokio::time::sleep(timeout) => {
-                writer.send_message(Message::Heartbeat).await?;
+                // the idle keepalive doubles as a round trip measurement
+                let ping = control_link.lock().unwrap().ping();
+                writer.send_message(ping).await?;
+            },
+            _ = link_quality_log_interval.tick() => {
+                println!("Control link quality: {}", control_link.lock().unwrap().quality());
             },
             _ = cancellation_token.cancelled() => {
                 return Ok(())
@@ -122,3 +180,80 @@ pub async fn special_event_sender(
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use chacha20poly1305::ChaCha20Poly1305;
+    use network::{
+        channel::ChannelTransport,
+        transport::{SplitTransport, Transport},
+        Message,
+    };
+    use tokio::sync::mpsc;
+    use tokio_util::sync::CancellationToken;
+
+    use super::{special_event_listener, SpecialEventProcessorError};
+
+    #[tokio::test]
+    async fn given_silent_server_should_time_out() {
+        // Given
+        let (client, _server) = ChannelTransport::<ChaCha20Poly1305>::pair();
+        let (reader, _writer) = client.into_split();
+        let (message_sender, _message_receiver) = mpsc::channel(8);
+        let (release_request_sender, _release_request_receiver) = mpsc::channel(8);
+        let peer_timeout = Duration::from_millis(50);
+
+        // When
+        let response = special_event_listener(
+            reader,
+            message_sender,
+            release_request_sender,
+            None,
+            Default::default(),
+            peer_timeout,
+            CancellationToken::new(),
+        )
+        .await;
+
+        // Then
+        assert!(matches!(
+            response,
+            Err(SpecialEventProcessorError::PeerTimeout(timeout)) if timeout == peer_timeout
+        ));
+    }
+
+    #[tokio::test]
+    async fn given_server_shutdown_notice_should_end_session_with_reason() {
+        // Given
+        let (client, mut server) = ChannelTransport::<ChaCha20Poly1305>::pair();
+        let (reader, _writer) = client.into_split();
+        let (message_sender, _message_receiver) = mpsc::channel(8);
+        let (release_request_sender, _release_request_receiver) = mpsc::channel(8);
+        server
+            .send_message(Message::ServerShuttingDown {
+                reason: "Restarting".to_string(),
+            })
+            .await
+            .unwrap();
+
+        // When
+        let response = special_event_listener(
+            reader,
+            message_sender,
+            release_request_sender,
+            None,
+            Default::default(),
+            Duration::from_secs(10),
+            CancellationToken::new(),
+        )
+        .await;
+
+        // Then
+        assert!(matches!(
+            response,
+            Err(SpecialEventProcessorError::ServerShuttingDown(reason)) if reason == "Restarting"
+        ));
+    }
+}
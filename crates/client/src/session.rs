@@ -0,0 +1,141 @@
+//! The session a client resumes, kept on disk so that a restarted client is given back its
+//! place in the server's cycle order.
+//!
+//! The file holds the client id, the resumption secret and then the ticket, and is only readable
+//! by its owner since the secret is enough to resume the session.
+
+use std::{
+    fs::{self, OpenOptions},
+    io::{self, Write},
+    os::unix::fs::OpenOptionsExt,
+    path::{Path, PathBuf},
+};
+
+use uuid::Uuid;
+
+const ID_LEN: usize = 16;
+const SECRET_LEN: usize = 32;
+
+/// What the client keeps from its last session in order to resume it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Session {
+    pub client_id: Uuid,
+    pub ticket: Vec<u8>,
+    pub secret: [u8; SECRET_LEN],
+}
+
+impl Session {
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(ID_LEN + SECRET_LEN + self.ticket.len());
+        bytes.extend_from_slice(self.client_id.as_bytes());
+        bytes.extend_from_slice(&self.secret);
+        bytes.extend_from_slice(&self.ticket);
+        bytes
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() <= ID_LEN + SECRET_LEN {
+            return None;
+        }
+        let (client_id, rest) = bytes.split_at(ID_LEN);
+        let (secret, ticket) = rest.split_at(SECRET_LEN);
+        Some(Session {
+            client_id: Uuid::from_slice(client_id).ok()?,
+            ticket: ticket.to_vec(),
+            secret: secret.try_into().ok()?,
+        })
+    }
+
+    /// Reads the session saved at `path`. A missing or unreadable file means starting afresh.
+    pub fn load(path: &Path) -> Option<Self> {
+        match fs::read(path) {
+            Ok(bytes) => {
+                let session = Session::from_bytes(&bytes);
+                if session.is_none() {
+                    eprintln!("Ignoring malformed session file {}", path.display());
+                }
+                session
+            }
+            Err(err) if err.kind() == io::ErrorKind::NotFound => None,
+            Err(err) => {
+                eprintln!("Could not read session file {}: {}", path.display(), err);
+                None
+            }
+        }
+    }
+
+    /// Saves the session to `path`, replacing the one there all at once so that a client
+    /// stopped part way through never leaves half a session behind.
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let partial = path.with_extension("partial");
+        let mut file = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .mode(0o600)
+            .open(&partial)?;
+        file.write_all(&self.to_bytes())?;
+        file.sync_all()?;
+        fs::rename(partial, path)
+    }
+}
+
+/// Where the session is kept by default, under the XDG state directory. `None` if neither
+/// `XDG_STATE_HOME` nor `HOME` is set.
+pub fn default_session_path() -> Option<PathBuf> {
+    let state_dir = std::env::var_os("XDG_STATE_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".local/state")))?;
+    Some(state_dir.join("rust_virtual_kvm").join("session"))
+}
+
+#[cfg(test)]
+mod test {
+    use std::os::unix::fs::PermissionsExt;
+
+    use uuid::Uuid;
+
+    use super::Session;
+
+    fn session() -> Session {
+        Session {
+            client_id: Uuid::new_v4(),
+            ticket: vec![1, 2, 3, 4],
+            secret: [9; 32],
+        }
+    }
+
+    #[test]
+    fn given_saved_session_should_load_the_same_session() {
+        // Given
+        let dir = std::env::temp_dir().join(format!("client-session-{}", std::process::id()));
+        let path = dir.join("session");
+        let session = session();
+
+        // When
+        session.save(&path).unwrap();
+        let loaded = Session::load(&path);
+
+        // Then
+        assert_eq!(loaded, Some(session));
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn given_truncated_bytes_should_not_be_a_session() {
+        // Given
+        let bytes = session().to_bytes();
+
+        // When
+        let session = Session::from_bytes(&bytes[..40]);
+
+        // Then
+        assert!(session.is_none());
+    }
+}
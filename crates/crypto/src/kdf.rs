@@ -0,0 +1,26 @@
+use hkdf::Hkdf;
+use sha2::Sha256;
+
+pub const KEY_LEN: usize = 32;
+
+/// Derives a key from `secret` with HKDF-SHA256. `salt` should hold fresh values from both
+/// peers so that every derivation from the same secret yields a different key.
+pub fn derive_key(secret: &[u8], salt: &[u8], info: &[u8]) -> [u8; KEY_LEN] {
+    let mut key = [0; KEY_LEN];
+    Hkdf::<Sha256>::new(Some(salt), secret)
+        .expand(info, &mut key)
+        .expect("32 bytes is a valid HKDF-SHA256 output length");
+    key
+}
+
+/// Derives the key for a resumed session from the ticket secret and both peers' nonces.
+pub fn resumption_key(
+    secret: &[u8; KEY_LEN],
+    client_nonce: &[u8; 32],
+    server_nonce: &[u8; 32],
+) -> [u8; KEY_LEN] {
+    let mut salt = [0; 64];
+    salt[..32].copy_from_slice(client_nonce);
+    salt[32..].copy_from_slice(server_nonce);
+    derive_key(secret, &salt, b"session resumption")
+}
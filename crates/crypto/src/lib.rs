@@ -2,6 +2,7 @@ use chacha20poly1305::Nonce;
 use thiserror::Error;
 
 pub mod chacha;
+pub mod kdf;
 
 #[derive(Debug, Error)]
 pub enum EncryptionError {
@@ -11,7 +12,7 @@ pub enum EncryptionError {
     DecryptionError,
 }
 
-pub trait Crypto: Encryptor + Decryptor {}
+pub trait Crypto: Encryptor + Decryptor + Send + Sync {}
 
 pub trait Encryptor: Clone {
     // TODO: remove chacha20poly1305::Nonce from return type
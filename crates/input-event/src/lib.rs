@@ -9,6 +9,32 @@ pub mod mapper;
 pub enum InputEvent {
     Keyboard(KeyboardEvent),
     Mouse(MouseEvent),
+    Tablet(TabletEvent),
+    Touch(TouchFrame),
+    Gamepad(GamepadEvent),
+}
+
+impl InputEvent {
+    /// Whether this says where something is rather than how it moved, so that a later event of
+    /// the same kind makes it obsolete.
+    pub fn is_absolute(&self) -> bool {
+        match self {
+            InputEvent::Tablet(_) | InputEvent::Touch(_) => true,
+            InputEvent::Gamepad(event) => matches!(event.input, GamepadInput::Axis { .. }),
+            _ => false,
+        }
+    }
+
+    /// Whether `self` makes `other` obsolete, being a later value of the same absolute axis or a
+    /// later touch frame.
+    pub fn supersedes(&self, other: &InputEvent) -> bool {
+        match (self, other) {
+            (InputEvent::Tablet(event), InputEvent::Tablet(other)) => event.supersedes(other),
+            (InputEvent::Touch(_), InputEvent::Touch(_)) => true,
+            (InputEvent::Gamepad(event), InputEvent::Gamepad(other)) => event.supersedes(other),
+            _ => false,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
@@ -33,10 +59,316 @@ pub enum MouseEvent {
         event_type: KeyboardEventType,
         button: Button,
     },
+    /// Wheel movement in detents.
     Scroll {
         axis: PointerAxis,
         diff: i32,
     },
+    /// Wheel movement in 120ths of a detent. High-resolution wheels report this alongside
+    /// `Scroll`, so a consumer should act on one or the other.
+    HiResScroll {
+        axis: PointerAxis,
+        diff: i32,
+    },
+}
+
+/// The largest value of an absolute axis once scaled from the range its device reports in.
+pub const ABSOLUTE_MAX: i32 = 0xffff;
+
+/// Absolute pointer input, from touchscreens and graphics tablets.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Display)]
+pub enum TabletEvent {
+    /// Position along an axis as a fraction of the device's range, from 0 to `ABSOLUTE_MAX`.
+    Position { axis: PointerAxis, value: i32 },
+    /// Position along an axis in pixels of the screen it is replayed on, which the server
+    /// places `Position` at once it knows the client's screen.
+    ScreenPosition { axis: PointerAxis, pixel: i32 },
+    /// Pen pressure, from 0 to `ABSOLUTE_MAX`.
+    Pressure { value: i32 },
+    /// Pen tilt away from vertical, in degrees.
+    Tilt { axis: PointerAxis, degrees: i32 },
+}
+
+impl TabletEvent {
+    /// Whether `self` makes `other` obsolete, being a later value of the same axis.
+    pub fn supersedes(&self, other: &TabletEvent) -> bool {
+        match (self, other) {
+            (TabletEvent::Position { axis, .. }, TabletEvent::Position { axis: other, .. })
+            | (
+                TabletEvent::ScreenPosition { axis, .. },
+                TabletEvent::ScreenPosition { axis: other, .. },
+            )
+            | (TabletEvent::Tilt { axis, .. }, TabletEvent::Tilt { axis: other, .. }) => {
+                axis == other
+            }
+            (TabletEvent::Pressure { .. }, TabletEvent::Pressure { .. }) => true,
+            _ => false,
+        }
+    }
+}
+
+/// The fingers on a touchpad as of one report from the device. Each frame lists every finger,
+/// so a frame that is lost or dropped is made up for by the next one.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TouchFrame {
+    /// Size of the touch surface in millimetres, zero where the device does not say.
+    pub width_mm: u16,
+    pub height_mm: u16,
+    pub contacts: Vec<TouchContact>,
+}
+
+/// A finger on a touchpad, at a fraction of the surface from 0 to `ABSOLUTE_MAX` on each axis.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TouchContact {
+    /// The device's slot for the finger, which stays the same while it is down.
+    pub slot: u8,
+    pub x: i32,
+    pub y: i32,
+}
+
+/// Tells apart game controllers plugged in at the same time. Numbers are never reused, so input
+/// from a controller that was unplugged cannot reach one plugged in after it.
+pub type GamepadId = u32;
+
+/// Input from a game controller or joystick, which the client replays on a virtual controller
+/// of its own for each `pad`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GamepadEvent {
+    pub pad: GamepadId,
+    pub input: GamepadInput,
+}
+
+impl fmt::Display for GamepadEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "pad {} {}", self.pad, self.input)
+    }
+}
+
+impl GamepadEvent {
+    /// Whether `self` makes `other` obsolete, being a later value of the same axis on the same
+    /// controller.
+    pub fn supersedes(&self, other: &GamepadEvent) -> bool {
+        match (&self.input, &other.input) {
+            (
+                GamepadInput::Axis { axis, .. },
+                GamepadInput::Axis {
+                    axis: other_axis, ..
+                },
+            ) => self.pad == other.pad && axis == other_axis,
+            _ => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Display)]
+pub enum GamepadInput {
+    /// The controller was plugged in, or input is about to go to a target that has not been
+    /// told about it yet. Comes before the rest of its input.
+    Connected(GamepadCapabilities),
+    Disconnected,
+    Button {
+        event_type: KeyboardEventType,
+        button: GamepadButton,
+    },
+    /// Position of a stick or trigger from 0 to `ABSOLUTE_MAX`, or of a hat as -1, 0 or 1.
+    Axis {
+        axis: GamepadAxis,
+        value: i32,
+    },
+}
+
+/// What a controller reports, for the client to make a virtual controller that games treat the
+/// same way. The IDs are what games look up button layouts by.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GamepadCapabilities {
+    pub name: String,
+    pub bus_type: u16,
+    pub vendor: u16,
+    pub product: u16,
+    pub version: u16,
+    pub buttons: Vec<GamepadButton>,
+    pub axes: Vec<GamepadAxisInfo>,
+}
+
+impl GamepadCapabilities {
+    /// An Xbox 360 controller, whose layout games know without a mapping of their own, assumed
+    /// for a controller whose `Connected` was lost on the way.
+    pub fn standard() -> Self {
+        let centred = |axis| GamepadAxisInfo {
+            axis,
+            rest: ABSOLUTE_MAX / 2,
+        };
+        let released = |axis| GamepadAxisInfo { axis, rest: 0 };
+        GamepadCapabilities {
+            name: "Microsoft X-Box 360 pad".to_string(),
+            // USB
+            bus_type: 0x03,
+            vendor: 0x045e,
+            product: 0x028e,
+            version: 0x0114,
+            buttons: vec![
+                GamepadButton::BTN_SOUTH,
+                GamepadButton::BTN_EAST,
+                GamepadButton::BTN_NORTH,
+                GamepadButton::BTN_WEST,
+                GamepadButton::BTN_TL,
+                GamepadButton::BTN_TR,
+                GamepadButton::BTN_SELECT,
+                GamepadButton::BTN_START,
+                GamepadButton::BTN_MODE,
+                GamepadButton::BTN_THUMBL,
+                GamepadButton::BTN_THUMBR,
+            ],
+            axes: vec![
+                centred(GamepadAxis::ABS_X),
+                centred(GamepadAxis::ABS_Y),
+                released(GamepadAxis::ABS_Z),
+                centred(GamepadAxis::ABS_RX),
+                centred(GamepadAxis::ABS_RY),
+                released(GamepadAxis::ABS_RZ),
+                released(GamepadAxis::ABS_HAT0X),
+                released(GamepadAxis::ABS_HAT0Y),
+            ],
+        }
+    }
+}
+
+impl fmt::Display for GamepadCapabilities {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "\"{}\" ({:04x}:{:04x}, {} buttons, {} axes)",
+            self.name,
+            self.vendor,
+            self.product,
+            self.buttons.len(),
+            self.axes.len()
+        )
+    }
+}
+
+/// An axis a controller reports, with where it sits when nobody touches it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GamepadAxisInfo {
+    pub axis: GamepadAxis,
+    /// Uses the same scale as `GamepadInput::Axis`.
+    pub rest: i32,
+}
+
+/// The size of a client's screen in pixels, used to place absolute pointer input on it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScreenGeometry {
+    pub width: u32,
+    pub height: u32,
+}
+
+impl ScreenGeometry {
+    /// Scales a `Position` onto this screen. Other events are returned unchanged.
+    pub fn place(&self, event: TabletEvent) -> TabletEvent {
+        let TabletEvent::Position { axis, value } = event else {
+            return event;
+        };
+        let length = match axis {
+            PointerAxis::Horizontal => self.width,
+            PointerAxis::Vertical => self.height,
+        };
+        let last_pixel = i64::from(length.saturating_sub(1));
+        let pixel = i64::from(value.clamp(0, ABSOLUTE_MAX)) * last_pixel / i64::from(ABSOLUTE_MAX);
+        TabletEvent::ScreenPosition {
+            axis,
+            pixel: pixel as i32,
+        }
+    }
+}
+
+impl fmt::Display for ScreenGeometry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}x{}", self.width, self.height)
+    }
+}
+
+/// The buttons of game controllers and joysticks, in code order.
+#[allow(non_camel_case_types)]
+#[derive(Debug, EnumIter, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Display)]
+pub enum GamepadButton {
+    BTN_TRIGGER,
+    BTN_THUMB,
+    BTN_THUMB2,
+    BTN_TOP,
+    BTN_TOP2,
+    BTN_PINKIE,
+    BTN_BASE,
+    BTN_BASE2,
+    BTN_BASE3,
+    BTN_BASE4,
+    BTN_BASE5,
+    BTN_BASE6,
+    BTN_DEAD,
+    BTN_SOUTH,
+    BTN_EAST,
+    BTN_C,
+    BTN_NORTH,
+    BTN_WEST,
+    BTN_Z,
+    BTN_TL,
+    BTN_TR,
+    BTN_TL2,
+    BTN_TR2,
+    BTN_SELECT,
+    BTN_START,
+    BTN_MODE,
+    BTN_THUMBL,
+    BTN_THUMBR,
+    BTN_DPAD_UP,
+    BTN_DPAD_DOWN,
+    BTN_DPAD_LEFT,
+    BTN_DPAD_RIGHT,
+    /// A controller button code with no variant of its own, such as the `BTN_TRIGGER_HAPPY`
+    /// buttons of arcade sticks and dance mats, passed through unchanged.
+    #[strum(disabled)]
+    Raw(u16),
+}
+
+/// The sticks, triggers, pedals and hats of game controllers and joysticks.
+#[allow(non_camel_case_types)]
+#[derive(Debug, EnumIter, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Display)]
+pub enum GamepadAxis {
+    ABS_X,
+    ABS_Y,
+    ABS_Z,
+    ABS_RX,
+    ABS_RY,
+    ABS_RZ,
+    ABS_THROTTLE,
+    ABS_RUDDER,
+    ABS_WHEEL,
+    ABS_GAS,
+    ABS_BRAKE,
+    ABS_HAT0X,
+    ABS_HAT0Y,
+    ABS_HAT1X,
+    ABS_HAT1Y,
+    ABS_HAT2X,
+    ABS_HAT2Y,
+    ABS_HAT3X,
+    ABS_HAT3Y,
+}
+
+impl GamepadAxis {
+    /// Whether this is a direction pad reported as an axis, which only ever reads -1, 0 or 1.
+    pub fn is_hat(&self) -> bool {
+        matches!(
+            self,
+            GamepadAxis::ABS_HAT0X
+                | GamepadAxis::ABS_HAT0Y
+                | GamepadAxis::ABS_HAT1X
+                | GamepadAxis::ABS_HAT1Y
+                | GamepadAxis::ABS_HAT2X
+                | GamepadAxis::ABS_HAT2Y
+                | GamepadAxis::ABS_HAT3X
+                | GamepadAxis::ABS_HAT3Y
+        )
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Display)]
@@ -58,7 +390,7 @@ pub enum DeviceType {
     Mouse,
 }
 
-// TODO: delete some extra keys that arent used (eg KEY_KP_x)
+/// Every Linux key code, in code order.
 #[allow(non_camel_case_types)]
 #[derive(Debug, EnumIter, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Display)]
 pub enum Key {
@@ -145,8 +477,23 @@ pub enum Key {
     KEY_KP3,
     KEY_KP0,
     KEY_KPDOT,
+    KEY_ZENKAKUHANKAKU,
+    KEY_102ND,
     KEY_F11,
     KEY_F12,
+    KEY_RO,
+    KEY_KATAKANA,
+    KEY_HIRAGANA,
+    KEY_HENKAN,
+    KEY_KATAKANAHIRAGANA,
+    KEY_MUHENKAN,
+    KEY_KPJPCOMMA,
+    KEY_KPENTER,
+    KEY_RIGHTCTRL,
+    KEY_KPSLASH,
+    KEY_SYSRQ,
+    KEY_RIGHTALT,
+    KEY_LINEFEED,
     KEY_HOME,
     KEY_UP,
     KEY_PAGEUP,
@@ -157,17 +504,368 @@ pub enum Key {
     KEY_PAGEDOWN,
     KEY_INSERT,
     KEY_DELETE,
+    KEY_MACRO,
+    KEY_MUTE,
+    KEY_VOLUMEDOWN,
+    KEY_VOLUMEUP,
+    KEY_POWER,
+    KEY_KPEQUAL,
+    KEY_KPPLUSMINUS,
+    KEY_PAUSE,
+    KEY_SCALE,
+    KEY_KPCOMMA,
+    KEY_HANGEUL,
+    KEY_HANJA,
+    KEY_YEN,
     KEY_LEFTMETA,
     KEY_RIGHTMETA,
+    KEY_COMPOSE,
+    KEY_STOP,
+    KEY_AGAIN,
+    KEY_PROPS,
+    KEY_UNDO,
+    KEY_FRONT,
+    KEY_COPY,
+    KEY_OPEN,
+    KEY_PASTE,
+    KEY_FIND,
+    KEY_CUT,
+    KEY_HELP,
     KEY_MENU,
+    KEY_CALC,
+    KEY_SETUP,
+    KEY_SLEEP,
+    KEY_WAKEUP,
+    KEY_FILE,
+    KEY_SENDFILE,
+    KEY_DELETEFILE,
+    KEY_XFER,
+    KEY_PROG1,
+    KEY_PROG2,
+    KEY_WWW,
+    KEY_MSDOS,
+    KEY_COFFEE,
+    KEY_DIRECTION,
+    KEY_CYCLEWINDOWS,
+    KEY_MAIL,
+    KEY_BOOKMARKS,
+    KEY_COMPUTER,
+    KEY_BACK,
+    KEY_FORWARD,
+    KEY_CLOSECD,
+    KEY_EJECTCD,
+    KEY_EJECTCLOSECD,
+    KEY_NEXTSONG,
+    KEY_PLAYPAUSE,
+    KEY_PREVIOUSSONG,
+    KEY_STOPCD,
+    KEY_RECORD,
+    KEY_REWIND,
+    KEY_PHONE,
+    KEY_ISO,
+    KEY_CONFIG,
+    KEY_HOMEPAGE,
+    KEY_REFRESH,
+    KEY_EXIT,
+    KEY_MOVE,
+    KEY_EDIT,
+    KEY_SCROLLUP,
+    KEY_SCROLLDOWN,
+    KEY_KPLEFTPAREN,
+    KEY_KPRIGHTPAREN,
+    KEY_NEW,
+    KEY_REDO,
+    KEY_F13,
+    KEY_F14,
+    KEY_F15,
+    KEY_F16,
+    KEY_F17,
+    KEY_F18,
+    KEY_F19,
+    KEY_F20,
+    KEY_F21,
+    KEY_F22,
+    KEY_F23,
+    KEY_F24,
+    KEY_PLAYCD,
+    KEY_PAUSECD,
+    KEY_PROG3,
+    KEY_PROG4,
+    KEY_DASHBOARD,
+    KEY_SUSPEND,
+    KEY_CLOSE,
+    KEY_PLAY,
+    KEY_FASTFORWARD,
+    KEY_BASSBOOST,
+    KEY_PRINT,
+    KEY_HP,
+    KEY_CAMERA,
+    KEY_SOUND,
+    KEY_QUESTION,
+    KEY_EMAIL,
+    KEY_CHAT,
+    KEY_SEARCH,
+    KEY_CONNECT,
+    KEY_FINANCE,
+    KEY_SPORT,
+    KEY_SHOP,
+    KEY_ALTERASE,
+    KEY_CANCEL,
+    KEY_BRIGHTNESSDOWN,
+    KEY_BRIGHTNESSUP,
+    KEY_MEDIA,
+    KEY_SWITCHVIDEOMODE,
+    KEY_KBDILLUMTOGGLE,
+    KEY_KBDILLUMDOWN,
+    KEY_KBDILLUMUP,
+    KEY_SEND,
+    KEY_REPLY,
+    KEY_FORWARDMAIL,
+    KEY_SAVE,
+    KEY_DOCUMENTS,
+    KEY_BATTERY,
+    KEY_BLUETOOTH,
+    KEY_WLAN,
+    KEY_UWB,
+    KEY_UNKNOWN,
+    KEY_VIDEO_NEXT,
+    KEY_VIDEO_PREV,
+    KEY_BRIGHTNESS_CYCLE,
+    KEY_BRIGHTNESS_AUTO,
+    KEY_DISPLAY_OFF,
+    KEY_WWAN,
+    KEY_RFKILL,
+    KEY_MICMUTE,
+    KEY_OK,
+    KEY_SELECT,
+    KEY_GOTO,
+    KEY_CLEAR,
+    KEY_POWER2,
+    KEY_OPTION,
+    KEY_INFO,
+    KEY_TIME,
+    KEY_VENDOR,
+    KEY_ARCHIVE,
+    KEY_PROGRAM,
+    KEY_CHANNEL,
+    KEY_FAVORITES,
+    KEY_EPG,
+    KEY_PVR,
+    KEY_MHP,
+    KEY_LANGUAGE,
+    KEY_TITLE,
+    KEY_SUBTITLE,
+    KEY_ANGLE,
+    KEY_ZOOM,
+    KEY_MODE,
+    KEY_KEYBOARD,
+    KEY_SCREEN,
+    KEY_PC,
+    KEY_TV,
+    KEY_TV2,
+    KEY_VCR,
+    KEY_VCR2,
+    KEY_SAT,
+    KEY_SAT2,
+    KEY_CD,
+    KEY_TAPE,
+    KEY_RADIO,
+    KEY_TUNER,
+    KEY_PLAYER,
+    KEY_TEXT,
+    KEY_DVD,
+    KEY_AUX,
+    KEY_MP3,
+    KEY_AUDIO,
+    KEY_VIDEO,
+    KEY_DIRECTORY,
+    KEY_LIST,
+    KEY_MEMO,
+    KEY_CALENDAR,
+    KEY_RED,
+    KEY_GREEN,
+    KEY_YELLOW,
+    KEY_BLUE,
+    KEY_CHANNELUP,
+    KEY_CHANNELDOWN,
+    KEY_FIRST,
+    KEY_LAST,
+    KEY_AB,
+    KEY_NEXT,
+    KEY_RESTART,
+    KEY_SLOW,
+    KEY_SHUFFLE,
+    KEY_BREAK,
+    KEY_PREVIOUS,
+    KEY_DIGITS,
+    KEY_TEEN,
+    KEY_TWEN,
+    KEY_VIDEOPHONE,
+    KEY_GAMES,
+    KEY_ZOOMIN,
+    KEY_ZOOMOUT,
+    KEY_ZOOMRESET,
+    KEY_WORDPROCESSOR,
+    KEY_EDITOR,
+    KEY_SPREADSHEET,
+    KEY_GRAPHICSEDITOR,
+    KEY_PRESENTATION,
+    KEY_DATABASE,
+    KEY_NEWS,
+    KEY_VOICEMAIL,
+    KEY_ADDRESSBOOK,
+    KEY_MESSENGER,
+    KEY_DISPLAYTOGGLE,
+    KEY_SPELLCHECK,
+    KEY_LOGOFF,
+    KEY_DOLLAR,
+    KEY_EURO,
+    KEY_FRAMEBACK,
+    KEY_FRAMEFORWARD,
+    KEY_CONTEXT_MENU,
+    KEY_MEDIA_REPEAT,
+    KEY_10CHANNELSUP,
+    KEY_10CHANNELSDOWN,
+    KEY_IMAGES,
+    KEY_DEL_EOL,
+    KEY_DEL_EOS,
+    KEY_INS_LINE,
+    KEY_DEL_LINE,
+    KEY_FN,
+    KEY_FN_ESC,
+    KEY_FN_F1,
+    KEY_FN_F2,
+    KEY_FN_F3,
+    KEY_FN_F4,
+    KEY_FN_F5,
+    KEY_FN_F6,
+    KEY_FN_F7,
+    KEY_FN_F8,
+    KEY_FN_F9,
+    KEY_FN_F10,
+    KEY_FN_F11,
+    KEY_FN_F12,
+    KEY_FN_1,
+    KEY_FN_2,
+    KEY_FN_D,
+    KEY_FN_E,
+    KEY_FN_F,
+    KEY_FN_S,
+    KEY_FN_B,
+    KEY_BRL_DOT1,
+    KEY_BRL_DOT2,
+    KEY_BRL_DOT3,
+    KEY_BRL_DOT4,
+    KEY_BRL_DOT5,
+    KEY_BRL_DOT6,
+    KEY_BRL_DOT7,
+    KEY_BRL_DOT8,
+    KEY_BRL_DOT9,
+    KEY_BRL_DOT10,
+    KEY_NUMERIC_0,
+    KEY_NUMERIC_1,
+    KEY_NUMERIC_2,
+    KEY_NUMERIC_3,
+    KEY_NUMERIC_4,
+    KEY_NUMERIC_5,
+    KEY_NUMERIC_6,
+    KEY_NUMERIC_7,
+    KEY_NUMERIC_8,
+    KEY_NUMERIC_9,
+    KEY_NUMERIC_STAR,
+    KEY_NUMERIC_POUND,
+    KEY_NUMERIC_A,
+    KEY_NUMERIC_B,
+    KEY_NUMERIC_C,
+    KEY_NUMERIC_D,
+    KEY_CAMERA_FOCUS,
+    KEY_WPS_BUTTON,
+    KEY_TOUCHPAD_TOGGLE,
+    KEY_TOUCHPAD_ON,
+    KEY_TOUCHPAD_OFF,
+    KEY_CAMERA_ZOOMIN,
+    KEY_CAMERA_ZOOMOUT,
+    KEY_CAMERA_UP,
+    KEY_CAMERA_DOWN,
+    KEY_CAMERA_LEFT,
+    KEY_CAMERA_RIGHT,
+    KEY_ATTENDANT_ON,
+    KEY_ATTENDANT_OFF,
+    KEY_ATTENDANT_TOGGLE,
+    KEY_LIGHTS_TOGGLE,
+    KEY_ALS_TOGGLE,
+    KEY_BUTTONCONFIG,
+    KEY_TASKMANAGER,
+    KEY_JOURNAL,
+    KEY_CONTROLPANEL,
+    KEY_APPSELECT,
+    KEY_SCREENSAVER,
+    KEY_VOICECOMMAND,
+    KEY_ASSISTANT,
+    KEY_KBD_LAYOUT_NEXT,
+    KEY_BRIGHTNESS_MIN,
+    KEY_BRIGHTNESS_MAX,
+    KEY_KBDINPUTASSIST_PREV,
+    KEY_KBDINPUTASSIST_NEXT,
+    KEY_KBDINPUTASSIST_PREVGROUP,
+    KEY_KBDINPUTASSIST_NEXTGROUP,
+    KEY_KBDINPUTASSIST_ACCEPT,
+    KEY_KBDINPUTASSIST_CANCEL,
+    KEY_RIGHT_UP,
+    KEY_RIGHT_DOWN,
+    KEY_LEFT_UP,
+    KEY_LEFT_DOWN,
+    KEY_ROOT_MENU,
+    KEY_MEDIA_TOP_MENU,
+    KEY_NUMERIC_11,
+    KEY_NUMERIC_12,
+    KEY_AUDIO_DESC,
+    KEY_3D_MODE,
+    KEY_NEXT_FAVORITE,
+    KEY_STOP_RECORD,
+    KEY_PAUSE_RECORD,
+    KEY_VOD,
+    KEY_UNMUTE,
+    KEY_FASTREVERSE,
+    KEY_SLOWREVERSE,
+    KEY_DATA,
+    KEY_ONSCREEN_KEYBOARD,
+    KEY_PRIVACY_SCREEN_TOGGLE,
+    KEY_SELECTIVE_SCREENSHOT,
+    /// A key code with no variant of its own, such as one added to Linux after this list, passed
+    /// through unchanged.
+    #[strum(disabled)]
+    Raw(u16),
 }
 
 #[allow(non_camel_case_types)]
 #[derive(Debug, EnumIter, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Display)]
 pub enum Button {
+    BTN_0,
+    BTN_1,
+    BTN_2,
+    BTN_3,
+    BTN_4,
+    BTN_5,
+    BTN_6,
+    BTN_7,
+    BTN_8,
+    BTN_9,
     BTN_LEFT,
     BTN_RIGHT,
     BTN_MIDDLE,
+    BTN_SIDE,
+    BTN_EXTRA,
     BTN_FORWARD,
     BTN_BACK,
+    BTN_TASK,
+    BTN_TOOL_PEN,
+    BTN_TOOL_RUBBER,
+    BTN_TOUCH,
+    BTN_STYLUS,
+    BTN_STYLUS2,
+    /// A mouse or digitizer button code with no variant of its own, such as the unnamed buttons
+    /// of gaming mice, passed through unchanged.
+    #[strum(disabled)]
+    Raw(u16),
 }
@@ -1,8 +1,126 @@
-use crate::{mapper::error::EventMappingError, Button};
+use std::f64::consts::PI;
 
-impl From<crate::InputEvent> for evdev::InputEvent {
-    fn from(value: crate::InputEvent) -> Self {
-        match value {
+use crate::{mapper::error::EventMappingError, Button, GamepadInput, ABSOLUTE_MAX};
+
+/// The `BTN_MISC` and `BTN_MOUSE` ranges, which pointing devices report their buttons in.
+pub const MOUSE_BUTTON_CODES: std::ops::Range<u16> = 0x100..0x120;
+
+/// The `BTN_DIGI` range, which tablets and touchscreens report their tools and touches in.
+pub const DIGITIZER_BUTTON_CODES: std::ops::Range<u16> = 0x140..0x150;
+
+/// The `BTN_JOYSTICK` and `BTN_GAMEPAD` ranges, which controllers report their buttons in.
+pub const GAMEPAD_BUTTON_CODES: std::ops::Range<u16> = 0x120..0x140;
+
+/// The `BTN_DPAD` range, for controllers that report their direction pad as buttons.
+pub const DPAD_BUTTON_CODES: std::ops::Range<u16> = 0x220..0x224;
+
+/// The `BTN_TRIGGER_HAPPY` range, the extra buttons of arcade sticks and dance mats.
+pub const TRIGGER_HAPPY_BUTTON_CODES: std::ops::Range<u16> = 0x2c0..0x2e8;
+
+/// The range an absolute axis reports in, as given by the device's `input_absinfo`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AbsoluteRange {
+    pub minimum: i32,
+    pub maximum: i32,
+    /// Units per millimetre for positions, or per radian for tilt. Zero if unknown.
+    pub resolution: i32,
+}
+
+impl AbsoluteRange {
+    /// Scales `value` to a fraction of this range, from 0 to `ABSOLUTE_MAX`.
+    pub fn normalise(&self, value: i32) -> i32 {
+        let span = i64::from(self.maximum) - i64::from(self.minimum);
+        if span <= 0 {
+            return 0;
+        }
+        let offset = (i64::from(value) - i64::from(self.minimum)).clamp(0, span);
+        (offset * i64::from(ABSOLUTE_MAX) / span) as i32
+    }
+
+    /// The length of the axis in millimetres, or zero if the device gives no resolution.
+    pub fn millimetres(&self) -> u16 {
+        if self.resolution <= 0 {
+            return 0;
+        }
+        let span = i64::from(self.maximum) - i64::from(self.minimum);
+        u16::try_from(span / i64::from(self.resolution)).unwrap_or(u16::MAX)
+    }
+
+    /// Converts a tilt value to degrees, assuming degrees if the device gives no resolution.
+    fn degrees(&self, value: i32) -> i32 {
+        if self.resolution <= 0 {
+            return value;
+        }
+        (f64::from(value) * 180.0 / (PI * f64::from(self.resolution))).round() as i32
+    }
+}
+
+/// Maps an absolute axis event, which unlike other events needs the range the device reports
+/// that axis in.
+pub fn absolute_event(
+    value: evdev::InputEvent,
+    range: AbsoluteRange,
+) -> Result<crate::InputEvent, EventMappingError> {
+    if value.event_type() != evdev::EventType::ABSOLUTE {
+        return Err(EventMappingError::InvalidEvent);
+    }
+    let event = match evdev::AbsoluteAxisType(value.code()) {
+        evdev::AbsoluteAxisType::ABS_X => crate::TabletEvent::Position {
+            axis: crate::PointerAxis::Horizontal,
+            value: range.normalise(value.value()),
+        },
+        evdev::AbsoluteAxisType::ABS_Y => crate::TabletEvent::Position {
+            axis: crate::PointerAxis::Vertical,
+            value: range.normalise(value.value()),
+        },
+        evdev::AbsoluteAxisType::ABS_PRESSURE => crate::TabletEvent::Pressure {
+            value: range.normalise(value.value()),
+        },
+        evdev::AbsoluteAxisType::ABS_TILT_X => crate::TabletEvent::Tilt {
+            axis: crate::PointerAxis::Horizontal,
+            degrees: range.degrees(value.value()),
+        },
+        evdev::AbsoluteAxisType::ABS_TILT_Y => crate::TabletEvent::Tilt {
+            axis: crate::PointerAxis::Vertical,
+            degrees: range.degrees(value.value()),
+        },
+        // tablets report axes such as distance many times a second, so this is not logged
+        _ => return Err(EventMappingError::UnsupportedEventType),
+    };
+    Ok(crate::InputEvent::Tablet(event))
+}
+
+/// Maps an event from a game controller. Axes are scaled from `range`, the range the controller
+/// reports that axis in, except for hats, which keep their direction.
+pub fn gamepad_input(
+    value: evdev::InputEvent,
+    range: Option<AbsoluteRange>,
+) -> Result<GamepadInput, EventMappingError> {
+    match value.event_type() {
+        evdev::EventType::KEY => Ok(GamepadInput::Button {
+            event_type: from_evdev_value(&value)?,
+            button: evdev::Key::new(value.code()).try_into()?,
+        }),
+        evdev::EventType::ABSOLUTE => {
+            let axis: crate::GamepadAxis = evdev::AbsoluteAxisType(value.code()).try_into()?;
+            let value = if axis.is_hat() {
+                value.value().signum()
+            } else {
+                range
+                    .ok_or(EventMappingError::InvalidEvent)?
+                    .normalise(value.value())
+            };
+            Ok(GamepadInput::Axis { axis, value })
+        }
+        _ => Err(EventMappingError::UnsupportedEventType),
+    }
+}
+
+impl TryFrom<crate::InputEvent> for evdev::InputEvent {
+    type Error = EventMappingError;
+
+    fn try_from(value: crate::InputEvent) -> Result<Self, Self::Error> {
+        Ok(match value {
             crate::InputEvent::Keyboard(event) => evdev::InputEvent::new(
                 evdev::EventType::KEY,
                 evdev::Key::from(event.key).code(),
@@ -38,11 +156,68 @@ impl From<crate::InputEvent> for evdev::InputEvent {
                         to_evdev_value(event_type),
                     ),
                 },
-                crate::MouseEvent::Scroll { axis, diff } => {
-                    unimplemented!("Scroll events are not supported")
+                crate::MouseEvent::Scroll { axis, diff } => evdev::InputEvent::new(
+                    evdev::EventType::RELATIVE,
+                    match axis {
+                        crate::PointerAxis::Horizontal => evdev::RelativeAxisType::REL_HWHEEL.0,
+                        crate::PointerAxis::Vertical => evdev::RelativeAxisType::REL_WHEEL.0,
+                    },
+                    diff,
+                ),
+                crate::MouseEvent::HiResScroll { axis, diff } => evdev::InputEvent::new(
+                    evdev::EventType::RELATIVE,
+                    match axis {
+                        crate::PointerAxis::Horizontal => {
+                            evdev::RelativeAxisType::REL_HWHEEL_HI_RES.0
+                        }
+                        crate::PointerAxis::Vertical => evdev::RelativeAxisType::REL_WHEEL_HI_RES.0,
+                    },
+                    diff,
+                ),
+            },
+            // a position placed on the screen is replayed by a device whose axes span the
+            // screen's pixels, see `input_simulator::x11::dev::make_tablet`
+            crate::InputEvent::Tablet(event) => {
+                let (axis, value) = match event {
+                    crate::TabletEvent::Position { axis, value }
+                    | crate::TabletEvent::ScreenPosition { axis, pixel: value } => match axis {
+                        crate::PointerAxis::Horizontal => (evdev::AbsoluteAxisType::ABS_X, value),
+                        crate::PointerAxis::Vertical => (evdev::AbsoluteAxisType::ABS_Y, value),
+                    },
+                    crate::TabletEvent::Pressure { value } => {
+                        (evdev::AbsoluteAxisType::ABS_PRESSURE, value)
+                    }
+                    crate::TabletEvent::Tilt { axis, degrees } => match axis {
+                        crate::PointerAxis::Horizontal => {
+                            (evdev::AbsoluteAxisType::ABS_TILT_X, degrees)
+                        }
+                        crate::PointerAxis::Vertical => {
+                            (evdev::AbsoluteAxisType::ABS_TILT_Y, degrees)
+                        }
+                    },
+                };
+                evdev::InputEvent::new(evdev::EventType::ABSOLUTE, axis.0, value)
+            }
+            // a frame takes several events, and which depends on the frame before it
+            crate::InputEvent::Touch(_) => return Err(EventMappingError::UnsupportedEventType),
+            // axes are replayed by a controller whose ranges are those of the event, see
+            // `input_simulator::x11::dev::make_gamepad`
+            crate::InputEvent::Gamepad(event) => match event.input {
+                GamepadInput::Button { event_type, button } => evdev::InputEvent::new(
+                    evdev::EventType::KEY,
+                    evdev::Key::from(button).code(),
+                    to_evdev_value(event_type),
+                ),
+                GamepadInput::Axis { axis, value } => evdev::InputEvent::new(
+                    evdev::EventType::ABSOLUTE,
+                    evdev::AbsoluteAxisType::from(axis).0,
+                    value,
+                ),
+                GamepadInput::Connected(_) | GamepadInput::Disconnected => {
+                    return Err(EventMappingError::UnsupportedEventType)
                 }
             },
-        }
+        })
     }
 }
 
@@ -52,16 +227,7 @@ impl TryFrom<evdev::InputEvent> for crate::InputEvent {
     fn try_from(value: evdev::InputEvent) -> Result<Self, Self::Error> {
         match value.event_type() {
             evdev::EventType::KEY => {
-                // TODO: rework the type so it is consistent
-                let event_type = match value.value() {
-                    0 => crate::KeyboardEventType::KeyReleased,
-                    1 => crate::KeyboardEventType::KeyPressed,
-                    2 => crate::KeyboardEventType::KeyHeld,
-                    _ => {
-                        eprintln!("Invalid key event type value: {value:?}");
-                        return Err(EventMappingError::InvalidEvent);
-                    }
-                };
+                let event_type = from_evdev_value(&value)?;
                 if let Ok(button) =
                     std::convert::TryInto::<Button>::try_into(evdev::Key::new(value.code()))
                 {
@@ -77,28 +243,42 @@ impl TryFrom<evdev::InputEvent> for crate::InputEvent {
                 }))
             }
             evdev::EventType::RELATIVE => {
-                let (axis, is_motion) = if value.code() == evdev::RelativeAxisType::REL_X.0 {
-                    (crate::PointerAxis::Horizontal, true)
-                } else if value.code() == evdev::RelativeAxisType::REL_Y.0 {
-                    (crate::PointerAxis::Vertical, true)
-                } else if value.code() == evdev::RelativeAxisType::REL_WHEEL.0 {
-                    (crate::PointerAxis::Vertical, false)
-                } else {
-                    eprintln!("Invalid relative event axis value: {value:?}");
-                    return Err(EventMappingError::InvalidEvent);
+                let axis = evdev::RelativeAxisType(value.code());
+                let diff = value.value();
+                let event = match axis {
+                    evdev::RelativeAxisType::REL_X => crate::MouseEvent::Motion {
+                        axis: crate::PointerAxis::Horizontal,
+                        diff,
+                    },
+                    evdev::RelativeAxisType::REL_Y => crate::MouseEvent::Motion {
+                        axis: crate::PointerAxis::Vertical,
+                        diff,
+                    },
+                    evdev::RelativeAxisType::REL_WHEEL => crate::MouseEvent::Scroll {
+                        axis: crate::PointerAxis::Vertical,
+                        diff,
+                    },
+                    evdev::RelativeAxisType::REL_HWHEEL => crate::MouseEvent::Scroll {
+                        axis: crate::PointerAxis::Horizontal,
+                        diff,
+                    },
+                    evdev::RelativeAxisType::REL_WHEEL_HI_RES => crate::MouseEvent::HiResScroll {
+                        axis: crate::PointerAxis::Vertical,
+                        diff,
+                    },
+                    evdev::RelativeAxisType::REL_HWHEEL_HI_RES => crate::MouseEvent::HiResScroll {
+                        axis: crate::PointerAxis::Horizontal,
+                        diff,
+                    },
+                    _ => {
+                        eprintln!("Invalid relative event axis value: {value:?}");
+                        return Err(EventMappingError::InvalidEvent);
+                    }
                 };
-                if is_motion {
-                    Ok(crate::InputEvent::Mouse(crate::MouseEvent::Motion {
-                        axis,
-                        diff: value.value(),
-                    }))
-                } else {
-                    Ok(crate::InputEvent::Mouse(crate::MouseEvent::Scroll {
-                        axis,
-                        diff: value.value(),
-                    }))
-                }
+                Ok(crate::InputEvent::Mouse(event))
             }
+            // values only mean something relative to the device's range, see `absolute_event`
+            evdev::EventType::ABSOLUTE => Err(EventMappingError::UnsupportedEventType),
             _ => {
                 eprintln!("Unsupported event type: {value:?}");
                 Err(EventMappingError::UnsupportedEventType)
@@ -107,6 +287,21 @@ impl TryFrom<evdev::InputEvent> for crate::InputEvent {
     }
 }
 
+// TODO: rework the type so it is consistent
+fn from_evdev_value(
+    value: &evdev::InputEvent,
+) -> Result<crate::KeyboardEventType, EventMappingError> {
+    Ok(match value.value() {
+        0 => crate::KeyboardEventType::KeyReleased,
+        1 => crate::KeyboardEventType::KeyPressed,
+        2 => crate::KeyboardEventType::KeyHeld,
+        _ => {
+            eprintln!("Invalid key event type value: {value:?}");
+            return Err(EventMappingError::InvalidEvent);
+        }
+    })
+}
+
 fn to_evdev_value(event: crate::KeyboardEventType) -> i32 {
     match event {
         crate::KeyboardEventType::KeyPressed => 1,
@@ -201,8 +396,23 @@ impl From<crate::Key> for evdev::Key {
             crate::Key::KEY_KP3 => evdev::Key::KEY_KP3,
             crate::Key::KEY_KP0 => evdev::Key::KEY_KP0,
             crate::Key::KEY_KPDOT => evdev::Key::KEY_KPDOT,
+            crate::Key::KEY_ZENKAKUHANKAKU => evdev::Key::KEY_ZENKAKUHANKAKU,
+            crate::Key::KEY_102ND => evdev::Key::KEY_102ND,
             crate::Key::KEY_F11 => evdev::Key::KEY_F11,
             crate::Key::KEY_F12 => evdev::Key::KEY_F12,
+            crate::Key::KEY_RO => evdev::Key::KEY_RO,
+            crate::Key::KEY_KATAKANA => evdev::Key::KEY_KATAKANA,
+            crate::Key::KEY_HIRAGANA => evdev::Key::KEY_HIRAGANA,
+            crate::Key::KEY_HENKAN => evdev::Key::KEY_HENKAN,
+            crate::Key::KEY_KATAKANAHIRAGANA => evdev::Key::KEY_KATAKANAHIRAGANA,
+            crate::Key::KEY_MUHENKAN => evdev::Key::KEY_MUHENKAN,
+            crate::Key::KEY_KPJPCOMMA => evdev::Key::KEY_KPJPCOMMA,
+            crate::Key::KEY_KPENTER => evdev::Key::KEY_KPENTER,
+            crate::Key::KEY_RIGHTCTRL => evdev::Key::KEY_RIGHTCTRL,
+            crate::Key::KEY_KPSLASH => evdev::Key::KEY_KPSLASH,
+            crate::Key::KEY_SYSRQ => evdev::Key::KEY_SYSRQ,
+            crate::Key::KEY_RIGHTALT => evdev::Key::KEY_RIGHTALT,
+            crate::Key::KEY_LINEFEED => evdev::Key::KEY_LINEFEED,
             crate::Key::KEY_HOME => evdev::Key::KEY_HOME,
             crate::Key::KEY_UP => evdev::Key::KEY_UP,
             crate::Key::KEY_PAGEUP => evdev::Key::KEY_PAGEUP,
@@ -213,9 +423,335 @@ impl From<crate::Key> for evdev::Key {
             crate::Key::KEY_PAGEDOWN => evdev::Key::KEY_PAGEDOWN,
             crate::Key::KEY_INSERT => evdev::Key::KEY_INSERT,
             crate::Key::KEY_DELETE => evdev::Key::KEY_DELETE,
+            crate::Key::KEY_MACRO => evdev::Key::KEY_MACRO,
+            crate::Key::KEY_MUTE => evdev::Key::KEY_MUTE,
+            crate::Key::KEY_VOLUMEDOWN => evdev::Key::KEY_VOLUMEDOWN,
+            crate::Key::KEY_VOLUMEUP => evdev::Key::KEY_VOLUMEUP,
+            crate::Key::KEY_POWER => evdev::Key::KEY_POWER,
+            crate::Key::KEY_KPEQUAL => evdev::Key::KEY_KPEQUAL,
+            crate::Key::KEY_KPPLUSMINUS => evdev::Key::KEY_KPPLUSMINUS,
+            crate::Key::KEY_PAUSE => evdev::Key::KEY_PAUSE,
+            crate::Key::KEY_SCALE => evdev::Key::KEY_SCALE,
+            crate::Key::KEY_KPCOMMA => evdev::Key::KEY_KPCOMMA,
+            crate::Key::KEY_HANGEUL => evdev::Key::KEY_HANGEUL,
+            crate::Key::KEY_HANJA => evdev::Key::KEY_HANJA,
+            crate::Key::KEY_YEN => evdev::Key::KEY_YEN,
             crate::Key::KEY_LEFTMETA => evdev::Key::KEY_LEFTMETA,
             crate::Key::KEY_RIGHTMETA => evdev::Key::KEY_RIGHTMETA,
+            crate::Key::KEY_COMPOSE => evdev::Key::KEY_COMPOSE,
+            crate::Key::KEY_STOP => evdev::Key::KEY_STOP,
+            crate::Key::KEY_AGAIN => evdev::Key::KEY_AGAIN,
+            crate::Key::KEY_PROPS => evdev::Key::KEY_PROPS,
+            crate::Key::KEY_UNDO => evdev::Key::KEY_UNDO,
+            crate::Key::KEY_FRONT => evdev::Key::KEY_FRONT,
+            crate::Key::KEY_COPY => evdev::Key::KEY_COPY,
+            crate::Key::KEY_OPEN => evdev::Key::KEY_OPEN,
+            crate::Key::KEY_PASTE => evdev::Key::KEY_PASTE,
+            crate::Key::KEY_FIND => evdev::Key::KEY_FIND,
+            crate::Key::KEY_CUT => evdev::Key::KEY_CUT,
+            crate::Key::KEY_HELP => evdev::Key::KEY_HELP,
             crate::Key::KEY_MENU => evdev::Key::KEY_MENU,
+            crate::Key::KEY_CALC => evdev::Key::KEY_CALC,
+            crate::Key::KEY_SETUP => evdev::Key::KEY_SETUP,
+            crate::Key::KEY_SLEEP => evdev::Key::KEY_SLEEP,
+            crate::Key::KEY_WAKEUP => evdev::Key::KEY_WAKEUP,
+            crate::Key::KEY_FILE => evdev::Key::KEY_FILE,
+            crate::Key::KEY_SENDFILE => evdev::Key::KEY_SENDFILE,
+            crate::Key::KEY_DELETEFILE => evdev::Key::KEY_DELETEFILE,
+            crate::Key::KEY_XFER => evdev::Key::KEY_XFER,
+            crate::Key::KEY_PROG1 => evdev::Key::KEY_PROG1,
+            crate::Key::KEY_PROG2 => evdev::Key::KEY_PROG2,
+            crate::Key::KEY_WWW => evdev::Key::KEY_WWW,
+            crate::Key::KEY_MSDOS => evdev::Key::KEY_MSDOS,
+            crate::Key::KEY_COFFEE => evdev::Key::KEY_COFFEE,
+            crate::Key::KEY_DIRECTION => evdev::Key::KEY_DIRECTION,
+            crate::Key::KEY_CYCLEWINDOWS => evdev::Key::KEY_CYCLEWINDOWS,
+            crate::Key::KEY_MAIL => evdev::Key::KEY_MAIL,
+            crate::Key::KEY_BOOKMARKS => evdev::Key::KEY_BOOKMARKS,
+            crate::Key::KEY_COMPUTER => evdev::Key::KEY_COMPUTER,
+            crate::Key::KEY_BACK => evdev::Key::KEY_BACK,
+            crate::Key::KEY_FORWARD => evdev::Key::KEY_FORWARD,
+            crate::Key::KEY_CLOSECD => evdev::Key::KEY_CLOSECD,
+            crate::Key::KEY_EJECTCD => evdev::Key::KEY_EJECTCD,
+            crate::Key::KEY_EJECTCLOSECD => evdev::Key::KEY_EJECTCLOSECD,
+            crate::Key::KEY_NEXTSONG => evdev::Key::KEY_NEXTSONG,
+            crate::Key::KEY_PLAYPAUSE => evdev::Key::KEY_PLAYPAUSE,
+            crate::Key::KEY_PREVIOUSSONG => evdev::Key::KEY_PREVIOUSSONG,
+            crate::Key::KEY_STOPCD => evdev::Key::KEY_STOPCD,
+            crate::Key::KEY_RECORD => evdev::Key::KEY_RECORD,
+            crate::Key::KEY_REWIND => evdev::Key::KEY_REWIND,
+            crate::Key::KEY_PHONE => evdev::Key::KEY_PHONE,
+            crate::Key::KEY_ISO => evdev::Key::KEY_ISO,
+            crate::Key::KEY_CONFIG => evdev::Key::KEY_CONFIG,
+            crate::Key::KEY_HOMEPAGE => evdev::Key::KEY_HOMEPAGE,
+            crate::Key::KEY_REFRESH => evdev::Key::KEY_REFRESH,
+            crate::Key::KEY_EXIT => evdev::Key::KEY_EXIT,
+            crate::Key::KEY_MOVE => evdev::Key::KEY_MOVE,
+            crate::Key::KEY_EDIT => evdev::Key::KEY_EDIT,
+            crate::Key::KEY_SCROLLUP => evdev::Key::KEY_SCROLLUP,
+            crate::Key::KEY_SCROLLDOWN => evdev::Key::KEY_SCROLLDOWN,
+            crate::Key::KEY_KPLEFTPAREN => evdev::Key::KEY_KPLEFTPAREN,
+            crate::Key::KEY_KPRIGHTPAREN => evdev::Key::KEY_KPRIGHTPAREN,
+            crate::Key::KEY_NEW => evdev::Key::KEY_NEW,
+            crate::Key::KEY_REDO => evdev::Key::KEY_REDO,
+            crate::Key::KEY_F13 => evdev::Key::KEY_F13,
+            crate::Key::KEY_F14 => evdev::Key::KEY_F14,
+            crate::Key::KEY_F15 => evdev::Key::KEY_F15,
+            crate::Key::KEY_F16 => evdev::Key::KEY_F16,
+            crate::Key::KEY_F17 => evdev::Key::KEY_F17,
+            crate::Key::KEY_F18 => evdev::Key::KEY_F18,
+            crate::Key::KEY_F19 => evdev::Key::KEY_F19,
+            crate::Key::KEY_F20 => evdev::Key::KEY_F20,
+            crate::Key::KEY_F21 => evdev::Key::KEY_F21,
+            crate::Key::KEY_F22 => evdev::Key::KEY_F22,
+            crate::Key::KEY_F23 => evdev::Key::KEY_F23,
+            crate::Key::KEY_F24 => evdev::Key::KEY_F24,
+            crate::Key::KEY_PLAYCD => evdev::Key::KEY_PLAYCD,
+            crate::Key::KEY_PAUSECD => evdev::Key::KEY_PAUSECD,
+            crate::Key::KEY_PROG3 => evdev::Key::KEY_PROG3,
+            crate::Key::KEY_PROG4 => evdev::Key::KEY_PROG4,
+            crate::Key::KEY_DASHBOARD => evdev::Key::KEY_DASHBOARD,
+            crate::Key::KEY_SUSPEND => evdev::Key::KEY_SUSPEND,
+            crate::Key::KEY_CLOSE => evdev::Key::KEY_CLOSE,
+            crate::Key::KEY_PLAY => evdev::Key::KEY_PLAY,
+            crate::Key::KEY_FASTFORWARD => evdev::Key::KEY_FASTFORWARD,
+            crate::Key::KEY_BASSBOOST => evdev::Key::KEY_BASSBOOST,
+            crate::Key::KEY_PRINT => evdev::Key::KEY_PRINT,
+            crate::Key::KEY_HP => evdev::Key::KEY_HP,
+            crate::Key::KEY_CAMERA => evdev::Key::KEY_CAMERA,
+            crate::Key::KEY_SOUND => evdev::Key::KEY_SOUND,
+            crate::Key::KEY_QUESTION => evdev::Key::KEY_QUESTION,
+            crate::Key::KEY_EMAIL => evdev::Key::KEY_EMAIL,
+            crate::Key::KEY_CHAT => evdev::Key::KEY_CHAT,
+            crate::Key::KEY_SEARCH => evdev::Key::KEY_SEARCH,
+            crate::Key::KEY_CONNECT => evdev::Key::KEY_CONNECT,
+            crate::Key::KEY_FINANCE => evdev::Key::KEY_FINANCE,
+            crate::Key::KEY_SPORT => evdev::Key::KEY_SPORT,
+            crate::Key::KEY_SHOP => evdev::Key::KEY_SHOP,
+            crate::Key::KEY_ALTERASE => evdev::Key::KEY_ALTERASE,
+            crate::Key::KEY_CANCEL => evdev::Key::KEY_CANCEL,
+            crate::Key::KEY_BRIGHTNESSDOWN => evdev::Key::KEY_BRIGHTNESSDOWN,
+            crate::Key::KEY_BRIGHTNESSUP => evdev::Key::KEY_BRIGHTNESSUP,
+            crate::Key::KEY_MEDIA => evdev::Key::KEY_MEDIA,
+            crate::Key::KEY_SWITCHVIDEOMODE => evdev::Key::KEY_SWITCHVIDEOMODE,
+            crate::Key::KEY_KBDILLUMTOGGLE => evdev::Key::KEY_KBDILLUMTOGGLE,
+            crate::Key::KEY_KBDILLUMDOWN => evdev::Key::KEY_KBDILLUMDOWN,
+            crate::Key::KEY_KBDILLUMUP => evdev::Key::KEY_KBDILLUMUP,
+            crate::Key::KEY_SEND => evdev::Key::KEY_SEND,
+            crate::Key::KEY_REPLY => evdev::Key::KEY_REPLY,
+            crate::Key::KEY_FORWARDMAIL => evdev::Key::KEY_FORWARDMAIL,
+            crate::Key::KEY_SAVE => evdev::Key::KEY_SAVE,
+            crate::Key::KEY_DOCUMENTS => evdev::Key::KEY_DOCUMENTS,
+            crate::Key::KEY_BATTERY => evdev::Key::KEY_BATTERY,
+            crate::Key::KEY_BLUETOOTH => evdev::Key::KEY_BLUETOOTH,
+            crate::Key::KEY_WLAN => evdev::Key::KEY_WLAN,
+            crate::Key::KEY_UWB => evdev::Key::KEY_UWB,
+            crate::Key::KEY_UNKNOWN => evdev::Key::KEY_UNKNOWN,
+            crate::Key::KEY_VIDEO_NEXT => evdev::Key::KEY_VIDEO_NEXT,
+            crate::Key::KEY_VIDEO_PREV => evdev::Key::KEY_VIDEO_PREV,
+            crate::Key::KEY_BRIGHTNESS_CYCLE => evdev::Key::KEY_BRIGHTNESS_CYCLE,
+            crate::Key::KEY_BRIGHTNESS_AUTO => evdev::Key::KEY_BRIGHTNESS_AUTO,
+            crate::Key::KEY_DISPLAY_OFF => evdev::Key::KEY_DISPLAY_OFF,
+            crate::Key::KEY_WWAN => evdev::Key::KEY_WWAN,
+            crate::Key::KEY_RFKILL => evdev::Key::KEY_RFKILL,
+            crate::Key::KEY_MICMUTE => evdev::Key::KEY_MICMUTE,
+            crate::Key::KEY_OK => evdev::Key::KEY_OK,
+            crate::Key::KEY_SELECT => evdev::Key::KEY_SELECT,
+            crate::Key::KEY_GOTO => evdev::Key::KEY_GOTO,
+            crate::Key::KEY_CLEAR => evdev::Key::KEY_CLEAR,
+            crate::Key::KEY_POWER2 => evdev::Key::KEY_POWER2,
+            crate::Key::KEY_OPTION => evdev::Key::KEY_OPTION,
+            crate::Key::KEY_INFO => evdev::Key::KEY_INFO,
+            crate::Key::KEY_TIME => evdev::Key::KEY_TIME,
+            crate::Key::KEY_VENDOR => evdev::Key::KEY_VENDOR,
+            crate::Key::KEY_ARCHIVE => evdev::Key::KEY_ARCHIVE,
+            crate::Key::KEY_PROGRAM => evdev::Key::KEY_PROGRAM,
+            crate::Key::KEY_CHANNEL => evdev::Key::KEY_CHANNEL,
+            crate::Key::KEY_FAVORITES => evdev::Key::KEY_FAVORITES,
+            crate::Key::KEY_EPG => evdev::Key::KEY_EPG,
+            crate::Key::KEY_PVR => evdev::Key::KEY_PVR,
+            crate::Key::KEY_MHP => evdev::Key::KEY_MHP,
+            crate::Key::KEY_LANGUAGE => evdev::Key::KEY_LANGUAGE,
+            crate::Key::KEY_TITLE => evdev::Key::KEY_TITLE,
+            crate::Key::KEY_SUBTITLE => evdev::Key::KEY_SUBTITLE,
+            crate::Key::KEY_ANGLE => evdev::Key::KEY_ANGLE,
+            crate::Key::KEY_ZOOM => evdev::Key::KEY_ZOOM,
+            crate::Key::KEY_MODE => evdev::Key::KEY_MODE,
+            crate::Key::KEY_KEYBOARD => evdev::Key::KEY_KEYBOARD,
+            crate::Key::KEY_SCREEN => evdev::Key::KEY_SCREEN,
+            crate::Key::KEY_PC => evdev::Key::KEY_PC,
+            crate::Key::KEY_TV => evdev::Key::KEY_TV,
+            crate::Key::KEY_TV2 => evdev::Key::KEY_TV2,
+            crate::Key::KEY_VCR => evdev::Key::KEY_VCR,
+            crate::Key::KEY_VCR2 => evdev::Key::KEY_VCR2,
+            crate::Key::KEY_SAT => evdev::Key::KEY_SAT,
+            crate::Key::KEY_SAT2 => evdev::Key::KEY_SAT2,
+            crate::Key::KEY_CD => evdev::Key::KEY_CD,
+            crate::Key::KEY_TAPE => evdev::Key::KEY_TAPE,
+            crate::Key::KEY_RADIO => evdev::Key::KEY_RADIO,
+            crate::Key::KEY_TUNER => evdev::Key::KEY_TUNER,
+            crate::Key::KEY_PLAYER => evdev::Key::KEY_PLAYER,
+            crate::Key::KEY_TEXT => evdev::Key::KEY_TEXT,
+            crate::Key::KEY_DVD => evdev::Key::KEY_DVD,
+            crate::Key::KEY_AUX => evdev::Key::KEY_AUX,
+            crate::Key::KEY_MP3 => evdev::Key::KEY_MP3,
+            crate::Key::KEY_AUDIO => evdev::Key::KEY_AUDIO,
+            crate::Key::KEY_VIDEO => evdev::Key::KEY_VIDEO,
+            crate::Key::KEY_DIRECTORY => evdev::Key::KEY_DIRECTORY,
+            crate::Key::KEY_LIST => evdev::Key::KEY_LIST,
+            crate::Key::KEY_MEMO => evdev::Key::KEY_MEMO,
+            crate::Key::KEY_CALENDAR => evdev::Key::KEY_CALENDAR,
+            crate::Key::KEY_RED => evdev::Key::KEY_RED,
+            crate::Key::KEY_GREEN => evdev::Key::KEY_GREEN,
+            crate::Key::KEY_YELLOW => evdev::Key::KEY_YELLOW,
+            crate::Key::KEY_BLUE => evdev::Key::KEY_BLUE,
+            crate::Key::KEY_CHANNELUP => evdev::Key::KEY_CHANNELUP,
+            crate::Key::KEY_CHANNELDOWN => evdev::Key::KEY_CHANNELDOWN,
+            crate::Key::KEY_FIRST => evdev::Key::KEY_FIRST,
+            crate::Key::KEY_LAST => evdev::Key::KEY_LAST,
+            crate::Key::KEY_AB => evdev::Key::KEY_AB,
+            crate::Key::KEY_NEXT => evdev::Key::KEY_NEXT,
+            crate::Key::KEY_RESTART => evdev::Key::KEY_RESTART,
+            crate::Key::KEY_SLOW => evdev::Key::KEY_SLOW,
+            crate::Key::KEY_SHUFFLE => evdev::Key::KEY_SHUFFLE,
+            crate::Key::KEY_BREAK => evdev::Key::KEY_BREAK,
+            crate::Key::KEY_PREVIOUS => evdev::Key::KEY_PREVIOUS,
+            crate::Key::KEY_DIGITS => evdev::Key::KEY_DIGITS,
+            crate::Key::KEY_TEEN => evdev::Key::KEY_TEEN,
+            crate::Key::KEY_TWEN => evdev::Key::KEY_TWEN,
+            crate::Key::KEY_VIDEOPHONE => evdev::Key::KEY_VIDEOPHONE,
+            crate::Key::KEY_GAMES => evdev::Key::KEY_GAMES,
+            crate::Key::KEY_ZOOMIN => evdev::Key::KEY_ZOOMIN,
+            crate::Key::KEY_ZOOMOUT => evdev::Key::KEY_ZOOMOUT,
+            crate::Key::KEY_ZOOMRESET => evdev::Key::KEY_ZOOMRESET,
+            crate::Key::KEY_WORDPROCESSOR => evdev::Key::KEY_WORDPROCESSOR,
+            crate::Key::KEY_EDITOR => evdev::Key::KEY_EDITOR,
+            crate::Key::KEY_SPREADSHEET => evdev::Key::KEY_SPREADSHEET,
+            crate::Key::KEY_GRAPHICSEDITOR => evdev::Key::KEY_GRAPHICSEDITOR,
+            crate::Key::KEY_PRESENTATION => evdev::Key::KEY_PRESENTATION,
+            crate::Key::KEY_DATABASE => evdev::Key::KEY_DATABASE,
+            crate::Key::KEY_NEWS => evdev::Key::KEY_NEWS,
+            crate::Key::KEY_VOICEMAIL => evdev::Key::KEY_VOICEMAIL,
+            crate::Key::KEY_ADDRESSBOOK => evdev::Key::KEY_ADDRESSBOOK,
+            crate::Key::KEY_MESSENGER => evdev::Key::KEY_MESSENGER,
+            crate::Key::KEY_DISPLAYTOGGLE => evdev::Key::KEY_DISPLAYTOGGLE,
+            crate::Key::KEY_SPELLCHECK => evdev::Key::KEY_SPELLCHECK,
+            crate::Key::KEY_LOGOFF => evdev::Key::KEY_LOGOFF,
+            crate::Key::KEY_DOLLAR => evdev::Key::KEY_DOLLAR,
+            crate::Key::KEY_EURO => evdev::Key::KEY_EURO,
+            crate::Key::KEY_FRAMEBACK => evdev::Key::KEY_FRAMEBACK,
+            crate::Key::KEY_FRAMEFORWARD => evdev::Key::KEY_FRAMEFORWARD,
+            crate::Key::KEY_CONTEXT_MENU => evdev::Key::KEY_CONTEXT_MENU,
+            crate::Key::KEY_MEDIA_REPEAT => evdev::Key::KEY_MEDIA_REPEAT,
+            crate::Key::KEY_10CHANNELSUP => evdev::Key::KEY_10CHANNELSUP,
+            crate::Key::KEY_10CHANNELSDOWN => evdev::Key::KEY_10CHANNELSDOWN,
+            crate::Key::KEY_IMAGES => evdev::Key::KEY_IMAGES,
+            crate::Key::KEY_DEL_EOL => evdev::Key::KEY_DEL_EOL,
+            crate::Key::KEY_DEL_EOS => evdev::Key::KEY_DEL_EOS,
+            crate::Key::KEY_INS_LINE => evdev::Key::KEY_INS_LINE,
+            crate::Key::KEY_DEL_LINE => evdev::Key::KEY_DEL_LINE,
+            crate::Key::KEY_FN => evdev::Key::KEY_FN,
+            crate::Key::KEY_FN_ESC => evdev::Key::KEY_FN_ESC,
+            crate::Key::KEY_FN_F1 => evdev::Key::KEY_FN_F1,
+            crate::Key::KEY_FN_F2 => evdev::Key::KEY_FN_F2,
+            crate::Key::KEY_FN_F3 => evdev::Key::KEY_FN_F3,
+            crate::Key::KEY_FN_F4 => evdev::Key::KEY_FN_F4,
+            crate::Key::KEY_FN_F5 => evdev::Key::KEY_FN_F5,
+            crate::Key::KEY_FN_F6 => evdev::Key::KEY_FN_F6,
+            crate::Key::KEY_FN_F7 => evdev::Key::KEY_FN_F7,
+            crate::Key::KEY_FN_F8 => evdev::Key::KEY_FN_F8,
+            crate::Key::KEY_FN_F9 => evdev::Key::KEY_FN_F9,
+            crate::Key::KEY_FN_F10 => evdev::Key::KEY_FN_F10,
+            crate::Key::KEY_FN_F11 => evdev::Key::KEY_FN_F11,
+            crate::Key::KEY_FN_F12 => evdev::Key::KEY_FN_F12,
+            crate::Key::KEY_FN_1 => evdev::Key::KEY_FN_1,
+            crate::Key::KEY_FN_2 => evdev::Key::KEY_FN_2,
+            crate::Key::KEY_FN_D => evdev::Key::KEY_FN_D,
+            crate::Key::KEY_FN_E => evdev::Key::KEY_FN_E,
+            crate::Key::KEY_FN_F => evdev::Key::KEY_FN_F,
+            crate::Key::KEY_FN_S => evdev::Key::KEY_FN_S,
+            crate::Key::KEY_FN_B => evdev::Key::KEY_FN_B,
+            crate::Key::KEY_BRL_DOT1 => evdev::Key::KEY_BRL_DOT1,
+            crate::Key::KEY_BRL_DOT2 => evdev::Key::KEY_BRL_DOT2,
+            crate::Key::KEY_BRL_DOT3 => evdev::Key::KEY_BRL_DOT3,
+            crate::Key::KEY_BRL_DOT4 => evdev::Key::KEY_BRL_DOT4,
+            crate::Key::KEY_BRL_DOT5 => evdev::Key::KEY_BRL_DOT5,
+            crate::Key::KEY_BRL_DOT6 => evdev::Key::KEY_BRL_DOT6,
+            crate::Key::KEY_BRL_DOT7 => evdev::Key::KEY_BRL_DOT7,
+            crate::Key::KEY_BRL_DOT8 => evdev::Key::KEY_BRL_DOT8,
+            crate::Key::KEY_BRL_DOT9 => evdev::Key::KEY_BRL_DOT9,
+            crate::Key::KEY_BRL_DOT10 => evdev::Key::KEY_BRL_DOT10,
+            crate::Key::KEY_NUMERIC_0 => evdev::Key::KEY_NUMERIC_0,
+            crate::Key::KEY_NUMERIC_1 => evdev::Key::KEY_NUMERIC_1,
+            crate::Key::KEY_NUMERIC_2 => evdev::Key::KEY_NUMERIC_2,
+            crate::Key::KEY_NUMERIC_3 => evdev::Key::KEY_NUMERIC_3,
+            crate::Key::KEY_NUMERIC_4 => evdev::Key::KEY_NUMERIC_4,
+            crate::Key::KEY_NUMERIC_5 => evdev::Key::KEY_NUMERIC_5,
+            crate::Key::KEY_NUMERIC_6 => evdev::Key::KEY_NUMERIC_6,
+            crate::Key::KEY_NUMERIC_7 => evdev::Key::KEY_NUMERIC_7,
+            crate::Key::KEY_NUMERIC_8 => evdev::Key::KEY_NUMERIC_8,
+            crate::Key::KEY_NUMERIC_9 => evdev::Key::KEY_NUMERIC_9,
+            crate::Key::KEY_NUMERIC_STAR => evdev::Key::KEY_NUMERIC_STAR,
+            crate::Key::KEY_NUMERIC_POUND => evdev::Key::KEY_NUMERIC_POUND,
+            crate::Key::KEY_NUMERIC_A => evdev::Key::KEY_NUMERIC_A,
+            crate::Key::KEY_NUMERIC_B => evdev::Key::KEY_NUMERIC_B,
+            crate::Key::KEY_NUMERIC_C => evdev::Key::KEY_NUMERIC_C,
+            crate::Key::KEY_NUMERIC_D => evdev::Key::KEY_NUMERIC_D,
+            crate::Key::KEY_CAMERA_FOCUS => evdev::Key::KEY_CAMERA_FOCUS,
+            crate::Key::KEY_WPS_BUTTON => evdev::Key::KEY_WPS_BUTTON,
+            crate::Key::KEY_TOUCHPAD_TOGGLE => evdev::Key::KEY_TOUCHPAD_TOGGLE,
+            crate::Key::KEY_TOUCHPAD_ON => evdev::Key::KEY_TOUCHPAD_ON,
+            crate::Key::KEY_TOUCHPAD_OFF => evdev::Key::KEY_TOUCHPAD_OFF,
+            crate::Key::KEY_CAMERA_ZOOMIN => evdev::Key::KEY_CAMERA_ZOOMIN,
+            crate::Key::KEY_CAMERA_ZOOMOUT => evdev::Key::KEY_CAMERA_ZOOMOUT,
+            crate::Key::KEY_CAMERA_UP => evdev::Key::KEY_CAMERA_UP,
+            crate::Key::KEY_CAMERA_DOWN => evdev::Key::KEY_CAMERA_DOWN,
+            crate::Key::KEY_CAMERA_LEFT => evdev::Key::KEY_CAMERA_LEFT,
+            crate::Key::KEY_CAMERA_RIGHT => evdev::Key::KEY_CAMERA_RIGHT,
+            crate::Key::KEY_ATTENDANT_ON => evdev::Key::KEY_ATTENDANT_ON,
+            crate::Key::KEY_ATTENDANT_OFF => evdev::Key::KEY_ATTENDANT_OFF,
+            crate::Key::KEY_ATTENDANT_TOGGLE => evdev::Key::KEY_ATTENDANT_TOGGLE,
+            crate::Key::KEY_LIGHTS_TOGGLE => evdev::Key::KEY_LIGHTS_TOGGLE,
+            crate::Key::KEY_ALS_TOGGLE => evdev::Key::KEY_ALS_TOGGLE,
+            crate::Key::KEY_BUTTONCONFIG => evdev::Key::KEY_BUTTONCONFIG,
+            crate::Key::KEY_TASKMANAGER => evdev::Key::KEY_TASKMANAGER,
+            crate::Key::KEY_JOURNAL => evdev::Key::KEY_JOURNAL,
+            crate::Key::KEY_CONTROLPANEL => evdev::Key::KEY_CONTROLPANEL,
+            crate::Key::KEY_APPSELECT => evdev::Key::KEY_APPSELECT,
+            crate::Key::KEY_SCREENSAVER => evdev::Key::KEY_SCREENSAVER,
+            crate::Key::KEY_VOICECOMMAND => evdev::Key::KEY_VOICECOMMAND,
+            crate::Key::KEY_ASSISTANT => evdev::Key::KEY_ASSISTANT,
+            crate::Key::KEY_KBD_LAYOUT_NEXT => evdev::Key::KEY_KBD_LAYOUT_NEXT,
+            crate::Key::KEY_BRIGHTNESS_MIN => evdev::Key::KEY_BRIGHTNESS_MIN,
+            crate::Key::KEY_BRIGHTNESS_MAX => evdev::Key::KEY_BRIGHTNESS_MAX,
+            crate::Key::KEY_KBDINPUTASSIST_PREV => evdev::Key::KEY_KBDINPUTASSIST_PREV,
+            crate::Key::KEY_KBDINPUTASSIST_NEXT => evdev::Key::KEY_KBDINPUTASSIST_NEXT,
+            crate::Key::KEY_KBDINPUTASSIST_PREVGROUP => evdev::Key::KEY_KBDINPUTASSIST_PREVGROUP,
+            crate::Key::KEY_KBDINPUTASSIST_NEXTGROUP => evdev::Key::KEY_KBDINPUTASSIST_NEXTGROUP,
+            crate::Key::KEY_KBDINPUTASSIST_ACCEPT => evdev::Key::KEY_KBDINPUTASSIST_ACCEPT,
+            crate::Key::KEY_KBDINPUTASSIST_CANCEL => evdev::Key::KEY_KBDINPUTASSIST_CANCEL,
+            crate::Key::KEY_RIGHT_UP => evdev::Key::KEY_RIGHT_UP,
+            crate::Key::KEY_RIGHT_DOWN => evdev::Key::KEY_RIGHT_DOWN,
+            crate::Key::KEY_LEFT_UP => evdev::Key::KEY_LEFT_UP,
+            crate::Key::KEY_LEFT_DOWN => evdev::Key::KEY_LEFT_DOWN,
+            crate::Key::KEY_ROOT_MENU => evdev::Key::KEY_ROOT_MENU,
+            crate::Key::KEY_MEDIA_TOP_MENU => evdev::Key::KEY_MEDIA_TOP_MENU,
+            crate::Key::KEY_NUMERIC_11 => evdev::Key::KEY_NUMERIC_11,
+            crate::Key::KEY_NUMERIC_12 => evdev::Key::KEY_NUMERIC_12,
+            crate::Key::KEY_AUDIO_DESC => evdev::Key::KEY_AUDIO_DESC,
+            crate::Key::KEY_3D_MODE => evdev::Key::KEY_3D_MODE,
+            crate::Key::KEY_NEXT_FAVORITE => evdev::Key::KEY_NEXT_FAVORITE,
+            crate::Key::KEY_STOP_RECORD => evdev::Key::KEY_STOP_RECORD,
+            crate::Key::KEY_PAUSE_RECORD => evdev::Key::KEY_PAUSE_RECORD,
+            crate::Key::KEY_VOD => evdev::Key::KEY_VOD,
+            crate::Key::KEY_UNMUTE => evdev::Key::KEY_UNMUTE,
+            crate::Key::KEY_FASTREVERSE => evdev::Key::KEY_FASTREVERSE,
+            crate::Key::KEY_SLOWREVERSE => evdev::Key::KEY_SLOWREVERSE,
+            crate::Key::KEY_DATA => evdev::Key::KEY_DATA,
+            crate::Key::KEY_ONSCREEN_KEYBOARD => evdev::Key::KEY_ONSCREEN_KEYBOARD,
+            crate::Key::KEY_PRIVACY_SCREEN_TOGGLE => evdev::Key::KEY_PRIVACY_SCREEN_TOGGLE,
+            crate::Key::KEY_SELECTIVE_SCREENSHOT => evdev::Key::KEY_SELECTIVE_SCREENSHOT,
+            crate::Key::Raw(code) => evdev::Key::new(code),
         }
     }
 }
@@ -223,11 +759,30 @@ impl From<crate::Key> for evdev::Key {
 impl From<crate::Button> for evdev::Key {
     fn from(value: crate::Button) -> Self {
         match value {
+            crate::Button::BTN_0 => evdev::Key::BTN_0,
+            crate::Button::BTN_1 => evdev::Key::BTN_1,
+            crate::Button::BTN_2 => evdev::Key::BTN_2,
+            crate::Button::BTN_3 => evdev::Key::BTN_3,
+            crate::Button::BTN_4 => evdev::Key::BTN_4,
+            crate::Button::BTN_5 => evdev::Key::BTN_5,
+            crate::Button::BTN_6 => evdev::Key::BTN_6,
+            crate::Button::BTN_7 => evdev::Key::BTN_7,
+            crate::Button::BTN_8 => evdev::Key::BTN_8,
+            crate::Button::BTN_9 => evdev::Key::BTN_9,
             crate::Button::BTN_LEFT => evdev::Key::BTN_LEFT,
             crate::Button::BTN_RIGHT => evdev::Key::BTN_RIGHT,
             crate::Button::BTN_MIDDLE => evdev::Key::BTN_MIDDLE,
+            crate::Button::BTN_SIDE => evdev::Key::BTN_SIDE,
+            crate::Button::BTN_EXTRA => evdev::Key::BTN_EXTRA,
             crate::Button::BTN_FORWARD => evdev::Key::BTN_FORWARD,
             crate::Button::BTN_BACK => evdev::Key::BTN_BACK,
+            crate::Button::BTN_TASK => evdev::Key::BTN_TASK,
+            crate::Button::BTN_TOOL_PEN => evdev::Key::BTN_TOOL_PEN,
+            crate::Button::BTN_TOOL_RUBBER => evdev::Key::BTN_TOOL_RUBBER,
+            crate::Button::BTN_TOUCH => evdev::Key::BTN_TOUCH,
+            crate::Button::BTN_STYLUS => evdev::Key::BTN_STYLUS,
+            crate::Button::BTN_STYLUS2 => evdev::Key::BTN_STYLUS2,
+            crate::Button::Raw(code) => evdev::Key::new(code),
         }
     }
 }
@@ -320,8 +875,23 @@ impl TryFrom<evdev::Key> for crate::Key {
             evdev::Key::KEY_KP3 => crate::Key::KEY_KP3,
             evdev::Key::KEY_KP0 => crate::Key::KEY_KP0,
             evdev::Key::KEY_KPDOT => crate::Key::KEY_KPDOT,
+            evdev::Key::KEY_ZENKAKUHANKAKU => crate::Key::KEY_ZENKAKUHANKAKU,
+            evdev::Key::KEY_102ND => crate::Key::KEY_102ND,
             evdev::Key::KEY_F11 => crate::Key::KEY_F11,
             evdev::Key::KEY_F12 => crate::Key::KEY_F12,
+            evdev::Key::KEY_RO => crate::Key::KEY_RO,
+            evdev::Key::KEY_KATAKANA => crate::Key::KEY_KATAKANA,
+            evdev::Key::KEY_HIRAGANA => crate::Key::KEY_HIRAGANA,
+            evdev::Key::KEY_HENKAN => crate::Key::KEY_HENKAN,
+            evdev::Key::KEY_KATAKANAHIRAGANA => crate::Key::KEY_KATAKANAHIRAGANA,
+            evdev::Key::KEY_MUHENKAN => crate::Key::KEY_MUHENKAN,
+            evdev::Key::KEY_KPJPCOMMA => crate::Key::KEY_KPJPCOMMA,
+            evdev::Key::KEY_KPENTER => crate::Key::KEY_KPENTER,
+            evdev::Key::KEY_RIGHTCTRL => crate::Key::KEY_RIGHTCTRL,
+            evdev::Key::KEY_KPSLASH => crate::Key::KEY_KPSLASH,
+            evdev::Key::KEY_SYSRQ => crate::Key::KEY_SYSRQ,
+            evdev::Key::KEY_RIGHTALT => crate::Key::KEY_RIGHTALT,
+            evdev::Key::KEY_LINEFEED => crate::Key::KEY_LINEFEED,
             evdev::Key::KEY_HOME => crate::Key::KEY_HOME,
             evdev::Key::KEY_UP => crate::Key::KEY_UP,
             evdev::Key::KEY_PAGEUP => crate::Key::KEY_PAGEUP,
@@ -332,12 +902,336 @@ impl TryFrom<evdev::Key> for crate::Key {
             evdev::Key::KEY_PAGEDOWN => crate::Key::KEY_PAGEDOWN,
             evdev::Key::KEY_INSERT => crate::Key::KEY_INSERT,
             evdev::Key::KEY_DELETE => crate::Key::KEY_DELETE,
+            evdev::Key::KEY_MACRO => crate::Key::KEY_MACRO,
+            evdev::Key::KEY_MUTE => crate::Key::KEY_MUTE,
+            evdev::Key::KEY_VOLUMEDOWN => crate::Key::KEY_VOLUMEDOWN,
+            evdev::Key::KEY_VOLUMEUP => crate::Key::KEY_VOLUMEUP,
+            evdev::Key::KEY_POWER => crate::Key::KEY_POWER,
+            evdev::Key::KEY_KPEQUAL => crate::Key::KEY_KPEQUAL,
+            evdev::Key::KEY_KPPLUSMINUS => crate::Key::KEY_KPPLUSMINUS,
+            evdev::Key::KEY_PAUSE => crate::Key::KEY_PAUSE,
+            evdev::Key::KEY_SCALE => crate::Key::KEY_SCALE,
+            evdev::Key::KEY_KPCOMMA => crate::Key::KEY_KPCOMMA,
+            evdev::Key::KEY_HANGEUL => crate::Key::KEY_HANGEUL,
+            evdev::Key::KEY_HANJA => crate::Key::KEY_HANJA,
+            evdev::Key::KEY_YEN => crate::Key::KEY_YEN,
             evdev::Key::KEY_LEFTMETA => crate::Key::KEY_LEFTMETA,
             evdev::Key::KEY_RIGHTMETA => crate::Key::KEY_RIGHTMETA,
+            evdev::Key::KEY_COMPOSE => crate::Key::KEY_COMPOSE,
+            evdev::Key::KEY_STOP => crate::Key::KEY_STOP,
+            evdev::Key::KEY_AGAIN => crate::Key::KEY_AGAIN,
+            evdev::Key::KEY_PROPS => crate::Key::KEY_PROPS,
+            evdev::Key::KEY_UNDO => crate::Key::KEY_UNDO,
+            evdev::Key::KEY_FRONT => crate::Key::KEY_FRONT,
+            evdev::Key::KEY_COPY => crate::Key::KEY_COPY,
+            evdev::Key::KEY_OPEN => crate::Key::KEY_OPEN,
+            evdev::Key::KEY_PASTE => crate::Key::KEY_PASTE,
+            evdev::Key::KEY_FIND => crate::Key::KEY_FIND,
+            evdev::Key::KEY_CUT => crate::Key::KEY_CUT,
+            evdev::Key::KEY_HELP => crate::Key::KEY_HELP,
             evdev::Key::KEY_MENU => crate::Key::KEY_MENU,
-            _ => {
-                return Err(EventMappingError::UnknownKey);
-            }
+            evdev::Key::KEY_CALC => crate::Key::KEY_CALC,
+            evdev::Key::KEY_SETUP => crate::Key::KEY_SETUP,
+            evdev::Key::KEY_SLEEP => crate::Key::KEY_SLEEP,
+            evdev::Key::KEY_WAKEUP => crate::Key::KEY_WAKEUP,
+            evdev::Key::KEY_FILE => crate::Key::KEY_FILE,
+            evdev::Key::KEY_SENDFILE => crate::Key::KEY_SENDFILE,
+            evdev::Key::KEY_DELETEFILE => crate::Key::KEY_DELETEFILE,
+            evdev::Key::KEY_XFER => crate::Key::KEY_XFER,
+            evdev::Key::KEY_PROG1 => crate::Key::KEY_PROG1,
+            evdev::Key::KEY_PROG2 => crate::Key::KEY_PROG2,
+            evdev::Key::KEY_WWW => crate::Key::KEY_WWW,
+            evdev::Key::KEY_MSDOS => crate::Key::KEY_MSDOS,
+            evdev::Key::KEY_COFFEE => crate::Key::KEY_COFFEE,
+            evdev::Key::KEY_DIRECTION => crate::Key::KEY_DIRECTION,
+            evdev::Key::KEY_CYCLEWINDOWS => crate::Key::KEY_CYCLEWINDOWS,
+            evdev::Key::KEY_MAIL => crate::Key::KEY_MAIL,
+            evdev::Key::KEY_BOOKMARKS => crate::Key::KEY_BOOKMARKS,
+            evdev::Key::KEY_COMPUTER => crate::Key::KEY_COMPUTER,
+            evdev::Key::KEY_BACK => crate::Key::KEY_BACK,
+            evdev::Key::KEY_FORWARD => crate::Key::KEY_FORWARD,
+            evdev::Key::KEY_CLOSECD => crate::Key::KEY_CLOSECD,
+            evdev::Key::KEY_EJECTCD => crate::Key::KEY_EJECTCD,
+            evdev::Key::KEY_EJECTCLOSECD => crate::Key::KEY_EJECTCLOSECD,
+            evdev::Key::KEY_NEXTSONG => crate::Key::KEY_NEXTSONG,
+            evdev::Key::KEY_PLAYPAUSE => crate::Key::KEY_PLAYPAUSE,
+            evdev::Key::KEY_PREVIOUSSONG => crate::Key::KEY_PREVIOUSSONG,
+            evdev::Key::KEY_STOPCD => crate::Key::KEY_STOPCD,
+            evdev::Key::KEY_RECORD => crate::Key::KEY_RECORD,
+            evdev::Key::KEY_REWIND => crate::Key::KEY_REWIND,
+            evdev::Key::KEY_PHONE => crate::Key::KEY_PHONE,
+            evdev::Key::KEY_ISO => crate::Key::KEY_ISO,
+            evdev::Key::KEY_CONFIG => crate::Key::KEY_CONFIG,
+            evdev::Key::KEY_HOMEPAGE => crate::Key::KEY_HOMEPAGE,
+            evdev::Key::KEY_REFRESH => crate::Key::KEY_REFRESH,
+            evdev::Key::KEY_EXIT => crate::Key::KEY_EXIT,
+            evdev::Key::KEY_MOVE => crate::Key::KEY_MOVE,
+            evdev::Key::KEY_EDIT => crate::Key::KEY_EDIT,
+            evdev::Key::KEY_SCROLLUP => crate::Key::KEY_SCROLLUP,
+            evdev::Key::KEY_SCROLLDOWN => crate::Key::KEY_SCROLLDOWN,
+            evdev::Key::KEY_KPLEFTPAREN => crate::Key::KEY_KPLEFTPAREN,
+            evdev::Key::KEY_KPRIGHTPAREN => crate::Key::KEY_KPRIGHTPAREN,
+            evdev::Key::KEY_NEW => crate::Key::KEY_NEW,
+            evdev::Key::KEY_REDO => crate::Key::KEY_REDO,
+            evdev::Key::KEY_F13 => crate::Key::KEY_F13,
+            evdev::Key::KEY_F14 => crate::Key::KEY_F14,
+            evdev::Key::KEY_F15 => crate::Key::KEY_F15,
+            evdev::Key::KEY_F16 => crate::Key::KEY_F16,
+            evdev::Key::KEY_F17 => crate::Key::KEY_F17,
+            evdev::Key::KEY_F18 => crate::Key::KEY_F18,
+            evdev::Key::KEY_F19 => crate::Key::KEY_F19,
+            evdev::Key::KEY_F20 => crate::Key::KEY_F20,
+            evdev::Key::KEY_F21 => crate::Key::KEY_F21,
+            evdev::Key::KEY_F22 => crate::Key::KEY_F22,
+            evdev::Key::KEY_F23 => crate::Key::KEY_F23,
+            evdev::Key::KEY_F24 => crate::Key::KEY_F24,
+            evdev::Key::KEY_PLAYCD => crate::Key::KEY_PLAYCD,
+            evdev::Key::KEY_PAUSECD => crate::Key::KEY_PAUSECD,
+            evdev::Key::KEY_PROG3 => crate::Key::KEY_PROG3,
+            evdev::Key::KEY_PROG4 => crate::Key::KEY_PROG4,
+            evdev::Key::KEY_DASHBOARD => crate::Key::KEY_DASHBOARD,
+            evdev::Key::KEY_SUSPEND => crate::Key::KEY_SUSPEND,
+            evdev::Key::KEY_CLOSE => crate::Key::KEY_CLOSE,
+            evdev::Key::KEY_PLAY => crate::Key::KEY_PLAY,
+            evdev::Key::KEY_FASTFORWARD => crate::Key::KEY_FASTFORWARD,
+            evdev::Key::KEY_BASSBOOST => crate::Key::KEY_BASSBOOST,
+            evdev::Key::KEY_PRINT => crate::Key::KEY_PRINT,
+            evdev::Key::KEY_HP => crate::Key::KEY_HP,
+            evdev::Key::KEY_CAMERA => crate::Key::KEY_CAMERA,
+            evdev::Key::KEY_SOUND => crate::Key::KEY_SOUND,
+            evdev::Key::KEY_QUESTION => crate::Key::KEY_QUESTION,
+            evdev::Key::KEY_EMAIL => crate::Key::KEY_EMAIL,
+            evdev::Key::KEY_CHAT => crate::Key::KEY_CHAT,
+            evdev::Key::KEY_SEARCH => crate::Key::KEY_SEARCH,
+            evdev::Key::KEY_CONNECT => crate::Key::KEY_CONNECT,
+            evdev::Key::KEY_FINANCE => crate::Key::KEY_FINANCE,
+            evdev::Key::KEY_SPORT => crate::Key::KEY_SPORT,
+            evdev::Key::KEY_SHOP => crate::Key::KEY_SHOP,
+            evdev::Key::KEY_ALTERASE => crate::Key::KEY_ALTERASE,
+            evdev::Key::KEY_CANCEL => crate::Key::KEY_CANCEL,
+            evdev::Key::KEY_BRIGHTNESSDOWN => crate::Key::KEY_BRIGHTNESSDOWN,
+            evdev::Key::KEY_BRIGHTNESSUP => crate::Key::KEY_BRIGHTNESSUP,
+            evdev::Key::KEY_MEDIA => crate::Key::KEY_MEDIA,
+            evdev::Key::KEY_SWITCHVIDEOMODE => crate::Key::KEY_SWITCHVIDEOMODE,
+            evdev::Key::KEY_KBDILLUMTOGGLE => crate::Key::KEY_KBDILLUMTOGGLE,
+            evdev::Key::KEY_KBDILLUMDOWN => crate::Key::KEY_KBDILLUMDOWN,
+            evdev::Key::KEY_KBDILLUMUP => crate::Key::KEY_KBDILLUMUP,
+            evdev::Key::KEY_SEND => crate::Key::KEY_SEND,
+            evdev::Key::KEY_REPLY => crate::Key::KEY_REPLY,
+            evdev::Key::KEY_FORWARDMAIL => crate::Key::KEY_FORWARDMAIL,
+            evdev::Key::KEY_SAVE => crate::Key::KEY_SAVE,
+            evdev::Key::KEY_DOCUMENTS => crate::Key::KEY_DOCUMENTS,
+            evdev::Key::KEY_BATTERY => crate::Key::KEY_BATTERY,
+            evdev::Key::KEY_BLUETOOTH => crate::Key::KEY_BLUETOOTH,
+            evdev::Key::KEY_WLAN => crate::Key::KEY_WLAN,
+            evdev::Key::KEY_UWB => crate::Key::KEY_UWB,
+            evdev::Key::KEY_UNKNOWN => crate::Key::KEY_UNKNOWN,
+            evdev::Key::KEY_VIDEO_NEXT => crate::Key::KEY_VIDEO_NEXT,
+            evdev::Key::KEY_VIDEO_PREV => crate::Key::KEY_VIDEO_PREV,
+            evdev::Key::KEY_BRIGHTNESS_CYCLE => crate::Key::KEY_BRIGHTNESS_CYCLE,
+            evdev::Key::KEY_BRIGHTNESS_AUTO => crate::Key::KEY_BRIGHTNESS_AUTO,
+            evdev::Key::KEY_DISPLAY_OFF => crate::Key::KEY_DISPLAY_OFF,
+            evdev::Key::KEY_WWAN => crate::Key::KEY_WWAN,
+            evdev::Key::KEY_RFKILL => crate::Key::KEY_RFKILL,
+            evdev::Key::KEY_MICMUTE => crate::Key::KEY_MICMUTE,
+            evdev::Key::KEY_OK => crate::Key::KEY_OK,
+            evdev::Key::KEY_SELECT => crate::Key::KEY_SELECT,
+            evdev::Key::KEY_GOTO => crate::Key::KEY_GOTO,
+            evdev::Key::KEY_CLEAR => crate::Key::KEY_CLEAR,
+            evdev::Key::KEY_POWER2 => crate::Key::KEY_POWER2,
+            evdev::Key::KEY_OPTION => crate::Key::KEY_OPTION,
+            evdev::Key::KEY_INFO => crate::Key::KEY_INFO,
+            evdev::Key::KEY_TIME => crate::Key::KEY_TIME,
+            evdev::Key::KEY_VENDOR => crate::Key::KEY_VENDOR,
+            evdev::Key::KEY_ARCHIVE => crate::Key::KEY_ARCHIVE,
+            evdev::Key::KEY_PROGRAM => crate::Key::KEY_PROGRAM,
+            evdev::Key::KEY_CHANNEL => crate::Key::KEY_CHANNEL,
+            evdev::Key::KEY_FAVORITES => crate::Key::KEY_FAVORITES,
+            evdev::Key::KEY_EPG => crate::Key::KEY_EPG,
+            evdev::Key::KEY_PVR => crate::Key::KEY_PVR,
+            evdev::Key::KEY_MHP => crate::Key::KEY_MHP,
+            evdev::Key::KEY_LANGUAGE => crate::Key::KEY_LANGUAGE,
+            evdev::Key::KEY_TITLE => crate::Key::KEY_TITLE,
+            evdev::Key::KEY_SUBTITLE => crate::Key::KEY_SUBTITLE,
+            evdev::Key::KEY_ANGLE => crate::Key::KEY_ANGLE,
+            evdev::Key::KEY_ZOOM => crate::Key::KEY_ZOOM,
+            evdev::Key::KEY_MODE => crate::Key::KEY_MODE,
+            evdev::Key::KEY_KEYBOARD => crate::Key::KEY_KEYBOARD,
+            evdev::Key::KEY_SCREEN => crate::Key::KEY_SCREEN,
+            evdev::Key::KEY_PC => crate::Key::KEY_PC,
+            evdev::Key::KEY_TV => crate::Key::KEY_TV,
+            evdev::Key::KEY_TV2 => crate::Key::KEY_TV2,
+            evdev::Key::KEY_VCR => crate::Key::KEY_VCR,
+            evdev::Key::KEY_VCR2 => crate::Key::KEY_VCR2,
+            evdev::Key::KEY_SAT => crate::Key::KEY_SAT,
+            evdev::Key::KEY_SAT2 => crate::Key::KEY_SAT2,
+            evdev::Key::KEY_CD => crate::Key::KEY_CD,
+            evdev::Key::KEY_TAPE => crate::Key::KEY_TAPE,
+            evdev::Key::KEY_RADIO => crate::Key::KEY_RADIO,
+            evdev::Key::KEY_TUNER => crate::Key::KEY_TUNER,
+            evdev::Key::KEY_PLAYER => crate::Key::KEY_PLAYER,
+            evdev::Key::KEY_TEXT => crate::Key::KEY_TEXT,
+            evdev::Key::KEY_DVD => crate::Key::KEY_DVD,
+            evdev::Key::KEY_AUX => crate::Key::KEY_AUX,
+            evdev::Key::KEY_MP3 => crate::Key::KEY_MP3,
+            evdev::Key::KEY_AUDIO => crate::Key::KEY_AUDIO,
+            evdev::Key::KEY_VIDEO => crate::Key::KEY_VIDEO,
+            evdev::Key::KEY_DIRECTORY => crate::Key::KEY_DIRECTORY,
+            evdev::Key::KEY_LIST => crate::Key::KEY_LIST,
+            evdev::Key::KEY_MEMO => crate::Key::KEY_MEMO,
+            evdev::Key::KEY_CALENDAR => crate::Key::KEY_CALENDAR,
+            evdev::Key::KEY_RED => crate::Key::KEY_RED,
+            evdev::Key::KEY_GREEN => crate::Key::KEY_GREEN,
+            evdev::Key::KEY_YELLOW => crate::Key::KEY_YELLOW,
+            evdev::Key::KEY_BLUE => crate::Key::KEY_BLUE,
+            evdev::Key::KEY_CHANNELUP => crate::Key::KEY_CHANNELUP,
+            evdev::Key::KEY_CHANNELDOWN => crate::Key::KEY_CHANNELDOWN,
+            evdev::Key::KEY_FIRST => crate::Key::KEY_FIRST,
+            evdev::Key::KEY_LAST => crate::Key::KEY_LAST,
+            evdev::Key::KEY_AB => crate::Key::KEY_AB,
+            evdev::Key::KEY_NEXT => crate::Key::KEY_NEXT,
+            evdev::Key::KEY_RESTART => crate::Key::KEY_RESTART,
+            evdev::Key::KEY_SLOW => crate::Key::KEY_SLOW,
+            evdev::Key::KEY_SHUFFLE => crate::Key::KEY_SHUFFLE,
+            evdev::Key::KEY_BREAK => crate::Key::KEY_BREAK,
+            evdev::Key::KEY_PREVIOUS => crate::Key::KEY_PREVIOUS,
+            evdev::Key::KEY_DIGITS => crate::Key::KEY_DIGITS,
+            evdev::Key::KEY_TEEN => crate::Key::KEY_TEEN,
+            evdev::Key::KEY_TWEN => crate::Key::KEY_TWEN,
+            evdev::Key::KEY_VIDEOPHONE => crate::Key::KEY_VIDEOPHONE,
+            evdev::Key::KEY_GAMES => crate::Key::KEY_GAMES,
+            evdev::Key::KEY_ZOOMIN => crate::Key::KEY_ZOOMIN,
+            evdev::Key::KEY_ZOOMOUT => crate::Key::KEY_ZOOMOUT,
+            evdev::Key::KEY_ZOOMRESET => crate::Key::KEY_ZOOMRESET,
+            evdev::Key::KEY_WORDPROCESSOR => crate::Key::KEY_WORDPROCESSOR,
+            evdev::Key::KEY_EDITOR => crate::Key::KEY_EDITOR,
+            evdev::Key::KEY_SPREADSHEET => crate::Key::KEY_SPREADSHEET,
+            evdev::Key::KEY_GRAPHICSEDITOR => crate::Key::KEY_GRAPHICSEDITOR,
+            evdev::Key::KEY_PRESENTATION => crate::Key::KEY_PRESENTATION,
+            evdev::Key::KEY_DATABASE => crate::Key::KEY_DATABASE,
+            evdev::Key::KEY_NEWS => crate::Key::KEY_NEWS,
+            evdev::Key::KEY_VOICEMAIL => crate::Key::KEY_VOICEMAIL,
+            evdev::Key::KEY_ADDRESSBOOK => crate::Key::KEY_ADDRESSBOOK,
+            evdev::Key::KEY_MESSENGER => crate::Key::KEY_MESSENGER,
+            evdev::Key::KEY_DISPLAYTOGGLE => crate::Key::KEY_DISPLAYTOGGLE,
+            evdev::Key::KEY_SPELLCHECK => crate::Key::KEY_SPELLCHECK,
+            evdev::Key::KEY_LOGOFF => crate::Key::KEY_LOGOFF,
+            evdev::Key::KEY_DOLLAR => crate::Key::KEY_DOLLAR,
+            evdev::Key::KEY_EURO => crate::Key::KEY_EURO,
+            evdev::Key::KEY_FRAMEBACK => crate::Key::KEY_FRAMEBACK,
+            evdev::Key::KEY_FRAMEFORWARD => crate::Key::KEY_FRAMEFORWARD,
+            evdev::Key::KEY_CONTEXT_MENU => crate::Key::KEY_CONTEXT_MENU,
+            evdev::Key::KEY_MEDIA_REPEAT => crate::Key::KEY_MEDIA_REPEAT,
+            evdev::Key::KEY_10CHANNELSUP => crate::Key::KEY_10CHANNELSUP,
+            evdev::Key::KEY_10CHANNELSDOWN => crate::Key::KEY_10CHANNELSDOWN,
+            evdev::Key::KEY_IMAGES => crate::Key::KEY_IMAGES,
+            evdev::Key::KEY_DEL_EOL => crate::Key::KEY_DEL_EOL,
+            evdev::Key::KEY_DEL_EOS => crate::Key::KEY_DEL_EOS,
+            evdev::Key::KEY_INS_LINE => crate::Key::KEY_INS_LINE,
+            evdev::Key::KEY_DEL_LINE => crate::Key::KEY_DEL_LINE,
+            evdev::Key::KEY_FN => crate::Key::KEY_FN,
+            evdev::Key::KEY_FN_ESC => crate::Key::KEY_FN_ESC,
+            evdev::Key::KEY_FN_F1 => crate::Key::KEY_FN_F1,
+            evdev::Key::KEY_FN_F2 => crate::Key::KEY_FN_F2,
+            evdev::Key::KEY_FN_F3 => crate::Key::KEY_FN_F3,
+            evdev::Key::KEY_FN_F4 => crate::Key::KEY_FN_F4,
+            evdev::Key::KEY_FN_F5 => crate::Key::KEY_FN_F5,
+            evdev::Key::KEY_FN_F6 => crate::Key::KEY_FN_F6,
+            evdev::Key::KEY_FN_F7 => crate::Key::KEY_FN_F7,
+            evdev::Key::KEY_FN_F8 => crate::Key::KEY_FN_F8,
+            evdev::Key::KEY_FN_F9 => crate::Key::KEY_FN_F9,
+            evdev::Key::KEY_FN_F10 => crate::Key::KEY_FN_F10,
+            evdev::Key::KEY_FN_F11 => crate::Key::KEY_FN_F11,
+            evdev::Key::KEY_FN_F12 => crate::Key::KEY_FN_F12,
+            evdev::Key::KEY_FN_1 => crate::Key::KEY_FN_1,
+            evdev::Key::KEY_FN_2 => crate::Key::KEY_FN_2,
+            evdev::Key::KEY_FN_D => crate::Key::KEY_FN_D,
+            evdev::Key::KEY_FN_E => crate::Key::KEY_FN_E,
+            evdev::Key::KEY_FN_F => crate::Key::KEY_FN_F,
+            evdev::Key::KEY_FN_S => crate::Key::KEY_FN_S,
+            evdev::Key::KEY_FN_B => crate::Key::KEY_FN_B,
+            evdev::Key::KEY_BRL_DOT1 => crate::Key::KEY_BRL_DOT1,
+            evdev::Key::KEY_BRL_DOT2 => crate::Key::KEY_BRL_DOT2,
+            evdev::Key::KEY_BRL_DOT3 => crate::Key::KEY_BRL_DOT3,
+            evdev::Key::KEY_BRL_DOT4 => crate::Key::KEY_BRL_DOT4,
+            evdev::Key::KEY_BRL_DOT5 => crate::Key::KEY_BRL_DOT5,
+            evdev::Key::KEY_BRL_DOT6 => crate::Key::KEY_BRL_DOT6,
+            evdev::Key::KEY_BRL_DOT7 => crate::Key::KEY_BRL_DOT7,
+            evdev::Key::KEY_BRL_DOT8 => crate::Key::KEY_BRL_DOT8,
+            evdev::Key::KEY_BRL_DOT9 => crate::Key::KEY_BRL_DOT9,
+            evdev::Key::KEY_BRL_DOT10 => crate::Key::KEY_BRL_DOT10,
+            evdev::Key::KEY_NUMERIC_0 => crate::Key::KEY_NUMERIC_0,
+            evdev::Key::KEY_NUMERIC_1 => crate::Key::KEY_NUMERIC_1,
+            evdev::Key::KEY_NUMERIC_2 => crate::Key::KEY_NUMERIC_2,
+            evdev::Key::KEY_NUMERIC_3 => crate::Key::KEY_NUMERIC_3,
+            evdev::Key::KEY_NUMERIC_4 => crate::Key::KEY_NUMERIC_4,
+            evdev::Key::KEY_NUMERIC_5 => crate::Key::KEY_NUMERIC_5,
+            evdev::Key::KEY_NUMERIC_6 => crate::Key::KEY_NUMERIC_6,
+            evdev::Key::KEY_NUMERIC_7 => crate::Key::KEY_NUMERIC_7,
+            evdev::Key::KEY_NUMERIC_8 => crate::Key::KEY_NUMERIC_8,
+            evdev::Key::KEY_NUMERIC_9 => crate::Key::KEY_NUMERIC_9,
+            evdev::Key::KEY_NUMERIC_STAR => crate::Key::KEY_NUMERIC_STAR,
+            evdev::Key::KEY_NUMERIC_POUND => crate::Key::KEY_NUMERIC_POUND,
+            evdev::Key::KEY_NUMERIC_A => crate::Key::KEY_NUMERIC_A,
+            evdev::Key::KEY_NUMERIC_B => crate::Key::KEY_NUMERIC_B,
+            evdev::Key::KEY_NUMERIC_C => crate::Key::KEY_NUMERIC_C,
+            evdev::Key::KEY_NUMERIC_D => crate::Key::KEY_NUMERIC_D,
+            evdev::Key::KEY_CAMERA_FOCUS => crate::Key::KEY_CAMERA_FOCUS,
+            evdev::Key::KEY_WPS_BUTTON => crate::Key::KEY_WPS_BUTTON,
+            evdev::Key::KEY_TOUCHPAD_TOGGLE => crate::Key::KEY_TOUCHPAD_TOGGLE,
+            evdev::Key::KEY_TOUCHPAD_ON => crate::Key::KEY_TOUCHPAD_ON,
+            evdev::Key::KEY_TOUCHPAD_OFF => crate::Key::KEY_TOUCHPAD_OFF,
+            evdev::Key::KEY_CAMERA_ZOOMIN => crate::Key::KEY_CAMERA_ZOOMIN,
+            evdev::Key::KEY_CAMERA_ZOOMOUT => crate::Key::KEY_CAMERA_ZOOMOUT,
+            evdev::Key::KEY_CAMERA_UP => crate::Key::KEY_CAMERA_UP,
+            evdev::Key::KEY_CAMERA_DOWN => crate::Key::KEY_CAMERA_DOWN,
+            evdev::Key::KEY_CAMERA_LEFT => crate::Key::KEY_CAMERA_LEFT,
+            evdev::Key::KEY_CAMERA_RIGHT => crate::Key::KEY_CAMERA_RIGHT,
+            evdev::Key::KEY_ATTENDANT_ON => crate::Key::KEY_ATTENDANT_ON,
+            evdev::Key::KEY_ATTENDANT_OFF => crate::Key::KEY_ATTENDANT_OFF,
+            evdev::Key::KEY_ATTENDANT_TOGGLE => crate::Key::KEY_ATTENDANT_TOGGLE,
+            evdev::Key::KEY_LIGHTS_TOGGLE => crate::Key::KEY_LIGHTS_TOGGLE,
+            evdev::Key::KEY_ALS_TOGGLE => crate::Key::KEY_ALS_TOGGLE,
+            evdev::Key::KEY_BUTTONCONFIG => crate::Key::KEY_BUTTONCONFIG,
+            evdev::Key::KEY_TASKMANAGER => crate::Key::KEY_TASKMANAGER,
+            evdev::Key::KEY_JOURNAL => crate::Key::KEY_JOURNAL,
+            evdev::Key::KEY_CONTROLPANEL => crate::Key::KEY_CONTROLPANEL,
+            evdev::Key::KEY_APPSELECT => crate::Key::KEY_APPSELECT,
+            evdev::Key::KEY_SCREENSAVER => crate::Key::KEY_SCREENSAVER,
+            evdev::Key::KEY_VOICECOMMAND => crate::Key::KEY_VOICECOMMAND,
+            evdev::Key::KEY_ASSISTANT => crate::Key::KEY_ASSISTANT,
+            evdev::Key::KEY_KBD_LAYOUT_NEXT => crate::Key::KEY_KBD_LAYOUT_NEXT,
+            evdev::Key::KEY_BRIGHTNESS_MIN => crate::Key::KEY_BRIGHTNESS_MIN,
+            evdev::Key::KEY_BRIGHTNESS_MAX => crate::Key::KEY_BRIGHTNESS_MAX,
+            evdev::Key::KEY_KBDINPUTASSIST_PREV => crate::Key::KEY_KBDINPUTASSIST_PREV,
+            evdev::Key::KEY_KBDINPUTASSIST_NEXT => crate::Key::KEY_KBDINPUTASSIST_NEXT,
+            evdev::Key::KEY_KBDINPUTASSIST_PREVGROUP => crate::Key::KEY_KBDINPUTASSIST_PREVGROUP,
+            evdev::Key::KEY_KBDINPUTASSIST_NEXTGROUP => crate::Key::KEY_KBDINPUTASSIST_NEXTGROUP,
+            evdev::Key::KEY_KBDINPUTASSIST_ACCEPT => crate::Key::KEY_KBDINPUTASSIST_ACCEPT,
+            evdev::Key::KEY_KBDINPUTASSIST_CANCEL => crate::Key::KEY_KBDINPUTASSIST_CANCEL,
+            evdev::Key::KEY_RIGHT_UP => crate::Key::KEY_RIGHT_UP,
+            evdev::Key::KEY_RIGHT_DOWN => crate::Key::KEY_RIGHT_DOWN,
+            evdev::Key::KEY_LEFT_UP => crate::Key::KEY_LEFT_UP,
+            evdev::Key::KEY_LEFT_DOWN => crate::Key::KEY_LEFT_DOWN,
+            evdev::Key::KEY_ROOT_MENU => crate::Key::KEY_ROOT_MENU,
+            evdev::Key::KEY_MEDIA_TOP_MENU => crate::Key::KEY_MEDIA_TOP_MENU,
+            evdev::Key::KEY_NUMERIC_11 => crate::Key::KEY_NUMERIC_11,
+            evdev::Key::KEY_NUMERIC_12 => crate::Key::KEY_NUMERIC_12,
+            evdev::Key::KEY_AUDIO_DESC => crate::Key::KEY_AUDIO_DESC,
+            evdev::Key::KEY_3D_MODE => crate::Key::KEY_3D_MODE,
+            evdev::Key::KEY_NEXT_FAVORITE => crate::Key::KEY_NEXT_FAVORITE,
+            evdev::Key::KEY_STOP_RECORD => crate::Key::KEY_STOP_RECORD,
+            evdev::Key::KEY_PAUSE_RECORD => crate::Key::KEY_PAUSE_RECORD,
+            evdev::Key::KEY_VOD => crate::Key::KEY_VOD,
+            evdev::Key::KEY_UNMUTE => crate::Key::KEY_UNMUTE,
+            evdev::Key::KEY_FASTREVERSE => crate::Key::KEY_FASTREVERSE,
+            evdev::Key::KEY_SLOWREVERSE => crate::Key::KEY_SLOWREVERSE,
+            evdev::Key::KEY_DATA => crate::Key::KEY_DATA,
+            evdev::Key::KEY_ONSCREEN_KEYBOARD => crate::Key::KEY_ONSCREEN_KEYBOARD,
+            evdev::Key::KEY_PRIVACY_SCREEN_TOGGLE => crate::Key::KEY_PRIVACY_SCREEN_TOGGLE,
+            evdev::Key::KEY_SELECTIVE_SCREENSHOT => crate::Key::KEY_SELECTIVE_SCREENSHOT,
+            evdev::Key::KEY_RESERVED => return Err(EventMappingError::UnknownKey),
+            evdev::Key(code) => crate::Key::Raw(code),
         })
     }
 }
@@ -347,14 +1241,445 @@ impl TryFrom<evdev::Key> for crate::Button {
 
     fn try_from(val: evdev::Key) -> Result<crate::Button, Self::Error> {
         Ok(match val {
+            evdev::Key::BTN_0 => crate::Button::BTN_0,
+            evdev::Key::BTN_1 => crate::Button::BTN_1,
+            evdev::Key::BTN_2 => crate::Button::BTN_2,
+            evdev::Key::BTN_3 => crate::Button::BTN_3,
+            evdev::Key::BTN_4 => crate::Button::BTN_4,
+            evdev::Key::BTN_5 => crate::Button::BTN_5,
+            evdev::Key::BTN_6 => crate::Button::BTN_6,
+            evdev::Key::BTN_7 => crate::Button::BTN_7,
+            evdev::Key::BTN_8 => crate::Button::BTN_8,
+            evdev::Key::BTN_9 => crate::Button::BTN_9,
             evdev::Key::BTN_LEFT => crate::Button::BTN_LEFT,
             evdev::Key::BTN_RIGHT => crate::Button::BTN_RIGHT,
             evdev::Key::BTN_MIDDLE => crate::Button::BTN_MIDDLE,
+            evdev::Key::BTN_SIDE => crate::Button::BTN_SIDE,
+            evdev::Key::BTN_EXTRA => crate::Button::BTN_EXTRA,
             evdev::Key::BTN_FORWARD => crate::Button::BTN_FORWARD,
             evdev::Key::BTN_BACK => crate::Button::BTN_BACK,
+            evdev::Key::BTN_TASK => crate::Button::BTN_TASK,
+            evdev::Key::BTN_TOOL_PEN => crate::Button::BTN_TOOL_PEN,
+            evdev::Key::BTN_TOOL_RUBBER => crate::Button::BTN_TOOL_RUBBER,
+            evdev::Key::BTN_TOUCH => crate::Button::BTN_TOUCH,
+            evdev::Key::BTN_STYLUS => crate::Button::BTN_STYLUS,
+            evdev::Key::BTN_STYLUS2 => crate::Button::BTN_STYLUS2,
+            evdev::Key(code)
+                if MOUSE_BUTTON_CODES.contains(&code) || DIGITIZER_BUTTON_CODES.contains(&code) =>
+            {
+                crate::Button::Raw(code)
+            }
             _ => {
                 return Err(EventMappingError::UnknownButton);
             }
         })
     }
 }
+
+impl From<crate::GamepadButton> for evdev::Key {
+    fn from(value: crate::GamepadButton) -> Self {
+        match value {
+            crate::GamepadButton::BTN_TRIGGER => evdev::Key::BTN_TRIGGER,
+            crate::GamepadButton::BTN_THUMB => evdev::Key::BTN_THUMB,
+            crate::GamepadButton::BTN_THUMB2 => evdev::Key::BTN_THUMB2,
+            crate::GamepadButton::BTN_TOP => evdev::Key::BTN_TOP,
+            crate::GamepadButton::BTN_TOP2 => evdev::Key::BTN_TOP2,
+            crate::GamepadButton::BTN_PINKIE => evdev::Key::BTN_PINKIE,
+            crate::GamepadButton::BTN_BASE => evdev::Key::BTN_BASE,
+            crate::GamepadButton::BTN_BASE2 => evdev::Key::BTN_BASE2,
+            crate::GamepadButton::BTN_BASE3 => evdev::Key::BTN_BASE3,
+            crate::GamepadButton::BTN_BASE4 => evdev::Key::BTN_BASE4,
+            crate::GamepadButton::BTN_BASE5 => evdev::Key::BTN_BASE5,
+            crate::GamepadButton::BTN_BASE6 => evdev::Key::BTN_BASE6,
+            crate::GamepadButton::BTN_DEAD => evdev::Key::BTN_DEAD,
+            crate::GamepadButton::BTN_SOUTH => evdev::Key::BTN_SOUTH,
+            crate::GamepadButton::BTN_EAST => evdev::Key::BTN_EAST,
+            crate::GamepadButton::BTN_C => evdev::Key::BTN_C,
+            crate::GamepadButton::BTN_NORTH => evdev::Key::BTN_NORTH,
+            crate::GamepadButton::BTN_WEST => evdev::Key::BTN_WEST,
+            crate::GamepadButton::BTN_Z => evdev::Key::BTN_Z,
+            crate::GamepadButton::BTN_TL => evdev::Key::BTN_TL,
+            crate::GamepadButton::BTN_TR => evdev::Key::BTN_TR,
+            crate::GamepadButton::BTN_TL2 => evdev::Key::BTN_TL2,
+            crate::GamepadButton::BTN_TR2 => evdev::Key::BTN_TR2,
+            crate::GamepadButton::BTN_SELECT => evdev::Key::BTN_SELECT,
+            crate::GamepadButton::BTN_START => evdev::Key::BTN_START,
+            crate::GamepadButton::BTN_MODE => evdev::Key::BTN_MODE,
+            crate::GamepadButton::BTN_THUMBL => evdev::Key::BTN_THUMBL,
+            crate::GamepadButton::BTN_THUMBR => evdev::Key::BTN_THUMBR,
+            crate::GamepadButton::BTN_DPAD_UP => evdev::Key::BTN_DPAD_UP,
+            crate::GamepadButton::BTN_DPAD_DOWN => evdev::Key::BTN_DPAD_DOWN,
+            crate::GamepadButton::BTN_DPAD_LEFT => evdev::Key::BTN_DPAD_LEFT,
+            crate::GamepadButton::BTN_DPAD_RIGHT => evdev::Key::BTN_DPAD_RIGHT,
+            crate::GamepadButton::Raw(code) => evdev::Key::new(code),
+        }
+    }
+}
+
+impl TryFrom<evdev::Key> for crate::GamepadButton {
+    type Error = EventMappingError;
+
+    fn try_from(val: evdev::Key) -> Result<crate::GamepadButton, Self::Error> {
+        Ok(match val {
+            evdev::Key::BTN_TRIGGER => crate::GamepadButton::BTN_TRIGGER,
+            evdev::Key::BTN_THUMB => crate::GamepadButton::BTN_THUMB,
+            evdev::Key::BTN_THUMB2 => crate::GamepadButton::BTN_THUMB2,
+            evdev::Key::BTN_TOP => crate::GamepadButton::BTN_TOP,
+            evdev::Key::BTN_TOP2 => crate::GamepadButton::BTN_TOP2,
+            evdev::Key::BTN_PINKIE => crate::GamepadButton::BTN_PINKIE,
+            evdev::Key::BTN_BASE => crate::GamepadButton::BTN_BASE,
+            evdev::Key::BTN_BASE2 => crate::GamepadButton::BTN_BASE2,
+            evdev::Key::BTN_BASE3 => crate::GamepadButton::BTN_BASE3,
+            evdev::Key::BTN_BASE4 => crate::GamepadButton::BTN_BASE4,
+            evdev::Key::BTN_BASE5 => crate::GamepadButton::BTN_BASE5,
+            evdev::Key::BTN_BASE6 => crate::GamepadButton::BTN_BASE6,
+            evdev::Key::BTN_DEAD => crate::GamepadButton::BTN_DEAD,
+            evdev::Key::BTN_SOUTH => crate::GamepadButton::BTN_SOUTH,
+            evdev::Key::BTN_EAST => crate::GamepadButton::BTN_EAST,
+            evdev::Key::BTN_C => crate::GamepadButton::BTN_C,
+            evdev::Key::BTN_NORTH => crate::GamepadButton::BTN_NORTH,
+            evdev::Key::BTN_WEST => crate::GamepadButton::BTN_WEST,
+            evdev::Key::BTN_Z => crate::GamepadButton::BTN_Z,
+            evdev::Key::BTN_TL => crate::GamepadButton::BTN_TL,
+            evdev::Key::BTN_TR => crate::GamepadButton::BTN_TR,
+            evdev::Key::BTN_TL2 => crate::GamepadButton::BTN_TL2,
+            evdev::Key::BTN_TR2 => crate::GamepadButton::BTN_TR2,
+            evdev::Key::BTN_SELECT => crate::GamepadButton::BTN_SELECT,
+            evdev::Key::BTN_START => crate::GamepadButton::BTN_START,
+            evdev::Key::BTN_MODE => crate::GamepadButton::BTN_MODE,
+            evdev::Key::BTN_THUMBL => crate::GamepadButton::BTN_THUMBL,
+            evdev::Key::BTN_THUMBR => crate::GamepadButton::BTN_THUMBR,
+            evdev::Key::BTN_DPAD_UP => crate::GamepadButton::BTN_DPAD_UP,
+            evdev::Key::BTN_DPAD_DOWN => crate::GamepadButton::BTN_DPAD_DOWN,
+            evdev::Key::BTN_DPAD_LEFT => crate::GamepadButton::BTN_DPAD_LEFT,
+            evdev::Key::BTN_DPAD_RIGHT => crate::GamepadButton::BTN_DPAD_RIGHT,
+            evdev::Key(code)
+                if GAMEPAD_BUTTON_CODES.contains(&code)
+                    || DPAD_BUTTON_CODES.contains(&code)
+                    || TRIGGER_HAPPY_BUTTON_CODES.contains(&code) =>
+            {
+                crate::GamepadButton::Raw(code)
+            }
+            _ => return Err(EventMappingError::UnknownButton),
+        })
+    }
+}
+
+impl From<crate::GamepadAxis> for evdev::AbsoluteAxisType {
+    fn from(value: crate::GamepadAxis) -> Self {
+        match value {
+            crate::GamepadAxis::ABS_X => evdev::AbsoluteAxisType::ABS_X,
+            crate::GamepadAxis::ABS_Y => evdev::AbsoluteAxisType::ABS_Y,
+            crate::GamepadAxis::ABS_Z => evdev::AbsoluteAxisType::ABS_Z,
+            crate::GamepadAxis::ABS_RX => evdev::AbsoluteAxisType::ABS_RX,
+            crate::GamepadAxis::ABS_RY => evdev::AbsoluteAxisType::ABS_RY,
+            crate::GamepadAxis::ABS_RZ => evdev::AbsoluteAxisType::ABS_RZ,
+            crate::GamepadAxis::ABS_THROTTLE => evdev::AbsoluteAxisType::ABS_THROTTLE,
+            crate::GamepadAxis::ABS_RUDDER => evdev::AbsoluteAxisType::ABS_RUDDER,
+            crate::GamepadAxis::ABS_WHEEL => evdev::AbsoluteAxisType::ABS_WHEEL,
+            crate::GamepadAxis::ABS_GAS => evdev::AbsoluteAxisType::ABS_GAS,
+            crate::GamepadAxis::ABS_BRAKE => evdev::AbsoluteAxisType::ABS_BRAKE,
+            crate::GamepadAxis::ABS_HAT0X => evdev::AbsoluteAxisType::ABS_HAT0X,
+            crate::GamepadAxis::ABS_HAT0Y => evdev::AbsoluteAxisType::ABS_HAT0Y,
+            crate::GamepadAxis::ABS_HAT1X => evdev::AbsoluteAxisType::ABS_HAT1X,
+            crate::GamepadAxis::ABS_HAT1Y => evdev::AbsoluteAxisType::ABS_HAT1Y,
+            crate::GamepadAxis::ABS_HAT2X => evdev::AbsoluteAxisType::ABS_HAT2X,
+            crate::GamepadAxis::ABS_HAT2Y => evdev::AbsoluteAxisType::ABS_HAT2Y,
+            crate::GamepadAxis::ABS_HAT3X => evdev::AbsoluteAxisType::ABS_HAT3X,
+            crate::GamepadAxis::ABS_HAT3Y => evdev::AbsoluteAxisType::ABS_HAT3Y,
+        }
+    }
+}
+
+impl TryFrom<evdev::AbsoluteAxisType> for crate::GamepadAxis {
+    type Error = EventMappingError;
+
+    fn try_from(val: evdev::AbsoluteAxisType) -> Result<crate::GamepadAxis, Self::Error> {
+        Ok(match val {
+            evdev::AbsoluteAxisType::ABS_X => crate::GamepadAxis::ABS_X,
+            evdev::AbsoluteAxisType::ABS_Y => crate::GamepadAxis::ABS_Y,
+            evdev::AbsoluteAxisType::ABS_Z => crate::GamepadAxis::ABS_Z,
+            evdev::AbsoluteAxisType::ABS_RX => crate::GamepadAxis::ABS_RX,
+            evdev::AbsoluteAxisType::ABS_RY => crate::GamepadAxis::ABS_RY,
+            evdev::AbsoluteAxisType::ABS_RZ => crate::GamepadAxis::ABS_RZ,
+            evdev::AbsoluteAxisType::ABS_THROTTLE => crate::GamepadAxis::ABS_THROTTLE,
+            evdev::AbsoluteAxisType::ABS_RUDDER => crate::GamepadAxis::ABS_RUDDER,
+            evdev::AbsoluteAxisType::ABS_WHEEL => crate::GamepadAxis::ABS_WHEEL,
+            evdev::AbsoluteAxisType::ABS_GAS => crate::GamepadAxis::ABS_GAS,
+            evdev::AbsoluteAxisType::ABS_BRAKE => crate::GamepadAxis::ABS_BRAKE,
+            evdev::AbsoluteAxisType::ABS_HAT0X => crate::GamepadAxis::ABS_HAT0X,
+            evdev::AbsoluteAxisType::ABS_HAT0Y => crate::GamepadAxis::ABS_HAT0Y,
+            evdev::AbsoluteAxisType::ABS_HAT1X => crate::GamepadAxis::ABS_HAT1X,
+            evdev::AbsoluteAxisType::ABS_HAT1Y => crate::GamepadAxis::ABS_HAT1Y,
+            evdev::AbsoluteAxisType::ABS_HAT2X => crate::GamepadAxis::ABS_HAT2X,
+            evdev::AbsoluteAxisType::ABS_HAT2Y => crate::GamepadAxis::ABS_HAT2Y,
+            evdev::AbsoluteAxisType::ABS_HAT3X => crate::GamepadAxis::ABS_HAT3X,
+            evdev::AbsoluteAxisType::ABS_HAT3Y => crate::GamepadAxis::ABS_HAT3Y,
+            _ => return Err(EventMappingError::UnsupportedEventType),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{absolute_event, gamepad_input, AbsoluteRange};
+    use crate::{
+        Button, GamepadAxis, GamepadButton, GamepadEvent, GamepadInput, InputEvent, Key,
+        KeyboardEventType, MouseEvent, PointerAxis, TabletEvent, ABSOLUTE_MAX,
+    };
+
+    #[test]
+    fn given_wheel_events_should_map_to_scroll_and_back() {
+        // Given
+        let axes = [
+            (
+                evdev::RelativeAxisType::REL_WHEEL,
+                PointerAxis::Vertical,
+                false,
+            ),
+            (
+                evdev::RelativeAxisType::REL_HWHEEL,
+                PointerAxis::Horizontal,
+                false,
+            ),
+            (
+                evdev::RelativeAxisType::REL_WHEEL_HI_RES,
+                PointerAxis::Vertical,
+                true,
+            ),
+            (
+                evdev::RelativeAxisType::REL_HWHEEL_HI_RES,
+                PointerAxis::Horizontal,
+                true,
+            ),
+        ];
+
+        for (code, axis, hi_res) in axes {
+            // When
+            let raw = evdev::InputEvent::new(evdev::EventType::RELATIVE, code.0, -60);
+            let event = InputEvent::try_from(raw).unwrap();
+
+            // Then
+            let expected = if hi_res {
+                MouseEvent::HiResScroll { axis, diff: -60 }
+            } else {
+                MouseEvent::Scroll { axis, diff: -60 }
+            };
+            assert_eq!(event, InputEvent::Mouse(expected));
+            let back = evdev::InputEvent::try_from(event).unwrap();
+            assert_eq!((back.code(), back.value()), (code.0, -60));
+        }
+    }
+
+    #[test]
+    fn given_extended_keys_should_map_both_ways() {
+        // Given
+        let keys = [
+            (evdev::Key::KEY_RIGHTCTRL, Key::KEY_RIGHTCTRL),
+            (evdev::Key::KEY_F24, Key::KEY_F24),
+            (evdev::Key::KEY_VOLUMEUP, Key::KEY_VOLUMEUP),
+            (evdev::Key::KEY_102ND, Key::KEY_102ND),
+            (evdev::Key::KEY_HANGEUL, Key::KEY_HANGEUL),
+        ];
+
+        for (raw, key) in keys {
+            // When
+            let mapped = Key::try_from(raw).unwrap();
+
+            // Then
+            assert_eq!(mapped, key);
+            assert_eq!(evdev::Key::from(mapped), raw);
+        }
+    }
+
+    #[test]
+    fn given_unknown_key_code_should_pass_through_raw() {
+        // Given
+        let raw = evdev::Key::new(0x2fe);
+
+        // When
+        let key = Key::try_from(raw).unwrap();
+
+        // Then
+        assert_eq!(key, Key::Raw(0x2fe));
+        assert_eq!(evdev::Key::from(key), raw);
+    }
+
+    #[test]
+    fn given_mouse_button_codes_should_map_to_buttons() {
+        // Given
+        let codes = [
+            evdev::Key::BTN_SIDE.code(),
+            evdev::Key::BTN_EXTRA.code(),
+            evdev::Key::BTN_TASK.code(),
+            0x118,
+        ];
+
+        // When
+        let events: Vec<_> = codes
+            .iter()
+            .map(|&code| {
+                InputEvent::try_from(evdev::InputEvent::new(evdev::EventType::KEY, code, 1))
+                    .unwrap()
+            })
+            .collect();
+
+        // Then
+        let pressed = |button| {
+            InputEvent::Mouse(MouseEvent::Button {
+                event_type: KeyboardEventType::KeyPressed,
+                button,
+            })
+        };
+        assert_eq!(
+            events,
+            vec![
+                pressed(Button::BTN_SIDE),
+                pressed(Button::BTN_EXTRA),
+                pressed(Button::BTN_TASK),
+                pressed(Button::Raw(0x118)),
+            ]
+        );
+        assert_eq!(evdev::Key::from(Button::Raw(0x118)).code(), 0x118);
+    }
+
+    #[test]
+    fn given_tablet_axes_should_scale_to_device_range() {
+        // Given
+        let position = AbsoluteRange {
+            minimum: 100,
+            maximum: 2100,
+            resolution: 100,
+        };
+        let tilt = AbsoluteRange {
+            minimum: -64,
+            maximum: 63,
+            resolution: 57,
+        };
+        let abs = |axis: evdev::AbsoluteAxisType, value| {
+            evdev::InputEvent::new(evdev::EventType::ABSOLUTE, axis.0, value)
+        };
+
+        // When
+        let events: Vec<_> = [
+            (abs(evdev::AbsoluteAxisType::ABS_X, 1100), position),
+            (abs(evdev::AbsoluteAxisType::ABS_Y, 5000), position),
+            (abs(evdev::AbsoluteAxisType::ABS_PRESSURE, 100), position),
+            (abs(evdev::AbsoluteAxisType::ABS_TILT_X, -30), tilt),
+        ]
+        .into_iter()
+        .map(|(event, range)| absolute_event(event, range).unwrap())
+        .collect();
+
+        // Then
+        assert_eq!(
+            events,
+            vec![
+                InputEvent::Tablet(TabletEvent::Position {
+                    axis: PointerAxis::Horizontal,
+                    value: ABSOLUTE_MAX / 2,
+                }),
+                InputEvent::Tablet(TabletEvent::Position {
+                    axis: PointerAxis::Vertical,
+                    value: ABSOLUTE_MAX,
+                }),
+                InputEvent::Tablet(TabletEvent::Pressure { value: 0 }),
+                InputEvent::Tablet(TabletEvent::Tilt {
+                    axis: PointerAxis::Horizontal,
+                    degrees: -30,
+                }),
+            ]
+        );
+    }
+
+    #[test]
+    fn given_unmapped_absolute_axis_should_be_unsupported() {
+        // Given
+        let range = AbsoluteRange {
+            minimum: 0,
+            maximum: 63,
+            resolution: 0,
+        };
+        let distance = evdev::InputEvent::new(
+            evdev::EventType::ABSOLUTE,
+            evdev::AbsoluteAxisType::ABS_DISTANCE.0,
+            10,
+        );
+
+        // When
+        let event = absolute_event(distance, range);
+
+        // Then
+        assert!(event.is_err());
+        assert!(InputEvent::try_from(distance).is_err());
+    }
+
+    #[test]
+    fn given_gamepad_events_should_scale_sticks_and_keep_hat_directions() {
+        // Given
+        let stick = AbsoluteRange {
+            minimum: -32768,
+            maximum: 32767,
+            resolution: 0,
+        };
+        let abs = |axis: evdev::AbsoluteAxisType, value| {
+            evdev::InputEvent::new(evdev::EventType::ABSOLUTE, axis.0, value)
+        };
+        let key = |code, value| evdev::InputEvent::new(evdev::EventType::KEY, code, value);
+
+        // When
+        let inputs: Vec<_> = [
+            (abs(evdev::AbsoluteAxisType::ABS_RX, 32767), Some(stick)),
+            (abs(evdev::AbsoluteAxisType::ABS_HAT0Y, -1), None),
+            (key(evdev::Key::BTN_SOUTH.code(), 1), None),
+            (key(0x2c0, 0), None),
+        ]
+        .into_iter()
+        .map(|(event, range)| gamepad_input(event, range).unwrap())
+        .collect();
+
+        // Then
+        assert_eq!(
+            inputs,
+            vec![
+                GamepadInput::Axis {
+                    axis: GamepadAxis::ABS_RX,
+                    value: ABSOLUTE_MAX,
+                },
+                GamepadInput::Axis {
+                    axis: GamepadAxis::ABS_HAT0Y,
+                    value: -1,
+                },
+                GamepadInput::Button {
+                    event_type: KeyboardEventType::KeyPressed,
+                    button: GamepadButton::BTN_SOUTH,
+                },
+                GamepadInput::Button {
+                    event_type: KeyboardEventType::KeyReleased,
+                    button: GamepadButton::Raw(0x2c0),
+                },
+            ]
+        );
+        let back = evdev::InputEvent::try_from(InputEvent::Gamepad(GamepadEvent {
+            pad: 0,
+            input: inputs[1].clone(),
+        }))
+        .unwrap();
+        assert_eq!(
+            (back.code(), back.value()),
+            (evdev::AbsoluteAxisType::ABS_HAT0Y.0, -1)
+        );
+    }
+
+    #[test]
+    fn given_keyboard_key_should_not_map_to_gamepad_button() {
+        // Given
+        let key = evdev::InputEvent::new(evdev::EventType::KEY, evdev::Key::KEY_A.code(), 1);
+
+        // When
+        let input = gamepad_input(key, None);
+
+        // Then
+        assert!(input.is_err());
+    }
+}
@@ -7,15 +7,27 @@ use x11::{
 
 use crate::{mapper::error::EventMappingError, Button, Key, PointerAxis};
 
+use super::evdev::DIGITIZER_BUTTON_CODES;
+
+const X11_EVDEV_KEYCODE_OFFSET: u16 = 8;
+
+const BTN_MISC: u16 = 0x100;
+const BTN_SIDE: u16 = 0x113;
+const BTN_JOYSTICK: u16 = 0x120;
+
 impl Key {
     /// Converts a `Key` to an X11 keycode using the provided display.
     ///
+    /// Keys are looked up by keysym so that they type the same character on the client's layout.
+    /// Keys without a keysym, or whose keysym the layout does not have, fall back to the keycode
+    /// the X evdev driver gives their Linux key code.
+    ///
     /// # Parameters
     /// - `display`: A pointer to an X11 `Display`
     ///
     /// # Returns
     /// - `Ok(c_uchar)`: The corresponding X11 keycode.
-    /// - `Err(EventMappingError)`: If the mapping fails.
+    /// - `Err(EventMappingError)`: If the key is beyond the range of X11 keycodes.
     ///
     /// # Safety
     /// This function assumes the provided `display` pointer is valid and properly initialized.
@@ -107,8 +119,21 @@ impl Key {
             Key::KEY_KP3 => keysym::XK_KP_3,
             Key::KEY_KP0 => keysym::XK_KP_0,
             Key::KEY_KPDOT => keysym::XK_KP_Decimal,
+            Key::KEY_ZENKAKUHANKAKU => keysym::XK_Zenkaku_Hankaku,
+            Key::KEY_102ND => keysym::XK_less,
             Key::KEY_F11 => keysym::XK_F11,
             Key::KEY_F12 => keysym::XK_F12,
+            Key::KEY_KATAKANA => keysym::XK_Katakana,
+            Key::KEY_HIRAGANA => keysym::XK_Hiragana,
+            Key::KEY_HENKAN => keysym::XK_Henkan,
+            Key::KEY_KATAKANAHIRAGANA => keysym::XK_Hiragana_Katakana,
+            Key::KEY_MUHENKAN => keysym::XK_Muhenkan,
+            Key::KEY_KPENTER => keysym::XK_KP_Enter,
+            Key::KEY_RIGHTCTRL => keysym::XK_Control_R,
+            Key::KEY_KPSLASH => keysym::XK_KP_Divide,
+            Key::KEY_SYSRQ => keysym::XK_Print,
+            Key::KEY_RIGHTALT => keysym::XK_Alt_R,
+            Key::KEY_LINEFEED => keysym::XK_Linefeed,
             Key::KEY_HOME => keysym::XK_Home,
             Key::KEY_UP => keysym::XK_Up,
             Key::KEY_PAGEUP => keysym::XK_Prior,
@@ -119,33 +144,169 @@ impl Key {
             Key::KEY_PAGEDOWN => keysym::XK_Next,
             Key::KEY_INSERT => keysym::XK_Insert,
             Key::KEY_DELETE => keysym::XK_Delete,
+            Key::KEY_MUTE => keysym::XF86XK_AudioMute,
+            Key::KEY_VOLUMEDOWN => keysym::XF86XK_AudioLowerVolume,
+            Key::KEY_VOLUMEUP => keysym::XF86XK_AudioRaiseVolume,
+            Key::KEY_POWER => keysym::XF86XK_PowerOff,
+            Key::KEY_KPEQUAL => keysym::XK_KP_Equal,
+            Key::KEY_KPPLUSMINUS => keysym::XK_plusminus,
+            Key::KEY_PAUSE => keysym::XK_Pause,
+            Key::KEY_KPCOMMA => keysym::XK_KP_Separator,
+            Key::KEY_YEN => keysym::XK_yen,
             Key::KEY_LEFTMETA => keysym::XK_Super_L,
             Key::KEY_RIGHTMETA => keysym::XK_Super_R,
+            Key::KEY_COMPOSE => keysym::XK_Multi_key,
+            Key::KEY_STOP => keysym::XK_Cancel,
+            Key::KEY_AGAIN => keysym::XK_Redo,
+            Key::KEY_UNDO => keysym::XK_Undo,
+            Key::KEY_COPY => keysym::XF86XK_Copy,
+            Key::KEY_OPEN => keysym::XF86XK_Open,
+            Key::KEY_PASTE => keysym::XF86XK_Paste,
+            Key::KEY_FIND => keysym::XK_Find,
+            Key::KEY_CUT => keysym::XF86XK_Cut,
+            Key::KEY_HELP => keysym::XK_Help,
             Key::KEY_MENU => keysym::XK_Menu,
+            Key::KEY_CALC => keysym::XF86XK_Calculator,
+            Key::KEY_SLEEP => keysym::XF86XK_Sleep,
+            Key::KEY_WAKEUP => keysym::XF86XK_WakeUp,
+            Key::KEY_FILE => keysym::XF86XK_Explorer,
+            Key::KEY_XFER => keysym::XF86XK_Xfer,
+            Key::KEY_WWW => keysym::XF86XK_WWW,
+            Key::KEY_CYCLEWINDOWS => keysym::XF86XK_RotateWindows,
+            Key::KEY_MAIL => keysym::XF86XK_Mail,
+            Key::KEY_BOOKMARKS => keysym::XF86XK_Favorites,
+            Key::KEY_COMPUTER => keysym::XF86XK_MyComputer,
+            Key::KEY_BACK => keysym::XF86XK_Back,
+            Key::KEY_FORWARD => keysym::XF86XK_Forward,
+            Key::KEY_EJECTCD => keysym::XF86XK_Eject,
+            Key::KEY_NEXTSONG => keysym::XF86XK_AudioNext,
+            Key::KEY_PLAYPAUSE => keysym::XF86XK_AudioPlay,
+            Key::KEY_PREVIOUSSONG => keysym::XF86XK_AudioPrev,
+            Key::KEY_STOPCD => keysym::XF86XK_AudioStop,
+            Key::KEY_RECORD => keysym::XF86XK_AudioRecord,
+            Key::KEY_REWIND => keysym::XF86XK_AudioRewind,
+            Key::KEY_PHONE => keysym::XF86XK_Phone,
+            Key::KEY_CONFIG => keysym::XF86XK_Tools,
+            Key::KEY_HOMEPAGE => keysym::XF86XK_HomePage,
+            Key::KEY_REFRESH => keysym::XF86XK_Refresh,
+            Key::KEY_EXIT => keysym::XF86XK_Close,
+            Key::KEY_EDIT => keysym::XK_Execute,
+            Key::KEY_SCROLLUP => keysym::XF86XK_ScrollUp,
+            Key::KEY_SCROLLDOWN => keysym::XF86XK_ScrollDown,
+            Key::KEY_KPLEFTPAREN => keysym::XK_parenleft,
+            Key::KEY_KPRIGHTPAREN => keysym::XK_parenright,
+            Key::KEY_NEW => keysym::XF86XK_New,
+            Key::KEY_REDO => keysym::XK_Redo,
+            Key::KEY_F13 => keysym::XK_F13,
+            Key::KEY_F14 => keysym::XK_F14,
+            Key::KEY_F15 => keysym::XK_F15,
+            Key::KEY_F16 => keysym::XK_F16,
+            Key::KEY_F17 => keysym::XK_F17,
+            Key::KEY_F18 => keysym::XK_F18,
+            Key::KEY_F19 => keysym::XK_F19,
+            Key::KEY_F20 => keysym::XK_F20,
+            Key::KEY_F21 => keysym::XK_F21,
+            Key::KEY_F22 => keysym::XK_F22,
+            Key::KEY_F23 => keysym::XK_F23,
+            Key::KEY_F24 => keysym::XK_F24,
+            Key::KEY_PLAYCD => keysym::XF86XK_AudioPlay,
+            Key::KEY_PAUSECD => keysym::XF86XK_AudioPause,
+            Key::KEY_SUSPEND => keysym::XF86XK_Suspend,
+            Key::KEY_CLOSE => keysym::XF86XK_Close,
+            Key::KEY_PLAY => keysym::XF86XK_AudioPlay,
+            Key::KEY_FASTFORWARD => keysym::XF86XK_AudioForward,
+            Key::KEY_PRINT => keysym::XK_Print,
+            Key::KEY_CAMERA => keysym::XF86XK_WebCam,
+            Key::KEY_SEARCH => keysym::XF86XK_Search,
+            Key::KEY_FINANCE => keysym::XF86XK_Finance,
+            Key::KEY_SHOP => keysym::XF86XK_Shop,
+            Key::KEY_CANCEL => keysym::XK_Cancel,
+            Key::KEY_BRIGHTNESSDOWN => keysym::XF86XK_MonBrightnessDown,
+            Key::KEY_BRIGHTNESSUP => keysym::XF86XK_MonBrightnessUp,
+            Key::KEY_MEDIA => keysym::XF86XK_AudioMedia,
+            Key::KEY_SWITCHVIDEOMODE => keysym::XF86XK_Display,
+            Key::KEY_KBDILLUMTOGGLE => keysym::XF86XK_KbdLightOnOff,
+            Key::KEY_KBDILLUMDOWN => keysym::XF86XK_KbdBrightnessDown,
+            Key::KEY_KBDILLUMUP => keysym::XF86XK_KbdBrightnessUp,
+            Key::KEY_SEND => keysym::XF86XK_Send,
+            Key::KEY_REPLY => keysym::XF86XK_Reply,
+            Key::KEY_FORWARDMAIL => keysym::XF86XK_MailForward,
+            Key::KEY_SAVE => keysym::XF86XK_Save,
+            Key::KEY_DOCUMENTS => keysym::XF86XK_Documents,
+            Key::KEY_BATTERY => keysym::XF86XK_Battery,
+            Key::KEY_BLUETOOTH => keysym::XF86XK_Bluetooth,
+            Key::KEY_WLAN => keysym::XF86XK_WLAN,
+            Key::KEY_MICMUTE => keysym::XF86XK_AudioMicMute,
+            Key::KEY_SELECT => keysym::XK_Select,
+            Key::KEY_CLEAR => keysym::XK_Clear,
+            Key::KEY_RED => keysym::XF86XK_Red,
+            Key::KEY_GREEN => keysym::XF86XK_Green,
+            Key::KEY_YELLOW => keysym::XF86XK_Yellow,
+            Key::KEY_BLUE => keysym::XF86XK_Blue,
+            Key::KEY_ZOOMIN => keysym::XF86XK_ZoomIn,
+            Key::KEY_ZOOMOUT => keysym::XF86XK_ZoomOut,
+            Key::KEY_SPELLCHECK => keysym::XF86XK_Spell,
+            Key::KEY_LOGOFF => keysym::XF86XK_LogOff,
+            Key::KEY_TOUCHPAD_TOGGLE => keysym::XF86XK_TouchpadToggle,
+            Key::KEY_SCREENSAVER => keysym::XF86XK_ScreenSaver,
+            _ => return self.to_x11_evdev_keycode(),
         };
 
-        unsafe { Ok(XKeysymToKeycode(display, keysym.into())) }
+        match unsafe { XKeysymToKeycode(display, keysym.into()) } {
+            0 => self.to_x11_evdev_keycode(),
+            keycode => Ok(keycode),
+        }
+    }
+
+    /// Converts a `Key` to the keycode the X evdev driver uses for it, which is its Linux key code
+    /// plus 8.
+    pub fn to_x11_evdev_keycode(self) -> Result<c_uchar, EventMappingError> {
+        evdev::Key::from(self)
+            .code()
+            .checked_add(X11_EVDEV_KEYCODE_OFFSET)
+            .and_then(|keycode| c_uchar::try_from(keycode).ok())
+            .ok_or(EventMappingError::UnsupportedKeyError(self))
     }
 }
 
 impl Button {
-    /// Converts a `Button` to its corresponding X11 button number.
+    /// Converts a `Button` to its corresponding X11 button number, numbered as the X input
+    /// drivers number a local mouse so that thumb buttons keep their usual meaning.
     ///
     /// # Returns
     /// - X11 button number for the given button:
-    ///   - **Left Button:** `1`
-    ///   - **Right Button:** `3`
-    ///   - **Middle Button:** `2`
-    ///   - **Forward Button:** `9`
-    ///   - **Back Button:** `8`
-    pub fn to_x11_button_num(self) -> c_uint {
-        match self {
-            Button::BTN_LEFT => 1,
-            Button::BTN_RIGHT => 3,
-            Button::BTN_MIDDLE => 2,
-            Button::BTN_FORWARD => 9,
-            Button::BTN_BACK => 8,
-        }
+    ///   - **Left Button and Touch:** `1`
+    ///   - **Middle Button and first Stylus Button:** `2`
+    ///   - **Right Button and second Stylus Button:** `3`
+    ///   - **Side, Extra, Forward, Back and Task Buttons:** `8` to `12`
+    ///   - **Other mouse buttons:** from `13`, in code order
+    ///   - **Numbered Buttons:** from `21`, after the mouse buttons
+    /// - `None` for digitizer tools coming into range, which do not click.
+    pub fn to_x11_button_num(self) -> Option<c_uint> {
+        Some(match self {
+            Button::BTN_LEFT | Button::BTN_TOUCH => 1,
+            Button::BTN_MIDDLE | Button::BTN_STYLUS => 2,
+            Button::BTN_RIGHT | Button::BTN_STYLUS2 => 3,
+            Button::BTN_SIDE => 8,
+            Button::BTN_EXTRA => 9,
+            Button::BTN_FORWARD => 10,
+            Button::BTN_BACK => 11,
+            Button::BTN_TASK => 12,
+            Button::BTN_0 => 21,
+            Button::BTN_1 => 22,
+            Button::BTN_2 => 23,
+            Button::BTN_3 => 24,
+            Button::BTN_4 => 25,
+            Button::BTN_5 => 26,
+            Button::BTN_6 => 27,
+            Button::BTN_7 => 28,
+            Button::BTN_8 => 29,
+            Button::BTN_9 => 30,
+            Button::BTN_TOOL_PEN | Button::BTN_TOOL_RUBBER => return None,
+            Button::Raw(code) if DIGITIZER_BUTTON_CODES.contains(&code) => return None,
+            Button::Raw(code @ BTN_SIDE..BTN_JOYSTICK) => 8 + c_uint::from(code - BTN_SIDE),
+            Button::Raw(code) => 21 + c_uint::from(code.wrapping_sub(BTN_MISC)),
+        })
     }
 }
 
@@ -171,6 +332,67 @@ impl PointerAxis {
 
 #[cfg(test)]
 mod test {
+    use crate::{mapper::error::EventMappingError, Button, Key};
+
+    #[test]
+    fn given_some_key_should_map_to_corresponding_x11_keycode() {
+        // Given
+        let keys = [Key::KEY_ESC, Key::KEY_RIGHTCTRL, Key::Raw(0xf0)];
+
+        // When
+        let keycodes: Vec<_> = keys
+            .iter()
+            .map(|key| key.to_x11_evdev_keycode().unwrap())
+            .collect();
+
+        // Then
+        assert_eq!(keycodes, vec![9, 105, 0xf8]);
+    }
+
     #[test]
-    fn given_some_key_should_map_to_corresponding_x11_keycode() {}
+    fn given_buttons_should_map_to_x11_button_numbers() {
+        // Given
+        let buttons = [
+            Button::BTN_LEFT,
+            Button::BTN_SIDE,
+            Button::BTN_TASK,
+            Button::Raw(0x118),
+            Button::BTN_0,
+            Button::Raw(0x10a),
+            Button::BTN_TOUCH,
+            Button::BTN_STYLUS2,
+            Button::BTN_TOOL_PEN,
+        ];
+
+        // When
+        let numbers: Vec<_> = buttons.iter().map(|b| b.to_x11_button_num()).collect();
+
+        // Then
+        assert_eq!(
+            numbers,
+            vec![
+                Some(1),
+                Some(8),
+                Some(12),
+                Some(13),
+                Some(21),
+                Some(31),
+                Some(1),
+                Some(3),
+                None
+            ]
+        );
+    }
+
+    #[test]
+    fn given_key_beyond_x11_keycodes_should_fail() {
+        // When
+        let response = Key::KEY_OK.to_x11_evdev_keycode();
+
+        // Then
+        assert!(matches!(
+            response,
+            Err(EventMappingError::UnsupportedKeyError(Key::KEY_OK))
+        ));
+    }
 }
@@ -1,8 +1,15 @@
-use std::pin::Pin;
+use std::{
+    future::poll_fn,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use futures::Stream;
 
 use input_event::{mapper::error::EventMappingError, InputEvent};
 use thiserror::Error;
 
+pub mod stream;
 pub mod x11;
 
 #[derive(Debug, Error)]
@@ -15,11 +22,8 @@ pub enum DeviceInputError {
 }
 
 // TODO: Consider making a sync version
-// TODO: Consider implementing the Stream<Item = Result<InputEvent, DeviceInputError>> trait
 pub trait DeviceInputStreamTrait: Send {
-    fn next_event(
-        &mut self,
-    ) -> Pin<Box<dyn std::future::Future<Output = Result<InputEvent, DeviceInputError>> + Send + '_>>;
+    fn poll_event(&mut self, cx: &mut Context<'_>) -> Poll<Result<InputEvent, DeviceInputError>>;
     fn grab_device(&mut self) -> Result<(), DeviceInputError>;
     fn ungrab_device(&mut self) -> Result<(), DeviceInputError>;
 }
@@ -31,12 +35,22 @@ pub struct DeviceInputStream {
 impl DeviceInputStream {
     // TODO: support multiple platforms
     pub fn new(stream: evdev::EventStream) -> Self {
+        if x11::discovery::is_touchpad(stream.device()) {
+            return DeviceInputStream::from_backend(x11::touchpad::TouchpadStream::new(stream));
+        }
+        if x11::discovery::is_gamepad(stream.device()) {
+            return DeviceInputStream::from_backend(x11::gamepad::GamepadStream::new(stream));
+        }
+        DeviceInputStream::from_backend(stream)
+    }
+
+    pub fn from_backend(stream: impl DeviceInputStreamTrait + Sync + 'static) -> Self {
         let stream = Box::new(stream);
         DeviceInputStream { stream }
     }
 
     pub async fn next_event(&mut self) -> Result<InputEvent, DeviceInputError> {
-        self.stream.next_event().await
+        poll_fn(|cx| self.stream.poll_event(cx)).await
     }
 
     pub fn grab_device(&mut self) -> Result<(), DeviceInputError> {
@@ -47,3 +61,12 @@ impl DeviceInputStream {
         self.stream.ungrab_device()
     }
 }
+
+/// Never ends, since a device that goes away reports an error instead.
+impl Stream for DeviceInputStream {
+    type Item = Result<InputEvent, DeviceInputError>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.get_mut().stream.poll_event(cx).map(Some)
+    }
+}
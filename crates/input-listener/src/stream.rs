@@ -0,0 +1,364 @@
+//! Combinators for consuming input from many devices as a single stream.
+
+use std::{
+    pin::Pin,
+    task::{Context, Poll, Waker},
+};
+
+use futures::Stream;
+use input_event::InputEvent;
+
+use crate::{DeviceInputError, DeviceInputStream};
+
+/// The events of a changing set of devices, each tagged with the key it was inserted under.
+///
+/// Devices are polled starting after the one that last produced an event, so that a busy device
+/// cannot starve the others. The stream never ends, and waits while it has no devices.
+pub struct MergedInputStream<K> {
+    streams: Vec<(K, DeviceInputStream)>,
+    next: usize,
+    waker: Option<Waker>,
+}
+
+impl<K> Default for MergedInputStream<K> {
+    fn default() -> Self {
+        MergedInputStream {
+            streams: Vec::new(),
+            next: 0,
+            waker: None,
+        }
+    }
+}
+
+impl<K: PartialEq> MergedInputStream<K> {
+    pub fn insert(&mut self, key: K, stream: DeviceInputStream) {
+        self.streams.push((key, stream));
+        // the stream may be waiting with no devices to poll
+        if let Some(waker) = self.waker.take() {
+            waker.wake();
+        }
+    }
+
+    pub fn remove(&mut self, key: &K) -> Option<DeviceInputStream> {
+        let index = self.streams.iter().position(|(k, _)| k == key)?;
+        Some(self.streams.remove(index).1)
+    }
+
+    pub fn get_mut(&mut self, key: &K) -> Option<&mut DeviceInputStream> {
+        self.streams
+            .iter_mut()
+            .find(|(k, _)| k == key)
+            .map(|(_, stream)| stream)
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &K> {
+        self.streams.iter().map(|(key, _)| key)
+    }
+
+    pub fn len(&self) -> usize {
+        self.streams.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.streams.is_empty()
+    }
+
+    /// Grabs every device, even if some fail, and returns the devices that could not be grabbed.
+    pub fn grab_all(&mut self) -> Vec<(K, DeviceInputError)>
+    where
+        K: Clone,
+    {
+        self.for_each_device(DeviceInputStream::grab_device)
+    }
+
+    /// Releases every device, even if some fail, and returns the devices that could not be released.
+    pub fn ungrab_all(&mut self) -> Vec<(K, DeviceInputError)>
+    where
+        K: Clone,
+    {
+        self.for_each_device(DeviceInputStream::ungrab_device)
+    }
+
+    fn for_each_device(
+        &mut self,
+        mut f: impl FnMut(&mut DeviceInputStream) -> Result<(), DeviceInputError>,
+    ) -> Vec<(K, DeviceInputError)>
+    where
+        K: Clone,
+    {
+        self.streams
+            .iter_mut()
+            .filter_map(|(key, stream)| f(stream).err().map(|err| (key.clone(), err)))
+            .collect()
+    }
+}
+
+impl<K: PartialEq> FromIterator<(K, DeviceInputStream)> for MergedInputStream<K> {
+    fn from_iter<I: IntoIterator<Item = (K, DeviceInputStream)>>(iter: I) -> Self {
+        MergedInputStream {
+            streams: iter.into_iter().collect(),
+            ..Default::default()
+        }
+    }
+}
+
+impl<K: Clone + Unpin> Stream for MergedInputStream<K> {
+    type Item = (K, Result<InputEvent, DeviceInputError>);
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        let len = this.streams.len();
+        for offset in 0..len {
+            let index = (this.next + offset) % len;
+            let (key, stream) = &mut this.streams[index];
+            if let Poll::Ready(Some(event)) = Pin::new(stream).poll_next(cx) {
+                this.next = index + 1;
+                return Poll::Ready(Some((key.clone(), event)));
+            }
+        }
+        this.waker = Some(cx.waker().clone());
+        Poll::Pending
+    }
+}
+
+/// Anything a device stream yields, so that combinators work on single and merged streams.
+pub trait DeviceItem {
+    /// Whether this is an event the backend could not map to a generic event.
+    fn is_unmapped(&self) -> bool;
+}
+
+impl DeviceItem for Result<InputEvent, DeviceInputError> {
+    fn is_unmapped(&self) -> bool {
+        matches!(self, Err(DeviceInputError::InputEventConversionError(_)))
+    }
+}
+
+impl<K, T: DeviceItem> DeviceItem for (K, T) {
+    fn is_unmapped(&self) -> bool {
+        self.1.is_unmapped()
+    }
+}
+
+pub trait DeviceStreamExt: Stream + Sized {
+    /// Tags every item with the device it came from.
+    fn tagged<K: Clone>(self, source: K) -> Tagged<Self, K> {
+        Tagged {
+            stream: self,
+            source,
+        }
+    }
+
+    /// Drops events the backend has no generic event for, such as sync and LED events, which
+    /// would otherwise arrive as errors.
+    fn skip_unmapped(self) -> SkipUnmapped<Self>
+    where
+        Self::Item: DeviceItem,
+    {
+        SkipUnmapped { stream: self }
+    }
+}
+
+impl<S: Stream> DeviceStreamExt for S {}
+
+pub struct Tagged<S, K> {
+    stream: S,
+    source: K,
+}
+
+impl<S: Stream + Unpin, K: Clone + Unpin> Stream for Tagged<S, K> {
+    type Item = (K, S::Item);
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        Pin::new(&mut this.stream)
+            .poll_next(cx)
+            .map(|item| item.map(|item| (this.source.clone(), item)))
+    }
+}
+
+pub struct SkipUnmapped<S> {
+    stream: S,
+}
+
+impl<S> SkipUnmapped<S> {
+    pub fn get_ref(&self) -> &S {
+        &self.stream
+    }
+
+    pub fn get_mut(&mut self) -> &mut S {
+        &mut self.stream
+    }
+
+    pub fn into_inner(self) -> S {
+        self.stream
+    }
+}
+
+impl<S: Stream + Unpin> Stream for SkipUnmapped<S>
+where
+    S::Item: DeviceItem,
+{
+    type Item = S::Item;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let stream = &mut self.get_mut().stream;
+        loop {
+            match Pin::new(&mut *stream).poll_next(cx) {
+                Poll::Ready(Some(item)) if item.is_unmapped() => continue,
+                poll => return poll,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        collections::VecDeque,
+        task::{Context, Poll},
+    };
+
+    use futures::{FutureExt, StreamExt};
+    use input_event::{
+        mapper::error::EventMappingError, InputEvent, Key, KeyboardEvent, KeyboardEventType,
+    };
+
+    use super::{DeviceStreamExt, MergedInputStream};
+    use crate::{DeviceInputError, DeviceInputStream, DeviceInputStreamTrait};
+
+    #[derive(Default)]
+    struct FakeDevice {
+        events: VecDeque<Result<InputEvent, DeviceInputError>>,
+        grab_fails: bool,
+    }
+
+    impl DeviceInputStreamTrait for FakeDevice {
+        fn poll_event(
+            &mut self,
+            _cx: &mut Context<'_>,
+        ) -> Poll<Result<InputEvent, DeviceInputError>> {
+            match self.events.pop_front() {
+                Some(event) => Poll::Ready(event),
+                None => Poll::Pending,
+            }
+        }
+
+        fn grab_device(&mut self) -> Result<(), DeviceInputError> {
+            if self.grab_fails {
+                return Err(std::io::Error::other("busy").into());
+            }
+            Ok(())
+        }
+
+        fn ungrab_device(&mut self) -> Result<(), DeviceInputError> {
+            Ok(())
+        }
+    }
+
+    fn press(key: Key) -> InputEvent {
+        InputEvent::Keyboard(KeyboardEvent {
+            event_type: KeyboardEventType::KeyPressed,
+            key,
+        })
+    }
+
+    fn device(events: Vec<Result<InputEvent, DeviceInputError>>) -> DeviceInputStream {
+        DeviceInputStream::from_backend(FakeDevice {
+            events: events.into(),
+            ..Default::default()
+        })
+    }
+
+    /// Takes every item that is ready without waiting.
+    fn ready<S: futures::Stream + Unpin>(stream: &mut S) -> Vec<S::Item> {
+        std::iter::from_fn(|| stream.next().now_or_never().flatten()).collect()
+    }
+
+    #[test]
+    fn given_busy_devices_should_alternate_between_them() {
+        // Given
+        let mut merged: MergedInputStream<_> = [
+            (
+                "a",
+                device(vec![Ok(press(Key::KEY_A)), Ok(press(Key::KEY_A))]),
+            ),
+            (
+                "b",
+                device(vec![Ok(press(Key::KEY_B)), Ok(press(Key::KEY_B))]),
+            ),
+        ]
+        .into_iter()
+        .collect();
+
+        // When
+        let sources: Vec<_> = ready(&mut merged)
+            .into_iter()
+            .map(|(source, _)| source)
+            .collect();
+
+        // Then
+        assert_eq!(sources, vec!["a", "b", "a", "b"]);
+    }
+
+    #[test]
+    fn given_removed_device_should_stop_yielding_its_events() {
+        // Given
+        let mut merged = MergedInputStream::default();
+        merged.insert(1, device(vec![Ok(press(Key::KEY_A))]));
+        merged.insert(2, device(vec![Ok(press(Key::KEY_B))]));
+
+        // When
+        merged.remove(&1);
+
+        // Then
+        let events: Vec<_> = ready(&mut merged)
+            .into_iter()
+            .map(|(source, event)| (source, event.unwrap()))
+            .collect();
+        assert_eq!(events, vec![(2, press(Key::KEY_B))]);
+    }
+
+    #[test]
+    fn given_unmapped_events_should_skip_only_those() {
+        // Given
+        let mut stream = device(vec![
+            Err(EventMappingError::UnsupportedEventType.into()),
+            Ok(press(Key::KEY_A)),
+            Err(std::io::Error::other("unplugged").into()),
+        ])
+        .tagged("keyboard")
+        .skip_unmapped();
+
+        // When
+        let items = ready(&mut stream);
+
+        // Then
+        assert_eq!(items.len(), 2);
+        assert!(matches!(&items[0], ("keyboard", Ok(event)) if *event == press(Key::KEY_A)));
+        assert!(matches!(
+            &items[1],
+            ("keyboard", Err(DeviceInputError::IOError(_)))
+        ));
+    }
+
+    #[test]
+    fn given_device_failing_to_grab_should_still_grab_the_others() {
+        // Given
+        let failing = || {
+            DeviceInputStream::from_backend(FakeDevice {
+                grab_fails: true,
+                ..Default::default()
+            })
+        };
+        let mut merged: MergedInputStream<_> =
+            [("a", failing()), ("b", device(vec![])), ("c", failing())]
+                .into_iter()
+                .collect();
+
+        // When
+        let failed: Vec<_> = merged.grab_all().into_iter().map(|(key, _)| key).collect();
+
+        // Then
+        assert_eq!(failed, vec!["a", "c"]);
+    }
+}
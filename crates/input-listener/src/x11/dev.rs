@@ -1,14 +1,27 @@
-use std::{future::Future, pin::Pin};
+use std::task::{Context, Poll};
 
-use input_event::InputEvent;
+use input_event::{
+    mapper::{
+        error::EventMappingError,
+        x11::evdev::{absolute_event, AbsoluteRange},
+    },
+    InputEvent,
+};
 
 use crate::{DeviceInputError, DeviceInputStreamTrait};
 
 impl DeviceInputStreamTrait for evdev::EventStream {
-    fn next_event(
-        &mut self,
-    ) -> Pin<Box<dyn Future<Output = Result<InputEvent, DeviceInputError>> + Send + '_>> {
-        Box::pin(async { Ok(self.next_event().await?.try_into()?) })
+    fn poll_event(&mut self, cx: &mut Context<'_>) -> Poll<Result<InputEvent, DeviceInputError>> {
+        let event = match evdev::EventStream::poll_event(self, cx) {
+            Poll::Ready(event) => event?,
+            Poll::Pending => return Poll::Pending,
+        };
+        if event.event_type() == evdev::EventType::ABSOLUTE {
+            let range = absolute_range(self.device(), event.code())
+                .ok_or(EventMappingError::InvalidEvent)?;
+            return Poll::Ready(Ok(absolute_event(event, range)?));
+        }
+        Poll::Ready(Ok(event.try_into()?))
     }
 
     fn grab_device(&mut self) -> Result<(), DeviceInputError> {
@@ -22,20 +35,12 @@ impl DeviceInputStreamTrait for evdev::EventStream {
     }
 }
 
-// TODO: move this into a new method for a generic struct
-pub fn pick_device(name: &str) -> evdev::Device {
-    use std::io::prelude::*;
-
-    let mut devices = evdev::enumerate().map(|t| t.1).collect::<Vec<_>>();
-    // readdir returns them in reverse order from their eventN names for some reason
-    devices.reverse();
-    for (i, d) in devices.iter().enumerate() {
-        println!("{}: {}", i, d.name().unwrap_or("Unnamed device"));
-    }
-    print!("Select the {} device [0-{}]: ", name, devices.len());
-    let _ = std::io::stdout().flush();
-    let mut chosen = String::new();
-    std::io::stdin().read_line(&mut chosen).unwrap();
-    let n = chosen.trim().parse::<usize>().unwrap();
-    devices.into_iter().nth(n).unwrap()
+/// The range the device reports an absolute axis in, as read when it was opened.
+pub(crate) fn absolute_range(device: &evdev::Device, code: u16) -> Option<AbsoluteRange> {
+    let info = device.cached_state().abs_vals()?.get(usize::from(code))?;
+    Some(AbsoluteRange {
+        minimum: info.minimum,
+        maximum: info.maximum,
+        resolution: info.resolution,
+    })
 }
@@ -0,0 +1,446 @@
+//! Finds input devices by what they can do, so the server can start without asking which
+//! device is which.
+//!
+//! A keyboard is a device that reports every letter key, a mouse is one that reports `REL_X`,
+//! `REL_Y` and `BTN_LEFT` or is a multitouch touchpad, and a tablet is one that reports `ABS_X`
+//! and `ABS_Y` with a pen or directly on a screen, which covers touchscreens. A gamepad is one
+//! that reports buttons in the joystick or gamepad ranges, which covers joysticks, wheels and
+//! arcade sticks. Configuration can name a device instead, by name, path (including
+//! `/dev/input/by-id` links), or USB vendor and product ID.
+
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use evdev::{AbsoluteAxisType, Key, PropType, RelativeAxisType};
+use input_event::mapper::x11::evdev::{DIGITIZER_BUTTON_CODES, GAMEPAD_BUTTON_CODES};
+use thiserror::Error;
+
+const LETTER_KEYS: [Key; 26] = [
+    Key::KEY_A,
+    Key::KEY_B,
+    Key::KEY_C,
+    Key::KEY_D,
+    Key::KEY_E,
+    Key::KEY_F,
+    Key::KEY_G,
+    Key::KEY_H,
+    Key::KEY_I,
+    Key::KEY_J,
+    Key::KEY_K,
+    Key::KEY_L,
+    Key::KEY_M,
+    Key::KEY_N,
+    Key::KEY_O,
+    Key::KEY_P,
+    Key::KEY_Q,
+    Key::KEY_R,
+    Key::KEY_S,
+    Key::KEY_T,
+    Key::KEY_U,
+    Key::KEY_V,
+    Key::KEY_W,
+    Key::KEY_X,
+    Key::KEY_Y,
+    Key::KEY_Z,
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeviceKind {
+    Keyboard,
+    Mouse,
+    Tablet,
+    Gamepad,
+}
+
+impl fmt::Display for DeviceKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DeviceKind::Keyboard => write!(f, "keyboard"),
+            DeviceKind::Mouse => write!(f, "mouse"),
+            DeviceKind::Tablet => write!(f, "tablet"),
+            DeviceKind::Gamepad => write!(f, "gamepad"),
+        }
+    }
+}
+
+/// How a device is chosen.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub enum DeviceSelector {
+    /// Every device with the capabilities of the wanted kind.
+    #[default]
+    Auto,
+    /// Every device with exactly this name.
+    Name(String),
+    /// The device node at this path, or the node a link such as `/dev/input/by-id/...` points to.
+    Path(PathBuf),
+    /// Every device with this USB vendor and product ID.
+    UsbId { vendor: u16, product: u16 },
+}
+
+impl FromStr for DeviceSelector {
+    type Err = std::convert::Infallible;
+
+    /// Parses `auto`, a path containing `/`, a `vendor:product` pair of four digit hex IDs, or
+    /// otherwise a device name.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s == "auto" {
+            return Ok(DeviceSelector::Auto);
+        }
+        if s.contains('/') {
+            return Ok(DeviceSelector::Path(PathBuf::from(s)));
+        }
+        if let Some((vendor, product)) = s.split_once(':') {
+            let parse = |id: &str| {
+                (id.len() == 4)
+                    .then(|| u16::from_str_radix(id, 16).ok())
+                    .flatten()
+            };
+            if let (Some(vendor), Some(product)) = (parse(vendor), parse(product)) {
+                return Ok(DeviceSelector::UsbId { vendor, product });
+            }
+        }
+        Ok(DeviceSelector::Name(s.to_string()))
+    }
+}
+
+impl fmt::Display for DeviceSelector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DeviceSelector::Auto => write!(f, "auto"),
+            DeviceSelector::Name(name) => write!(f, "name \"{}\"", name),
+            DeviceSelector::Path(path) => write!(f, "path {}", path.display()),
+            DeviceSelector::UsbId { vendor, product } => {
+                write!(f, "id {:04x}:{:04x}", vendor, product)
+            }
+        }
+    }
+}
+
+/// What discovery needs to know about a device.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeviceInfo {
+    pub path: PathBuf,
+    pub name: Option<String>,
+    pub vendor: u16,
+    pub product: u16,
+    pub has_letter_keys: bool,
+    pub has_pointer: bool,
+    pub has_absolute_pointer: bool,
+    pub has_gamepad_buttons: bool,
+}
+
+impl DeviceInfo {
+    pub fn from_device(path: PathBuf, device: &evdev::Device) -> Self {
+        let keys = device.supported_keys();
+        let has_key = |key| keys.is_some_and(|keys| keys.contains(key));
+        let has_axis = |axis| {
+            device
+                .supported_relative_axes()
+                .is_some_and(|axes| axes.contains(axis))
+        };
+        let has_absolute_axis = |axis| {
+            device
+                .supported_absolute_axes()
+                .is_some_and(|axes| axes.contains(axis))
+        };
+        let is_direct = device.properties().contains(PropType::DIRECT);
+        DeviceInfo {
+            path,
+            name: device.name().map(str::to_string),
+            vendor: device.input_id().vendor(),
+            product: device.input_id().product(),
+            has_letter_keys: LETTER_KEYS.into_iter().all(has_key),
+            has_pointer: (has_axis(RelativeAxisType::REL_X)
+                && has_axis(RelativeAxisType::REL_Y)
+                && has_key(Key::BTN_LEFT))
+                || is_touchpad(device),
+            // touchpads also report absolute axes, but move the pointer relative to where it is
+            has_absolute_pointer: has_absolute_axis(AbsoluteAxisType::ABS_X)
+                && has_absolute_axis(AbsoluteAxisType::ABS_Y)
+                && (has_key(Key::BTN_TOOL_PEN) || is_direct),
+            has_gamepad_buttons: is_gamepad(device),
+        }
+    }
+
+    pub fn is(&self, kind: DeviceKind) -> bool {
+        match kind {
+            DeviceKind::Keyboard => self.has_letter_keys,
+            DeviceKind::Mouse => self.has_pointer,
+            DeviceKind::Tablet => self.has_absolute_pointer,
+            DeviceKind::Gamepad => self.has_gamepad_buttons,
+        }
+    }
+
+    pub fn matches(&self, kind: DeviceKind, selector: &DeviceSelector) -> bool {
+        match selector {
+            DeviceSelector::Auto => self.is(kind),
+            DeviceSelector::Name(name) => self.name.as_deref() == Some(name.as_str()),
+            DeviceSelector::Path(path) => same_file(path, &self.path),
+            DeviceSelector::UsbId { vendor, product } => {
+                self.vendor == *vendor && self.product == *product
+            }
+        }
+    }
+}
+
+/// Whether `device` is a touchpad reporting fingers with the multitouch protocol B, as opposed
+/// to a touchscreen or a tablet, which report positions on a screen.
+pub fn is_touchpad(device: &evdev::Device) -> bool {
+    let has_axis = |axis| {
+        device
+            .supported_absolute_axes()
+            .is_some_and(|axes| axes.contains(axis))
+    };
+    let has_key = |key| {
+        device
+            .supported_keys()
+            .is_some_and(|keys| keys.contains(key))
+    };
+    has_axis(AbsoluteAxisType::ABS_MT_SLOT)
+        && has_axis(AbsoluteAxisType::ABS_MT_POSITION_X)
+        && has_axis(AbsoluteAxisType::ABS_MT_POSITION_Y)
+        && has_key(Key::BTN_TOOL_FINGER)
+        && !has_key(Key::BTN_TOOL_PEN)
+        && !device.properties().contains(PropType::DIRECT)
+}
+
+/// Whether `device` is a game controller or joystick. The motion sensors of a controller are a
+/// device of their own, which reports no buttons. The button pads of graphics tablets number
+/// their buttons in the same ranges, but also report a digitizer button.
+pub fn is_gamepad(device: &evdev::Device) -> bool {
+    let Some(keys) = device.supported_keys() else {
+        return false;
+    };
+    let has_code_in =
+        |codes: std::ops::Range<u16>| keys.iter().any(|key| codes.contains(&key.code()));
+    has_code_in(GAMEPAD_BUTTON_CODES)
+        && !has_code_in(DIGITIZER_BUTTON_CODES)
+        && !device.properties().contains(PropType::DIRECT)
+}
+
+impl fmt::Display for DeviceInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} \"{}\" ({:04x}:{:04x})",
+            self.path.display(),
+            self.name.as_deref().unwrap_or("Unnamed device"),
+            self.vendor,
+            self.product
+        )
+    }
+}
+
+fn same_file(a: &Path, b: &Path) -> bool {
+    match (a.canonicalize(), b.canonicalize()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a == b,
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum DiscoveryError {
+    #[error("No {kind} matches {selector}. Available devices:{}", list(.available))]
+    NoMatch {
+        kind: DeviceKind,
+        selector: DeviceSelector,
+        available: Vec<DeviceInfo>,
+    },
+}
+
+fn list(devices: &[DeviceInfo]) -> String {
+    if devices.is_empty() {
+        return " none, is the server running with permission to read /dev/input?".to_string();
+    }
+    devices
+        .iter()
+        .map(|device| format!("\n  {}", device))
+        .collect()
+}
+
+/// Picks the devices for `kind` out of `devices`, keeping their order.
+pub fn find_devices<'a>(
+    devices: &'a [DeviceInfo],
+    kind: DeviceKind,
+    selector: &DeviceSelector,
+) -> Result<Vec<&'a DeviceInfo>, DiscoveryError> {
+    let found: Vec<_> = devices
+        .iter()
+        .filter(|device| device.matches(kind, selector))
+        .collect();
+    if found.is_empty() {
+        return Err(DiscoveryError::NoMatch {
+            kind,
+            selector: selector.clone(),
+            available: devices.to_vec(),
+        });
+    }
+    Ok(found)
+}
+
+/// Lists the input devices on this machine in event number order.
+pub fn list_devices() -> Vec<DeviceInfo> {
+    let mut devices: Vec<_> = evdev::enumerate()
+        .map(|(path, device)| DeviceInfo::from_device(path, &device))
+        .collect();
+    devices.sort_by_key(|device| event_number(&device.path));
+    devices
+}
+
+fn event_number(path: &Path) -> Option<u32> {
+    path.file_name()?
+        .to_str()?
+        .strip_prefix("event")?
+        .parse()
+        .ok()
+}
+
+/// Finds the paths of the devices for `kind` on this machine.
+pub fn discover(
+    kind: DeviceKind,
+    selector: &DeviceSelector,
+) -> Result<Vec<PathBuf>, DiscoveryError> {
+    let devices = list_devices();
+    let found = find_devices(&devices, kind, selector)?;
+    for device in found.iter() {
+        println!("Using {} {}", kind, device);
+    }
+    Ok(found
+        .into_iter()
+        .map(|device| device.path.clone())
+        .collect())
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::PathBuf;
+
+    use super::{find_devices, DeviceInfo, DeviceKind, DeviceSelector, DiscoveryError};
+
+    fn device(n: u32, name: &str, has_letter_keys: bool, has_pointer: bool) -> DeviceInfo {
+        DeviceInfo {
+            path: PathBuf::from(format!("/dev/input/event{}", n)),
+            name: Some(name.to_string()),
+            vendor: 0x046d,
+            product: n as u16,
+            has_letter_keys,
+            has_pointer,
+            has_absolute_pointer: false,
+            has_gamepad_buttons: false,
+        }
+    }
+
+    fn devices() -> Vec<DeviceInfo> {
+        vec![
+            device(0, "Power Button", false, false),
+            device(1, "USB Receiver Mouse", false, true),
+            device(2, "USB Receiver Keyboard", true, false),
+            device(3, "Laptop Keyboard", true, false),
+        ]
+    }
+
+    #[test]
+    fn given_auto_should_pick_every_device_with_capabilities() {
+        // Given
+        let devices = devices();
+
+        // When
+        let keyboards = find_devices(&devices, DeviceKind::Keyboard, &DeviceSelector::Auto);
+        let mice = find_devices(&devices, DeviceKind::Mouse, &DeviceSelector::Auto);
+
+        // Then
+        assert_eq!(keyboards.unwrap(), vec![&devices[2], &devices[3]]);
+        assert_eq!(mice.unwrap(), vec![&devices[1]]);
+    }
+
+    #[test]
+    fn given_auto_tablet_should_pick_only_absolute_pointers() {
+        // Given
+        let mut devices = devices();
+        devices.push(DeviceInfo {
+            has_absolute_pointer: true,
+            ..device(4, "Wacom Intuos Pen", false, false)
+        });
+
+        // When
+        let tablets = find_devices(&devices, DeviceKind::Tablet, &DeviceSelector::Auto);
+
+        // Then
+        assert_eq!(tablets.unwrap(), vec![&devices[4]]);
+    }
+
+    #[test]
+    fn given_auto_gamepad_should_pick_only_controllers() {
+        // Given
+        let mut devices = devices();
+        devices.push(DeviceInfo {
+            has_gamepad_buttons: true,
+            ..device(4, "Xbox Wireless Controller", false, false)
+        });
+
+        // When
+        let gamepads = find_devices(&devices, DeviceKind::Gamepad, &DeviceSelector::Auto);
+
+        // Then
+        assert_eq!(gamepads.unwrap(), vec![&devices[4]]);
+    }
+
+    #[test]
+    fn given_configured_selector_should_override_auto_detection() {
+        // Given
+        let devices = devices();
+        let by_name: DeviceSelector = "Laptop Keyboard".parse().unwrap();
+        let by_id: DeviceSelector = "046d:0003".parse().unwrap();
+        let by_path: DeviceSelector = "/dev/input/event3".parse().unwrap();
+
+        // When
+        let found: Vec<_> = [by_name, by_id, by_path]
+            .iter()
+            .map(|selector| find_devices(&devices, DeviceKind::Keyboard, selector).unwrap())
+            .collect();
+
+        // Then
+        assert!(found.iter().all(|found| found == &vec![&devices[3]]));
+    }
+
+    #[test]
+    fn given_no_matching_device_should_list_available_devices() {
+        // Given
+        let devices = vec![device(0, "Power Button", false, false)];
+
+        // When
+        let response = find_devices(&devices, DeviceKind::Mouse, &DeviceSelector::Auto);
+
+        // Then
+        let Err(err @ DiscoveryError::NoMatch { .. }) = response else {
+            panic!("Expected no match");
+        };
+        assert!(err.to_string().contains("Power Button"));
+    }
+
+    #[test]
+    fn given_selector_strings_should_parse_each_form() {
+        // Given
+        let inputs = ["auto", "/dev/input/by-id/usb-kbd", "1a2b:3c4d", "My Mouse"];
+
+        // When
+        let selectors: Vec<DeviceSelector> = inputs.iter().map(|s| s.parse().unwrap()).collect();
+
+        // Then
+        assert_eq!(
+            selectors,
+            vec![
+                DeviceSelector::Auto,
+                DeviceSelector::Path(PathBuf::from("/dev/input/by-id/usb-kbd")),
+                DeviceSelector::UsbId {
+                    vendor: 0x1a2b,
+                    product: 0x3c4d
+                },
+                DeviceSelector::Name("My Mouse".to_string()),
+            ]
+        );
+    }
+}
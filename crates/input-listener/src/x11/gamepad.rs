@@ -0,0 +1,127 @@
+//! Reads game controllers and joysticks.
+//!
+//! Their buttons and axes overlap with those of keyboards and tablets, so a controller's events
+//! are mapped here rather than by the generic mapping, which cannot tell that `ABS_X` is a stick
+//! and not a pen. Each controller is numbered as it is opened, and its first event announces
+//! what it reports so the client can make a virtual controller that matches it.
+
+use std::{
+    sync::atomic::{AtomicU32, Ordering},
+    task::{Context, Poll},
+};
+
+use input_event::{
+    mapper::x11::evdev::gamepad_input, GamepadAxis, GamepadAxisInfo, GamepadButton,
+    GamepadCapabilities, GamepadEvent, GamepadId, GamepadInput, InputEvent,
+};
+
+use crate::{DeviceInputError, DeviceInputStreamTrait};
+
+use super::dev::absolute_range;
+
+static NEXT_PAD: AtomicU32 = AtomicU32::new(0);
+
+pub struct GamepadStream {
+    stream: evdev::EventStream,
+    pad: GamepadId,
+    /// What the controller reports, until it has been announced.
+    unannounced: Option<GamepadCapabilities>,
+}
+
+impl GamepadStream {
+    pub fn new(stream: evdev::EventStream) -> Self {
+        let capabilities = capabilities(stream.device());
+        GamepadStream {
+            stream,
+            pad: NEXT_PAD.fetch_add(1, Ordering::Relaxed),
+            unannounced: Some(capabilities),
+        }
+    }
+
+    fn event(&self, input: GamepadInput) -> InputEvent {
+        InputEvent::Gamepad(GamepadEvent {
+            pad: self.pad,
+            input,
+        })
+    }
+}
+
+impl DeviceInputStreamTrait for GamepadStream {
+    fn poll_event(&mut self, cx: &mut Context<'_>) -> Poll<Result<InputEvent, DeviceInputError>> {
+        if let Some(capabilities) = self.unannounced.take() {
+            return Poll::Ready(Ok(self.event(GamepadInput::Connected(capabilities))));
+        }
+        let event = match self.stream.poll_event(cx) {
+            Poll::Ready(event) => event?,
+            Poll::Pending => return Poll::Pending,
+        };
+        let input = match event.kind() {
+            evdev::InputEventKind::Key(key) if GamepadButton::try_from(key).is_ok() => {
+                gamepad_input(event, None)?
+            }
+            evdev::InputEventKind::AbsAxis(axis) => {
+                gamepad_input(event, absolute_range(self.stream.device(), axis.0))?
+            }
+            // such as the guide button of some controllers, which is reported as a key
+            _ => return Poll::Ready(Ok(event.try_into()?)),
+        };
+        Poll::Ready(Ok(self.event(input)))
+    }
+
+    fn grab_device(&mut self) -> Result<(), DeviceInputError> {
+        self.stream.device_mut().grab()?;
+        Ok(())
+    }
+
+    fn ungrab_device(&mut self) -> Result<(), DeviceInputError> {
+        self.stream.device_mut().ungrab()?;
+        Ok(())
+    }
+}
+
+/// Reads what `device` reports, with each axis resting where it was when the device was opened.
+fn capabilities(device: &evdev::Device) -> GamepadCapabilities {
+    let buttons = device
+        .supported_keys()
+        .map(|keys| {
+            keys.iter()
+                .filter_map(|key| GamepadButton::try_from(key).ok())
+                .collect()
+        })
+        .unwrap_or_default();
+    let axes = device
+        .supported_absolute_axes()
+        .map(|axes| {
+            axes.iter()
+                .filter_map(|axis| axis_info(device, axis))
+                .collect()
+        })
+        .unwrap_or_default();
+    let id = device.input_id();
+    GamepadCapabilities {
+        name: device.name().unwrap_or("Gamepad").to_string(),
+        bus_type: id.bus_type().0,
+        vendor: id.vendor(),
+        product: id.product(),
+        version: id.version(),
+        buttons,
+        axes,
+    }
+}
+
+fn axis_info(device: &evdev::Device, axis: evdev::AbsoluteAxisType) -> Option<GamepadAxisInfo> {
+    let axis_type = GamepadAxis::try_from(axis).ok()?;
+    let current = device
+        .cached_state()
+        .abs_vals()?
+        .get(usize::from(axis.0))?
+        .value;
+    let event = evdev::InputEvent::new(evdev::EventType::ABSOLUTE, axis.0, current);
+    match gamepad_input(event, absolute_range(device, axis.0)) {
+        Ok(GamepadInput::Axis { value, .. }) => Some(GamepadAxisInfo {
+            axis: axis_type,
+            rest: value,
+        }),
+        _ => None,
+    }
+}
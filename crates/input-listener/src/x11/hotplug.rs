@@ -0,0 +1,137 @@
+//! Watches `/dev/input` for input devices being plugged in and unplugged.
+//!
+//! A new node is reported both when it is created and when its attributes change, because udev
+//! only makes it readable a moment after creating it. Listeners should ignore a node they already
+//! have open.
+
+use std::{
+    collections::VecDeque,
+    ffi::OsStr,
+    io,
+    os::unix::io::{AsRawFd, RawFd},
+    path::{Path, PathBuf},
+};
+
+use nix::{
+    errno::Errno,
+    sys::inotify::{AddWatchFlags, InitFlags, Inotify, InotifyEvent},
+};
+use tokio::io::unix::AsyncFd;
+
+pub const INPUT_DEVICE_DIR: &str = "/dev/input";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DeviceChange {
+    Added(PathBuf),
+    Removed(PathBuf),
+}
+
+pub struct DeviceWatcher {
+    dir: PathBuf,
+    inotify: AsyncFd<OwnedInotify>,
+    changes: VecDeque<DeviceChange>,
+}
+
+/// Closes the inotify instance when dropped, which nix leaves to the caller.
+struct OwnedInotify(Inotify);
+
+impl AsRawFd for OwnedInotify {
+    fn as_raw_fd(&self) -> RawFd {
+        self.0.as_raw_fd()
+    }
+}
+
+impl Drop for OwnedInotify {
+    fn drop(&mut self) {
+        let _ = nix::unistd::close(self.0.as_raw_fd());
+    }
+}
+
+impl DeviceWatcher {
+    pub fn new() -> io::Result<Self> {
+        DeviceWatcher::watch(Path::new(INPUT_DEVICE_DIR))
+    }
+
+    pub fn watch(dir: &Path) -> io::Result<Self> {
+        let inotify = OwnedInotify(Inotify::init(
+            InitFlags::IN_NONBLOCK | InitFlags::IN_CLOEXEC,
+        )?);
+        inotify.0.add_watch(
+            dir,
+            AddWatchFlags::IN_CREATE | AddWatchFlags::IN_ATTRIB | AddWatchFlags::IN_DELETE,
+        )?;
+        Ok(DeviceWatcher {
+            dir: dir.to_path_buf(),
+            inotify: AsyncFd::new(inotify)?,
+            changes: VecDeque::new(),
+        })
+    }
+
+    /// Waits for the next device to appear or disappear.
+    pub async fn next_change(&mut self) -> io::Result<DeviceChange> {
+        loop {
+            if let Some(change) = self.changes.pop_front() {
+                return Ok(change);
+            }
+            let mut guard = self.inotify.readable().await?;
+            match guard.get_inner().0.read_events() {
+                Ok(events) => {
+                    let dir = &self.dir;
+                    self.changes
+                        .extend(events.iter().filter_map(|event| to_change(dir, event)));
+                }
+                Err(Errno::EAGAIN) => guard.clear_ready(),
+                Err(err) => return Err(err.into()),
+            }
+        }
+    }
+}
+
+fn to_change(dir: &Path, event: &InotifyEvent) -> Option<DeviceChange> {
+    let name = event.name.as_deref()?;
+    if !is_event_node(name) {
+        return None;
+    }
+    let path = dir.join(name);
+    if event.mask.contains(AddWatchFlags::IN_DELETE) {
+        Some(DeviceChange::Removed(path))
+    } else {
+        Some(DeviceChange::Added(path))
+    }
+}
+
+fn is_event_node(name: &OsStr) -> bool {
+    name.to_str()
+        .and_then(|name| name.strip_prefix("event"))
+        .is_some_and(|n| !n.is_empty() && n.bytes().all(|b| b.is_ascii_digit()))
+}
+
+#[cfg(test)]
+mod test {
+    use std::{fs, time::Duration};
+
+    use tokio::time::timeout;
+
+    use super::{DeviceChange, DeviceWatcher};
+
+    #[tokio::test]
+    async fn given_event_node_created_and_deleted_should_report_both() {
+        // Given
+        let dir = std::env::temp_dir().join(format!("hotplug-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let mut watcher = DeviceWatcher::watch(&dir).unwrap();
+        let path = dir.join("event7");
+
+        // When
+        fs::write(dir.join("mouse0"), []).unwrap();
+        fs::write(&path, []).unwrap();
+        let added = timeout(Duration::from_secs(1), watcher.next_change()).await;
+        fs::remove_file(&path).unwrap();
+        let removed = timeout(Duration::from_secs(1), watcher.next_change()).await;
+
+        // Then
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(added.unwrap().unwrap(), DeviceChange::Added(path.clone()));
+        assert_eq!(removed.unwrap().unwrap(), DeviceChange::Removed(path));
+    }
+}
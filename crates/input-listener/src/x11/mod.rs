@@ -1 +1,5 @@
 pub mod dev;
+pub mod discovery;
+pub mod gamepad;
+pub mod hotplug;
+pub mod touchpad;
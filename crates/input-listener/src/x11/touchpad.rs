@@ -0,0 +1,280 @@
+//! Follows the fingers on a touchpad, which the kernel reports with the multitouch protocol B.
+//!
+//! Each finger is tracked in a slot. `ABS_MT_SLOT` selects the slot the events after it are
+//! about, a tracking ID of -1 lifts the finger in that slot, and `SYN_REPORT` ends the report.
+//! Every report that changes a finger becomes one `TouchFrame` listing all fingers. The
+//! single-touch axes and tool buttons that a touchpad reports as well are dropped, since the
+//! frame already says the same.
+
+use std::task::{Context, Poll};
+
+use input_event::{
+    mapper::x11::evdev::{AbsoluteRange, DIGITIZER_BUTTON_CODES},
+    InputEvent, TouchContact, TouchFrame,
+};
+
+use crate::{DeviceInputError, DeviceInputStreamTrait};
+
+use super::dev::absolute_range;
+
+/// Slots assumed for a touchpad that does not give the range of `ABS_MT_SLOT`.
+const DEFAULT_SLOTS: usize = 5;
+
+pub struct TouchpadStream {
+    stream: evdev::EventStream,
+    tracker: TouchTracker,
+}
+
+impl TouchpadStream {
+    pub fn new(stream: evdev::EventStream) -> Self {
+        let device = stream.device();
+        let range = |axis: evdev::AbsoluteAxisType| absolute_range(device, axis.0);
+        let unknown = AbsoluteRange {
+            minimum: 0,
+            maximum: 0,
+            resolution: 0,
+        };
+        let slots = range(evdev::AbsoluteAxisType::ABS_MT_SLOT)
+            .and_then(|slots| usize::try_from(slots.maximum).ok())
+            .map_or(DEFAULT_SLOTS, |maximum| maximum + 1);
+        let tracker = TouchTracker::new(
+            range(evdev::AbsoluteAxisType::ABS_MT_POSITION_X).unwrap_or(unknown),
+            range(evdev::AbsoluteAxisType::ABS_MT_POSITION_Y).unwrap_or(unknown),
+            slots,
+        );
+        TouchpadStream { stream, tracker }
+    }
+}
+
+impl DeviceInputStreamTrait for TouchpadStream {
+    fn poll_event(&mut self, cx: &mut Context<'_>) -> Poll<Result<InputEvent, DeviceInputError>> {
+        loop {
+            let event = match self.stream.poll_event(cx) {
+                Poll::Ready(event) => event?,
+                Poll::Pending => return Poll::Pending,
+            };
+            match self.tracker.track(event) {
+                Tracked::Consumed => continue,
+                Tracked::Frame(frame) => return Poll::Ready(Ok(InputEvent::Touch(frame))),
+                Tracked::Passed(event) => return Poll::Ready(Ok(event.try_into()?)),
+            }
+        }
+    }
+
+    fn grab_device(&mut self) -> Result<(), DeviceInputError> {
+        self.stream.device_mut().grab()?;
+        Ok(())
+    }
+
+    fn ungrab_device(&mut self) -> Result<(), DeviceInputError> {
+        self.stream.device_mut().ungrab()?;
+        Ok(())
+    }
+}
+
+enum Tracked {
+    /// Part of a report, or something the frames already cover.
+    Consumed,
+    /// The end of a report that changed a finger.
+    Frame(TouchFrame),
+    /// Not about fingers, such as the click of a clickpad.
+    Passed(evdev::InputEvent),
+}
+
+/// Where a finger is, in device units.
+#[derive(Debug, Default, Clone, Copy)]
+struct Finger {
+    x: i32,
+    y: i32,
+}
+
+struct TouchTracker {
+    x: AbsoluteRange,
+    y: AbsoluteRange,
+    slot: usize,
+    slots: Vec<Option<Finger>>,
+    changed: bool,
+}
+
+impl TouchTracker {
+    fn new(x: AbsoluteRange, y: AbsoluteRange, slots: usize) -> Self {
+        TouchTracker {
+            x,
+            y,
+            slot: 0,
+            // a slot is sent as a u8
+            slots: vec![None; slots.min(usize::from(u8::MAX) + 1)],
+            changed: false,
+        }
+    }
+
+    fn track(&mut self, event: evdev::InputEvent) -> Tracked {
+        match event.kind() {
+            evdev::InputEventKind::AbsAxis(axis) => {
+                self.track_axis(axis, event.value());
+                Tracked::Consumed
+            }
+            evdev::InputEventKind::Key(evdev::Key(code))
+                if DIGITIZER_BUTTON_CODES.contains(&code) =>
+            {
+                Tracked::Consumed
+            }
+            evdev::InputEventKind::Synchronization(evdev::Synchronization::SYN_REPORT) => {
+                if !std::mem::take(&mut self.changed) {
+                    return Tracked::Consumed;
+                }
+                Tracked::Frame(self.frame())
+            }
+            _ => Tracked::Passed(event),
+        }
+    }
+
+    fn track_axis(&mut self, axis: evdev::AbsoluteAxisType, value: i32) {
+        if axis == evdev::AbsoluteAxisType::ABS_MT_SLOT {
+            self.slot = usize::try_from(value).unwrap_or(usize::MAX);
+            return;
+        }
+        // events for a slot beyond those the device gave are ignored
+        let Some(finger) = self.slots.get_mut(self.slot) else {
+            return;
+        };
+        match axis {
+            evdev::AbsoluteAxisType::ABS_MT_TRACKING_ID if value < 0 => *finger = None,
+            evdev::AbsoluteAxisType::ABS_MT_TRACKING_ID => {
+                *finger = Some(finger.unwrap_or_default())
+            }
+            evdev::AbsoluteAxisType::ABS_MT_POSITION_X => {
+                finger.get_or_insert_with(Finger::default).x = value
+            }
+            evdev::AbsoluteAxisType::ABS_MT_POSITION_Y => {
+                finger.get_or_insert_with(Finger::default).y = value
+            }
+            _ => return,
+        }
+        self.changed = true;
+    }
+
+    fn frame(&self) -> TouchFrame {
+        TouchFrame {
+            width_mm: self.x.millimetres(),
+            height_mm: self.y.millimetres(),
+            contacts: self
+                .slots
+                .iter()
+                .enumerate()
+                .filter_map(|(slot, finger)| {
+                    finger.map(|finger| TouchContact {
+                        slot: slot as u8,
+                        x: self.x.normalise(finger.x),
+                        y: self.y.normalise(finger.y),
+                    })
+                })
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use input_event::{mapper::x11::evdev::AbsoluteRange, TouchContact, TouchFrame, ABSOLUTE_MAX};
+
+    use super::{TouchTracker, Tracked};
+
+    fn abs(axis: evdev::AbsoluteAxisType, value: i32) -> evdev::InputEvent {
+        evdev::InputEvent::new(evdev::EventType::ABSOLUTE, axis.0, value)
+    }
+
+    fn report() -> evdev::InputEvent {
+        evdev::InputEvent::new(
+            evdev::EventType::SYNCHRONIZATION,
+            evdev::Synchronization::SYN_REPORT.0,
+            0,
+        )
+    }
+
+    fn tracker() -> TouchTracker {
+        let range = AbsoluteRange {
+            minimum: 0,
+            maximum: 1000,
+            resolution: 10,
+        };
+        TouchTracker::new(range, range, 5)
+    }
+
+    fn frame(contacts: Vec<TouchContact>) -> TouchFrame {
+        TouchFrame {
+            width_mm: 100,
+            height_mm: 100,
+            contacts,
+        }
+    }
+
+    #[test]
+    fn given_two_fingers_down_and_one_lifted_should_report_frames() {
+        // Given
+        let mut tracker = tracker();
+        let events = [
+            abs(evdev::AbsoluteAxisType::ABS_MT_SLOT, 0),
+            abs(evdev::AbsoluteAxisType::ABS_MT_TRACKING_ID, 7),
+            abs(evdev::AbsoluteAxisType::ABS_MT_POSITION_X, 0),
+            abs(evdev::AbsoluteAxisType::ABS_MT_POSITION_Y, 500),
+            abs(evdev::AbsoluteAxisType::ABS_MT_SLOT, 1),
+            abs(evdev::AbsoluteAxisType::ABS_MT_TRACKING_ID, 8),
+            abs(evdev::AbsoluteAxisType::ABS_MT_POSITION_X, 1000),
+            abs(evdev::AbsoluteAxisType::ABS_MT_POSITION_Y, 500),
+            // the single-touch copy of the first finger
+            abs(evdev::AbsoluteAxisType::ABS_X, 0),
+            evdev::InputEvent::new(evdev::EventType::KEY, evdev::Key::BTN_TOOL_DOUBLETAP.0, 1),
+            report(),
+            abs(evdev::AbsoluteAxisType::ABS_MT_SLOT, 0),
+            abs(evdev::AbsoluteAxisType::ABS_MT_TRACKING_ID, -1),
+            report(),
+            // nothing changed
+            report(),
+        ];
+
+        // When
+        let tracked: Vec<_> = events
+            .into_iter()
+            .filter_map(|event| match tracker.track(event) {
+                Tracked::Consumed => None,
+                Tracked::Frame(frame) => Some(frame),
+                Tracked::Passed(event) => panic!("Passed through {:?}", event.kind()),
+            })
+            .collect();
+
+        // Then
+        let right = TouchContact {
+            slot: 1,
+            x: ABSOLUTE_MAX,
+            y: ABSOLUTE_MAX / 2,
+        };
+        assert_eq!(
+            tracked,
+            vec![
+                frame(vec![
+                    TouchContact {
+                        slot: 0,
+                        x: 0,
+                        y: ABSOLUTE_MAX / 2,
+                    },
+                    right,
+                ]),
+                frame(vec![right]),
+            ]
+        );
+    }
+
+    #[test]
+    fn given_clickpad_button_should_pass_it_through() {
+        // Given
+        let mut tracker = tracker();
+        let click = evdev::InputEvent::new(evdev::EventType::KEY, evdev::Key::BTN_LEFT.0, 1);
+
+        // When
+        let tracked = tracker.track(click);
+
+        // Then
+        assert!(matches!(tracked, Tracked::Passed(event) if event.code() == click.code()));
+    }
+}
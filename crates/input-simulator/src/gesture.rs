@@ -0,0 +1,338 @@
+//! Turns touch frames into the pointer motion, scrolling and zooming a touchpad would cause, for
+//! outputs that cannot replay the fingers themselves.
+//!
+//! One finger moves the pointer and two fingers scroll. Two fingers moving apart or together
+//! zoom, sent as scrolling with Ctrl held, which most applications treat as zooming. Ctrl is only
+//! pressed and released around it when the user is not already holding it. Nothing is
+//! sent for a frame whose fingers differ from the frame before, so a finger landing or lifting
+//! never moves anything.
+
+use input_event::{
+    InputEvent, Key, KeyboardEvent, KeyboardEventType, MouseEvent, PointerAxis, TouchContact,
+    TouchFrame, ABSOLUTE_MAX,
+};
+
+/// Assumed for a touchpad that does not give its size.
+const DEFAULT_SURFACE_MM: u16 = 100;
+const POINTER_PIXELS_PER_MM: i64 = 8;
+const SCROLL_UM_PER_DETENT: i64 = 5_000;
+const ZOOM_UM_PER_STEP: i64 = 10_000;
+
+#[derive(Debug, Default)]
+pub struct GestureTranslator {
+    previous: Vec<TouchContact>,
+    /// Movement in micrometres that has not yet added up to a pixel, detent or zoom step.
+    motion: [i64; 2],
+    scroll: [i64; 2],
+    zoom: i64,
+    /// The Ctrl keys held on the output, which a pinch must leave held.
+    held_ctrl: Vec<Key>,
+}
+
+impl GestureTranslator {
+    pub fn translate(&mut self, frame: &TouchFrame) -> Vec<InputEvent> {
+        let previous = std::mem::replace(&mut self.previous, frame.contacts.clone());
+        let same_fingers = previous.len() == frame.contacts.len()
+            && previous
+                .iter()
+                .zip(&frame.contacts)
+                .all(|(before, now)| before.slot == now.slot);
+        if !same_fingers {
+            self.motion = [0; 2];
+            self.scroll = [0; 2];
+            self.zoom = 0;
+            return Vec::new();
+        }
+
+        let surface = Surface::of(frame);
+        match (previous.as_slice(), frame.contacts.as_slice()) {
+            ([before], [now]) => self.pointer(surface.movement(before, now)),
+            ([a_before, b_before], [a_now, b_now]) => {
+                let (a, b) = (
+                    surface.movement(a_before, a_now),
+                    surface.movement(b_before, b_now),
+                );
+                let centre = [(a[0] + b[0]) / 2, (a[1] + b[1]) / 2];
+                let spread = surface.distance(a_now, b_now) - surface.distance(a_before, b_before);
+                if spread.abs() > centre[0].abs().max(centre[1].abs()) {
+                    self.pinch(spread)
+                } else {
+                    self.two_finger_scroll(centre)
+                }
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    /// Notes an event sent to the output alongside the gestures, to know which keys are held.
+    pub fn observe(&mut self, event: &InputEvent) {
+        let InputEvent::Keyboard(KeyboardEvent {
+            event_type,
+            key: key @ (Key::KEY_LEFTCTRL | Key::KEY_RIGHTCTRL),
+        }) = *event
+        else {
+            return;
+        };
+        self.held_ctrl.retain(|held| *held != key);
+        if event_type != KeyboardEventType::KeyReleased {
+            self.held_ctrl.push(key);
+        }
+    }
+
+    /// Forgets the keys held on the output, once they have all been released.
+    pub fn release_all(&mut self) {
+        self.held_ctrl.clear();
+    }
+
+    fn pointer(&mut self, movement: [i64; 2]) -> Vec<InputEvent> {
+        let mut events = Vec::new();
+        for (idx, axis) in [PointerAxis::Horizontal, PointerAxis::Vertical]
+            .into_iter()
+            .enumerate()
+        {
+            let pixels = take_steps(
+                &mut self.motion[idx],
+                movement[idx],
+                1_000 / POINTER_PIXELS_PER_MM,
+            );
+            if pixels != 0 {
+                events.push(InputEvent::Mouse(MouseEvent::Motion { axis, diff: pixels }));
+            }
+        }
+        events
+    }
+
+    fn two_finger_scroll(&mut self, movement: [i64; 2]) -> Vec<InputEvent> {
+        let mut events = Vec::new();
+        let right = take_steps(&mut self.scroll[0], movement[0], SCROLL_UM_PER_DETENT);
+        if right != 0 {
+            events.push(InputEvent::Mouse(MouseEvent::Scroll {
+                axis: PointerAxis::Horizontal,
+                diff: right,
+            }));
+        }
+        // fingers moving down scroll down, as a wheel turned towards the user does
+        let down = take_steps(&mut self.scroll[1], movement[1], SCROLL_UM_PER_DETENT);
+        if down != 0 {
+            events.push(InputEvent::Mouse(MouseEvent::Scroll {
+                axis: PointerAxis::Vertical,
+                diff: -down,
+            }));
+        }
+        events
+    }
+
+    fn pinch(&mut self, spread: i64) -> Vec<InputEvent> {
+        let steps = take_steps(&mut self.zoom, spread, ZOOM_UM_PER_STEP);
+        if steps == 0 {
+            return Vec::new();
+        }
+        let scroll = InputEvent::Mouse(MouseEvent::Scroll {
+            axis: PointerAxis::Vertical,
+            diff: steps,
+        });
+        // releasing a Ctrl the user holds would end their own zoom or shortcut
+        if !self.held_ctrl.is_empty() {
+            return vec![scroll];
+        }
+        let ctrl = |event_type| {
+            InputEvent::Keyboard(KeyboardEvent {
+                event_type,
+                key: Key::KEY_LEFTCTRL,
+            })
+        };
+        vec![
+            ctrl(KeyboardEventType::KeyPressed),
+            scroll,
+            ctrl(KeyboardEventType::KeyReleased),
+        ]
+    }
+}
+
+/// Adds `movement` to `pending` and takes out as many whole steps of `step` as it holds.
+fn take_steps(pending: &mut i64, movement: i64, step: i64) -> i32 {
+    *pending += movement;
+    let steps = *pending / step;
+    *pending -= steps * step;
+    steps as i32
+}
+
+/// The size of the touch surface in micrometres.
+#[derive(Debug, Clone, Copy)]
+struct Surface {
+    width: i64,
+    height: i64,
+}
+
+impl Surface {
+    fn of(frame: &TouchFrame) -> Self {
+        let micrometres = |mm: u16| {
+            let mm = if mm == 0 { DEFAULT_SURFACE_MM } else { mm };
+            i64::from(mm) * 1_000
+        };
+        Surface {
+            width: micrometres(frame.width_mm),
+            height: micrometres(frame.height_mm),
+        }
+    }
+
+    fn position(&self, contact: &TouchContact) -> [i64; 2] {
+        [
+            i64::from(contact.x) * self.width / i64::from(ABSOLUTE_MAX),
+            i64::from(contact.y) * self.height / i64::from(ABSOLUTE_MAX),
+        ]
+    }
+
+    fn movement(&self, before: &TouchContact, now: &TouchContact) -> [i64; 2] {
+        let (before, now) = (self.position(before), self.position(now));
+        [now[0] - before[0], now[1] - before[1]]
+    }
+
+    fn distance(&self, a: &TouchContact, b: &TouchContact) -> i64 {
+        let (a, b) = (self.position(a), self.position(b));
+        ((a[0] - b[0]) as f64).hypot((a[1] - b[1]) as f64) as i64
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use input_event::{
+        InputEvent, Key, KeyboardEvent, KeyboardEventType, MouseEvent, PointerAxis, TouchContact,
+        TouchFrame, ABSOLUTE_MAX,
+    };
+
+    use super::GestureTranslator;
+
+    /// A frame on a 100mm square touchpad, with fingers given in millimetres.
+    fn frame(fingers: &[(u8, i32, i32)]) -> TouchFrame {
+        TouchFrame {
+            width_mm: 100,
+            height_mm: 100,
+            contacts: fingers
+                .iter()
+                .map(|&(slot, x, y)| TouchContact {
+                    slot,
+                    x: x * ABSOLUTE_MAX / 100,
+                    y: y * ABSOLUTE_MAX / 100,
+                })
+                .collect(),
+        }
+    }
+
+    fn translate(frames: &[TouchFrame]) -> Vec<InputEvent> {
+        let mut translator = GestureTranslator::default();
+        frames
+            .iter()
+            .flat_map(|frame| translator.translate(frame))
+            .collect()
+    }
+
+    #[test]
+    fn given_one_finger_moving_should_move_pointer() {
+        // Given
+        let frames = [frame(&[(0, 10, 10)]), frame(&[(0, 20, 10)])];
+
+        // When
+        let events = translate(&frames);
+
+        // Then
+        assert_eq!(
+            events,
+            vec![InputEvent::Mouse(MouseEvent::Motion {
+                axis: PointerAxis::Horizontal,
+                diff: 80,
+            })]
+        );
+    }
+
+    #[test]
+    fn given_two_fingers_moving_down_should_scroll_down() {
+        // Given
+        let frames = [
+            frame(&[(0, 40, 20), (1, 60, 20)]),
+            frame(&[(0, 40, 31), (1, 60, 31)]),
+        ];
+
+        // When
+        let events = translate(&frames);
+
+        // Then
+        assert_eq!(
+            events,
+            vec![InputEvent::Mouse(MouseEvent::Scroll {
+                axis: PointerAxis::Vertical,
+                diff: -2,
+            })]
+        );
+    }
+
+    #[test]
+    fn given_two_fingers_moving_apart_should_zoom_in() {
+        // Given
+        let frames = [
+            frame(&[(0, 40, 50), (1, 60, 50)]),
+            frame(&[(0, 30, 50), (1, 70, 50)]),
+        ];
+
+        // When
+        let events = translate(&frames);
+
+        // Then
+        let ctrl = |event_type| {
+            InputEvent::Keyboard(KeyboardEvent {
+                event_type,
+                key: Key::KEY_LEFTCTRL,
+            })
+        };
+        assert_eq!(
+            events,
+            vec![
+                ctrl(KeyboardEventType::KeyPressed),
+                InputEvent::Mouse(MouseEvent::Scroll {
+                    axis: PointerAxis::Vertical,
+                    diff: 2,
+                }),
+                ctrl(KeyboardEventType::KeyReleased),
+            ]
+        );
+    }
+
+    #[test]
+    fn given_finger_landing_should_not_move_anything() {
+        // Given
+        let frames = [
+            frame(&[(0, 10, 10)]),
+            frame(&[(0, 10, 10), (1, 50, 50)]),
+            frame(&[(1, 90, 90)]),
+        ];
+
+        // When
+        let events = translate(&frames);
+
+        // Then
+        assert!(events.is_empty());
+    }
+
+    #[test]
+    fn given_ctrl_held_should_zoom_without_touching_ctrl() {
+        // Given
+        let mut translator = GestureTranslator::default();
+        translator.observe(&InputEvent::Keyboard(KeyboardEvent {
+            event_type: KeyboardEventType::KeyPressed,
+            key: Key::KEY_RIGHTCTRL,
+        }));
+        translator.translate(&frame(&[(0, 40, 50), (1, 60, 50)]));
+
+        // When
+        let events = translator.translate(&frame(&[(0, 30, 50), (1, 70, 50)]));
+
+        // Then
+        assert_eq!(
+            events,
+            vec![InputEvent::Mouse(MouseEvent::Scroll {
+                axis: PointerAxis::Vertical,
+                diff: 2,
+            })]
+        );
+    }
+}
@@ -1,7 +1,13 @@
-use input_event::{mapper::error::EventMappingError, InputEvent};
+use std::collections::HashMap;
+
+use input_event::{
+    mapper::error::EventMappingError, GamepadCapabilities, GamepadEvent, GamepadId, GamepadInput,
+    InputEvent, MouseEvent, ScreenGeometry, TouchFrame,
+};
 use thiserror::Error;
 use x11::xtest::X11VirtualDevice;
 
+pub mod gesture;
 pub mod x11;
 
 #[derive(Debug, Error)]
@@ -21,6 +27,46 @@ pub trait VirtualDevice: Send {
 
 pub struct InputSimulator {
     virtual_device: Box<dyn VirtualDevice + Send + Sync>,
+    /// Replays touch frames where uinput is available, made on the first frame. Otherwise the
+    /// virtual device turns them into gestures.
+    touchpad: TouchpadState,
+    /// Replays pen input where uinput is available, made on the first pen event. Otherwise the
+    /// virtual device moves the pointer and drops pressure and tilt.
+    tablet: TabletState,
+    /// A virtual controller for each controller on the server, made when it is announced.
+    gamepads: HashMap<GamepadId, GamepadState>,
+}
+
+enum TouchpadState {
+    NotMade,
+    Made(Box<dyn VirtualDevice + Send + Sync>),
+    Unavailable,
+}
+
+enum TabletState {
+    NotMade,
+    Made(Box<x11::dev::VirtualTablet>),
+    Unavailable,
+}
+
+enum GamepadState {
+    Made(Box<x11::dev::VirtualGamepad>),
+    Unavailable,
+}
+
+impl GamepadState {
+    fn make(capabilities: &GamepadCapabilities) -> Self {
+        match x11::dev::make_gamepad(capabilities) {
+            Ok(gamepad) => {
+                println!("Made a virtual gamepad for {}", capabilities);
+                GamepadState::Made(Box::new(gamepad))
+            }
+            Err(err) => {
+                eprintln!("Could not make a virtual gamepad, dropping its input: {err}");
+                GamepadState::Unavailable
+            }
+        }
+    }
 }
 
 impl InputSimulator {
@@ -28,14 +74,126 @@ impl InputSimulator {
     #[allow(clippy::new_without_default)]
     pub fn new() -> Self {
         let virtual_device = Box::new(X11VirtualDevice::new());
-        InputSimulator { virtual_device }
+        InputSimulator {
+            virtual_device,
+            touchpad: TouchpadState::NotMade,
+            tablet: TabletState::NotMade,
+            gamepads: HashMap::new(),
+        }
+    }
+
+    /// The size of the screen input is replayed on, for the server to place absolute pointer
+    /// input on. `None` if there is no display to ask.
+    pub fn screen_geometry() -> Option<ScreenGeometry> {
+        x11::xtest::screen_geometry()
     }
 
     pub fn emit(&mut self, event: InputEvent) -> Result<(), DeviceOutputError> {
+        let event = match event {
+            InputEvent::Gamepad(event) => return self.emit_gamepad(event),
+            event => event,
+        };
+        if let InputEvent::Touch(frame) = &event {
+            if let Some(touchpad) = self.touchpad(frame) {
+                return touchpad.emit(event);
+            }
+        }
+        if is_pen_event(&event) {
+            if let Some(tablet) = self.tablet() {
+                return tablet.emit(event);
+            }
+        }
         self.virtual_device.emit(event)
     }
 
     pub fn release_all(&mut self) -> Result<(), DeviceOutputError> {
+        if let TouchpadState::Made(touchpad) = &mut self.touchpad {
+            touchpad.release_all()?;
+        }
+        if let TabletState::Made(tablet) = &mut self.tablet {
+            tablet.release_all()?;
+        }
+        for gamepad in self.gamepads.values_mut() {
+            if let GamepadState::Made(gamepad) = gamepad {
+                gamepad.release_all()?;
+            }
+        }
         self.virtual_device.release_all()
     }
+
+    /// Replays controller input on the virtual controller made for it. Input for a controller
+    /// that was never announced goes to one with a standard layout, which is better than none.
+    fn emit_gamepad(&mut self, event: GamepadEvent) -> Result<(), DeviceOutputError> {
+        let pad = event.pad;
+        match &event.input {
+            GamepadInput::Connected(capabilities) => {
+                // announced again when input comes back to this client
+                let unchanged = matches!(
+                    self.gamepads.get(&pad),
+                    Some(GamepadState::Made(gamepad)) if gamepad.capabilities() == capabilities
+                );
+                if !unchanged {
+                    self.gamepads.insert(pad, GamepadState::make(capabilities));
+                }
+                return Ok(());
+            }
+            GamepadInput::Disconnected => {
+                self.gamepads.remove(&pad);
+                return Ok(());
+            }
+            _ => {}
+        }
+        let gamepad = self
+            .gamepads
+            .entry(pad)
+            .or_insert_with(|| GamepadState::make(&GamepadCapabilities::standard()));
+        match gamepad {
+            GamepadState::Made(gamepad) => gamepad.emit(InputEvent::Gamepad(event)),
+            GamepadState::Unavailable => Ok(()),
+        }
+    }
+
+    fn touchpad(&mut self, frame: &TouchFrame) -> Option<&mut (dyn VirtualDevice + Send + Sync)> {
+        if let TouchpadState::NotMade = self.touchpad {
+            self.touchpad = match x11::dev::make_touchpad(frame.width_mm, frame.height_mm) {
+                Ok(touchpad) => TouchpadState::Made(Box::new(touchpad)),
+                Err(err) => {
+                    eprintln!("Could not make a virtual touchpad, sending gestures instead: {err}");
+                    TouchpadState::Unavailable
+                }
+            };
+        }
+        match &mut self.touchpad {
+            TouchpadState::Made(touchpad) => Some(touchpad.as_mut()),
+            _ => None,
+        }
+    }
+    fn tablet(&mut self) -> Option<&mut x11::dev::VirtualTablet> {
+        if let TabletState::NotMade = self.tablet {
+            self.tablet = match Self::screen_geometry().map(x11::dev::make_tablet) {
+                Some(Ok(tablet)) => TabletState::Made(Box::new(tablet)),
+                Some(Err(err)) => {
+                    eprintln!("Could not make a virtual tablet, moving the pointer instead: {err}");
+                    TabletState::Unavailable
+                }
+                // with no display to span, the pointer is no use either
+                None => TabletState::Unavailable,
+            };
+        }
+        match &mut self.tablet {
+            TabletState::Made(tablet) => Some(tablet.as_mut()),
+            _ => None,
+        }
+    }
+}
+
+/// Whether `event` comes from a pen, and so belongs on the virtual tablet.
+fn is_pen_event(event: &InputEvent) -> bool {
+    match event {
+        InputEvent::Tablet(_) => true,
+        InputEvent::Mouse(MouseEvent::Button { button, .. }) => {
+            x11::dev::is_digitizer_button(*button)
+        }
+        _ => false,
+    }
 }
@@ -1,25 +1,36 @@
 use std::{thread, time::Duration};
 
-use evdev::{uinput::VirtualDeviceBuilder, AttributeSet, EventType, Key, RelativeAxisType};
+use evdev::{
+    uinput::VirtualDeviceBuilder, AbsInfo, AbsoluteAxisType, AttributeSet, BusType, EventType,
+    InputId, Key, PropType, RelativeAxisType, UinputAbsSetup,
+};
+use input_event::{
+    mapper::x11::evdev::{DIGITIZER_BUTTON_CODES, MOUSE_BUTTON_CODES},
+    GamepadCapabilities, ScreenGeometry, TouchFrame, ABSOLUTE_MAX,
+};
+use strum::IntoEnumIterator;
 
 use crate::{DeviceOutputError, VirtualDevice};
 
 impl VirtualDevice for evdev::uinput::VirtualDevice {
     fn emit(&mut self, event: input_event::InputEvent) -> Result<(), DeviceOutputError> {
-        Ok(self.emit(&[event.into()])?)
+        Ok(self.emit(&[event.try_into()?])?)
     }
 
     fn release_all(&mut self) -> Result<(), DeviceOutputError> {
-        Ok(ALL_KEYS.iter().try_for_each(|key| {
-            self.emit(&[evdev::InputEvent::new(EventType::KEY, key.code(), 0)])
-        })?)
+        Ok(all_keys()
+            .chain(all_buttons())
+            .chain(all_digitizer_buttons())
+            .try_for_each(|key| {
+                self.emit(&[evdev::InputEvent::new(EventType::KEY, key.code(), 0)])
+            })?)
     }
 }
 
 pub fn make_keyboard() -> Result<evdev::uinput::VirtualDevice, DeviceOutputError> {
     let device = VirtualDeviceBuilder::new()?
         .name("Fake KVM Keyboard")
-        .with_keys(&AttributeSet::from_iter(ALL_KEYS))?
+        .with_keys(&AttributeSet::from_iter(all_keys()))?
         .build()?;
     thread::sleep(Duration::from_millis(100)); // delay so events will be emitted instantly
     Ok(device)
@@ -28,219 +39,457 @@ pub fn make_keyboard() -> Result<evdev::uinput::VirtualDevice, DeviceOutputError
 pub fn make_mouse() -> Result<evdev::uinput::VirtualDevice, DeviceOutputError> {
     let device = VirtualDeviceBuilder::new()?
         .name("Fake KVM Mouse")
+        .with_keys(&AttributeSet::from_iter(all_buttons()))?
         .with_relative_axes(&AttributeSet::from_iter([
             RelativeAxisType::REL_X,
             RelativeAxisType::REL_Y,
             RelativeAxisType::REL_WHEEL,
             RelativeAxisType::REL_HWHEEL,
+            RelativeAxisType::REL_WHEEL_HI_RES,
+            RelativeAxisType::REL_HWHEEL_HI_RES,
         ]))?
         .build()?;
     thread::sleep(Duration::from_millis(100)); // delay so events will be emitted instantly
     Ok(device)
 }
 
-// TODO: replace with strum iterable from input-event crate
-const ALL_KEYS: [Key; 202] = [
-    Key::KEY_RESERVED,
-    Key::KEY_ESC,
-    Key::KEY_1,
-    Key::KEY_2,
-    Key::KEY_3,
-    Key::KEY_4,
-    Key::KEY_5,
-    Key::KEY_6,
-    Key::KEY_7,
-    Key::KEY_8,
-    Key::KEY_9,
-    Key::KEY_0,
-    Key::KEY_MINUS,
-    Key::KEY_EQUAL,
-    Key::KEY_BACKSPACE,
-    Key::KEY_TAB,
-    Key::KEY_Q,
-    Key::KEY_W,
-    Key::KEY_E,
-    Key::KEY_R,
-    Key::KEY_T,
-    Key::KEY_Y,
-    Key::KEY_U,
-    Key::KEY_I,
-    Key::KEY_O,
-    Key::KEY_P,
-    Key::KEY_LEFTBRACE,
-    Key::KEY_RIGHTBRACE,
-    Key::KEY_ENTER,
-    Key::KEY_LEFTCTRL,
-    Key::KEY_A,
-    Key::KEY_S,
-    Key::KEY_D,
-    Key::KEY_F,
-    Key::KEY_G,
-    Key::KEY_H,
-    Key::KEY_J,
-    Key::KEY_K,
-    Key::KEY_L,
-    Key::KEY_SEMICOLON,
-    Key::KEY_APOSTROPHE,
-    Key::KEY_GRAVE,
-    Key::KEY_LEFTSHIFT,
-    Key::KEY_BACKSLASH,
-    Key::KEY_Z,
-    Key::KEY_X,
-    Key::KEY_C,
-    Key::KEY_V,
-    Key::KEY_B,
-    Key::KEY_N,
-    Key::KEY_M,
-    Key::KEY_COMMA,
-    Key::KEY_DOT,
-    Key::KEY_SLASH,
-    Key::KEY_RIGHTSHIFT,
-    Key::KEY_KPASTERISK,
-    Key::KEY_LEFTALT,
-    Key::KEY_SPACE,
-    Key::KEY_CAPSLOCK,
-    Key::KEY_F1,
-    Key::KEY_F2,
-    Key::KEY_F3,
-    Key::KEY_F4,
-    Key::KEY_F5,
-    Key::KEY_F6,
-    Key::KEY_F7,
-    Key::KEY_F8,
-    Key::KEY_F9,
-    Key::KEY_F10,
-    Key::KEY_NUMLOCK,
-    Key::KEY_SCROLLLOCK,
-    Key::KEY_KP7,
-    Key::KEY_KP8,
-    Key::KEY_KP9,
-    Key::KEY_KPMINUS,
-    Key::KEY_KP4,
-    Key::KEY_KP5,
-    Key::KEY_KP6,
-    Key::KEY_KPPLUS,
-    Key::KEY_KP1,
-    Key::KEY_KP2,
-    Key::KEY_KP3,
-    Key::KEY_KP0,
-    Key::KEY_KPDOT,
-    Key::KEY_ZENKAKUHANKAKU,
-    Key::KEY_102ND,
-    Key::KEY_F11,
-    Key::KEY_F12,
-    Key::KEY_RO,
-    Key::KEY_KATAKANA,
-    Key::KEY_HIRAGANA,
-    Key::KEY_HENKAN,
-    Key::KEY_KATAKANAHIRAGANA,
-    Key::KEY_MUHENKAN,
-    Key::KEY_KPJPCOMMA,
-    Key::KEY_KPENTER,
-    Key::KEY_RIGHTCTRL,
-    Key::KEY_KPSLASH,
-    Key::KEY_SYSRQ,
-    Key::KEY_RIGHTALT,
-    Key::KEY_LINEFEED,
-    Key::KEY_HOME,
-    Key::KEY_UP,
-    Key::KEY_PAGEUP,
-    Key::KEY_LEFT,
-    Key::KEY_RIGHT,
-    Key::KEY_END,
-    Key::KEY_DOWN,
-    Key::KEY_PAGEDOWN,
-    Key::KEY_INSERT,
-    Key::KEY_DELETE,
-    Key::KEY_MACRO,
-    Key::KEY_MUTE,
-    Key::KEY_VOLUMEDOWN,
-    Key::KEY_VOLUMEUP,
-    Key::KEY_POWER,
-    Key::KEY_KPEQUAL,
-    Key::KEY_KPPLUSMINUS,
-    Key::KEY_PAUSE,
-    Key::KEY_SCALE,
-    Key::KEY_KPCOMMA,
-    Key::KEY_HANGEUL,
-    Key::KEY_HANJA,
-    Key::KEY_YEN,
-    Key::KEY_LEFTMETA,
-    Key::KEY_RIGHTMETA,
-    Key::KEY_COMPOSE,
-    Key::KEY_STOP,
-    Key::KEY_AGAIN,
-    Key::KEY_PROPS,
-    Key::KEY_UNDO,
-    Key::KEY_FRONT,
-    Key::KEY_COPY,
-    Key::KEY_OPEN,
-    Key::KEY_PASTE,
-    Key::KEY_FIND,
-    Key::KEY_CUT,
-    Key::KEY_HELP,
-    Key::KEY_MENU,
-    Key::KEY_CALC,
-    Key::KEY_SETUP,
-    Key::KEY_SLEEP,
-    Key::KEY_WAKEUP,
-    Key::KEY_FILE,
-    Key::KEY_SENDFILE,
-    Key::KEY_DELETEFILE,
-    Key::KEY_XFER,
-    Key::KEY_PROG1,
-    Key::KEY_PROG2,
-    Key::KEY_WWW,
-    Key::KEY_MSDOS,
-    Key::KEY_COFFEE,
-    Key::KEY_DIRECTION,
-    Key::KEY_ROTATE_DISPLAY,
-    Key::KEY_CYCLEWINDOWS,
-    Key::KEY_MAIL,
-    Key::KEY_BOOKMARKS,
-    Key::KEY_COMPUTER,
-    Key::KEY_BACK,
-    Key::KEY_FORWARD,
-    Key::KEY_CLOSECD,
-    Key::KEY_EJECTCD,
-    Key::KEY_EJECTCLOSECD,
-    Key::KEY_NEXTSONG,
-    Key::KEY_PLAYPAUSE,
-    Key::KEY_PREVIOUSSONG,
-    Key::KEY_STOPCD,
-    Key::KEY_RECORD,
-    Key::KEY_REWIND,
-    Key::KEY_PHONE,
-    Key::KEY_ISO,
-    Key::KEY_CONFIG,
-    Key::KEY_HOMEPAGE,
-    Key::KEY_REFRESH,
-    Key::KEY_EXIT,
-    Key::KEY_MOVE,
-    Key::KEY_EDIT,
-    Key::KEY_SCROLLUP,
-    Key::KEY_SCROLLDOWN,
-    Key::KEY_KPLEFTPAREN,
-    Key::KEY_KPRIGHTPAREN,
-    Key::KEY_NEW,
-    Key::KEY_REDO,
-    Key::KEY_F13,
-    Key::KEY_F14,
-    Key::KEY_F15,
-    Key::KEY_F16,
-    Key::KEY_F17,
-    Key::KEY_F18,
-    Key::KEY_F19,
-    Key::KEY_F20,
-    Key::KEY_F21,
-    Key::KEY_F22,
-    Key::KEY_F23,
-    Key::KEY_F24,
-    Key::BTN_LEFT,
-    Key::BTN_RIGHT,
-    Key::BTN_MIDDLE,
-    Key::BTN_SIDE,
-    Key::BTN_EXTRA,
-    Key::BTN_FORWARD,
-    Key::BTN_BACK,
+/// Tilt is reported in degrees, which is this many units per radian.
+const TILT_RESOLUTION: i32 = 57;
+
+/// A pen tablet that replays pressure and tilt, which the core pointer has no way to.
+pub struct VirtualTablet {
+    device: evdev::uinput::VirtualDevice,
+    screen: ScreenGeometry,
+}
+
+impl VirtualDevice for VirtualTablet {
+    fn emit(&mut self, event: input_event::InputEvent) -> Result<(), DeviceOutputError> {
+        // a server without this client's screen geometry leaves positions to be placed here
+        let event = match event {
+            input_event::InputEvent::Tablet(event) => {
+                input_event::InputEvent::Tablet(self.screen.place(event))
+            }
+            event => event,
+        };
+        Ok(self.device.emit(&[event.try_into()?])?)
+    }
+
+    fn release_all(&mut self) -> Result<(), DeviceOutputError> {
+        let buttons = all_digitizer_buttons()
+            .map(|key| evdev::InputEvent::new(EventType::KEY, key.code(), 0));
+        let pressure =
+            evdev::InputEvent::new(EventType::ABSOLUTE, AbsoluteAxisType::ABS_PRESSURE.0, 0);
+        let events: Vec<_> = buttons.chain([pressure]).collect();
+        Ok(self.device.emit(&events)?)
+    }
+}
+
+/// Whether `button` is a pen or touch button, which only a tablet reports.
+pub fn is_digitizer_button(button: input_event::Button) -> bool {
+    DIGITIZER_BUTTON_CODES.contains(&Key::from(button).code())
+}
+
+/// A pen tablet whose surface spans `screen`, so that positions the server placed on the screen
+/// land on the same pixel.
+pub fn make_tablet(screen: ScreenGeometry) -> Result<VirtualTablet, DeviceOutputError> {
+    let axis = |axis, minimum, maximum, resolution| {
+        UinputAbsSetup::new(axis, AbsInfo::new(0, minimum, maximum, 0, 0, resolution))
+    };
+    let last_pixel = |length: u32| i32::try_from(length.saturating_sub(1)).unwrap_or(i32::MAX);
+    let device = VirtualDeviceBuilder::new()?
+        .name("Fake KVM Tablet")
+        .with_properties(&AttributeSet::from_iter([PropType::POINTER]))?
+        .with_keys(&AttributeSet::from_iter(all_digitizer_buttons()))?
+        .with_absolute_axis(&axis(
+            AbsoluteAxisType::ABS_X,
+            0,
+            last_pixel(screen.width),
+            0,
+        ))?
+        .with_absolute_axis(&axis(
+            AbsoluteAxisType::ABS_Y,
+            0,
+            last_pixel(screen.height),
+            0,
+        ))?
+        .with_absolute_axis(&axis(AbsoluteAxisType::ABS_PRESSURE, 0, ABSOLUTE_MAX, 0))?
+        .with_absolute_axis(&axis(
+            AbsoluteAxisType::ABS_TILT_X,
+            -90,
+            90,
+            TILT_RESOLUTION,
+        ))?
+        .with_absolute_axis(&axis(
+            AbsoluteAxisType::ABS_TILT_Y,
+            -90,
+            90,
+            TILT_RESOLUTION,
+        ))?
+        .build()?;
+    thread::sleep(Duration::from_millis(100)); // delay so events will be emitted instantly
+    Ok(VirtualTablet { device, screen })
+}
+
+/// Fingers the virtual touchpad tracks, which is as many as it reports a tap count for.
+const TOUCH_SLOTS: usize = 5;
+/// Assumed for a touchpad that does not give its size.
+const DEFAULT_SURFACE_MM: u16 = 100;
+
+const TAP_COUNT_KEYS: [Key; TOUCH_SLOTS] = [
+    Key::BTN_TOOL_FINGER,
+    Key::BTN_TOOL_DOUBLETAP,
+    Key::BTN_TOOL_TRIPLETAP,
+    Key::BTN_TOOL_QUADTAP,
+    Key::BTN_TOOL_QUINTTAP,
 ];
+
+/// A clickpad that replays touch frames, so the client's own touchpad driver recognises the
+/// gestures.
+pub struct VirtualTouchpad {
+    device: evdev::uinput::VirtualDevice,
+    replay: TouchReplay,
+}
+
+impl VirtualDevice for VirtualTouchpad {
+    fn emit(&mut self, event: input_event::InputEvent) -> Result<(), DeviceOutputError> {
+        let events = match event {
+            input_event::InputEvent::Touch(frame) => self.replay.frame(&frame),
+            event => vec![event.try_into()?],
+        };
+        Ok(self.device.emit(&events)?)
+    }
+
+    fn release_all(&mut self) -> Result<(), DeviceOutputError> {
+        let mut events = self.replay.frame(&TouchFrame {
+            width_mm: 0,
+            height_mm: 0,
+            contacts: Vec::new(),
+        });
+        events.push(evdev::InputEvent::new(
+            EventType::KEY,
+            Key::BTN_LEFT.code(),
+            0,
+        ));
+        Ok(self.device.emit(&events)?)
+    }
+}
+
+pub fn make_touchpad(width_mm: u16, height_mm: u16) -> Result<VirtualTouchpad, DeviceOutputError> {
+    let axis = |axis, minimum, maximum, mm: u16| {
+        let mm = if mm == 0 { DEFAULT_SURFACE_MM } else { mm };
+        let resolution = maximum / i32::from(mm);
+        UinputAbsSetup::new(axis, AbsInfo::new(0, minimum, maximum, 0, 0, resolution))
+    };
+    let device = VirtualDeviceBuilder::new()?
+        .name("Fake KVM Touchpad")
+        .with_properties(&AttributeSet::from_iter([
+            PropType::POINTER,
+            PropType::BUTTONPAD,
+        ]))?
+        .with_keys(&AttributeSet::from_iter(
+            [Key::BTN_LEFT, Key::BTN_TOUCH]
+                .into_iter()
+                .chain(TAP_COUNT_KEYS),
+        ))?
+        .with_absolute_axis(&axis(AbsoluteAxisType::ABS_X, 0, ABSOLUTE_MAX, width_mm))?
+        .with_absolute_axis(&axis(AbsoluteAxisType::ABS_Y, 0, ABSOLUTE_MAX, height_mm))?
+        .with_absolute_axis(&UinputAbsSetup::new(
+            AbsoluteAxisType::ABS_MT_SLOT,
+            AbsInfo::new(0, 0, TOUCH_SLOTS as i32 - 1, 0, 0, 0),
+        ))?
+        .with_absolute_axis(&UinputAbsSetup::new(
+            AbsoluteAxisType::ABS_MT_TRACKING_ID,
+            AbsInfo::new(0, 0, i32::from(u16::MAX), 0, 0, 0),
+        ))?
+        .with_absolute_axis(&axis(
+            AbsoluteAxisType::ABS_MT_POSITION_X,
+            0,
+            ABSOLUTE_MAX,
+            width_mm,
+        ))?
+        .with_absolute_axis(&axis(
+            AbsoluteAxisType::ABS_MT_POSITION_Y,
+            0,
+            ABSOLUTE_MAX,
+            height_mm,
+        ))?
+        .build()?;
+    thread::sleep(Duration::from_millis(100)); // delay so events will be emitted instantly
+    Ok(VirtualTouchpad {
+        device,
+        replay: TouchReplay::default(),
+    })
+}
+
+/// Turns touch frames back into multitouch protocol B events, sending only what changed since
+/// the last frame.
+#[derive(Debug, Default)]
+struct TouchReplay {
+    /// The tracking ID of the finger in each slot.
+    slots: [Option<i32>; TOUCH_SLOTS],
+    next_tracking_id: i32,
+}
+
+impl TouchReplay {
+    fn frame(&mut self, frame: &TouchFrame) -> Vec<evdev::InputEvent> {
+        let abs = |axis: AbsoluteAxisType, value| {
+            evdev::InputEvent::new(EventType::ABSOLUTE, axis.0, value)
+        };
+        let key = |key: Key, down: bool| {
+            evdev::InputEvent::new(EventType::KEY, key.code(), i32::from(down))
+        };
+        let mut events = Vec::new();
+        for (slot, tracking_id) in self.slots.iter_mut().enumerate() {
+            let contact = frame
+                .contacts
+                .iter()
+                .find(|contact| usize::from(contact.slot) == slot);
+            match (contact, *tracking_id) {
+                (None, None) => continue,
+                (None, Some(_)) => {
+                    events.push(abs(AbsoluteAxisType::ABS_MT_SLOT, slot as i32));
+                    events.push(abs(AbsoluteAxisType::ABS_MT_TRACKING_ID, -1));
+                    *tracking_id = None;
+                }
+                (Some(contact), _) => {
+                    events.push(abs(AbsoluteAxisType::ABS_MT_SLOT, slot as i32));
+                    if tracking_id.is_none() {
+                        events.push(abs(
+                            AbsoluteAxisType::ABS_MT_TRACKING_ID,
+                            self.next_tracking_id,
+                        ));
+                        *tracking_id = Some(self.next_tracking_id);
+                        self.next_tracking_id = (self.next_tracking_id + 1) & i32::from(u16::MAX);
+                    }
+                    events.push(abs(AbsoluteAxisType::ABS_MT_POSITION_X, contact.x));
+                    events.push(abs(AbsoluteAxisType::ABS_MT_POSITION_Y, contact.y));
+                }
+            }
+        }
+
+        // the single-touch view of the same report, for drivers that only read that
+        let fingers = self.slots.iter().flatten().count();
+        events.push(key(Key::BTN_TOUCH, fingers > 0));
+        for (idx, tap_count) in TAP_COUNT_KEYS.into_iter().enumerate() {
+            events.push(key(tap_count, fingers == idx + 1));
+        }
+        if let Some(first) = frame
+            .contacts
+            .iter()
+            .find(|contact| usize::from(contact.slot) < TOUCH_SLOTS)
+        {
+            events.push(abs(AbsoluteAxisType::ABS_X, first.x));
+            events.push(abs(AbsoluteAxisType::ABS_Y, first.y));
+        }
+        events
+    }
+}
+
+/// A controller that reports what one on the server does, so games treat it the same way.
+pub struct VirtualGamepad {
+    device: evdev::uinput::VirtualDevice,
+    capabilities: GamepadCapabilities,
+}
+
+impl VirtualGamepad {
+    pub fn capabilities(&self) -> &GamepadCapabilities {
+        &self.capabilities
+    }
+}
+
+impl VirtualDevice for VirtualGamepad {
+    fn emit(&mut self, event: input_event::InputEvent) -> Result<(), DeviceOutputError> {
+        Ok(self.device.emit(&[event.try_into()?])?)
+    }
+
+    fn release_all(&mut self) -> Result<(), DeviceOutputError> {
+        Ok(self.device.emit(&resting_events(&self.capabilities))?)
+    }
+}
+
+/// Makes a controller with the buttons, axes and IDs in `capabilities`. Sticks and triggers
+/// span 0 to `ABSOLUTE_MAX` and hats -1 to 1, the ranges the server scales them to.
+pub fn make_gamepad(
+    capabilities: &GamepadCapabilities,
+) -> Result<VirtualGamepad, DeviceOutputError> {
+    let mut builder = VirtualDeviceBuilder::new()?
+        .name(&capabilities.name)
+        .input_id(InputId::new(
+            BusType(capabilities.bus_type),
+            capabilities.vendor,
+            capabilities.product,
+            capabilities.version,
+        ))
+        .with_keys(&AttributeSet::from_iter(
+            capabilities.buttons.iter().copied().map(Key::from),
+        ))?;
+    for info in &capabilities.axes {
+        let (minimum, maximum) = if info.axis.is_hat() {
+            (-1, 1)
+        } else {
+            (0, ABSOLUTE_MAX)
+        };
+        builder = builder.with_absolute_axis(&UinputAbsSetup::new(
+            info.axis.into(),
+            AbsInfo::new(info.rest, minimum, maximum, 0, 0, 0),
+        ))?;
+    }
+    let device = builder.build()?;
+    thread::sleep(Duration::from_millis(100)); // delay so events will be emitted instantly
+    Ok(VirtualGamepad {
+        device,
+        capabilities: capabilities.clone(),
+    })
+}
+
+/// Releases every button and returns every axis to where it rests.
+fn resting_events(capabilities: &GamepadCapabilities) -> Vec<evdev::InputEvent> {
+    let buttons = capabilities
+        .buttons
+        .iter()
+        .map(|&button| evdev::InputEvent::new(EventType::KEY, Key::from(button).code(), 0));
+    let axes = capabilities.axes.iter().map(|info| {
+        evdev::InputEvent::new(
+            EventType::ABSOLUTE,
+            AbsoluteAxisType::from(info.axis).0,
+            info.rest,
+        )
+    });
+    buttons.chain(axes).collect()
+}
+
+fn all_keys() -> impl Iterator<Item = Key> {
+    input_event::Key::iter().map(Key::from)
+}
+
+/// Every mouse button code, including those passed through as `Button::Raw`.
+fn all_buttons() -> impl Iterator<Item = Key> {
+    MOUSE_BUTTON_CODES.map(Key::new)
+}
+
+/// Every digitizer tool and touch code, including those passed through as `Button::Raw`.
+fn all_digitizer_buttons() -> impl Iterator<Item = Key> {
+    DIGITIZER_BUTTON_CODES.map(Key::new)
+}
+
+#[cfg(test)]
+mod test {
+    use evdev::{AbsoluteAxisType, EventType, InputEventKind};
+    use input_event::{
+        Button, GamepadAxis, GamepadAxisInfo, GamepadButton, GamepadCapabilities, TouchContact,
+        TouchFrame,
+    };
+
+    use super::{is_digitizer_button, resting_events, TouchReplay};
+
+    fn frame(contacts: &[(u8, i32, i32)]) -> TouchFrame {
+        TouchFrame {
+            width_mm: 100,
+            height_mm: 60,
+            contacts: contacts
+                .iter()
+                .map(|&(slot, x, y)| TouchContact { slot, x, y })
+                .collect(),
+        }
+    }
+
+    /// The multitouch events of a replayed frame, leaving out the single-touch view.
+    fn multitouch(events: Vec<evdev::InputEvent>) -> Vec<(AbsoluteAxisType, i32)> {
+        events
+            .into_iter()
+            .filter(|event| event.event_type() == EventType::ABSOLUTE)
+            .filter_map(|event| match event.kind() {
+                InputEventKind::AbsAxis(axis) if axis.0 >= AbsoluteAxisType::ABS_MT_SLOT.0 => {
+                    Some((axis, event.value()))
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn given_frames_should_replay_only_changed_fingers() {
+        // Given
+        let mut replay = TouchReplay::default();
+        replay.frame(&frame(&[(0, 10, 20)]));
+
+        // When
+        let moved = multitouch(replay.frame(&frame(&[(0, 11, 20), (1, 30, 40)])));
+        let lifted = multitouch(replay.frame(&frame(&[(1, 30, 40)])));
+
+        // Then
+        assert_eq!(
+            moved,
+            vec![
+                (AbsoluteAxisType::ABS_MT_SLOT, 0),
+                (AbsoluteAxisType::ABS_MT_POSITION_X, 11),
+                (AbsoluteAxisType::ABS_MT_POSITION_Y, 20),
+                (AbsoluteAxisType::ABS_MT_SLOT, 1),
+                (AbsoluteAxisType::ABS_MT_TRACKING_ID, 1),
+                (AbsoluteAxisType::ABS_MT_POSITION_X, 30),
+                (AbsoluteAxisType::ABS_MT_POSITION_Y, 40),
+            ]
+        );
+        assert_eq!(
+            lifted,
+            vec![
+                (AbsoluteAxisType::ABS_MT_SLOT, 0),
+                (AbsoluteAxisType::ABS_MT_TRACKING_ID, -1),
+                (AbsoluteAxisType::ABS_MT_SLOT, 1),
+                (AbsoluteAxisType::ABS_MT_POSITION_X, 30),
+                (AbsoluteAxisType::ABS_MT_POSITION_Y, 40),
+            ]
+        );
+    }
+
+    #[test]
+    fn given_capabilities_should_release_buttons_and_rest_axes() {
+        // Given
+        let capabilities = GamepadCapabilities {
+            buttons: vec![GamepadButton::BTN_SOUTH, GamepadButton::Raw(0x2c0)],
+            axes: vec![
+                GamepadAxisInfo {
+                    axis: GamepadAxis::ABS_X,
+                    rest: 32767,
+                },
+                GamepadAxisInfo {
+                    axis: GamepadAxis::ABS_HAT0X,
+                    rest: 0,
+                },
+            ],
+            ..GamepadCapabilities::standard()
+        };
+
+        // When
+        let events: Vec<_> = resting_events(&capabilities)
+            .into_iter()
+            .map(|event| (event.event_type(), event.code(), event.value()))
+            .collect();
+
+        // Then
+        assert_eq!(
+            events,
+            vec![
+                (EventType::KEY, evdev::Key::BTN_SOUTH.code(), 0),
+                (EventType::KEY, 0x2c0, 0),
+                (EventType::ABSOLUTE, AbsoluteAxisType::ABS_X.0, 32767),
+                (EventType::ABSOLUTE, AbsoluteAxisType::ABS_HAT0X.0, 0),
+            ]
+        );
+    }
+
+    #[test]
+    fn given_pen_buttons_should_be_digitizer_buttons() {
+        // Given
+        let pen = [Button::BTN_TOOL_PEN, Button::BTN_TOUCH, Button::BTN_STYLUS];
+        let mouse = [Button::BTN_LEFT, Button::BTN_SIDE, Button::Raw(0x118)];
+
+        // When
+        let pen: Vec<_> = pen.into_iter().map(is_digitizer_button).collect();
+        let mouse: Vec<_> = mouse.into_iter().map(is_digitizer_button).collect();
+
+        // Then
+        assert_eq!(pen, vec![true; 3]);
+        assert_eq!(mouse, vec![false; 3]);
+    }
+}
@@ -1,21 +1,27 @@
 use std::{
-    ffi::{c_uint, c_ulong},
+    ffi::{c_int, c_uint, c_ulong},
     ptr,
     sync::Mutex,
 };
 
-use input_event::{InputEvent, Key, KeyboardEvent, KeyboardEventType};
+use input_event::{InputEvent, Key, KeyboardEventType, PointerAxis, ScreenGeometry, TabletEvent};
 use strum::IntoEnumIterator;
 use x11::{
-    xlib::{self, Display, XCloseDisplay, XFlush, XOpenDisplay},
-    xtest::{XTestFakeButtonEvent, XTestFakeKeyEvent, XTestFakeRelativeMotionEvent},
+    xlib::{
+        Display, XCloseDisplay, XDefaultRootWindow, XDefaultScreen, XDisplayHeight, XDisplayWidth,
+        XFlush, XOpenDisplay, XQueryPointer,
+    },
+    xtest::{
+        XTestFakeButtonEvent, XTestFakeKeyEvent, XTestFakeMotionEvent, XTestFakeRelativeMotionEvent,
+    },
 };
 
-use crate::{DeviceOutputError, VirtualDevice};
+use crate::{gesture::GestureTranslator, DeviceOutputError, VirtualDevice};
 
 pub(crate) struct X11VirtualDevice {
     // TODO: probably can remove the mutex since it is only ever used in one thread
     display: Mutex<*mut Display>, // TODO: Consider the use of a blocking lock with tokio
+    gestures: GestureTranslator,
 }
 
 // Assume display never gets closed
@@ -26,7 +32,7 @@ impl X11VirtualDevice {
     pub fn new() -> Self {
         let display = unsafe {
             match XOpenDisplay(ptr::null()) {
-                d if d == ptr::null::<xlib::Display>() as *mut xlib::Display => {
+                d if d.is_null() => {
                     panic!("Could not open x11 display")
                 }
                 display => display,
@@ -35,6 +41,30 @@ impl X11VirtualDevice {
 
         Self {
             display: Mutex::new(display),
+            gestures: GestureTranslator::default(),
+        }
+    }
+}
+
+/// The size of the default X screen, or `None` if there is no display to ask.
+pub(crate) fn screen_geometry() -> Option<ScreenGeometry> {
+    unsafe {
+        let display = XOpenDisplay(ptr::null());
+        if display.is_null() {
+            return None;
+        }
+        let geometry = display_geometry(display);
+        XCloseDisplay(display);
+        Some(geometry)
+    }
+}
+
+unsafe fn display_geometry(display: *mut Display) -> ScreenGeometry {
+    unsafe {
+        let screen = XDefaultScreen(display);
+        ScreenGeometry {
+            width: XDisplayWidth(display, screen).max(0) as u32,
+            height: XDisplayHeight(display, screen).max(0) as u32,
         }
     }
 }
@@ -50,8 +80,18 @@ impl Drop for X11VirtualDevice {
 impl VirtualDevice for X11VirtualDevice {
     fn emit(&mut self, event: InputEvent) -> Result<(), DeviceOutputError> {
         let display = self.display.lock().unwrap();
+        // XTest has no touch input, so touchpads send what their gestures would have done
+        let events = match &event {
+            InputEvent::Touch(frame) => self.gestures.translate(frame),
+            _ => {
+                self.gestures.observe(&event);
+                vec![event]
+            }
+        };
         unsafe {
-            emit(*display, event)?;
+            events
+                .into_iter()
+                .try_for_each(|event| emit(*display, event))?;
             flush(*display)?;
         }
         Ok(())
@@ -59,11 +99,17 @@ impl VirtualDevice for X11VirtualDevice {
 
     fn release_all(&mut self) -> Result<(), DeviceOutputError> {
         let display = self.display.lock().unwrap();
-        let event_type = KeyboardEventType::KeyReleased;
+        self.gestures.release_all();
+        // a key the display cannot type cannot be held either, so it is skipped without a warning
         Key::iter()
-            .try_for_each(|key| {
-                let event = InputEvent::Keyboard(KeyboardEvent { event_type, key });
-                unsafe { emit(*display, event) }
+            .filter_map(|key| unsafe { key.to_x11_keycode(*display) }.ok())
+            .try_for_each(|keycode| unsafe {
+                if XTestFakeKeyEvent(*display, keycode.into(), 0, 0) == 0 {
+                    return Err(DeviceOutputError::EmitError(
+                        "Could not emit Xtest fake key event".into(),
+                    ));
+                }
+                Ok(())
             })
             .and(unsafe { flush(*display) })
     }
@@ -76,14 +122,16 @@ unsafe fn emit(display: *mut Display, event: InputEvent) -> Result<(), DeviceOut
                 KeyboardEventType::KeyPressed | KeyboardEventType::KeyHeld => 1,
                 KeyboardEventType::KeyReleased => 0,
             };
+            // a key the display cannot type is skipped rather than stopping all input
+            let keycode = match unsafe { event.key.to_x11_keycode(display) } {
+                Ok(keycode) => keycode,
+                Err(err) => {
+                    eprintln!("Skipping key event: {}", err);
+                    return Ok(());
+                }
+            };
             unsafe {
-                if XTestFakeKeyEvent(
-                    display,
-                    event.key.to_x11_keycode(display)?.into(),
-                    is_press,
-                    0,
-                ) == 0
-                {
+                if XTestFakeKeyEvent(display, keycode.into(), is_press, 0) == 0 {
                     eprintln!("Could not emit Xtest fake key event");
                     return Err(DeviceOutputError::EmitError(
                         "Could not emit Xtest fake key event".into(),
@@ -111,7 +159,9 @@ unsafe fn emit(display: *mut Display, event: InputEvent) -> Result<(), DeviceOut
                     KeyboardEventType::KeyPressed | KeyboardEventType::KeyHeld => 1,
                     KeyboardEventType::KeyReleased => 0,
                 };
-                let button = button.to_x11_button_num();
+                let Some(button) = button.to_x11_button_num() else {
+                    return Ok(());
+                };
                 unsafe {
                     if XTestFakeButtonEvent(display, button, is_press, 0) == 0 {
                         eprintln!("Could not emit Xtest fake motion event");
@@ -129,11 +179,72 @@ unsafe fn emit(display: *mut Display, event: InputEvent) -> Result<(), DeviceOut
                     }
                 }
             }
+            // XTest can only click the wheel buttons, so this is replayed from the detents sent
+            // alongside it
+            input_event::MouseEvent::HiResScroll { .. } => {}
         },
+        InputEvent::Tablet(event) => {
+            // a server without this client's screen geometry leaves positions to be placed here
+            let event = match event {
+                TabletEvent::Position { .. } => unsafe { display_geometry(display) }.place(event),
+                event => event,
+            };
+            let (axis, pixel) = match event {
+                TabletEvent::ScreenPosition { axis, pixel } => (axis, pixel),
+                // the core pointer has no pressure or tilt
+                _ => return Ok(()),
+            };
+            let (x, y) = unsafe { pointer_position(display)? };
+            let (x, y) = match axis {
+                PointerAxis::Horizontal => (pixel, y),
+                PointerAxis::Vertical => (x, pixel),
+            };
+            unsafe {
+                if XTestFakeMotionEvent(display, CURRENT_SCREEN, x, y, 0) == 0 {
+                    eprintln!("Could not emit Xtest fake motion event");
+                    return Err(DeviceOutputError::EmitError(
+                        "Could not emit Xtest fake motion event".into(),
+                    ));
+                }
+            }
+        }
+        // translated into gestures before getting here
+        InputEvent::Touch(_) => {}
+        // X has no controllers, these are only replayed through uinput
+        InputEvent::Gamepad(_) => {}
     }
     Ok(())
 }
 
+/// Tells XTest to move the pointer on whichever screen it is on.
+const CURRENT_SCREEN: c_int = -1;
+
+/// Where the pointer is, since an absolute axis event only moves it along one axis.
+unsafe fn pointer_position(display: *mut Display) -> Result<(c_int, c_int), DeviceOutputError> {
+    let (mut root, mut child) = (0, 0);
+    let (mut root_x, mut root_y, mut win_x, mut win_y) = (0, 0, 0, 0);
+    let mut mask = 0;
+    unsafe {
+        if XQueryPointer(
+            display,
+            XDefaultRootWindow(display),
+            &mut root,
+            &mut child,
+            &mut root_x,
+            &mut root_y,
+            &mut win_x,
+            &mut win_y,
+            &mut mask,
+        ) == 0
+        {
+            return Err(DeviceOutputError::EmitError(
+                "Pointer is not on the default screen".into(),
+            ));
+        }
+    }
+    Ok((root_x, root_y))
+}
+
 unsafe fn tap_button(
     display: *mut Display,
     button: c_uint,
@@ -174,7 +285,7 @@ mod test {
     use std::{ptr, time::Duration};
 
     use x11::{
-        xlib::{self, XFlush, XOpenDisplay},
+        xlib::{XFlush, XOpenDisplay},
         xtest::XTestFakeRelativeMotionEvent,
     };
 
@@ -182,7 +293,7 @@ mod test {
     fn test_motion() {
         unsafe {
             let display = match XOpenDisplay(ptr::null()) {
-                d if d == ptr::null::<xlib::Display>() as *mut xlib::Display => {
+                d if d.is_null() => {
                     panic!()
                 }
                 display => display,
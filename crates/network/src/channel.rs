@@ -0,0 +1,409 @@
+//! In-process transports backed by tokio channels.
+//!
+//! Messages go through the same serialisation and encryption as the socket transports, so a
+//! server and several clients can be run inside a single process without opening any ports.
+
+use std::{
+    collections::HashMap,
+    io,
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+};
+
+use crypto::Crypto;
+use tokio::sync::{self, mpsc};
+
+use crate::{
+    fragment::{DatagramCodec, DatagramStats},
+    transport::{
+        decrypt_and_deserialise_message, serialise_and_encrypt_message, SplitTransport, Transport,
+        TransportReader, TransportWriter,
+    },
+    Message, TransportError,
+};
+
+const CHANNEL_BUF_LEN: usize = 256;
+const EPHEMERAL_PORT_START: u16 = 49152;
+
+#[derive(Debug)]
+pub struct ChannelTransport<T: Crypto> {
+    sender: mpsc::Sender<Vec<u8>>,
+    receiver: mpsc::Receiver<Vec<u8>>,
+    key: Option<T>,
+}
+
+impl<T: Crypto> ChannelTransport<T> {
+    /// Creates two connected ends of an in-memory stream transport.
+    pub fn pair() -> (Self, Self) {
+        let (tx1, rx1) = mpsc::channel(CHANNEL_BUF_LEN);
+        let (tx2, rx2) = mpsc::channel(CHANNEL_BUF_LEN);
+        (
+            ChannelTransport {
+                sender: tx1,
+                receiver: rx2,
+                key: None,
+            },
+            ChannelTransport {
+                sender: tx2,
+                receiver: rx1,
+                key: None,
+            },
+        )
+    }
+}
+
+impl<T: Crypto + 'static> SplitTransport<T> for ChannelTransport<T> {
+    type Reader = ChannelTransportReader<T>;
+    type Writer = ChannelTransportWriter<T>;
+
+    fn set_key(&mut self, key: T) {
+        self.key = Some(key);
+    }
+
+    fn into_split(self) -> (ChannelTransportReader<T>, ChannelTransportWriter<T>) {
+        let reader_transport = ChannelTransportReader::new(self.receiver, self.key.clone());
+        let writer_transport = ChannelTransportWriter::new(self.sender, self.key);
+        (reader_transport, writer_transport)
+    }
+}
+
+impl<T: Crypto> Transport for ChannelTransport<T> {
+    async fn send_message(&mut self, message: Message) -> Result<(), TransportError> {
+        send_frame(&self.sender, &message, &self.key).await
+    }
+
+    async fn receive_message(&mut self) -> Result<Message, TransportError> {
+        receive_frame(&mut self.receiver, &self.key).await
+    }
+}
+
+pub struct ChannelTransportWriter<T: Crypto> {
+    sender: mpsc::Sender<Vec<u8>>,
+    key: Option<T>,
+}
+
+impl<T: Crypto> ChannelTransportWriter<T> {
+    pub fn new(sender: mpsc::Sender<Vec<u8>>, key: Option<T>) -> Self {
+        ChannelTransportWriter { sender, key }
+    }
+}
+
+impl<T: Crypto> TransportWriter for ChannelTransportWriter<T> {
+    async fn send_message(&mut self, message: Message) -> Result<(), TransportError> {
+        send_frame(&self.sender, &message, &self.key).await
+    }
+}
+
+pub struct ChannelTransportReader<T: Crypto> {
+    receiver: mpsc::Receiver<Vec<u8>>,
+    key: Option<T>,
+}
+
+impl<T: Crypto> ChannelTransportReader<T> {
+    pub fn new(receiver: mpsc::Receiver<Vec<u8>>, key: Option<T>) -> Self {
+        ChannelTransportReader { receiver, key }
+    }
+}
+
+impl<T: Crypto> TransportReader for ChannelTransportReader<T> {
+    async fn receive_message(&mut self) -> Result<Message, TransportError> {
+        receive_frame(&mut self.receiver, &self.key).await
+    }
+}
+
+async fn send_frame<T: Crypto>(
+    sender: &mpsc::Sender<Vec<u8>>,
+    message: &Message,
+    key: &Option<T>,
+) -> Result<(), TransportError> {
+    let frame = serialise_and_encrypt_message(message, key)?;
+    sender
+        .send(frame)
+        .await
+        .map_err(|_| TransportError::ConnectionClosed)
+}
+
+async fn receive_frame<T: Crypto>(
+    receiver: &mut mpsc::Receiver<Vec<u8>>,
+    key: &Option<T>,
+) -> Result<Message, TransportError> {
+    let frame = receiver
+        .recv()
+        .await
+        .ok_or(TransportError::ConnectionClosed)?;
+    decrypt_and_deserialise_message(&frame, key)
+}
+
+type Datagram = (Vec<u8>, SocketAddr);
+
+/// An in-memory datagram network where sockets are addressed by virtual `SocketAddr`s.
+///
+/// Like UDP, datagrams sent to an address with no bound socket or a full queue are dropped.
+#[derive(Debug, Clone, Default)]
+pub struct ChannelNetwork {
+    sockets: Arc<Mutex<HashMap<SocketAddr, mpsc::Sender<Datagram>>>>,
+}
+
+impl ChannelNetwork {
+    pub fn new() -> Self {
+        ChannelNetwork::default()
+    }
+
+    /// Binds a socket to `addr`. A port of 0 picks an unused ephemeral port.
+    pub fn bind(&self, mut addr: SocketAddr) -> io::Result<ChannelDatagramSocket> {
+        let mut sockets = self.sockets.lock().unwrap();
+
+        if addr.port() == 0 {
+            let port = (EPHEMERAL_PORT_START..=u16::MAX)
+                .find(|&port| !sockets.contains_key(&SocketAddr::new(addr.ip(), port)))
+                .ok_or(io::Error::from(io::ErrorKind::AddrNotAvailable))?;
+            addr.set_port(port);
+        }
+        if sockets.contains_key(&addr) {
+            return Err(io::Error::from(io::ErrorKind::AddrInUse));
+        }
+
+        let (sender, receiver) = mpsc::channel(CHANNEL_BUF_LEN);
+        sockets.insert(addr, sender);
+
+        Ok(ChannelDatagramSocket {
+            addr,
+            network: self.clone(),
+            receiver: sync::Mutex::new(receiver),
+        })
+    }
+}
+
+#[derive(Debug)]
+pub struct ChannelDatagramSocket {
+    addr: SocketAddr,
+    network: ChannelNetwork,
+    receiver: sync::Mutex<mpsc::Receiver<Datagram>>,
+}
+
+impl ChannelDatagramSocket {
+    pub fn local_addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    pub async fn send_to(&self, bytes: &[u8], addr: SocketAddr) -> io::Result<usize> {
+        let sender = self.network.sockets.lock().unwrap().get(&addr).cloned();
+        if let Some(sender) = sender {
+            let _ = sender.try_send((bytes.to_vec(), self.addr));
+        }
+        Ok(bytes.len())
+    }
+
+    pub async fn recv_from(&self) -> io::Result<Datagram> {
+        self.receiver
+            .lock()
+            .await
+            .recv()
+            .await
+            .ok_or(io::Error::from(io::ErrorKind::NotConnected))
+    }
+}
+
+impl Drop for ChannelDatagramSocket {
+    fn drop(&mut self) {
+        if let Ok(mut sockets) = self.network.sockets.lock() {
+            sockets.remove(&self.addr);
+        }
+    }
+}
+
+/// Client side of the input event path over a `ChannelNetwork`.
+pub struct ChannelDatagramTransport<T: Crypto> {
+    socket: ChannelDatagramSocket,
+    server_addr: SocketAddr,
+    symmetric_key: Option<T>,
+    codec: DatagramCodec,
+}
+
+impl<T: Crypto> ChannelDatagramTransport<T> {
+    pub fn new(
+        socket: ChannelDatagramSocket,
+        server_addr: SocketAddr,
+        symmetric_key: Option<T>,
+    ) -> Self {
+        ChannelDatagramTransport {
+            socket,
+            server_addr,
+            symmetric_key,
+            codec: DatagramCodec::default(),
+        }
+    }
+
+    pub fn stats(&self) -> DatagramStats {
+        self.codec.stats()
+    }
+}
+
+impl<T: Crypto> Transport for ChannelDatagramTransport<T> {
+    async fn send_message(&mut self, message: Message) -> Result<(), TransportError> {
+        let encoded_with_nonce = serialise_and_encrypt_message(&message, &self.symmetric_key)?;
+
+        for datagram in self.codec.fragment(&encoded_with_nonce)? {
+            self.socket.send_to(&datagram, self.server_addr).await?;
+        }
+        Ok(())
+    }
+
+    async fn receive_message(&mut self) -> Result<Message, TransportError> {
+        loop {
+            let (bytes, addr) = self.socket.recv_from().await?;
+            // mirror a connected UDP socket, which discards datagrams from other peers
+            if addr != self.server_addr {
+                continue;
+            }
+            let Some(bytes) = self.codec.reassemble(addr, &bytes) else {
+                continue;
+            };
+
+            match decrypt_and_deserialise_message(&bytes, &self.symmetric_key) {
+                Ok(message) => return Ok(message),
+                Err(e) => {
+                    self.codec.record_corrupt();
+                    eprintln!("Dropping datagram that could not be decrypted: {}", e);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use chacha20poly1305::{ChaCha20Poly1305, KeyInit};
+
+    use crate::{
+        input_event::InputEventTransport,
+        transport::{SplitTransport, Transport, TransportReader, TransportWriter},
+        Message, TransportError,
+    };
+
+    use super::{ChannelDatagramTransport, ChannelNetwork, ChannelTransport};
+
+    fn test_key() -> ChaCha20Poly1305 {
+        ChaCha20Poly1305::new_from_slice(&[7; 32]).unwrap()
+    }
+
+    #[tokio::test]
+    async fn given_encrypted_pair_should_round_trip_messages() {
+        // Given
+        let (mut a, mut b) = ChannelTransport::<ChaCha20Poly1305>::pair();
+        a.set_key(test_key());
+        b.set_key(test_key());
+
+        // When
+        a.send_message(Message::Heartbeat).await.unwrap();
+        let received = b.receive_message().await;
+
+        // Then
+        assert_eq!(received.unwrap(), Message::Heartbeat);
+    }
+
+    #[tokio::test]
+    async fn given_split_transport_should_send_and_receive_independently() {
+        // Given
+        let (a, mut b) = ChannelTransport::<ChaCha20Poly1305>::pair();
+        let (mut reader, mut writer) = a.into_split();
+
+        // When
+        writer.send_message(Message::Handshake).await.unwrap();
+        b.send_message(Message::TargetChangeResponse { seq: 0 })
+            .await
+            .unwrap();
+
+        // Then
+        assert_eq!(b.receive_message().await.unwrap(), Message::Handshake);
+        assert_eq!(
+            reader.receive_message().await.unwrap(),
+            Message::TargetChangeResponse { seq: 0 }
+        );
+    }
+
+    #[tokio::test]
+    async fn given_dropped_peer_should_report_connection_closed() {
+        // Given
+        let (mut a, b) = ChannelTransport::<ChaCha20Poly1305>::pair();
+        drop(b);
+
+        // When
+        let response = a.receive_message().await;
+
+        // Then
+        assert!(matches!(response, Err(TransportError::ConnectionClosed)));
+    }
+
+    #[tokio::test]
+    async fn given_channel_network_should_carry_input_event_datagrams_both_ways() {
+        // Given
+        let network = ChannelNetwork::new();
+        let server_addr = "10.0.0.1:5000".parse().unwrap();
+        let mut server = InputEventTransport::from(network.bind(server_addr).unwrap());
+        let client_socket = network.bind("10.0.0.2:0".parse().unwrap()).unwrap();
+        let client_addr = client_socket.local_addr();
+        let mut client =
+            ChannelDatagramTransport::new(client_socket, server_addr, Some(test_key()));
+
+        // When
+        client
+            .send_message(Message::UdpHello { seq: 0 })
+            .await
+            .unwrap();
+        let (_, source) = server.receive_from().await.unwrap();
+        server
+            .send_message_to(Message::Heartbeat, source, Some(test_key()))
+            .await
+            .unwrap();
+
+        // Then
+        assert_eq!(source, client_addr);
+        assert_eq!(client.receive_message().await.unwrap(), Message::Heartbeat);
+    }
+
+    #[tokio::test]
+    async fn given_corrupt_datagram_should_drop_it_and_receive_next_message() {
+        // Given
+        let network = ChannelNetwork::new();
+        let server_addr = "10.0.0.1:5000".parse().unwrap();
+        let mut server = InputEventTransport::from(network.bind(server_addr).unwrap());
+        let client_socket = network.bind("10.0.0.2:0".parse().unwrap()).unwrap();
+        let client_addr = client_socket.local_addr();
+        let mut client =
+            ChannelDatagramTransport::new(client_socket, server_addr, Some(test_key()));
+
+        // When
+        server
+            .send_message_to(Message::Heartbeat, client_addr, Some(test_key()))
+            .await
+            .unwrap();
+        server
+            .send_message_to(Message::Heartbeat, client_addr, None::<ChaCha20Poly1305>)
+            .await
+            .unwrap();
+        server
+            .send_message_to(Message::Handshake, client_addr, Some(test_key()))
+            .await
+            .unwrap();
+
+        // Then
+        assert_eq!(client.receive_message().await.unwrap(), Message::Heartbeat);
+        assert_eq!(client.receive_message().await.unwrap(), Message::Handshake);
+        assert_eq!(client.stats().corrupt, 1);
+    }
+
+    #[test]
+    fn given_bound_address_should_refuse_second_bind() {
+        // Given
+        let network = ChannelNetwork::new();
+        let addr = "10.0.0.1:5000".parse().unwrap();
+        let _socket = network.bind(addr).unwrap();
+
+        // When
+        let response = network.bind(addr);
+
+        // Then
+        assert!(response.is_err());
+    }
+}
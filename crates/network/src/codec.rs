@@ -0,0 +1,304 @@
+//! Length-delimited framing shared by the stream transports.
+//!
+//! Each frame is a 4-byte little-endian length followed by that many bytes of payload.
+
+use std::ops::Range;
+
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+use crate::TransportError;
+
+pub const HEADER_LEN: usize = 4;
+pub const DEFAULT_MAX_FRAME_LEN: usize = 1 << 20;
+const READ_CHUNK_LEN: usize = 4096;
+
+#[derive(Debug)]
+pub struct FrameCodec {
+    max_frame_len: usize,
+    read_buf: Vec<u8>,
+    // start of the unconsumed bytes in `read_buf`, so decoding a frame does not shift the rest
+    read_pos: usize,
+    write_buf: Vec<u8>,
+}
+
+impl Default for FrameCodec {
+    fn default() -> Self {
+        FrameCodec::new(DEFAULT_MAX_FRAME_LEN)
+    }
+}
+
+impl FrameCodec {
+    pub fn new(max_frame_len: usize) -> Self {
+        FrameCodec {
+            max_frame_len,
+            read_buf: Vec::new(),
+            read_pos: 0,
+            write_buf: Vec::new(),
+        }
+    }
+
+    pub fn max_frame_len(&self) -> usize {
+        self.max_frame_len
+    }
+
+    /// Frames `payload` into the codec's write buffer and returns the bytes to send.
+    pub fn encode(&mut self, payload: &[u8]) -> Result<&[u8], TransportError> {
+        self.check_len(payload.len())?;
+        let len = u32::try_from(payload.len()).map_err(|_| TransportError::FrameTooLarge {
+            len: payload.len(),
+            max: self.max_frame_len,
+        })?;
+
+        self.write_buf.clear();
+        self.write_buf.extend_from_slice(&len.to_le_bytes());
+        self.write_buf.extend_from_slice(payload);
+        Ok(&self.write_buf)
+    }
+
+    /// Decodes a buffer that must hold exactly one frame, as delivered by message based
+    /// transports.
+    pub fn decode_exact<'a>(&self, frame: &'a [u8]) -> Result<&'a [u8], TransportError> {
+        let len = self
+            .frame_len(frame)?
+            .ok_or(TransportError::InvalidMessageStructure)?;
+        if frame.len() != HEADER_LEN + len {
+            return Err(TransportError::InvalidMessageStructure);
+        }
+        Ok(&frame[HEADER_LEN..])
+    }
+
+    /// Returns the next complete frame, reading from `reader` only when the buffered bytes do
+    /// not already hold one.
+    pub async fn read_frame<R: AsyncRead + Unpin>(
+        &mut self,
+        reader: &mut R,
+    ) -> Result<&[u8], TransportError> {
+        loop {
+            if let Some(range) = self.next_frame()? {
+                return Ok(&self.read_buf[range]);
+            }
+            self.fill(reader).await?;
+        }
+    }
+
+    fn next_frame(&mut self) -> Result<Option<Range<usize>>, TransportError> {
+        let buffered = &self.read_buf[self.read_pos..];
+        let Some(len) = self.frame_len(buffered)? else {
+            return Ok(None);
+        };
+        if buffered.len() < HEADER_LEN + len {
+            return Ok(None);
+        }
+
+        let start = self.read_pos + HEADER_LEN;
+        self.read_pos = start + len;
+        Ok(Some(start..self.read_pos))
+    }
+
+    fn frame_len(&self, bytes: &[u8]) -> Result<Option<usize>, TransportError> {
+        let Some(prefix) = bytes.get(..HEADER_LEN) else {
+            return Ok(None);
+        };
+        let prefix_bytes: [u8; HEADER_LEN] = prefix
+            .try_into()
+            .map_err(|_| TransportError::InvalidMessageStructure)?;
+        let len: usize = u32::from_le_bytes(prefix_bytes)
+            .try_into()
+            .map_err(|_| TransportError::ByteArrayConversionError)?;
+        // reject before buffering so a bad prefix cannot make us allocate up to 4GiB
+        self.check_len(len)?;
+        Ok(Some(len))
+    }
+
+    fn check_len(&self, len: usize) -> Result<(), TransportError> {
+        if len > self.max_frame_len {
+            return Err(TransportError::FrameTooLarge {
+                len,
+                max: self.max_frame_len,
+            });
+        }
+        Ok(())
+    }
+
+    async fn fill<R: AsyncRead + Unpin>(&mut self, reader: &mut R) -> Result<(), TransportError> {
+        self.compact();
+
+        // reading into spare capacity only grows the buffer by what was read, so a read that is
+        // dropped before completing leaves nothing behind for the next frame
+        self.read_buf.reserve(READ_CHUNK_LEN);
+        let bytes_read = reader.read_buf(&mut self.read_buf).await?;
+
+        if bytes_read == 0 {
+            return Err(TransportError::ConnectionClosed);
+        }
+        Ok(())
+    }
+
+    /// Drops consumed bytes. Moving the remainder only happens once the consumed prefix is at
+    /// least as long as it, which keeps the copying linear in the bytes received.
+    fn compact(&mut self) {
+        if self.read_pos == 0 {
+            return;
+        }
+        let remaining = self.read_buf.len() - self.read_pos;
+        if remaining == 0 {
+            self.read_buf.clear();
+            self.read_pos = 0;
+        } else if self.read_pos >= remaining {
+            self.read_buf.copy_within(self.read_pos.., 0);
+            self.read_buf.truncate(remaining);
+            self.read_pos = 0;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::TransportError;
+
+    use super::{FrameCodec, HEADER_LEN};
+
+    fn framed(payloads: &[&[u8]]) -> Vec<u8> {
+        let mut codec = FrameCodec::default();
+        payloads
+            .iter()
+            .flat_map(|payload| codec.encode(payload).unwrap().to_vec())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn given_multiple_frames_in_one_read_should_decode_each_in_order() {
+        // Given
+        let bytes = framed(&[b"first", b"", b"third"]);
+        let mut reader = bytes.as_slice();
+        let mut codec = FrameCodec::default();
+
+        // When
+        let first = codec.read_frame(&mut reader).await.unwrap().to_vec();
+        let second = codec.read_frame(&mut reader).await.unwrap().to_vec();
+        let third = codec.read_frame(&mut reader).await.unwrap().to_vec();
+
+        // Then
+        assert_eq!(first, b"first");
+        assert_eq!(second, b"");
+        assert_eq!(third, b"third");
+        assert!(matches!(
+            codec.read_frame(&mut reader).await,
+            Err(TransportError::ConnectionClosed)
+        ));
+    }
+
+    #[tokio::test]
+    async fn given_frame_split_across_reads_should_wait_for_remainder() {
+        // Given
+        let bytes = framed(&[b"split frame"]);
+        let (mut client, mut server) = tokio::io::duplex(64);
+        let mut codec = FrameCodec::default();
+
+        // When
+        let writer = tokio::spawn(async move {
+            use tokio::io::AsyncWriteExt;
+            for chunk in bytes.chunks(3) {
+                client.write_all(chunk).await.unwrap();
+                tokio::task::yield_now().await;
+            }
+        });
+        let frame = codec.read_frame(&mut server).await.unwrap().to_vec();
+
+        // Then
+        writer.await.unwrap();
+        assert_eq!(frame, b"split frame");
+    }
+
+    #[tokio::test]
+    async fn given_read_dropped_before_data_arrives_should_decode_next_frame() {
+        // Given
+        let bytes = framed(&[b"after cancel"]);
+        let (mut client, mut server) = tokio::io::duplex(64);
+        let mut codec = FrameCodec::default();
+        let pending = tokio::time::timeout(
+            std::time::Duration::from_millis(10),
+            codec.read_frame(&mut server),
+        );
+        assert!(pending.await.is_err());
+
+        // When
+        tokio::io::AsyncWriteExt::write_all(&mut client, &bytes)
+            .await
+            .unwrap();
+        let frame = codec.read_frame(&mut server).await.unwrap().to_vec();
+
+        // Then
+        assert_eq!(frame, b"after cancel");
+    }
+
+    #[tokio::test]
+    async fn given_prefix_over_cap_should_reject_frame() {
+        // Given
+        let bytes = 1025u32.to_le_bytes();
+        let mut reader = bytes.as_slice();
+        let mut codec = FrameCodec::new(1024);
+
+        // When
+        let response = codec.read_frame(&mut reader).await;
+
+        // Then
+        assert!(matches!(
+            response,
+            Err(TransportError::FrameTooLarge {
+                len: 1025,
+                max: 1024
+            })
+        ));
+    }
+
+    #[test]
+    fn given_payload_over_cap_should_refuse_to_encode() {
+        // Given
+        let mut codec = FrameCodec::new(4);
+
+        // When
+        let response = codec.encode(b"too long");
+
+        // Then
+        assert!(matches!(
+            response,
+            Err(TransportError::FrameTooLarge { len: 8, max: 4 })
+        ));
+    }
+
+    #[tokio::test]
+    async fn given_many_small_frames_should_reuse_read_buffer() {
+        // Given
+        let payloads: Vec<&[u8]> = vec![b"payload"; 2000];
+        let bytes = framed(&payloads);
+        let mut reader = bytes.as_slice();
+        let mut codec = FrameCodec::default();
+
+        // When
+        for _ in &payloads {
+            codec.read_frame(&mut reader).await.unwrap();
+        }
+
+        // Then
+        assert!(codec.read_buf.capacity() < bytes.len() / 2);
+        assert!(codec.read_buf.len() - codec.read_pos < HEADER_LEN);
+    }
+
+    #[test]
+    fn given_trailing_bytes_should_reject_exact_frame() {
+        // Given
+        let codec = FrameCodec::default();
+        let mut bytes = framed(&[b"message"]);
+        bytes.push(0);
+
+        // When
+        let response = codec.decode_exact(&bytes);
+
+        // Then
+        assert!(matches!(
+            response,
+            Err(TransportError::InvalidMessageStructure)
+        ));
+    }
+}
@@ -0,0 +1,376 @@
+//! Fragmentation and reassembly for datagram transports.
+//!
+//! Every datagram starts with a fragment header so that messages larger than the payload budget
+//! can be split across several datagrams:
+//!
+//! | message id (u32 LE) | fragment index (u16 LE) | fragment count (u16 LE) | payload |
+//!
+//! A message that fits in one datagram is sent as fragment 0 of 1. Datagrams that are too large,
+//! malformed or that belong to a message that never completes are dropped and counted rather
+//! than reported as errors, since a single bad datagram should not end the session.
+
+use std::{
+    collections::HashMap,
+    net::SocketAddr,
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use crate::TransportError;
+
+pub const FRAGMENT_HEADER_LEN: usize = 8;
+/// Keeps datagrams under the 1280 byte IPv6 minimum MTU once IP and UDP headers are added.
+pub const DEFAULT_MAX_DATAGRAM_LEN: usize = 1200;
+pub const DEFAULT_REASSEMBLY_TIMEOUT: Duration = Duration::from_secs(2);
+const MAX_FRAGMENTS: usize = 64;
+const MAX_PARTIAL_MESSAGES: usize = 64;
+
+/// Counters for datagrams that were discarded instead of delivered.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct DatagramStats {
+    /// Datagrams larger than the configured maximum datagram length.
+    pub oversized: u64,
+    /// Datagrams with an invalid fragment header or that failed to decrypt.
+    pub corrupt: u64,
+    /// Partially received messages discarded after the reassembly timeout.
+    pub expired: u64,
+}
+
+#[derive(Debug)]
+struct PartialMessage {
+    fragments: Vec<Option<Vec<u8>>>,
+    received: usize,
+    first_seen: Instant,
+}
+
+#[derive(Debug)]
+pub struct DatagramCodec {
+    max_datagram_len: usize,
+    reassembly_timeout: Duration,
+    /// Shared by every codec sending on the same socket, so that the peer never sees two
+    /// messages in flight with the same id.
+    next_message_id: Arc<AtomicU32>,
+    partial: HashMap<(SocketAddr, u32), PartialMessage>,
+    stats: DatagramStats,
+}
+
+impl Default for DatagramCodec {
+    fn default() -> Self {
+        DatagramCodec::new(DEFAULT_MAX_DATAGRAM_LEN, DEFAULT_REASSEMBLY_TIMEOUT)
+    }
+}
+
+impl DatagramCodec {
+    pub fn new(max_datagram_len: usize, reassembly_timeout: Duration) -> Self {
+        assert!(
+            max_datagram_len > FRAGMENT_HEADER_LEN,
+            "Datagram length must leave room for a fragment header"
+        );
+        DatagramCodec {
+            max_datagram_len,
+            reassembly_timeout,
+            next_message_id: Arc::new(AtomicU32::new(0)),
+            partial: HashMap::new(),
+            stats: DatagramStats::default(),
+        }
+    }
+
+    /// Creates a codec for another sender on the same socket. It numbers its messages from
+    /// this codec's counter and keeps its own reassembly state and statistics.
+    pub fn share_message_ids(&self) -> Self {
+        DatagramCodec {
+            max_datagram_len: self.max_datagram_len,
+            reassembly_timeout: self.reassembly_timeout,
+            next_message_id: self.next_message_id.clone(),
+            partial: HashMap::new(),
+            stats: DatagramStats::default(),
+        }
+    }
+
+    pub fn max_datagram_len(&self) -> usize {
+        self.max_datagram_len
+    }
+
+    /// The largest message that can be sent, after fragmentation.
+    pub fn max_message_len(&self) -> usize {
+        self.payload_budget() * MAX_FRAGMENTS
+    }
+
+    pub fn stats(&self) -> DatagramStats {
+        self.stats
+    }
+
+    /// Records a reassembled message that failed to decrypt or deserialise.
+    pub fn record_corrupt(&mut self) {
+        self.stats.corrupt += 1;
+    }
+
+    fn payload_budget(&self) -> usize {
+        self.max_datagram_len - FRAGMENT_HEADER_LEN
+    }
+
+    /// Splits `message` into datagrams that each fit in the payload budget.
+    pub fn fragment(&mut self, message: &[u8]) -> Result<Vec<Vec<u8>>, TransportError> {
+        if message.len() > self.max_message_len() {
+            return Err(TransportError::FrameTooLarge {
+                len: message.len(),
+                max: self.max_message_len(),
+            });
+        }
+
+        let message_id = self.next_message_id.fetch_add(1, Ordering::Relaxed);
+
+        let chunks: Vec<&[u8]> = if message.is_empty() {
+            vec![message]
+        } else {
+            message.chunks(self.payload_budget()).collect()
+        };
+        let count = chunks.len() as u16;
+
+        Ok(chunks
+            .into_iter()
+            .enumerate()
+            .map(|(index, chunk)| {
+                let mut datagram = Vec::with_capacity(FRAGMENT_HEADER_LEN + chunk.len());
+                datagram.extend_from_slice(&message_id.to_le_bytes());
+                datagram.extend_from_slice(&(index as u16).to_le_bytes());
+                datagram.extend_from_slice(&count.to_le_bytes());
+                datagram.extend_from_slice(chunk);
+                datagram
+            })
+            .collect())
+    }
+
+    /// Accepts a received datagram and returns the full message once all of its fragments have
+    /// arrived. `datagram` may be up to one byte longer than the maximum so that truncated
+    /// receives can be recognised as oversized.
+    pub fn reassemble(&mut self, source: SocketAddr, datagram: &[u8]) -> Option<Vec<u8>> {
+        self.reassemble_at(source, datagram, Instant::now())
+    }
+
+    fn reassemble_at(
+        &mut self,
+        source: SocketAddr,
+        datagram: &[u8],
+        now: Instant,
+    ) -> Option<Vec<u8>> {
+        self.expire(now);
+
+        if datagram.len() > self.max_datagram_len {
+            self.stats.oversized += 1;
+            eprintln!(
+                "Dropping oversized datagram of at least {} bytes from {}",
+                datagram.len(),
+                source
+            );
+            return None;
+        }
+        let Some((message_id, index, count)) = parse_header(datagram) else {
+            self.stats.corrupt += 1;
+            eprintln!(
+                "Dropping datagram with invalid fragment header from {}",
+                source
+            );
+            return None;
+        };
+        let payload = &datagram[FRAGMENT_HEADER_LEN..];
+
+        if count == 1 {
+            return Some(payload.to_vec());
+        }
+
+        if !self.partial.contains_key(&(source, message_id))
+            && self.partial.len() >= MAX_PARTIAL_MESSAGES
+        {
+            self.evict_oldest();
+        }
+        let partial = self
+            .partial
+            .entry((source, message_id))
+            .or_insert_with(|| PartialMessage {
+                fragments: vec![None; count],
+                received: 0,
+                first_seen: now,
+            });
+        if partial.fragments.len() != count {
+            // a stale message with a reused id, most likely from a restarted sender
+            self.stats.corrupt += 1;
+            *partial = PartialMessage {
+                fragments: vec![None; count],
+                received: 0,
+                first_seen: now,
+            };
+        }
+        if partial.fragments[index].is_none() {
+            partial.fragments[index] = Some(payload.to_vec());
+            partial.received += 1;
+        }
+        if partial.received < count {
+            return None;
+        }
+
+        let partial = self.partial.remove(&(source, message_id))?;
+        Some(partial.fragments.into_iter().flatten().flatten().collect())
+    }
+
+    fn expire(&mut self, now: Instant) {
+        let timeout = self.reassembly_timeout;
+        let before = self.partial.len();
+        self.partial
+            .retain(|_, partial| now.duration_since(partial.first_seen) < timeout);
+        self.stats.expired += (before - self.partial.len()) as u64;
+    }
+
+    fn evict_oldest(&mut self) {
+        let oldest = self
+            .partial
+            .iter()
+            .min_by_key(|(_, partial)| partial.first_seen)
+            .map(|(key, _)| *key);
+        if let Some(key) = oldest {
+            self.partial.remove(&key);
+            self.stats.expired += 1;
+        }
+    }
+}
+
+fn parse_header(datagram: &[u8]) -> Option<(u32, usize, usize)> {
+    let header = datagram.get(..FRAGMENT_HEADER_LEN)?;
+    let message_id = u32::from_le_bytes(header[0..4].try_into().ok()?);
+    let index = u16::from_le_bytes(header[4..6].try_into().ok()?) as usize;
+    let count = u16::from_le_bytes(header[6..8].try_into().ok()?) as usize;
+
+    if count == 0 || count > MAX_FRAGMENTS || index >= count {
+        return None;
+    }
+    Some((message_id, index, count))
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        net::SocketAddr,
+        time::{Duration, Instant},
+    };
+
+    use super::{DatagramCodec, DatagramStats, FRAGMENT_HEADER_LEN};
+
+    fn source() -> SocketAddr {
+        "127.0.0.1:40000".parse().unwrap()
+    }
+
+    #[test]
+    fn given_small_message_should_send_single_datagram() {
+        // Given
+        let mut codec = DatagramCodec::default();
+
+        // When
+        let datagrams = codec.fragment(b"hello").unwrap();
+        let message = codec.reassemble(source(), &datagrams[0]);
+
+        // Then
+        assert_eq!(datagrams.len(), 1);
+        assert_eq!(message.unwrap(), b"hello");
+    }
+
+    #[test]
+    fn given_codecs_sharing_message_ids_should_reassemble_interleaved_fragments() {
+        // Given
+        let mut first = DatagramCodec::new(16, Duration::from_secs(1));
+        let mut second = first.share_message_ids();
+        let mut receiver = DatagramCodec::new(16, Duration::from_secs(1));
+        let first_message: Vec<u8> = (0..20).collect();
+        let second_message: Vec<u8> = (100..120).collect();
+        let first_datagrams = first.fragment(&first_message).unwrap();
+        let second_datagrams = second.fragment(&second_message).unwrap();
+
+        // When
+        let results: Vec<_> = first_datagrams
+            .iter()
+            .zip(&second_datagrams)
+            .flat_map(|(a, b)| [a, b])
+            .filter_map(|datagram| receiver.reassemble(source(), datagram))
+            .collect();
+
+        // Then
+        assert_eq!(results, vec![first_message, second_message]);
+    }
+
+    #[test]
+    fn given_large_message_should_reassemble_out_of_order_fragments() {
+        // Given
+        let mut sender = DatagramCodec::new(16, Duration::from_secs(1));
+        let mut receiver = DatagramCodec::new(16, Duration::from_secs(1));
+        let message: Vec<u8> = (0..50).collect();
+        let mut datagrams = sender.fragment(&message).unwrap();
+        datagrams.reverse();
+
+        // When
+        let results: Vec<_> = datagrams
+            .iter()
+            .map(|datagram| receiver.reassemble(source(), datagram))
+            .collect();
+
+        // Then
+        assert!(datagrams
+            .iter()
+            .all(|datagram| datagram.len() <= 16 && datagram.len() > FRAGMENT_HEADER_LEN));
+        assert!(results[..results.len() - 1].iter().all(Option::is_none));
+        assert_eq!(results.last().unwrap().as_ref().unwrap(), &message);
+    }
+
+    #[test]
+    fn given_oversized_or_corrupt_datagram_should_drop_and_count() {
+        // Given
+        let mut codec = DatagramCodec::new(16, Duration::from_secs(1));
+
+        // When
+        let oversized = codec.reassemble(source(), &[0; 17]);
+        let corrupt = codec.reassemble(source(), &[0, 0, 0, 0, 5, 0, 2, 0]);
+
+        // Then
+        assert!(oversized.is_none());
+        assert!(corrupt.is_none());
+        assert_eq!(
+            codec.stats(),
+            DatagramStats {
+                oversized: 1,
+                corrupt: 1,
+                expired: 0
+            }
+        );
+    }
+
+    #[test]
+    fn given_missing_fragment_should_expire_after_timeout() {
+        // Given
+        let mut sender = DatagramCodec::new(16, Duration::from_secs(1));
+        let mut receiver = DatagramCodec::new(16, Duration::from_secs(1));
+        let datagrams = sender.fragment(&[1; 20]).unwrap();
+        let now = Instant::now();
+        receiver.reassemble_at(source(), &datagrams[0], now);
+
+        // When
+        let late = receiver.reassemble_at(source(), &datagrams[1], now + Duration::from_secs(2));
+
+        // Then
+        assert!(late.is_none());
+        assert_eq!(receiver.stats().expired, 1);
+    }
+
+    #[test]
+    fn given_message_over_fragment_limit_should_refuse_to_send() {
+        // Given
+        let mut codec = DatagramCodec::new(16, Duration::from_secs(1));
+        let message = vec![0; codec.max_message_len() + 1];
+
+        // When
+        let response = codec.fragment(&message);
+
+        // Then
+        assert!(response.is_err());
+    }
+}
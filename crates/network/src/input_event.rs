@@ -1,18 +1,85 @@
-use std::net::SocketAddr;
+use std::{net::SocketAddr, sync::Arc};
 
-use chacha20poly1305::Nonce;
 use crypto::Crypto;
-use tokio::net::UdpSocket;
+use tokio::net::{UdpSocket, UnixDatagram};
 
-use crate::{Message, MessageWithNonce, TransportError};
+use crate::{
+    channel::ChannelDatagramSocket,
+    fragment::{DatagramCodec, DatagramStats},
+    transport::serialise_and_encrypt_message,
+    unix::UnixInputSocket,
+    Message, TransportError,
+};
+
+#[derive(Clone)]
+enum InputEventSocket {
+    Udp(Arc<UdpSocket>),
+    Channel(Arc<ChannelDatagramSocket>),
+    Unix(Arc<UnixInputSocket>),
+}
+
+impl InputEventSocket {
+    async fn send_message_to<T: Crypto>(
+        &self,
+        codec: &mut DatagramCodec,
+        message: Message,
+        address: SocketAddr,
+        encryptor: Option<T>,
+    ) -> Result<(), TransportError> {
+        let encoded_with_nonce = serialise_and_encrypt_message(&message, &encryptor)?;
+
+        for datagram in codec.fragment(&encoded_with_nonce)? {
+            match self {
+                InputEventSocket::Udp(socket) => socket.send_to(&datagram, address).await?,
+                InputEventSocket::Channel(socket) => socket.send_to(&datagram, address).await?,
+                InputEventSocket::Unix(socket) => socket.send_to(&datagram, address).await?,
+            };
+        }
+        Ok(())
+    }
+}
 
 pub struct InputEventTransport {
-    socket: UdpSocket,
+    socket: InputEventSocket,
+    codec: DatagramCodec,
+    buf: Vec<u8>,
 }
 
 impl InputEventTransport {
     pub fn new(socket: UdpSocket) -> Self {
-        InputEventTransport { socket }
+        InputEventTransport::with_socket(InputEventSocket::Udp(Arc::new(socket)))
+    }
+
+    fn with_socket(socket: InputEventSocket) -> Self {
+        InputEventTransport {
+            socket,
+            codec: DatagramCodec::default(),
+            buf: Vec::new(),
+        }
+    }
+
+    /// Replaces the default payload budget and reassembly timeout.
+    pub fn with_datagram_codec(mut self, codec: DatagramCodec) -> Self {
+        self.codec = codec;
+        self
+    }
+
+    pub fn stats(&self) -> DatagramStats {
+        self.codec.stats()
+    }
+
+    /// Creates a sender that shares this transport's socket, so that messages can be sent from
+    /// another task while this one keeps receiving.
+    pub fn sender(&self) -> InputEventSender {
+        InputEventSender {
+            socket: self.socket.clone(),
+            codec: self.codec.share_message_ids(),
+        }
+    }
+
+    /// Counts a reassembled datagram that the caller could not authenticate.
+    pub fn record_corrupt(&mut self) {
+        self.codec.record_corrupt();
     }
 
     pub async fn send_message_to<T: Crypto>(
@@ -21,18 +88,77 @@ impl InputEventTransport {
         address: SocketAddr,
         encryptor: Option<T>,
     ) -> Result<(), TransportError> {
-        let encoded_message: Vec<u8> = bincode::serialize(&message)?;
+        self.socket
+            .send_message_to(&mut self.codec, message, address, encryptor)
+            .await
+    }
 
-        let (encrypted, nonce) = if let Some(encryptor) = encryptor {
-            encryptor.encrypt(encoded_message)?
-        } else {
-            (encoded_message, Nonce::default())
-        };
+    /// Receives a single reassembled message along with the address it was sent from.
+    ///
+    /// The message is returned undecrypted since the key depends on which client sent it.
+    pub async fn receive_from(&mut self) -> Result<(Vec<u8>, SocketAddr), TransportError> {
+        // one spare byte so that a datagram over the budget is seen as oversized, not truncated
+        self.buf.resize(self.codec.max_datagram_len() + 1, 0);
 
-        let message_with_nonce = MessageWithNonce::new(encrypted, nonce);
-        let encoded_with_nonce: Vec<u8> = bincode::serialize(&message_with_nonce)?;
+        loop {
+            let reassembled = match &self.socket {
+                InputEventSocket::Udp(socket) => {
+                    let (bytes_read, addr) = socket.recv_from(&mut self.buf).await?;
+                    self.codec
+                        .reassemble(addr, &self.buf[..bytes_read])
+                        .map(|bytes| (bytes, addr))
+                }
+                InputEventSocket::Channel(socket) => {
+                    let (bytes, addr) = socket.recv_from().await?;
+                    self.codec
+                        .reassemble(addr, &bytes)
+                        .map(|bytes| (bytes, addr))
+                }
+                InputEventSocket::Unix(socket) => {
+                    let (bytes_read, addr) = socket.recv_from(&mut self.buf).await?;
+                    self.codec
+                        .reassemble(addr, &self.buf[..bytes_read])
+                        .map(|bytes| (bytes, addr))
+                }
+            };
 
-        self.socket.send_to(&encoded_with_nonce, address).await?;
-        Ok(())
+            if let Some(datagram) = reassembled {
+                return Ok(datagram);
+            }
+        }
+    }
+}
+
+impl From<ChannelDatagramSocket> for InputEventTransport {
+    fn from(socket: ChannelDatagramSocket) -> Self {
+        InputEventTransport::with_socket(InputEventSocket::Channel(Arc::new(socket)))
+    }
+}
+
+/// Receives input from clients connected through `UnixDatagramTransport`.
+impl From<UnixDatagram> for InputEventTransport {
+    fn from(socket: UnixDatagram) -> Self {
+        InputEventTransport::with_socket(InputEventSocket::Unix(Arc::new(UnixInputSocket::new(
+            socket,
+        ))))
+    }
+}
+
+/// The sending half of an `InputEventTransport`, numbering its messages from the same counter.
+pub struct InputEventSender {
+    socket: InputEventSocket,
+    codec: DatagramCodec,
+}
+
+impl InputEventSender {
+    pub async fn send_message_to<T: Crypto>(
+        &mut self,
+        message: Message,
+        address: SocketAddr,
+        encryptor: Option<T>,
+    ) -> Result<(), TransportError> {
+        self.socket
+            .send_message_to(&mut self.codec, message, address, encryptor)
+            .await
     }
 }
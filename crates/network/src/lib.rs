@@ -1,15 +1,49 @@
-use std::{fmt, net::SocketAddr};
+use std::{fmt, net::SocketAddr, path::PathBuf};
 
-use ::input_event::InputEvent;
+use ::input_event::{InputEvent, ScreenGeometry};
 use chacha20poly1305::Nonce;
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
+use uuid::Uuid;
 use x25519_dalek::PublicKey;
 
+pub mod channel;
+pub mod codec;
+pub mod fragment;
 pub mod input_event;
+pub mod link_stats;
+pub mod stream;
 pub mod tcp;
 pub mod transport;
 pub mod udp;
+pub mod unix;
+#[cfg(feature = "websocket")]
+pub mod websocket;
+
+/// Where a server listens and clients connect.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ServerAddress {
+    /// Control messages over TCP and input events over UDP, both on the same address.
+    Inet(SocketAddr),
+    /// Control messages over a Unix stream socket at this path, and input events over Unix
+    /// datagrams at the path given by `unix::input_socket_path`.
+    Unix(PathBuf),
+}
+
+impl From<SocketAddr> for ServerAddress {
+    fn from(addr: SocketAddr) -> Self {
+        ServerAddress::Inet(addr)
+    }
+}
+
+impl fmt::Display for ServerAddress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ServerAddress::Inet(addr) => write!(f, "{}", addr),
+            ServerAddress::Unix(path) => write!(f, "{}", path.display()),
+        }
+    }
+}
 
 #[derive(Debug, Error)]
 pub enum TransportError {
@@ -25,6 +59,18 @@ pub enum TransportError {
     IOError(#[from] std::io::Error),
     #[error("Connection was closed - 0 bytes read")]
     ConnectionClosed,
+    #[error("Frame of {len} bytes exceeds the maximum of {max} bytes")]
+    FrameTooLarge { len: usize, max: usize },
+    #[cfg(feature = "websocket")]
+    #[error("WebSocket error: {0}")]
+    WebSocketError(Box<tokio_tungstenite::tungstenite::Error>),
+}
+
+#[cfg(feature = "websocket")]
+impl From<tokio_tungstenite::tungstenite::Error> for TransportError {
+    fn from(err: tokio_tungstenite::tungstenite::Error) -> Self {
+        TransportError::WebSocketError(Box::new(err))
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -42,17 +88,67 @@ impl MessageWithNonce {
     }
 }
 
+/// Sent in place of a key exchange by a client that holds a ticket from an earlier session.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ResumeRequest {
+    /// The opaque ticket the server issued at the end of the previous session.
+    pub ticket: Vec<u8>,
+    /// Fresh randomness from the client, mixed into the resumed session key.
+    pub nonce: [u8; 32],
+}
+
 #[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
 pub enum Message {
-    InputEvent { event: InputEvent },
-    TargetChangeNotification,
-    TargetChangeResponse,
-    ClipboardChanged { content: String }, // TODO: content could be an image
-    ClientInit { addr: SocketAddr },
-    ExchangePubKey { pub_key: PublicKey },
+    InputEvent {
+        event: InputEvent,
+    },
+    /// Asks the client to release held keys. The client echoes `seq` in its response.
+    TargetChangeNotification {
+        seq: u32,
+    },
+    TargetChangeResponse {
+        seq: u32,
+    },
+    ClipboardChanged {
+        content: String,
+    }, // TODO: content could be an image
+    ClientInit {
+        tunnel_input: bool,
+        resume: Option<ResumeRequest>,
+        /// The screen input is replayed on, for placing absolute pointer input.
+        screen: Option<ScreenGeometry>,
+    },
+    ResumeAccepted {
+        nonce: [u8; 32],
+    },
+    SessionTicket {
+        client_id: Uuid,
+        ticket: Vec<u8>,
+        secret: [u8; 32],
+    },
+    ExchangePubKey {
+        pub_key: PublicKey,
+    },
     ExchangePubKeyResponse,
     Handshake,
     Heartbeat,
+    UdpHello {
+        seq: u64,
+    },
+    Ping {
+        seq: u32,
+        sent_at: u64,
+    },
+    Pong {
+        seq: u32,
+        sent_at: u64,
+    },
+    Goodbye {
+        reason: String,
+    },
+    ServerShuttingDown {
+        reason: String,
+    },
 }
 
 impl fmt::Display for Message {
@@ -61,18 +157,47 @@ impl fmt::Display for Message {
             Message::InputEvent { event } => {
                 write!(f, "InputEvent: event = {:?}", event)
             }
-            Message::TargetChangeNotification => write!(f, "TargetChangeNotification"),
-            Message::TargetChangeResponse => write!(f, "TargetChangeResponse"),
+            Message::TargetChangeNotification { seq } => {
+                write!(f, "TargetChangeNotification: seq = {}", seq)
+            }
+            Message::TargetChangeResponse { seq } => {
+                write!(f, "TargetChangeResponse: seq = {}", seq)
+            }
             Message::ClipboardChanged { content } => {
                 write!(f, "ClipboardChanged: content = {}", content)
             }
-            Message::ClientInit { addr } => write!(f, "ClientInit: addr = {}", addr),
+            Message::ClientInit {
+                tunnel_input,
+                resume,
+                screen,
+            } => write!(
+                f,
+                "ClientInit: tunnel_input = {}, resume = {}, screen = {}",
+                tunnel_input,
+                resume.is_some(),
+                screen.map_or("unknown".to_string(), |screen| screen.to_string())
+            ),
+            Message::ResumeAccepted { .. } => write!(f, "ResumeAccepted"),
+            Message::SessionTicket { client_id, .. } => {
+                write!(f, "SessionTicket: client_id = {}", client_id)
+            }
             Message::ExchangePubKey { pub_key } => {
                 write!(f, "ExchangePubKey: pub_key = {:?}", pub_key)
             }
             Message::ExchangePubKeyResponse => write!(f, "Ack"),
             Message::Handshake => write!(f, "Handshake"),
             Message::Heartbeat => write!(f, "Heartbeat"),
+            Message::UdpHello { seq } => write!(f, "UdpHello: seq = {}", seq),
+            Message::Ping { seq, sent_at } => {
+                write!(f, "Ping: seq = {}, sent_at = {}", seq, sent_at)
+            }
+            Message::Pong { seq, sent_at } => {
+                write!(f, "Pong: seq = {}, sent_at = {}", seq, sent_at)
+            }
+            Message::Goodbye { reason } => write!(f, "Goodbye: reason = {}", reason),
+            Message::ServerShuttingDown { reason } => {
+                write!(f, "ServerShuttingDown: reason = {}", reason)
+            }
         }
     }
 }
@@ -0,0 +1,204 @@
+//! Round-trip time, jitter and loss tracking for ping/pong exchanges.
+//!
+//! A ping carries a timestamp from the sender's own monotonic clock which the peer echoes back
+//! in its pong, so the clocks on the two machines never need to agree.
+
+use std::{
+    fmt,
+    sync::OnceLock,
+    time::{Duration, Instant},
+};
+
+use crate::Message;
+
+// smoothing factors from RFC 6298 (RTT) and RFC 3550 (jitter)
+const RTT_GAIN: f64 = 1.0 / 8.0;
+const JITTER_GAIN: f64 = 1.0 / 16.0;
+
+static EPOCH: OnceLock<Instant> = OnceLock::new();
+
+/// Microseconds on this process's monotonic clock, used to stamp pings.
+pub fn timestamp_micros() -> u64 {
+    EPOCH.get_or_init(Instant::now).elapsed().as_micros() as u64
+}
+
+/// A point in time summary of a link, suitable for logs and display.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct LinkQuality {
+    pub last_rtt: Option<Duration>,
+    pub smoothed_rtt: Option<Duration>,
+    pub jitter: Duration,
+    pub pings_sent: u64,
+    pub pongs_received: u64,
+    pub lost: u64,
+}
+
+impl LinkQuality {
+    /// Fraction of pings whose pong has not arrived even though a later pong has.
+    pub fn loss_ratio(&self) -> f64 {
+        let total = self.pongs_received + self.lost;
+        if total == 0 {
+            return 0.0;
+        }
+        self.lost as f64 / total as f64
+    }
+}
+
+impl fmt::Display for LinkQuality {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.smoothed_rtt {
+            Some(rtt) => write!(
+                f,
+                "rtt = {:.1}ms, jitter = {:.1}ms, loss = {:.1}% ({}/{} pongs)",
+                rtt.as_secs_f64() * 1000.0,
+                self.jitter.as_secs_f64() * 1000.0,
+                self.loss_ratio() * 100.0,
+                self.pongs_received,
+                self.pings_sent,
+            ),
+            None => write!(f, "no pongs yet ({} pings sent)", self.pings_sent),
+        }
+    }
+}
+
+/// Quality of both links between a client and the server.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct ConnectionQuality {
+    /// The control connection.
+    pub control: LinkQuality,
+    /// The UDP input event path, unused when input is tunnelled.
+    pub input: LinkQuality,
+}
+
+impl fmt::Display for ConnectionQuality {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "control: {}; input: {}", self.control, self.input)
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct LinkStats {
+    next_seq: u32,
+    highest_pong_seq: Option<u32>,
+    smoothed_rtt: Option<f64>,
+    jitter: f64,
+    quality: LinkQuality,
+}
+
+impl LinkStats {
+    pub fn new() -> Self {
+        LinkStats::default()
+    }
+
+    /// Creates the next ping to send and counts it as sent.
+    pub fn ping(&mut self) -> Message {
+        let seq = self.next_seq;
+        self.next_seq = self.next_seq.wrapping_add(1);
+        self.quality.pings_sent += 1;
+        Message::Ping {
+            seq,
+            sent_at: timestamp_micros(),
+        }
+    }
+
+    /// Records the pong for one of our pings and returns the measured round trip time.
+    pub fn record_pong(&mut self, seq: u32, sent_at: u64) -> Duration {
+        let rtt = Duration::from_micros(timestamp_micros().saturating_sub(sent_at));
+        self.record_rtt(seq, rtt);
+        rtt
+    }
+
+    fn record_rtt(&mut self, seq: u32, rtt: Duration) {
+        self.quality.pongs_received += 1;
+        match self.highest_pong_seq {
+            Some(highest) if seq <= highest => {
+                // a late pong was counted as lost when a newer one overtook it
+                self.quality.lost = self.quality.lost.saturating_sub(1);
+            }
+            Some(highest) => {
+                self.quality.lost += u64::from(seq - highest - 1);
+                self.highest_pong_seq = Some(seq);
+            }
+            None => {
+                self.quality.lost += u64::from(seq);
+                self.highest_pong_seq = Some(seq);
+            }
+        }
+
+        let sample = rtt.as_secs_f64();
+        if let Some(last_rtt) = self.quality.last_rtt {
+            let delta = (sample - last_rtt.as_secs_f64()).abs();
+            self.jitter += (delta - self.jitter) * JITTER_GAIN;
+        }
+        let smoothed = match self.smoothed_rtt {
+            Some(smoothed) => smoothed + (sample - smoothed) * RTT_GAIN,
+            None => sample,
+        };
+        self.smoothed_rtt = Some(smoothed);
+
+        self.quality.last_rtt = Some(rtt);
+        self.quality.smoothed_rtt = Some(Duration::from_secs_f64(smoothed));
+        self.quality.jitter = Duration::from_secs_f64(self.jitter);
+    }
+
+    pub fn quality(&self) -> LinkQuality {
+        self.quality
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use crate::Message;
+
+    use super::LinkStats;
+
+    #[test]
+    fn given_pongs_should_smooth_rtt_and_track_jitter() {
+        // Given
+        let mut stats = LinkStats::new();
+
+        // When
+        stats.record_rtt(0, Duration::from_millis(10));
+        stats.record_rtt(1, Duration::from_millis(26));
+
+        // Then
+        let quality = stats.quality();
+        assert_eq!(quality.last_rtt, Some(Duration::from_millis(26)));
+        assert!((quality.smoothed_rtt.unwrap().as_secs_f64() - 0.012).abs() < 1e-6);
+        assert!((quality.jitter.as_secs_f64() - 0.001).abs() < 1e-6);
+    }
+
+    #[test]
+    fn given_missing_pong_should_count_loss_until_it_arrives_late() {
+        // Given
+        let mut stats = LinkStats::new();
+        stats.record_rtt(0, Duration::from_millis(10));
+        stats.record_rtt(2, Duration::from_millis(10));
+        let lost = stats.quality().lost;
+
+        // When
+        stats.record_rtt(1, Duration::from_millis(30));
+
+        // Then
+        assert_eq!(lost, 1);
+        assert_eq!(stats.quality().lost, 0);
+        assert_eq!(stats.quality().pongs_received, 3);
+    }
+
+    #[test]
+    fn given_ping_should_stamp_increasing_sequence_numbers() {
+        // Given
+        let mut stats = LinkStats::new();
+
+        // When
+        let first = stats.ping();
+        let second = stats.ping();
+
+        // Then
+        assert!(matches!(first, Message::Ping { seq: 0, .. }));
+        assert!(matches!(second, Message::Ping { seq: 1, .. }));
+        assert_eq!(stats.quality().pings_sent, 2);
+    }
+}
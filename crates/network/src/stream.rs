@@ -0,0 +1,139 @@
+//! Control transports over stream sockets, shared by TCP and Unix sockets so that framing and
+//! encryption live in one place.
+
+use crypto::Crypto;
+use tokio::{
+    io::{AsyncRead, AsyncWrite, AsyncWriteExt},
+    net::{tcp, unix, TcpStream, UnixStream},
+};
+
+use crate::{codec::FrameCodec, Message, TransportError};
+
+use super::transport::{
+    decrypt_and_deserialise_message, serialise_and_encrypt_message, SplitTransport, Transport,
+    TransportReader, TransportWriter,
+};
+
+/// A stream socket that can be split into halves that are owned, and so used, independently.
+pub trait SplitStream: AsyncRead + AsyncWrite + Unpin + Send + 'static {
+    type ReadHalf: AsyncRead + Unpin + Send + 'static;
+    type WriteHalf: AsyncWrite + Unpin + Send + 'static;
+
+    fn into_split(self) -> (Self::ReadHalf, Self::WriteHalf);
+}
+
+impl SplitStream for TcpStream {
+    type ReadHalf = tcp::OwnedReadHalf;
+    type WriteHalf = tcp::OwnedWriteHalf;
+
+    fn into_split(self) -> (Self::ReadHalf, Self::WriteHalf) {
+        TcpStream::into_split(self)
+    }
+}
+
+impl SplitStream for UnixStream {
+    type ReadHalf = unix::OwnedReadHalf;
+    type WriteHalf = unix::OwnedWriteHalf;
+
+    fn into_split(self) -> (Self::ReadHalf, Self::WriteHalf) {
+        UnixStream::into_split(self)
+    }
+}
+
+#[derive(Debug)]
+pub struct StreamTransport<S, T: Crypto> {
+    socket: S,
+    key: Option<T>,
+    codec: FrameCodec,
+}
+
+impl<S, T: Crypto> StreamTransport<S, T> {
+    pub fn new(socket: S) -> Self {
+        StreamTransport {
+            socket,
+            key: None,
+            codec: FrameCodec::default(),
+        }
+    }
+
+    /// Sets the largest frame that will be sent or accepted on this connection.
+    pub fn with_max_frame_len(mut self, max_frame_len: usize) -> Self {
+        self.codec = FrameCodec::new(max_frame_len);
+        self
+    }
+}
+
+impl<S: SplitStream, T: Crypto + 'static> SplitTransport<T> for StreamTransport<S, T> {
+    type Reader = StreamTransportReader<S::ReadHalf, T>;
+    type Writer = StreamTransportWriter<S::WriteHalf, T>;
+
+    fn set_key(&mut self, key: T) {
+        self.key = Some(key);
+    }
+
+    fn into_split(self) -> (Self::Reader, Self::Writer) {
+        let (reader, writer) = self.socket.into_split();
+        let write_codec = FrameCodec::new(self.codec.max_frame_len());
+        let reader_transport = StreamTransportReader::new(reader, self.key.clone(), self.codec);
+        let writer_transport = StreamTransportWriter::new(writer, self.key, write_codec);
+        (reader_transport, writer_transport)
+    }
+}
+
+impl<S, T> Transport for StreamTransport<S, T>
+where
+    S: AsyncRead + AsyncWrite + Unpin + Send,
+    T: Crypto + Clone,
+{
+    async fn send_message(&mut self, message: Message) -> Result<(), TransportError> {
+        let encoded_with_nonce = serialise_and_encrypt_message(&message, &self.key)?;
+        let frame = self.codec.encode(&encoded_with_nonce)?;
+        self.socket.write_all(frame).await?;
+        Ok(())
+    }
+
+    async fn receive_message(&mut self) -> Result<Message, TransportError> {
+        let frame = self.codec.read_frame(&mut self.socket).await?;
+        decrypt_and_deserialise_message(frame, &self.key)
+    }
+}
+
+pub struct StreamTransportWriter<W, T: Crypto> {
+    socket: W,
+    key: Option<T>,
+    codec: FrameCodec,
+}
+
+impl<W, T: Crypto> StreamTransportWriter<W, T> {
+    pub fn new(socket: W, key: Option<T>, codec: FrameCodec) -> Self {
+        StreamTransportWriter { socket, key, codec }
+    }
+}
+
+impl<W: AsyncWrite + Unpin + Send, T: Crypto> TransportWriter for StreamTransportWriter<W, T> {
+    async fn send_message(&mut self, message: Message) -> Result<(), TransportError> {
+        let encoded_with_nonce = serialise_and_encrypt_message(&message, &self.key)?;
+        let frame = self.codec.encode(&encoded_with_nonce)?;
+        self.socket.write_all(frame).await?;
+        Ok(())
+    }
+}
+
+pub struct StreamTransportReader<R, T: Crypto> {
+    socket: R,
+    key: Option<T>,
+    codec: FrameCodec,
+}
+
+impl<R, T: Crypto> StreamTransportReader<R, T> {
+    pub fn new(socket: R, key: Option<T>, codec: FrameCodec) -> Self {
+        StreamTransportReader { socket, key, codec }
+    }
+}
+
+impl<R: AsyncRead + Unpin + Send, T: Crypto> TransportReader for StreamTransportReader<R, T> {
+    async fn receive_message(&mut self) -> Result<Message, TransportError> {
+        let frame = self.codec.read_frame(&mut self.socket).await?;
+        decrypt_and_deserialise_message(frame, &self.key)
+    }
+}
@@ -1,3 +1,4 @@
+use chacha20poly1305::Nonce;
 use crypto::Crypto;
 
 use crate::{Message, MessageWithNonce, TransportError};
@@ -6,23 +7,49 @@ pub trait Transport {
     fn send_message(
         &mut self,
         message: Message,
-    ) -> impl std::future::Future<Output = Result<(), TransportError>>;
+    ) -> impl std::future::Future<Output = Result<(), TransportError>> + Send;
     fn receive_message(
         &mut self,
-    ) -> impl std::future::Future<Output = Result<Message, TransportError>>;
+    ) -> impl std::future::Future<Output = Result<Message, TransportError>> + Send;
 }
 
 pub trait TransportReader {
     fn receive_message(
         &mut self,
-    ) -> impl std::future::Future<Output = Result<Message, TransportError>>;
+    ) -> impl std::future::Future<Output = Result<Message, TransportError>> + Send;
 }
 
 pub trait TransportWriter {
     fn send_message(
         &mut self,
         message: Message,
-    ) -> impl std::future::Future<Output = Result<(), TransportError>>;
+    ) -> impl std::future::Future<Output = Result<(), TransportError>> + Send;
+}
+
+/// A connection oriented transport that carries control messages and can be split into
+/// independent read and write halves once the session key has been negotiated.
+pub trait SplitTransport<T: Crypto>: Transport + Send {
+    type Reader: TransportReader + Send + 'static;
+    type Writer: TransportWriter + Send + 'static;
+
+    fn set_key(&mut self, key: T);
+    fn into_split(self) -> (Self::Reader, Self::Writer);
+}
+
+pub fn serialise_and_encrypt_message<T: Crypto>(
+    message: &Message,
+    key: &Option<T>,
+) -> Result<Vec<u8>, TransportError> {
+    let encoded_message: Vec<u8> = bincode::serialize(message)?;
+
+    let (encrypted, nonce) = if let Some(encryptor) = key {
+        encryptor.encrypt(encoded_message)?
+    } else {
+        (encoded_message, Nonce::default())
+    };
+
+    let message_with_nonce = MessageWithNonce::new(encrypted, nonce);
+    Ok(bincode::serialize(&message_with_nonce)?)
 }
 
 pub fn decrypt_and_deserialise_message<T: Crypto>(
@@ -1,19 +1,21 @@
 use std::net::SocketAddr;
 
-use chacha20poly1305::Nonce;
 use crypto::Crypto;
 use tokio::net::UdpSocket;
 
-use crate::{Message, MessageWithNonce, TransportError};
+use crate::{
+    fragment::{DatagramCodec, DatagramStats},
+    Message, TransportError,
+};
 
-use super::transport::{decrypt_and_deserialise_message, Transport};
-
-const BUFFER_LEN: usize = 256;
+use super::transport::{decrypt_and_deserialise_message, serialise_and_encrypt_message, Transport};
 
 pub struct TokioUdpTransport<T: Crypto> {
     socket: UdpSocket,
     server_addr: SocketAddr,
     symmetric_key: Option<T>,
+    codec: DatagramCodec,
+    buf: Vec<u8>,
 }
 
 impl<T: Crypto> TokioUdpTransport<T> {
@@ -22,38 +24,52 @@ impl<T: Crypto> TokioUdpTransport<T> {
             socket,
             server_addr,
             symmetric_key,
+            codec: DatagramCodec::default(),
+            buf: Vec::new(),
         }
     }
+
+    /// Replaces the default payload budget and reassembly timeout.
+    pub fn with_datagram_codec(mut self, codec: DatagramCodec) -> Self {
+        self.codec = codec;
+        self
+    }
+
+    pub fn stats(&self) -> DatagramStats {
+        self.codec.stats()
+    }
 }
 
 impl<T: Crypto> Transport for TokioUdpTransport<T> {
     async fn send_message(&mut self, message: Message) -> Result<(), TransportError> {
-        let encoded_message: Vec<u8> = bincode::serialize(&message)?;
+        let encoded_with_nonce = serialise_and_encrypt_message(&message, &self.symmetric_key)?;
 
-        let (encrypted, nonce) = if let Some(encryptor) = &self.symmetric_key {
-            encryptor.encrypt(encoded_message)?
-        } else {
-            (encoded_message, Nonce::default())
-        };
-
-        let message_with_nonce = MessageWithNonce::new(encrypted, nonce);
-        let encoded_with_nonce: Vec<u8> = bincode::serialize(&message_with_nonce)?;
-
-        self.socket
-            .send_to(&encoded_with_nonce, self.server_addr)
-            .await?;
+        for datagram in self.codec.fragment(&encoded_with_nonce)? {
+            self.socket.send_to(&datagram, self.server_addr).await?;
+        }
         Ok(())
     }
 
     async fn receive_message(&mut self) -> Result<Message, TransportError> {
-        let mut buf = [0; BUFFER_LEN];
-        let bytes_read = self.socket.recv(&mut buf).await?;
+        // one spare byte so that a datagram over the budget is seen as oversized, not truncated
+        self.buf.resize(self.codec.max_datagram_len() + 1, 0);
 
-        if bytes_read == 0 {
-            return Err(TransportError::ConnectionClosed);
-        }
+        loop {
+            let bytes_read = self.socket.recv(&mut self.buf).await?;
+            let Some(bytes) = self
+                .codec
+                .reassemble(self.server_addr, &self.buf[..bytes_read])
+            else {
+                continue;
+            };
 
-        decrypt_and_deserialise_message(&buf[..bytes_read], &self.symmetric_key)
-            .inspect_err(|e| eprintln!("Error while decrypting and deserialising: {}", e))
+            match decrypt_and_deserialise_message(&bytes, &self.symmetric_key) {
+                Ok(message) => return Ok(message),
+                Err(e) => {
+                    self.codec.record_corrupt();
+                    eprintln!("Dropping datagram that could not be decrypted: {}", e);
+                }
+            }
+        }
     }
 }
@@ -0,0 +1,395 @@
+use std::{
+    collections::HashMap,
+    io,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+    os::unix::fs::FileTypeExt,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use crypto::Crypto;
+use tokio::net::{
+    unix::{OwnedReadHalf, OwnedWriteHalf},
+    UnixDatagram, UnixStream,
+};
+
+use crate::{
+    fragment::{DatagramCodec, DatagramStats},
+    stream::{StreamTransport, StreamTransportReader, StreamTransportWriter},
+    Message, TransportError,
+};
+
+use super::transport::{decrypt_and_deserialise_message, serialise_and_encrypt_message, Transport};
+
+// a connected datagram socket only has one peer, so reassembly does not need its address
+const CONNECTED_PEER: SocketAddr = SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), 0);
+
+/// The path of the datagram socket that carries input events for the server at `control_path`.
+pub fn input_socket_path(control_path: &Path) -> PathBuf {
+    let mut path = control_path.as_os_str().to_owned();
+    path.push(".input");
+    PathBuf::from(path)
+}
+
+/// Removes a socket left behind by an earlier run, which would otherwise stop `path` from being
+/// bound again. Anything other than a socket is left alone.
+pub fn remove_stale_socket(path: &Path) -> io::Result<()> {
+    match std::fs::symlink_metadata(path) {
+        Ok(metadata) if metadata.file_type().is_socket() => std::fs::remove_file(path),
+        Ok(_) => Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("{} exists and is not a socket", path.display()),
+        )),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(()),
+        Err(err) => Err(err),
+    }
+}
+
+pub type UnixSocketTransport<T> = StreamTransport<UnixStream, T>;
+pub type UnixSocketTransportWriter<T> = StreamTransportWriter<OwnedWriteHalf, T>;
+pub type UnixSocketTransportReader<T> = StreamTransportReader<OwnedReadHalf, T>;
+
+/// Client side of the input event path over a connected Unix datagram socket.
+pub struct UnixDatagramTransport<T: Crypto> {
+    socket: UnixDatagram,
+    symmetric_key: Option<T>,
+    codec: DatagramCodec,
+    buf: Vec<u8>,
+    /// The path this end was bound to, removed again when the transport is dropped.
+    bound_path: Option<PathBuf>,
+}
+
+impl<T: Crypto> UnixDatagramTransport<T> {
+    pub fn new(socket: UnixDatagram, symmetric_key: Option<T>) -> Self {
+        UnixDatagramTransport {
+            socket,
+            symmetric_key,
+            codec: DatagramCodec::default(),
+            buf: Vec::new(),
+            bound_path: None,
+        }
+    }
+
+    /// Binds to `local_path` and connects to the server's input socket at `server_path`. The
+    /// server can only answer a datagram from a bound socket, since it replies to the sender's
+    /// path.
+    pub fn connect(
+        local_path: &Path,
+        server_path: &Path,
+        symmetric_key: Option<T>,
+    ) -> io::Result<Self> {
+        let socket = UnixDatagram::bind(local_path)?;
+        let mut transport = UnixDatagramTransport::new(socket, symmetric_key);
+        transport.bound_path = Some(local_path.to_path_buf());
+        transport.socket.connect(server_path)?;
+        Ok(transport)
+    }
+
+    pub fn stats(&self) -> DatagramStats {
+        self.codec.stats()
+    }
+}
+
+impl<T: Crypto> Drop for UnixDatagramTransport<T> {
+    fn drop(&mut self) {
+        if let Some(path) = &self.bound_path {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+}
+
+impl<T: Crypto> Transport for UnixDatagramTransport<T> {
+    async fn send_message(&mut self, message: Message) -> Result<(), TransportError> {
+        let encoded_with_nonce = serialise_and_encrypt_message(&message, &self.symmetric_key)?;
+        for datagram in self.codec.fragment(&encoded_with_nonce)? {
+            self.socket.send(&datagram).await?;
+        }
+        Ok(())
+    }
+
+    async fn receive_message(&mut self) -> Result<Message, TransportError> {
+        // one spare byte so that a datagram over the budget is seen as oversized, not truncated
+        self.buf.resize(self.codec.max_datagram_len() + 1, 0);
+
+        loop {
+            let bytes_read = self.socket.recv(&mut self.buf).await?;
+            let Some(bytes) = self
+                .codec
+                .reassemble(CONNECTED_PEER, &self.buf[..bytes_read])
+            else {
+                continue;
+            };
+
+            match decrypt_and_deserialise_message(&bytes, &self.symmetric_key) {
+                Ok(message) => return Ok(message),
+                Err(e) => {
+                    self.codec.record_corrupt();
+                    eprintln!("Dropping datagram that could not be decrypted: {}", e);
+                }
+            }
+        }
+    }
+}
+
+/// Server side of the input event path over Unix datagrams.
+///
+/// Input peers are told apart by socket address, which a Unix socket does not have, so each
+/// client path is given a stand-in address the first time a datagram arrives from it.
+#[derive(Debug)]
+pub(crate) struct UnixInputSocket {
+    socket: UnixDatagram,
+    peers: Mutex<UnixPeers>,
+}
+
+impl UnixInputSocket {
+    pub(crate) fn new(socket: UnixDatagram) -> Self {
+        UnixInputSocket {
+            socket,
+            peers: Mutex::new(UnixPeers::default()),
+        }
+    }
+
+    pub(crate) async fn recv_from(&self, buf: &mut [u8]) -> io::Result<(usize, SocketAddr)> {
+        loop {
+            let (bytes_read, peer) = self.socket.recv_from(buf).await?;
+            // an unbound socket cannot be answered, so there is no point in reading its input
+            let Some(path) = peer.as_pathname() else {
+                continue;
+            };
+            let addr = self.peers.lock().unwrap().address_of(path);
+            return Ok((bytes_read, addr));
+        }
+    }
+
+    pub(crate) async fn send_to(&self, buf: &[u8], addr: SocketAddr) -> io::Result<usize> {
+        let path = self.peers.lock().unwrap().path_of(addr).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("No Unix socket peer has the address {}", addr),
+            )
+        })?;
+        let result = self.socket.send_to(buf, &path).await;
+        if let Err(err) = &result {
+            // the client has gone and removed its socket, so its path will not be seen again
+            if matches!(
+                err.kind(),
+                io::ErrorKind::NotFound | io::ErrorKind::ConnectionRefused
+            ) {
+                self.peers.lock().unwrap().forget(addr);
+            }
+        }
+        result
+    }
+}
+
+/// The most peer paths remembered at once. Clients that leave, and any other process that sends
+/// to the socket, would otherwise be remembered forever.
+const MAX_UNIX_PEERS: usize = 256;
+
+/// The stand-in address of each peer path, of which the least recently used is forgotten once
+/// there are `MAX_UNIX_PEERS`.
+#[derive(Debug, Default)]
+struct UnixPeers {
+    addresses: HashMap<PathBuf, SocketAddr>,
+    paths: HashMap<SocketAddr, (PathBuf, u64)>,
+    next: u128,
+    /// Counts uses, to tell which peer was used least recently.
+    uses: u64,
+}
+
+impl UnixPeers {
+    fn address_of(&mut self, path: &Path) -> SocketAddr {
+        self.uses += 1;
+        if let Some(addr) = self.addresses.get(path) {
+            if let Some((_, last_used)) = self.paths.get_mut(addr) {
+                *last_used = self.uses;
+            }
+            return *addr;
+        }
+        if self.paths.len() >= MAX_UNIX_PEERS {
+            self.forget_least_recently_used();
+        }
+        self.next += 1;
+        let addr = SocketAddr::new(IpAddr::V6(Ipv6Addr::from(self.next)), 0);
+        self.addresses.insert(path.to_path_buf(), addr);
+        self.paths.insert(addr, (path.to_path_buf(), self.uses));
+        addr
+    }
+
+    fn path_of(&mut self, addr: SocketAddr) -> Option<PathBuf> {
+        self.uses += 1;
+        let (path, last_used) = self.paths.get_mut(&addr)?;
+        *last_used = self.uses;
+        Some(path.clone())
+    }
+
+    fn forget(&mut self, addr: SocketAddr) {
+        if let Some((path, _)) = self.paths.remove(&addr) {
+            self.addresses.remove(&path);
+        }
+    }
+
+    fn forget_least_recently_used(&mut self) {
+        let oldest = self
+            .paths
+            .iter()
+            .min_by_key(|(_, (_, last_used))| *last_used)
+            .map(|(addr, _)| *addr);
+        if let Some(addr) = oldest {
+            self.forget(addr);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::PathBuf;
+
+    use chacha20poly1305::{ChaCha20Poly1305, KeyInit};
+    use tokio::net::{UnixDatagram, UnixStream};
+
+    use crate::{
+        input_event::InputEventTransport,
+        transport::{
+            decrypt_and_deserialise_message, SplitTransport, Transport, TransportReader,
+            TransportWriter,
+        },
+        Message,
+    };
+
+    use super::{
+        input_socket_path, UnixDatagramTransport, UnixPeers, UnixSocketTransport, MAX_UNIX_PEERS,
+    };
+
+    fn test_key() -> ChaCha20Poly1305 {
+        ChaCha20Poly1305::new_from_slice(&[3; 32]).unwrap()
+    }
+
+    fn test_socket_path(name: &str) -> PathBuf {
+        let path =
+            std::env::temp_dir().join(format!("network-{}-{}.sock", name, std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        path
+    }
+
+    #[tokio::test]
+    async fn given_consecutive_messages_should_receive_each_in_order() {
+        // Given
+        let (a, b) = UnixStream::pair().unwrap();
+        let mut a: UnixSocketTransport<ChaCha20Poly1305> = UnixSocketTransport::new(a);
+        let mut b: UnixSocketTransport<ChaCha20Poly1305> = UnixSocketTransport::new(b);
+        a.set_key(test_key());
+        b.set_key(test_key());
+
+        // When
+        a.send_message(Message::Handshake).await.unwrap();
+        a.send_message(Message::Heartbeat).await.unwrap();
+
+        // Then
+        assert_eq!(b.receive_message().await.unwrap(), Message::Handshake);
+        assert_eq!(b.receive_message().await.unwrap(), Message::Heartbeat);
+    }
+
+    #[tokio::test]
+    async fn given_split_transport_should_send_and_receive_independently() {
+        // Given
+        let (a, b) = UnixStream::pair().unwrap();
+        let a: UnixSocketTransport<ChaCha20Poly1305> = UnixSocketTransport::new(a);
+        let mut b: UnixSocketTransport<ChaCha20Poly1305> = UnixSocketTransport::new(b);
+        let (mut reader, mut writer) = a.into_split();
+
+        // When
+        writer
+            .send_message(Message::ClientInit {
+                tunnel_input: false,
+                resume: None,
+                screen: None,
+            })
+            .await
+            .unwrap();
+        b.send_message(Message::Handshake).await.unwrap();
+
+        // Then
+        assert_eq!(
+            b.receive_message().await.unwrap(),
+            Message::ClientInit {
+                tunnel_input: false,
+                resume: None,
+                screen: None,
+            }
+        );
+        assert_eq!(reader.receive_message().await.unwrap(), Message::Handshake);
+    }
+
+    #[tokio::test]
+    async fn given_datagram_pair_should_round_trip_encrypted_messages() {
+        // Given
+        let (a, b) = UnixDatagram::pair().unwrap();
+        let mut a = UnixDatagramTransport::new(a, Some(test_key()));
+        let mut b = UnixDatagramTransport::new(b, Some(test_key()));
+
+        // When
+        a.send_message(Message::UdpHello { seq: 1 }).await.unwrap();
+
+        // Then
+        assert_eq!(
+            b.receive_message().await.unwrap(),
+            Message::UdpHello { seq: 1 }
+        );
+    }
+
+    #[tokio::test]
+    async fn given_bound_client_should_exchange_messages_with_server_input_socket() {
+        // Given
+        let server_path = input_socket_path(&test_socket_path("server"));
+        let client_path = test_socket_path("client");
+        let mut server = InputEventTransport::from(UnixDatagram::bind(&server_path).unwrap());
+        let mut client =
+            UnixDatagramTransport::connect(&client_path, &server_path, Some(test_key())).unwrap();
+
+        // When
+        client
+            .send_message(Message::UdpHello { seq: 1 })
+            .await
+            .unwrap();
+        let (bytes, addr) = server.receive_from().await.unwrap();
+        server
+            .send_message_to(Message::Ping { seq: 2, sent_at: 3 }, addr, Some(test_key()))
+            .await
+            .unwrap();
+
+        // Then
+        assert_eq!(
+            decrypt_and_deserialise_message(&bytes, &Some(test_key())).unwrap(),
+            Message::UdpHello { seq: 1 }
+        );
+        assert_eq!(
+            client.receive_message().await.unwrap(),
+            Message::Ping { seq: 2, sent_at: 3 }
+        );
+        drop(client);
+        assert!(!client_path.exists());
+        std::fs::remove_file(server_path).unwrap();
+    }
+
+    #[test]
+    fn given_more_peers_than_the_cap_should_forget_the_least_recently_used() {
+        // Given
+        let mut peers = UnixPeers::default();
+        let path = |idx: usize| PathBuf::from(format!("/tmp/peer-{}.sock", idx));
+        let first = peers.address_of(&path(0));
+        let second = peers.address_of(&path(1));
+        for idx in 2..MAX_UNIX_PEERS {
+            peers.address_of(&path(idx));
+        }
+        peers.path_of(first);
+
+        // When
+        peers.address_of(&path(MAX_UNIX_PEERS));
+
+        // Then
+        assert_eq!(peers.paths.len(), MAX_UNIX_PEERS);
+        assert_eq!(peers.path_of(first), Some(path(0)));
+        assert_eq!(peers.path_of(second), None);
+    }
+}
@@ -0,0 +1,327 @@
+//! WebSocket tunnel for networks that only pass HTTP(S) traffic.
+//!
+//! Every WebSocket binary message carries exactly one length-prefixed frame, byte for byte the
+//! same as a frame on the TCP transport. Input events are tunnelled over the same connection
+//! since UDP is not expected to get through.
+
+use std::{io, net::SocketAddr, time::Duration};
+
+use crypto::Crypto;
+use futures_util::{
+    stream::{SplitSink, SplitStream},
+    SinkExt, StreamExt,
+};
+use tokio::{
+    io::{AsyncRead, AsyncWrite},
+    net::TcpStream,
+};
+use tokio_tungstenite::{
+    tungstenite::{self, protocol::WebSocketConfig},
+    MaybeTlsStream, WebSocketStream,
+};
+
+use crate::{
+    codec::{FrameCodec, DEFAULT_MAX_FRAME_LEN, HEADER_LEN},
+    Message, TransportError,
+};
+
+use super::transport::{
+    decrypt_and_deserialise_message, serialise_and_encrypt_message, SplitTransport, Transport,
+    TransportReader, TransportWriter,
+};
+
+const UPGRADE_PREFIX: &[u8] = b"GET ";
+/// How long an accepted connection may take to send the first bytes of its first message.
+const UPGRADE_DETECTION_TIMEOUT: Duration = Duration::from_secs(5);
+/// How often to look again while only part of the prefix has arrived.
+const UPGRADE_PEEK_INTERVAL: Duration = Duration::from_millis(10);
+
+/// The client end of a tunnel, connected over plain TCP.
+pub type ClientWebSocketTransport<T> = WebSocketTransport<T, MaybeTlsStream<TcpStream>>;
+
+pub struct WebSocketTransport<T: Crypto, S> {
+    socket: WebSocketStream<S>,
+    key: Option<T>,
+    codec: FrameCodec,
+}
+
+impl<T: Crypto> ClientWebSocketTransport<T> {
+    /// Opens a WebSocket connection to the server at `ws://{server_addr}/`.
+    pub async fn connect(server_addr: SocketAddr) -> Result<Self, TransportError> {
+        let url = format!("ws://{}/", server_addr);
+        let (socket, _) =
+            tokio_tungstenite::connect_async_with_config(url, Some(config()), false).await?;
+        Ok(WebSocketTransport::new(socket))
+    }
+}
+
+impl<T: Crypto, S: AsyncRead + AsyncWrite + Unpin> WebSocketTransport<T, S> {
+    /// Completes the server side of the WebSocket upgrade on an accepted stream.
+    pub async fn accept(stream: S) -> Result<Self, TransportError> {
+        let socket = tokio_tungstenite::accept_async_with_config(stream, Some(config())).await?;
+        Ok(WebSocketTransport::new(socket))
+    }
+
+    fn new(socket: WebSocketStream<S>) -> Self {
+        WebSocketTransport {
+            socket,
+            key: None,
+            codec: FrameCodec::default(),
+        }
+    }
+}
+
+impl<T, S> SplitTransport<T> for WebSocketTransport<T, S>
+where
+    T: Crypto + 'static,
+    S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    type Reader = WebSocketTransportReader<T, S>;
+    type Writer = WebSocketTransportWriter<T, S>;
+
+    fn set_key(&mut self, key: T) {
+        self.key = Some(key);
+    }
+
+    fn into_split(
+        self,
+    ) -> (
+        WebSocketTransportReader<T, S>,
+        WebSocketTransportWriter<T, S>,
+    ) {
+        let (writer, reader) = self.socket.split();
+        let write_codec = FrameCodec::new(self.codec.max_frame_len());
+        let reader_transport = WebSocketTransportReader::new(reader, self.key.clone(), self.codec);
+        let writer_transport = WebSocketTransportWriter::new(writer, self.key, write_codec);
+        (reader_transport, writer_transport)
+    }
+}
+
+impl<T: Crypto, S: AsyncRead + AsyncWrite + Unpin + Send> Transport for WebSocketTransport<T, S> {
+    async fn send_message(&mut self, message: Message) -> Result<(), TransportError> {
+        let frame = frame_message(&mut self.codec, &message, &self.key)?;
+        self.socket.send(frame).await?;
+        Ok(())
+    }
+
+    async fn receive_message(&mut self) -> Result<Message, TransportError> {
+        loop {
+            let message = self
+                .socket
+                .next()
+                .await
+                .ok_or(TransportError::ConnectionClosed)??;
+            if let Some(message) = unframe_message(&self.codec, message, &self.key)? {
+                return Ok(message);
+            }
+        }
+    }
+}
+
+pub struct WebSocketTransportWriter<T: Crypto, S> {
+    socket: SplitSink<WebSocketStream<S>, tungstenite::Message>,
+    key: Option<T>,
+    codec: FrameCodec,
+}
+
+impl<T: Crypto, S> WebSocketTransportWriter<T, S> {
+    pub fn new(
+        socket: SplitSink<WebSocketStream<S>, tungstenite::Message>,
+        key: Option<T>,
+        codec: FrameCodec,
+    ) -> Self {
+        WebSocketTransportWriter { socket, key, codec }
+    }
+}
+
+impl<T: Crypto, S: AsyncRead + AsyncWrite + Unpin + Send> TransportWriter
+    for WebSocketTransportWriter<T, S>
+{
+    async fn send_message(&mut self, message: Message) -> Result<(), TransportError> {
+        let frame = frame_message(&mut self.codec, &message, &self.key)?;
+        self.socket.send(frame).await?;
+        Ok(())
+    }
+}
+
+pub struct WebSocketTransportReader<T: Crypto, S> {
+    socket: SplitStream<WebSocketStream<S>>,
+    key: Option<T>,
+    codec: FrameCodec,
+}
+
+impl<T: Crypto, S> WebSocketTransportReader<T, S> {
+    pub fn new(socket: SplitStream<WebSocketStream<S>>, key: Option<T>, codec: FrameCodec) -> Self {
+        WebSocketTransportReader { socket, key, codec }
+    }
+}
+
+impl<T: Crypto, S: AsyncRead + AsyncWrite + Unpin + Send> TransportReader
+    for WebSocketTransportReader<T, S>
+{
+    async fn receive_message(&mut self) -> Result<Message, TransportError> {
+        loop {
+            let message = self
+                .socket
+                .next()
+                .await
+                .ok_or(TransportError::ConnectionClosed)??;
+            if let Some(message) = unframe_message(&self.codec, message, &self.key)? {
+                return Ok(message);
+            }
+        }
+    }
+}
+
+/// Returns true if the first bytes on a freshly accepted stream are an HTTP upgrade request
+/// rather than a length-prefixed frame, without consuming them. Fails if the peer has not sent
+/// enough to tell within `UPGRADE_DETECTION_TIMEOUT`.
+pub async fn is_websocket_upgrade(stream: &TcpStream) -> Result<bool, TransportError> {
+    tokio::time::timeout(UPGRADE_DETECTION_TIMEOUT, peek_upgrade_prefix(stream))
+        .await
+        .map_err(|_| {
+            io::Error::new(
+                io::ErrorKind::TimedOut,
+                "peer did not send enough to tell its protocol",
+            )
+        })?
+}
+
+async fn peek_upgrade_prefix(stream: &TcpStream) -> Result<bool, TransportError> {
+    let mut buf = [0; UPGRADE_PREFIX.len()];
+    // the prefix can arrive split across segments, so peek until it is complete or rejected
+    loop {
+        let bytes_read = stream.peek(&mut buf).await?;
+        if bytes_read == 0 {
+            return Err(TransportError::ConnectionClosed);
+        }
+        if buf[..bytes_read] != UPGRADE_PREFIX[..bytes_read] {
+            return Ok(false);
+        }
+        if bytes_read == buf.len() {
+            return Ok(true);
+        }
+        // peeking leaves the stream readable, so it would return at once without a pause
+        tokio::time::sleep(UPGRADE_PEEK_INTERVAL).await;
+    }
+}
+
+fn config() -> WebSocketConfig {
+    WebSocketConfig::default()
+        .max_message_size(Some(HEADER_LEN + DEFAULT_MAX_FRAME_LEN))
+        .max_frame_size(Some(HEADER_LEN + DEFAULT_MAX_FRAME_LEN))
+}
+
+fn frame_message<T: Crypto>(
+    codec: &mut FrameCodec,
+    message: &Message,
+    key: &Option<T>,
+) -> Result<tungstenite::Message, TransportError> {
+    let encoded_with_nonce = serialise_and_encrypt_message(message, key)?;
+    let frame = codec.encode(&encoded_with_nonce)?;
+    Ok(tungstenite::Message::binary(frame.to_vec()))
+}
+
+/// Decodes a binary WebSocket message, skipping control messages such as pings.
+fn unframe_message<T: Crypto>(
+    codec: &FrameCodec,
+    message: tungstenite::Message,
+    key: &Option<T>,
+) -> Result<Option<Message>, TransportError> {
+    let data = match message {
+        tungstenite::Message::Binary(data) => data,
+        tungstenite::Message::Close(_) => return Err(TransportError::ConnectionClosed),
+        _ => return Ok(None),
+    };
+
+    decrypt_and_deserialise_message(codec.decode_exact(&data)?, key).map(Some)
+}
+
+#[cfg(test)]
+mod test {
+    use chacha20poly1305::{ChaCha20Poly1305, KeyInit};
+    use tokio::{
+        io::AsyncWriteExt,
+        net::{TcpListener, TcpStream},
+    };
+
+    use crate::{
+        tcp::TokioTcpTransport,
+        transport::{SplitTransport, Transport, TransportReader, TransportWriter},
+        Message,
+    };
+
+    use super::{is_websocket_upgrade, WebSocketTransport};
+
+    fn test_key() -> ChaCha20Poly1305 {
+        ChaCha20Poly1305::new_from_slice(&[5; 32]).unwrap()
+    }
+
+    #[tokio::test]
+    async fn given_local_listener_should_tunnel_encrypted_messages() {
+        // Given
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let server_addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            assert!(is_websocket_upgrade(&stream).await.unwrap());
+            let mut transport: WebSocketTransport<ChaCha20Poly1305, TcpStream> =
+                WebSocketTransport::accept(stream).await.unwrap();
+            transport.set_key(test_key());
+            let (mut reader, mut writer) = transport.into_split();
+            let message = reader.receive_message().await.unwrap();
+            writer.send_message(message).await.unwrap();
+        });
+
+        // When
+        let mut client = WebSocketTransport::connect(server_addr).await.unwrap();
+        client.set_key(test_key());
+        let event = Message::UdpHello { seq: 42 };
+        client.send_message(event).await.unwrap();
+        let response = client.receive_message().await;
+
+        // Then
+        server.await.unwrap();
+        assert_eq!(response.unwrap(), Message::UdpHello { seq: 42 });
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn given_stalled_partial_prefix_should_time_out() {
+        // Given
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let server_addr = listener.local_addr().unwrap();
+        let mut client = TcpStream::connect(server_addr).await.unwrap();
+        let (stream, _) = listener.accept().await.unwrap();
+        client.write_all(b"GE").await.unwrap();
+
+        // When
+        let detected = is_websocket_upgrade(&stream).await;
+
+        // Then
+        assert!(detected.is_err());
+    }
+
+    #[tokio::test]
+    async fn given_raw_tcp_client_should_not_detect_upgrade() {
+        // Given
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let server_addr = listener.local_addr().unwrap();
+        let mut client: TokioTcpTransport<ChaCha20Poly1305> =
+            TokioTcpTransport::new(TcpStream::connect(server_addr).await.unwrap());
+
+        // When
+        client
+            .send_message(Message::ClientInit {
+                tunnel_input: false,
+                resume: None,
+                screen: None,
+            })
+            .await
+            .unwrap();
+        let (stream, _) = listener.accept().await.unwrap();
+        let response = is_websocket_upgrade(&stream).await;
+
+        // Then
+        assert!(!response.unwrap());
+    }
+}
@@ -2,7 +2,6 @@ use std::time::Duration;
 
 use chacha20poly1305::ChaCha20Poly1305;
 use network::{
-    tcp::{TokioTcpTransportReader, TokioTcpTransportWriter},
     transport::{TransportReader, TransportWriter},
     Message, TransportError,
 };
@@ -28,7 +27,7 @@ const MAX_RETRIES: u64 = 3;
 #[derive(Debug, Error)]
 pub enum ClientHandlerError {
     #[error("Could not send new client through channel: {0}")]
-    ClientSendError(#[from] SendError<Client<ChaCha20Poly1305>>),
+    ClientSendError(#[from] Box<SendError<Client<ChaCha20Poly1305>>>),
     #[error("Could not connect to client: {0}")]
     ClientConnectionError(#[from] ConnectionResourceError),
     #[error("Transport error: {0}")]
@@ -41,86 +40,95 @@ pub enum ClientHandlerError {
     SubTaskPanickedError(#[from] JoinError),
 }
 
-impl ConnectionResource<ChaCha20Poly1305> {
+impl<R, W> ConnectionResource<R, W>
+where
+    R: TransportReader + Send + 'static,
+    W: TransportWriter + Send + 'static,
+{
     pub async fn process_events(
         self,
         cancellation_token: CancellationToken,
     ) -> Result<(), ClientHandlerError> {
-        let client_message_sender = ClientMessageSender::new(self.id, self.client_message_sender);
-        let client_message_sender_clone = client_message_sender.clone();
-        let listener = tokio::spawn(async move {
-            tcp_listener(self.transport_reader, client_message_sender_clone).await
+        let (id, generation) = (self.id, self.generation);
+        let client_message_sender = ClientMessageSender::new(id, self.client_message_sender);
+        let listener_message_sender = client_message_sender.clone();
+        let mut listener = tokio::spawn(async move {
+            tcp_listener(self.transport_reader, listener_message_sender).await
         });
-        let sender = tokio::spawn(async move {
-            tcp_sender(
-                self.id,
-                self.transport_writer,
-                self.message_receiver,
-                client_message_sender,
-            )
-            .await
+        let mut sender = tokio::spawn(async move {
+            tcp_sender(id, self.transport_writer, self.message_receiver).await
         });
 
-        tokio::select! {
-            result = listener => {
-                result?
-            },
-            result = sender => {
-                result?
-            },
-            _ = cancellation_token.cancelled() => {
-                Ok(())
-            },
+        let result = tokio::select! {
+            result = &mut listener => result,
+            result = &mut sender => result,
+            _ = cancellation_token.cancelled() => Ok(Ok(())),
+        };
+        // neither half is any use alone, and a leftover listener would keep speaking for a
+        // client the state actor may already have forgotten
+        listener.abort();
+        sender.abort();
+        let result = result
+            .map_err(ClientHandlerError::from)
+            .and_then(|result| result);
+        // a client that crashed or dropped the connection never says goodbye, so the state
+        // actor would otherwise keep it connected
+        if result.is_err() {
+            let message = ServerMessage::ClientDisconnect { id, generation };
+            client_message_sender.send_server_message(message).await?;
         }
+        result
     }
 }
 
-async fn tcp_listener(
-    mut listener: TokioTcpTransportReader<ChaCha20Poly1305>,
+async fn tcp_listener<R: TransportReader>(
+    mut listener: R,
     client_message_sender: ClientMessageSender,
 ) -> Result<(), ClientHandlerError> {
     loop {
         let message = listener.receive_message().await?;
+        let goodbye = matches!(message, Message::Goodbye { .. });
         client_message_sender.send_client_message(message).await?;
+        if goodbye {
+            return Ok(());
+        }
     }
 }
 
-async fn tcp_sender(
+async fn tcp_sender<W: TransportWriter>(
     id: Uuid,
-    mut sender: TokioTcpTransportWriter<ChaCha20Poly1305>,
+    mut sender: W,
     mut message_receiver: Receiver<Message>,
-    client_message_sender: ClientMessageSender,
 ) -> Result<(), ClientHandlerError> {
     let duration = Duration::from_secs(HEARTBEAT_INTERVAL);
     let mut fail_count = 0;
 
     loop {
         tokio::select! {
-            Some(message) = message_receiver.recv() => {
+            message = message_receiver.recv() => {
+                // the state actor drops the sender once a resumed session replaces this connection
+                let Some(message) = message else {
+                    println!("Connection for client {} was replaced", id);
+                    return Ok(());
+                };
                 handle_send_result(
                     sender.send_message(message).await,
                     &mut fail_count,
-                    id,
-                    &client_message_sender,
-                ).await?;
+                )?;
             },
             _ = tokio::time::sleep(duration) => {
                 handle_send_result(
                     sender.send_message(Message::Heartbeat).await,
                     &mut fail_count,
-                    id,
-                    &client_message_sender,
-                ).await?;
+                )?;
             }
         }
     }
 }
 
-async fn handle_send_result(
+fn handle_send_result(
     result: Result<(), TransportError>,
     fail_count: &mut u64,
-    id: Uuid,
-    client_message_sender: &ClientMessageSender,
 ) -> Result<(), ClientHandlerError> {
     if let Err(err) = result {
         *fail_count += 1;
@@ -130,8 +138,6 @@ async fn handle_send_result(
         );
 
         if *fail_count >= MAX_RETRIES {
-            let message = ServerMessage::ClientDisconnect { id };
-            client_message_sender.send_server_message(message).await?;
             return Err(ClientHandlerError::HeartbeatFail);
         }
     } else {
@@ -140,3 +146,64 @@ async fn handle_send_result(
 
     Ok(())
 }
+
+#[cfg(test)]
+mod test {
+    use network::{
+        transport::{TransportReader, TransportWriter},
+        Message, TransportError,
+    };
+    use tokio::sync::mpsc;
+    use tokio_util::sync::CancellationToken;
+    use uuid::Uuid;
+
+    use crate::{actors::client::resource::ConnectionResource, InternalMessage, ServerMessage};
+
+    /// Reads what the test sends, and fails as a closed socket does once the test drops it.
+    struct FakeReader(mpsc::Receiver<Message>);
+
+    impl TransportReader for FakeReader {
+        async fn receive_message(&mut self) -> Result<Message, TransportError> {
+            self.0.recv().await.ok_or(TransportError::ConnectionClosed)
+        }
+    }
+
+    struct FakeWriter;
+
+    impl TransportWriter for FakeWriter {
+        async fn send_message(&mut self, _message: Message) -> Result<(), TransportError> {
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn given_connection_dropped_without_goodbye_should_disconnect_client() {
+        // Given
+        let (client, transport_reader) = mpsc::channel(1);
+        let (_message_sender, message_receiver) = mpsc::channel(1);
+        let (client_message_sender, mut client_messages) = mpsc::channel(8);
+        let id = Uuid::new_v4();
+        let connection = ConnectionResource {
+            id,
+            generation: 7,
+            transport_writer: FakeWriter,
+            transport_reader: FakeReader(transport_reader),
+            message_receiver,
+            client_message_sender,
+        };
+
+        // When
+        drop(client);
+        let result = connection.process_events(CancellationToken::new()).await;
+
+        // Then
+        assert!(result.is_err());
+        let message = client_messages.recv().await;
+        assert!(matches!(
+            message,
+            Some(InternalMessage::LocalMessage {
+                message: ServerMessage::ClientDisconnect { id: disconnected, generation: 7 },
+            }) if disconnected == id
+        ));
+    }
+}
@@ -1,18 +1,15 @@
 use chacha20poly1305::ChaCha20Poly1305;
-use crypto::Crypto;
 use network::{
-    tcp::{TokioTcpTransport, TokioTcpTransportReader, TokioTcpTransportWriter},
+    transport::{SplitTransport, TransportReader, TransportWriter},
     Message,
 };
 use thiserror::Error;
-use tokio::{
-    net::TcpStream,
-    sync::mpsc::{self, error::SendError, Receiver, Sender},
-};
+use tokio::sync::mpsc::{self, error::SendError, Receiver, Sender};
 use uuid::Uuid;
 
 use crate::{
     actors::state::client::{Client, ClientConnectionError, Connection},
+    session_ticket::SessionTickets,
     InternalMessage,
 };
 
@@ -23,37 +20,39 @@ pub enum ConnectionResourceError {
     #[error("Connection error: {0}")]
     ConnectionError(#[from] ClientConnectionError),
     #[error("Could not send client to state actor: {0}")]
-    ClientSendError(#[from] SendError<Client<ChaCha20Poly1305>>),
+    ClientSendError(#[from] Box<SendError<Client<ChaCha20Poly1305>>>),
 }
 
-pub struct ConnectionResource<T: Crypto> {
+pub struct ConnectionResource<R: TransportReader, W: TransportWriter> {
     pub id: Uuid,
-    pub transport_writer: TokioTcpTransportWriter<T>,
-    pub transport_reader: TokioTcpTransportReader<T>,
+    pub generation: u64,
+    pub transport_writer: W,
+    pub transport_reader: R,
     pub message_receiver: Receiver<Message>,
     pub client_message_sender: Sender<InternalMessage>,
 }
 
-impl ConnectionResource<ChaCha20Poly1305> {
-    pub async fn new(
-        stream: TcpStream,
+impl<R: TransportReader, W: TransportWriter> ConnectionResource<R, W> {
+    pub async fn new<Tr: SplitTransport<ChaCha20Poly1305, Reader = R, Writer = W>>(
+        mut transport: Tr,
         client_sender: Sender<Client<ChaCha20Poly1305>>,
         client_message_sender: Sender<InternalMessage>,
+        tickets: &SessionTickets,
     ) -> Result<Self, ConnectionResourceError> {
         // TODO: fix error type
-        let mut transport = TokioTcpTransport::new(stream);
         let (message_sender, message_receiver) = mpsc::channel(CHANNEL_BUF_LEN);
         let client: Client<ChaCha20Poly1305> =
-            Client::connect(&mut transport, message_sender).await?;
+            Client::connect(&mut transport, message_sender, tickets).await?;
 
         // send client to event processor
-        let id = client.id;
-        client_sender.send(client).await?;
+        let (id, generation) = (client.id, client.generation);
+        client_sender.send(client).await.map_err(Box::new)?;
 
         let (transport_reader, transport_writer) = transport.into_split();
 
         Ok(ConnectionResource {
             id,
+            generation,
             transport_writer,
             transport_reader,
             message_receiver,
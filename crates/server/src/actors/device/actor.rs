@@ -1,24 +1,30 @@
-use input_event::{InputEvent, KeyboardEventType};
-use input_listener::DeviceInputError;
+use std::io::ErrorKind;
+
+use futures::StreamExt;
+use input_event::{GamepadEvent, GamepadInput, InputEvent, KeyboardEventType, MouseEvent};
+use input_listener::{x11::hotplug::DeviceChange, DeviceInputError};
 use input_simulator::DeviceOutputError;
 use network::Message;
 use thiserror::Error;
 use tokio::sync::{
-    broadcast::{self, error::RecvError},
-    mpsc::{self, error::SendError},
+    mpsc::error::SendError,
+    watch::{self, error::RecvError},
 };
 use tokio_util::sync::CancellationToken;
 
 use crate::{
-    keyboard_state::{KeyboardState, CYCLE_TARGET},
+    keyboard_state::{release_event, DevicesKeyboardState, CYCLE_TARGET},
+    lanes::LaneSender,
     InternalMessage, ServerMessage,
 };
 
-use super::resource::DeviceResource;
+use super::resource::{DeviceId, DeviceResource};
 
 // TODO: rename error
 #[derive(Debug, Error)]
 pub enum DeviceListenerError {
+    #[error("Could not open device: {0}")]
+    DeviceOpenError(#[from] std::io::Error),
     #[error("Device output error: {0}")]
     DeviceOutputError(#[from] DeviceOutputError),
     #[error("Device input error: {0}")]
@@ -27,35 +33,53 @@ pub enum DeviceListenerError {
     InternalMessageSendError(#[from] SendError<InternalMessage>),
     #[error("Could not receive grab request: {0}")]
     GrabRequestRecvError(#[from] RecvError),
+    #[error("Could not watch for devices: {0}")]
+    DeviceWatchError(std::io::Error),
 }
 
 impl DeviceResource {
     pub async fn start_device_listener(
         mut self,
-        event_sender: mpsc::Sender<InternalMessage>,
-        mut grab_request_receiver: broadcast::Receiver<bool>,
+        event_sender: LaneSender,
+        mut grab_request_receiver: watch::Receiver<bool>,
         cancellation_token: CancellationToken,
     ) -> Result<(), DeviceListenerError> {
-        println!("Starting device listeners"); // TODO: add names of devices
-        let mut keyboard_state = KeyboardState::default();
+        println!("Starting {} device listeners", self.paths.len());
+        let mut keyboard_state = DevicesKeyboardState::default();
+        let mut grabbed = false;
 
         loop {
             tokio::select! {
-                // TODO: consider merging the two streams to avoid code repitition
-                event = self.kbd_input_stream.next_event() => {
+                Some((device, event)) = self.devices.next() => {
                     match event {
                         Ok(event) => {
+                            if let InputEvent::Gamepad(GamepadEvent { pad, input: GamepadInput::Connected(_) }) = &event {
+                                self.gamepads.insert(device, *pad);
+                            }
                             if let InputEvent::Keyboard(keyboard_event) = event {
                                 match keyboard_event.event_type {
                                     KeyboardEventType::KeyPressed => {
                                         // TODO: make keyboard_state use the generic input_event::Key enum
                                         // instead of coupling it to evdev
-                                        keyboard_state.press_key(keyboard_event.key.into());
+                                        keyboard_state.press_key(device, keyboard_event.key.into());
                                         // handle combinations
                                         self.handle_combinations(&mut keyboard_state, &event_sender).await?;
                                     }
-                                    KeyboardEventType::KeyReleased => {
-                                        keyboard_state.release_key(keyboard_event.key.into());
+                                    // the key stays down on the target while another keyboard holds it
+                                    KeyboardEventType::KeyReleased
+                                        if !keyboard_state.release_key(device, keyboard_event.key.into()) =>
+                                    {
+                                        continue;
+                                    }
+                                    _ => {}
+                                }
+                            }
+                            // tracked so that a mouse lost mid-drag does not leave its button held
+                            if let InputEvent::Mouse(MouseEvent::Button { event_type, button }) = event {
+                                match event_type {
+                                    KeyboardEventType::KeyPressed => keyboard_state.press_key(device, button.into()),
+                                    KeyboardEventType::KeyReleased if !keyboard_state.release_key(device, button.into()) => {
+                                        continue;
                                     }
                                     _ => {}
                                 }
@@ -66,58 +90,65 @@ impl DeviceResource {
                             };
                             event_sender.send(message).await?;
                         },
-                        Err(DeviceInputError::InputEventConversionError(_)) => {},
+                        // the device was most likely unplugged, and is attached again if it comes back
                         Err(err) => {
-                            return Err(err.into())
+                            if let Some(path) = self.paths.get(&device) {
+                                eprintln!("Lost {}: {}", path.display(), err);
+                            }
+                            self.remove_device(device, &mut keyboard_state, &event_sender).await?;
                         },
                     }
                 },
-                event = self.mouse_input_stream.next_event() => {
-                    match event {
-                        Ok(event) => {
-                            // if let InputEvent::Keyboard(keyboard_event) = event {
-                            //     match keyboard_event {
-                            //         KeyboardEvent::KeyPressed(key) => {
-                            //             // TODO: make keyboard_state use the generic input_event::Key enum
-                            //             // instead of coupling it to evdev
-                            //             keyboard_state.press_key(key.into());
-                            //             // handle combinations
-                            //             self.handle_combinations(&mut keyboard_state, &event_sender).await?;
-                            //         }
-                            //         KeyboardEvent::KeyReleased(key) => {
-                            //             keyboard_state.release_key(key.into());
-                            //         }
-                            //         _ => {}
-                            //     }
-                            // }
-                            let message = InternalMessage::ClientMessage {
-                                message: Message::InputEvent { event },
-                                sender: None,
-                            };
-                            event_sender.send(message).await?;
+                change = self.watcher.next_change() => {
+                    match change.map_err(DeviceListenerError::DeviceWatchError)? {
+                        DeviceChange::Added(path) => match self.attach_path(path.clone()) {
+                            Ok(Some(device)) => {
+                                println!("Attached {}", path.display());
+                                if grabbed {
+                                    let stream = self.devices.get_mut().get_mut(&device).expect("Device was just attached");
+                                    // an ungrabbed device would control this machine as well as the target
+                                    if let Err(err) = stream.grab_device() {
+                                        eprintln!("Could not grab {}: {}", path.display(), err);
+                                        self.remove_device(device, &mut keyboard_state, &event_sender).await?;
+                                    }
+                                }
+                            },
+                            Ok(None) => {},
+                            // udev makes new nodes readable shortly after creating them
+                            Err(err) if err.kind() == ErrorKind::PermissionDenied => {},
+                            Err(err) => eprintln!("Could not attach {}: {}", path.display(), err),
                         },
-                        Err(DeviceInputError::InputEventConversionError(_)) => {},
-                        Err(err) => {
-                            return Err(err.into())
+                        DeviceChange::Removed(path) => {
+                            if let Some(device) = self.find_path(&path) {
+                                println!("Detached {}", path.display());
+                                self.remove_device(device, &mut keyboard_state, &event_sender).await?;
+                            }
                         },
                     }
                 },
-                request = grab_request_receiver.recv() => {
-                    match request {
-                        Ok(true) => {
-                            self.kbd_input_stream.grab_device()?;
-                            self.mouse_input_stream.grab_device()?;
+                // only the latest request matters, so requests can never pile up behind input
+                changed = grab_request_receiver.changed() => {
+                    if let Err(err) = changed {
+                        eprintln!("Grab request receive had an error: {}", err);
+                        return Err(err.into())
+                    }
+                    let grab = *grab_request_receiver.borrow_and_update();
+                    match (grab, grabbed) {
+                        (true, false) => {
+                            for (device, err) in self.devices.get_mut().grab_all() {
+                                self.log_device_error("Could not grab", device, &err);
+                                self.remove_device(device, &mut keyboard_state, &event_sender).await?;
+                            }
                             self.input_simulator.release_all()?;
                         },
-                        Ok(false) => {
-                            self.kbd_input_stream.ungrab_device()?;
-                            self.mouse_input_stream.ungrab_device()?;
+                        (false, true) => {
+                            for (device, err) in self.devices.get_mut().ungrab_all() {
+                                self.log_device_error("Could not ungrab", device, &err);
+                            }
                         },
-                        Err(err) => {
-                            eprintln!("Grab request receive had an error: {}", err);
-                            return Err(err.into())
-                        }
+                        _ => {}
                     }
+                    grabbed = grab;
                 },
                 _ = cancellation_token.cancelled() => {
                     self.input_simulator.release_all()?;
@@ -127,10 +158,52 @@ impl DeviceResource {
         }
     }
 
+    /// Closes a device and releases the keys and buttons only it was holding, so that they do not
+    /// stay pressed on the target. A controller is unplugged from the target as well.
+    async fn remove_device(
+        &mut self,
+        device: DeviceId,
+        keyboard_state: &mut DevicesKeyboardState,
+        event_sender: &LaneSender,
+    ) -> Result<(), DeviceListenerError> {
+        self.detach(device);
+        if let Some(pad) = self.gamepads.remove(&device) {
+            let event = InputEvent::Gamepad(GamepadEvent {
+                pad,
+                input: GamepadInput::Disconnected,
+            });
+            let message = InternalMessage::ClientMessage {
+                message: Message::InputEvent { event },
+                sender: None,
+            };
+            event_sender.send(message).await?;
+        }
+        for event in keyboard_state
+            .forget_device(device)
+            .into_iter()
+            .filter_map(release_event)
+        {
+            let message = InternalMessage::ClientMessage {
+                message: Message::InputEvent { event },
+                sender: None,
+            };
+            event_sender.send(message).await?;
+        }
+
+        Ok(())
+    }
+
+    fn log_device_error(&self, context: &str, device: DeviceId, err: &DeviceInputError) {
+        match self.paths.get(&device) {
+            Some(path) => eprintln!("{} {}: {}", context, path.display(), err),
+            None => eprintln!("{} device {}: {}", context, device, err),
+        }
+    }
+
     async fn handle_combinations(
         &self,
-        keyboard_state: &mut KeyboardState,
-        event_sender: &mpsc::Sender<InternalMessage>,
+        keyboard_state: &mut DevicesKeyboardState,
+        event_sender: &LaneSender,
     ) -> Result<(), DeviceListenerError> {
         if keyboard_state.is_combination_pressed(CYCLE_TARGET.to_vec()) {
             event_sender
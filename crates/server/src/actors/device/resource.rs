@@ -1,24 +1,160 @@
-use input_listener::{x11::dev::pick_device, DeviceInputStream};
+use std::{
+    collections::HashMap,
+    io,
+    path::{Path, PathBuf},
+};
+
+use input_event::GamepadId;
+use input_listener::{
+    stream::{DeviceStreamExt, MergedInputStream, SkipUnmapped},
+    x11::{
+        discovery::{discover, list_devices, DeviceInfo, DeviceKind, DiscoveryError},
+        hotplug::DeviceWatcher,
+    },
+    DeviceInputStream,
+};
 use input_simulator::InputSimulator;
 
+pub use input_listener::x11::discovery::DeviceSelector;
+
+/// Which devices to listen to. Each is detected from its capabilities unless configured.
+#[derive(Debug, Default, Clone)]
+pub struct DeviceConfig {
+    pub keyboard: DeviceSelector,
+    pub mouse: DeviceSelector,
+    pub tablet: DeviceSelector,
+    pub gamepad: DeviceSelector,
+}
+
+impl DeviceConfig {
+    pub fn with_keyboard(mut self, keyboard: DeviceSelector) -> Self {
+        self.keyboard = keyboard;
+        self
+    }
+
+    pub fn with_mouse(mut self, mouse: DeviceSelector) -> Self {
+        self.mouse = mouse;
+        self
+    }
+
+    pub fn with_tablet(mut self, tablet: DeviceSelector) -> Self {
+        self.tablet = tablet;
+        self
+    }
+
+    pub fn with_gamepad(mut self, gamepad: DeviceSelector) -> Self {
+        self.gamepad = gamepad;
+        self
+    }
+
+    /// Finds the devices to listen to, failing when there is no keyboard or no mouse, or no
+    /// tablet or gamepad if one is configured.
+    pub fn discover(&self) -> Result<Vec<PathBuf>, DiscoveryError> {
+        let mut paths = discover(DeviceKind::Keyboard, &self.keyboard)?;
+        let mut add = |found: Vec<PathBuf>| {
+            // a device can be several, such as a keyboard with a trackpoint, but can only be
+            // grabbed once
+            for path in found {
+                if !paths.contains(&path) {
+                    paths.push(path);
+                }
+            }
+        };
+        add(discover(DeviceKind::Mouse, &self.mouse)?);
+        for (kind, selector) in [
+            (DeviceKind::Tablet, &self.tablet),
+            (DeviceKind::Gamepad, &self.gamepad),
+        ] {
+            match discover(kind, selector) {
+                Ok(found) => add(found),
+                Err(DiscoveryError::NoMatch { .. }) if *selector == DeviceSelector::Auto => {}
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(paths)
+    }
+
+    pub fn wants(&self, device: &DeviceInfo) -> bool {
+        device.matches(DeviceKind::Keyboard, &self.keyboard)
+            || device.matches(DeviceKind::Mouse, &self.mouse)
+            || device.matches(DeviceKind::Tablet, &self.tablet)
+            || device.matches(DeviceKind::Gamepad, &self.gamepad)
+    }
+}
+
+/// The devices being listened to, keyed by an id that is never reused, so state kept about a
+/// device that has gone away cannot be mistaken for state about a new one.
+pub type DeviceId = usize;
+
 pub struct DeviceResource {
-    pub kbd_input_stream: DeviceInputStream,
-    pub mouse_input_stream: DeviceInputStream,
+    pub config: DeviceConfig,
+    pub devices: SkipUnmapped<MergedInputStream<DeviceId>>,
+    pub paths: HashMap<DeviceId, PathBuf>,
+    /// The number each controller announced itself with, to say when it goes away.
+    pub gamepads: HashMap<DeviceId, GamepadId>,
+    pub watcher: DeviceWatcher,
     pub input_simulator: InputSimulator,
+    next_id: DeviceId,
 }
 
 impl DeviceResource {
-    pub fn new() -> Self {
-        // TODO: remove unwraps?
-        let kbd_input_stream =
-            DeviceInputStream::new(pick_device("Keyboard").into_event_stream().unwrap());
-        let mouse_input_stream =
-            DeviceInputStream::new(pick_device("Mouse").into_event_stream().unwrap());
-        let input_simulator = InputSimulator::new();
-        DeviceResource {
-            kbd_input_stream,
-            mouse_input_stream,
-            input_simulator,
+    /// Opens every configured device that is plugged in. There may be none, since more can be
+    /// attached as they are plugged in.
+    pub fn open(config: &DeviceConfig) -> io::Result<Self> {
+        // watch first so a device plugged in during the scan is not missed
+        let watcher = DeviceWatcher::new()?;
+        let mut resource = DeviceResource {
+            config: config.clone(),
+            devices: MergedInputStream::default().skip_unmapped(),
+            paths: HashMap::new(),
+            gamepads: HashMap::new(),
+            watcher,
+            input_simulator: InputSimulator::new(),
+            next_id: 0,
+        };
+        for device in list_devices() {
+            if resource.config.wants(&device) {
+                let opened = evdev::Device::open(&device.path)?;
+                resource.attach(device.path, opened)?;
+            }
+        }
+        Ok(resource)
+    }
+
+    /// Opens a newly plugged in device if it is configured and not already open, returning its
+    /// id if it was attached.
+    pub fn attach_path(&mut self, path: PathBuf) -> io::Result<Option<DeviceId>> {
+        if self.paths.values().any(|open| *open == path) {
+            return Ok(None);
         }
+        let device = evdev::Device::open(&path)?;
+        if !self
+            .config
+            .wants(&DeviceInfo::from_device(path.clone(), &device))
+        {
+            return Ok(None);
+        }
+        self.attach(path, device).map(Some)
+    }
+
+    fn attach(&mut self, path: PathBuf, device: evdev::Device) -> io::Result<DeviceId> {
+        let id = self.next_id;
+        let stream = DeviceInputStream::new(device.into_event_stream()?);
+        self.devices.get_mut().insert(id, stream);
+        self.paths.insert(id, path);
+        self.next_id += 1;
+        Ok(id)
+    }
+
+    pub fn find_path(&self, path: &Path) -> Option<DeviceId> {
+        self.paths
+            .iter()
+            .find_map(|(&id, open)| (open == path).then_some(id))
+    }
+
+    /// Closes a device, returning where it was opened from.
+    pub fn detach(&mut self, id: DeviceId) -> Option<PathBuf> {
+        self.devices.get_mut().remove(&id);
+        self.paths.remove(&id)
     }
 }
@@ -1,5 +1,9 @@
 use chacha20poly1305::ChaCha20Poly1305;
-use tokio::sync::mpsc::Sender;
+use network::{tcp::TokioTcpTransport, transport::SplitTransport, unix::UnixSocketTransport};
+use tokio::{
+    net::{TcpStream, UnixStream},
+    sync::mpsc::Sender,
+};
 use tokio_util::sync::CancellationToken;
 
 use crate::{
@@ -7,10 +11,17 @@ use crate::{
         client::{actor::ClientHandlerError, resource::ConnectionResource},
         state::client::Client,
     },
+    session_ticket::SessionTickets,
     InternalMessage,
 };
 
-use super::resource::ServerResource;
+use super::resource::{Listener, ServerResource};
+
+/// A connection accepted by either kind of listener.
+enum Accepted {
+    Tcp(TcpStream),
+    Unix(UnixStream),
+}
 
 impl ServerResource {
     pub async fn start_listening(
@@ -20,25 +31,47 @@ impl ServerResource {
         cancellation_token: CancellationToken,
     ) -> Result<(), std::io::Error> {
         loop {
-            let (socket, addr) = self.listener.accept().await?;
-            println!("Received incoming connection on {}", addr);
+            let socket = match &self.listener {
+                Listener::Tcp(listener) => {
+                    let (socket, addr) = listener.accept().await?;
+                    println!("Received incoming connection on {}", addr);
+                    Accepted::Tcp(socket)
+                }
+                Listener::Unix(listener) => {
+                    let (socket, _) = listener.accept().await?;
+                    println!("Received incoming connection on Unix socket");
+                    Accepted::Unix(socket)
+                }
+            };
 
             let client_sender_clone = client_sender.clone();
             let client_message_sender_clone = client_message_sender.clone();
             let cancellation_token_clone1 = cancellation_token.clone();
+            let tickets = self.tickets.clone();
 
             tokio::spawn(async move {
-                let result: Result<(), ClientHandlerError> = async {
-                    let connection = ConnectionResource::new(
-                        socket,
-                        client_sender_clone,
-                        client_message_sender_clone,
-                    )
-                    .await?;
-                    connection.process_events(cancellation_token_clone1).await?;
-                    Ok(())
-                }
-                .await;
+                let result = match socket {
+                    Accepted::Tcp(socket) => {
+                        handle_stream(
+                            socket,
+                            client_sender_clone,
+                            client_message_sender_clone,
+                            &tickets,
+                            cancellation_token_clone1,
+                        )
+                        .await
+                    }
+                    Accepted::Unix(socket) => {
+                        handle_transport(
+                            UnixSocketTransport::new(socket),
+                            client_sender_clone,
+                            client_message_sender_clone,
+                            &tickets,
+                            cancellation_token_clone1,
+                        )
+                        .await
+                    }
+                };
 
                 if let Err(err) = result {
                     eprintln!("Error processing client events: {}", err);
@@ -48,3 +81,49 @@ impl ServerResource {
         }
     }
 }
+
+/// Picks the transport for an accepted stream. WebSocket upgrades share the TCP listener so a
+/// client behind an HTTP-only proxy can reach the same configured address.
+async fn handle_stream(
+    socket: TcpStream,
+    client_sender: Sender<Client<ChaCha20Poly1305>>,
+    client_message_sender: Sender<InternalMessage>,
+    tickets: &SessionTickets,
+    cancellation_token: CancellationToken,
+) -> Result<(), ClientHandlerError> {
+    #[cfg(feature = "websocket")]
+    if network::websocket::is_websocket_upgrade(&socket).await? {
+        println!("Upgrading connection to WebSocket");
+        let transport = network::websocket::WebSocketTransport::accept(socket).await?;
+        return handle_transport(
+            transport,
+            client_sender,
+            client_message_sender,
+            tickets,
+            cancellation_token,
+        )
+        .await;
+    }
+
+    handle_transport(
+        TokioTcpTransport::new(socket),
+        client_sender,
+        client_message_sender,
+        tickets,
+        cancellation_token,
+    )
+    .await
+}
+
+async fn handle_transport<Tr: SplitTransport<ChaCha20Poly1305>>(
+    transport: Tr,
+    client_sender: Sender<Client<ChaCha20Poly1305>>,
+    client_message_sender: Sender<InternalMessage>,
+    tickets: &SessionTickets,
+    cancellation_token: CancellationToken,
+) -> Result<(), ClientHandlerError> {
+    let connection =
+        ConnectionResource::new(transport, client_sender, client_message_sender, tickets).await?;
+    connection.process_events(cancellation_token).await?;
+    Ok(())
+}
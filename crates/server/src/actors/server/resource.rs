@@ -1,17 +1,40 @@
-use std::net::SocketAddr;
+use network::{unix::remove_stale_socket, ServerAddress};
+use tokio::net::{TcpListener, UnixListener};
 
-use tokio::net::TcpListener;
+use crate::session_ticket::SessionTickets;
+
+pub enum Listener {
+    Tcp(TcpListener),
+    Unix(UnixListener),
+}
 
 pub struct ServerResource {
-    pub listener: TcpListener,
+    pub listener: Listener,
+    pub tickets: SessionTickets,
 }
 
 impl ServerResource {
-    pub async fn new(addr: SocketAddr) -> Self {
+    pub async fn new(addr: &ServerAddress) -> Self {
         // TODO: remove unwrap
-        let listener = TcpListener::bind(addr).await.unwrap();
-        println!("Bound TCP listener to {}", addr);
+        let listener = match addr {
+            ServerAddress::Inet(addr) => Listener::Tcp(TcpListener::bind(addr).await.unwrap()),
+            ServerAddress::Unix(path) => {
+                remove_stale_socket(path).unwrap();
+                Listener::Unix(UnixListener::bind(path).unwrap())
+            }
+        };
+        println!("Bound control listener to {}", addr);
+
+        ServerResource {
+            listener,
+            tickets: SessionTickets::new(),
+        }
+    }
 
-        ServerResource { listener }
+    /// Uses existing ticket keys, so that a restarted listener still accepts tickets issued
+    /// before the restart.
+    pub fn with_tickets(mut self, tickets: SessionTickets) -> Self {
+        self.tickets = tickets;
+        self
     }
 }
@@ -1,29 +1,38 @@
-use std::net::SocketAddr;
+use std::time::{Duration, Instant};
 
 use chacha20poly1305::ChaCha20Poly1305;
-use network::{input_event::InputEventTransport, Message, TransportError};
+use input_event::{GamepadEvent, GamepadInput, InputEvent};
+use network::{
+    input_event::InputEventTransport,
+    unix::{input_socket_path, remove_stale_socket},
+    Message, ServerAddress, TransportError,
+};
 use thiserror::Error;
 use tokio::{
-    net::UdpSocket,
-    sync::{broadcast, mpsc},
+    net::{UdpSocket, UnixDatagram},
+    sync::{mpsc, watch},
 };
 use tokio_util::sync::CancellationToken;
 
-use crate::{InternalMessage, ServerMessage};
+use crate::{lanes::LaneReceiver, InternalMessage, ServerMessage};
 
 use super::{
     client::Client,
     resource::{StateHandlerError, StateResource},
 };
 
+// TODO: refactor to a common location
+const PING_INTERVAL: u64 = 3;
+const LINK_QUALITY_LOG_INTERVAL: u64 = 60;
+const CLIENT_GC_INTERVAL: u64 = 30;
+const TARGET_CHANGE_CHECK_INTERVAL_MILLIS: u64 = 250;
+
 #[derive(Debug, Error)]
 pub enum ProcessorError {
-    #[error("Device message listener channel closed")]
-    DeviceChannelClosed,
-    #[error("Client message listener channel closed")]
-    ClientReceiverChannelClosed,
+    #[error("Internal message lanes closed")]
+    LanesClosed,
     #[error("New client receiver channel closed")]
-    ClientListenerChannelClosed,
+    ClientReceiverChannelClosed,
     #[error("Invalid argument")]
     InvalidArgument,
     #[error("State error: {0}")]
@@ -34,47 +43,107 @@ pub enum ProcessorError {
     IOError(#[from] std::io::Error),
 }
 
+/// The channels and socket the state actor works on, kept across restarts of the actor.
+pub struct StateEndpoints {
+    pub transport: InputEventTransport,
+    pub lanes: LaneReceiver,
+    pub client_receiver: mpsc::Receiver<Client<ChaCha20Poly1305>>,
+    pub grab_request_sender: watch::Sender<bool>,
+}
+
+impl StateEndpoints {
+    pub async fn bind(
+        server_addr: &ServerAddress,
+        lanes: LaneReceiver,
+        client_receiver: mpsc::Receiver<Client<ChaCha20Poly1305>>,
+        grab_request_sender: watch::Sender<bool>,
+    ) -> Result<Self, std::io::Error> {
+        let transport = match server_addr {
+            ServerAddress::Inet(addr) => InputEventTransport::new(UdpSocket::bind(addr).await?),
+            ServerAddress::Unix(path) => {
+                let path = input_socket_path(path);
+                remove_stale_socket(&path)?;
+                InputEventTransport::from(UnixDatagram::bind(path)?)
+            }
+        };
+        Ok(StateEndpoints {
+            transport,
+            lanes,
+            client_receiver,
+            grab_request_sender,
+        })
+    }
+}
+
 impl StateResource<ChaCha20Poly1305> {
     // TODO: handle batches of events, not just single events
     pub async fn process(
-        mut self,
-        server_addr: SocketAddr,
-        mut device_message_receiver: mpsc::Receiver<InternalMessage>,
-        mut client_message_receiver: mpsc::Receiver<InternalMessage>,
-        mut client_receiver: mpsc::Receiver<Client<ChaCha20Poly1305>>,
-        mut grab_request_sender: broadcast::Sender<bool>,
+        &mut self,
+        endpoints: &mut StateEndpoints,
         cancellation_token: CancellationToken,
     ) -> Result<(), ProcessorError> {
-        // device_receiver receives incoming messages from device listeners
-        // message is either of type Message or SpecialEvent
-        //
-        // client_receiver receives incoming messages from client listeners
-        // message is of type Message
+        // lanes receives input events from the device listener on the data lane, and hotkeys,
+        // client messages and admin commands on the control lane, which is always read first
         //
         // when sending a message over TCP to a client, should send through a channel to the handler.
         // then, inside the handler, we may have the option to catch some messages before sending them
         // back to the global processor
-        let socket = UdpSocket::bind(server_addr).await?;
-        let mut transport = InputEventTransport::new(socket);
+        let StateEndpoints {
+            transport,
+            lanes,
+            client_receiver,
+            grab_request_sender,
+        } = endpoints;
+        // after a restart the previous target may hold keys that were never released
+        if self.get_target_id().is_some() {
+            println!("Returning input to the server after a restart");
+            self.change_target(None, grab_request_sender).await?;
+        }
+        let mut ping_interval = tokio::time::interval(Duration::from_secs(PING_INTERVAL));
+        let mut link_quality_log_interval =
+            tokio::time::interval(Duration::from_secs(LINK_QUALITY_LOG_INTERVAL));
+        let mut client_gc_interval = tokio::time::interval(Duration::from_secs(CLIENT_GC_INTERVAL));
+        let mut target_change_check_interval =
+            tokio::time::interval(Duration::from_millis(TARGET_CHANGE_CHECK_INTERVAL_MILLIS));
 
         loop {
             tokio::select! {
-                msg = device_message_receiver.recv() => {
+                msg = lanes.recv() => {
                     if let Some(message) = msg {
-                        self.handle_device_message(message, &mut transport, &mut grab_request_sender).await?;
+                        self.handle_message(message, transport, grab_request_sender).await?;
                     } else {
-                        eprintln!("Device event processor receiver was closed");
-                        return Err(ProcessorError::DeviceChannelClosed);
+                        eprintln!("Internal message lanes were closed");
+                        return Err(ProcessorError::LanesClosed);
                     }
                 },
-                msg = client_message_receiver.recv() => {
-                    if let Some(message) = msg {
-                        self.handle_client_message(message, &mut transport, &mut grab_request_sender).await?;
-                    } else {
-                        eprintln!("Client receiver was closed");
-                        return Err(ProcessorError::ClientListenerChannelClosed);
+                datagram = transport.receive_from() => {
+                    match datagram {
+                        Ok((bytes, addr)) => {
+                            // a reply that cannot be sent only affects that client
+                            if let Err(err) = self.handle_udp_datagram(&bytes, addr, transport).await {
+                                eprintln!("Error while handling datagram from {}: {}", addr, err);
+                            }
+                        },
+                        Err(err) => {
+                            eprintln!("Error while receiving datagram: {}", err);
+                        }
                     }
                 },
+                _ = ping_interval.tick() => {
+                    self.send_pings(transport).await;
+                },
+                _ = link_quality_log_interval.tick() => {
+                    self.log_link_quality();
+                    println!("Internal queue depths: {}", lanes.depths());
+                },
+                _ = client_gc_interval.tick() => {
+                    for id in self.collect_disconnected_clients(Instant::now()) {
+                        println!("Forgot client {} after its grace period", id);
+                    }
+                },
+                _ = target_change_check_interval.tick() => {
+                    self.expire_target_change_acks(Instant::now(), transport);
+                },
                 client = client_receiver.recv() => {
                     match client {
                         Some(c) => {
@@ -94,49 +163,60 @@ impl StateResource<ChaCha20Poly1305> {
         }
     }
 
-    async fn handle_device_message(
+    async fn handle_message(
         &mut self,
         msg: InternalMessage,
         transport: &mut InputEventTransport,
-        grab_request_sender: &mut broadcast::Sender<bool>,
+        grab_request_sender: &mut watch::Sender<bool>,
     ) -> Result<(), ProcessorError> {
         match msg {
-            InternalMessage::ClientMessage { message, .. } => {
-                // send input event to correct client over udp
-                match &message {
-                    Message::InputEvent { .. } => {
-                        if let Some(target) = self.get_target_mut() {
-                            if target.can_receive() {
-                                transport
-                                    .send_message_to(
-                                        message,
-                                        target.address,
-                                        Some(target.key.clone()),
-                                    )
-                                    .await?;
-                            } else {
-                                target.buffer_message(message);
-                            }
-                        }
-                    }
-                    _ => {
-                        // TODO: send over tcp
-                        unimplemented!("TCP sending / non-input event processing is unimplemented");
-                    }
-                }
+            // messages without a sender come from the local devices
+            InternalMessage::ClientMessage {
+                message,
+                sender: None,
+            } => self.handle_device_message(message, transport),
+            msg => {
+                self.handle_client_message(msg, transport, grab_request_sender)
+                    .await
             }
-            InternalMessage::LocalMessage { message } => {
-                // forward special event to client handler to be sent over tcp
-                match &message {
-                    ServerMessage::ClientDisconnect { id } => {
-                        self.disconnect_client(*id, grab_request_sender).await?;
-                    }
-                    ServerMessage::Cycle => {
-                        self.cycle_target(grab_request_sender).await?;
+        }
+    }
+
+    fn handle_device_message(
+        &mut self,
+        message: Message,
+        transport: &mut InputEventTransport,
+    ) -> Result<(), ProcessorError> {
+        // route input event to the target's sender task
+        match &message {
+            Message::InputEvent { event } => {
+                if let InputEvent::Gamepad(GamepadEvent {
+                    pad,
+                    input: GamepadInput::Disconnected,
+                }) = event
+                {
+                    self.unplug_gamepad_from_others(*pad, transport);
+                }
+                let announcement = self.announce_gamepad(&message);
+                if let Some(target) = self.get_target_mut() {
+                    let message = target.place_on_screen(message);
+                    for message in announcement.into_iter().chain([message]) {
+                        if target.can_receive() {
+                            target
+                                .send_input_message(message, transport)
+                                .map_err(StateHandlerError::from)?;
+                        } else {
+                            target.buffer_message(message);
+                        }
                     }
                 }
             }
-        };
+            // TODO: send over tcp
+            message => eprintln!(
+                "Rejecting unexpected message from local devices: {}",
+                message
+            ),
+        }
         Ok(())
     }
 
@@ -144,31 +224,66 @@ impl StateResource<ChaCha20Poly1305> {
         &mut self,
         msg: InternalMessage,
         transport: &mut InputEventTransport,
-        grab_request_sender: &mut broadcast::Sender<bool>,
+        grab_request_sender: &mut watch::Sender<bool>,
     ) -> Result<(), ProcessorError> {
+        if let InternalMessage::ClientMessage {
+            sender: Some(id), ..
+        } = &msg
+        {
+            // a forgotten client's connection may still have messages in flight
+            if self.get_client_by_id(*id).is_none() {
+                eprintln!("Dropping message from unknown client {}", id);
+                return Ok(());
+            }
+        }
+
         match msg {
             InternalMessage::ClientMessage { message, sender } => match &message {
                 Message::Heartbeat => {}
+                Message::Ping { seq, sent_at } => {
+                    let sender = sender.ok_or(ProcessorError::InvalidArgument)?;
+                    self.handle_ping(sender, *seq, *sent_at)?;
+                }
+                Message::Pong { seq, sent_at } => {
+                    let sender = sender.ok_or(ProcessorError::InvalidArgument)?;
+                    self.handle_pong(sender, *seq, *sent_at)?;
+                }
+                Message::Goodbye { reason } => {
+                    let sender = sender.ok_or(ProcessorError::InvalidArgument)?;
+                    println!("Client {} left: {}", sender, reason);
+                    self.disconnect_client(sender, grab_request_sender).await?;
+                }
                 Message::ClipboardChanged { content: _ } => {
-                    unimplemented!("Clipboard change is not implemented in state actor");
+                    eprintln!("Ignoring clipboard change, clipboard sharing is not supported yet");
                 }
-                Message::TargetChangeResponse => {
+                Message::TargetChangeResponse { seq } => {
                     let sender = sender.ok_or(ProcessorError::InvalidArgument)?;
-                    self.handle_change_target_response(sender, transport)
+                    self.handle_change_target_response(sender, *seq, transport)
                         .await?;
                 }
-                _ => {
-                    unimplemented!("Received unimplemented client message: {:?}", message);
+                message => {
+                    let sender = sender.map_or("unknown client".to_string(), |id| id.to_string());
+                    eprintln!("Rejecting unexpected message from {}: {}", sender, message);
                 }
             },
             InternalMessage::LocalMessage { message } => match &message {
-                ServerMessage::ClientDisconnect { id } => self
-                    .disconnect_client(*id, grab_request_sender)
+                ServerMessage::ClientDisconnect { id, generation } => self
+                    .disconnect_connection(*id, *generation, grab_request_sender)
                     .await
                     .inspect_err(|err| eprintln!("Error while disconnecting client: {}", err))?,
                 ServerMessage::Cycle => {
                     self.cycle_target(grab_request_sender).await?;
                 }
+                ServerMessage::Shutdown { reason } => {
+                    self.notify_shutdown(reason).await;
+                }
+                ServerMessage::ListClients => self.log_clients(),
+                ServerMessage::ForgetClient { id } => {
+                    match self.forget_client(*id, grab_request_sender).await {
+                        Err(StateHandlerError::NotFound) => eprintln!("No client with id {}", id),
+                        result => result?,
+                    }
+                }
             },
         };
         Ok(())
@@ -1,18 +1,32 @@
-use std::collections::VecDeque;
+use std::collections::{HashSet, VecDeque};
 use std::net::SocketAddr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Instant;
 
-use chacha20poly1305::{aead::OsRng, ChaCha20Poly1305, KeyInit};
-use crypto::Crypto;
+use chacha20poly1305::{
+    aead::{rand_core::RngCore, OsRng},
+    ChaCha20Poly1305, KeyInit,
+};
+use crypto::{kdf, Crypto};
+use input_event::{GamepadId, InputEvent, ScreenGeometry};
 use network::{
-    input_event::InputEventTransport, tcp::TokioTcpTransport, transport::Transport, Message,
-    TransportError,
+    input_event::InputEventTransport,
+    link_stats::{ConnectionQuality, LinkStats},
+    transport::SplitTransport,
+    Message, TransportError,
 };
 use thiserror::Error;
 use tokio::sync::mpsc::Sender;
 use uuid::Uuid;
 use x25519_dalek::{EphemeralSecret, PublicKey};
 
-const RING_BUFFER_LEN: usize = 1024;
+use crate::session_ticket::{IssuedTicket, SessionTickets};
+
+use super::{
+    input_sender::{is_release, InputRoute, InputSender, DEFAULT_INPUT_QUEUE_LEN},
+    pending::PendingMessages,
+    target_change::TargetChangeAcks,
+};
 
 #[derive(Debug, Error)]
 pub enum ClientConnectionError {
@@ -24,38 +38,73 @@ pub enum ClientConnectionError {
     InvalidMessageError,
     #[error("Shared Diffe-Hellman secret was not contributory")]
     DHContributionError,
+    #[error("Control channel to client was closed")]
+    ChannelClosed,
+}
+
+static NEXT_GENERATION: AtomicU64 = AtomicU64::new(0);
+
+fn next_generation() -> u64 {
+    NEXT_GENERATION.fetch_add(1, Ordering::Relaxed)
 }
 
 pub trait Connection<T: Crypto>: Sized {
-    fn connect(
-        transport: &mut TokioTcpTransport<T>,
+    fn connect<Tr: SplitTransport<T>>(
+        transport: &mut Tr,
         message_sender: Sender<Message>,
-    ) -> impl std::future::Future<Output = Result<Self, ClientConnectionError>> + Send + Sync;
+        tickets: &SessionTickets,
+    ) -> impl std::future::Future<Output = Result<Self, ClientConnectionError>> + Send;
 }
 
 #[derive(Debug)]
 pub struct Client<T: Crypto> {
     pub id: Uuid,
+    /// Numbers the connection the client is on, which changes when a session is resumed over a
+    /// new one, so that a disconnect reported by the connection it replaced can be ignored.
+    pub generation: u64,
     pub connected: bool,
-    pub address: SocketAddr,
+    pub address: Option<SocketAddr>,
+    /// Input events are sent over the control connection instead of UDP.
+    pub tunnelled: bool,
+    /// The client's screen, which absolute pointer input is placed on.
+    pub screen: Option<ScreenGeometry>,
+    /// Controllers the client has been sent the capabilities of.
+    pub announced_gamepads: HashSet<GamepadId>,
     pub key: T,
     pub message_sender: Sender<Message>,
-    pub pending_target_change_responses: u32,
-    pending_messages: VecDeque<Message>,
+    /// Target change notifications that the client has not yet answered.
+    pub target_change_acks: TargetChangeAcks,
+    /// When the client was last seen disconnecting, used to forget it after a grace period.
+    pub disconnected_at: Option<Instant>,
+    /// Ping statistics for the control connection.
+    pub control_link: LinkStats,
+    /// Ping statistics for the UDP input path.
+    pub input_link: LinkStats,
+    last_udp_hello_seq: Option<u64>,
+    pending_messages: PendingMessages,
+    /// Delivers input events from a task of its own, started on first use.
+    input_sender: Option<InputSender>,
+    /// Input that a replaced input sender task had not sent, sent ahead of anything newer.
+    unsent_input: VecDeque<Message>,
 }
 
 // TODO: extract connection logic into another crate
 impl Connection<ChaCha20Poly1305> for Client<ChaCha20Poly1305> {
-    async fn connect(
-        transport: &mut TokioTcpTransport<ChaCha20Poly1305>,
+    async fn connect<Tr: SplitTransport<ChaCha20Poly1305>>(
+        transport: &mut Tr,
         message_sender: Sender<Message>,
+        tickets: &SessionTickets,
     ) -> Result<Self, ClientConnectionError> {
         println!("Initialising client");
 
-        let addr = match transport.receive_message().await {
-            Ok(Message::ClientInit { addr }) => {
-                println!("Received addr: {}", addr);
-                addr
+        let (tunnelled, resume, screen) = match transport.receive_message().await {
+            Ok(Message::ClientInit {
+                tunnel_input,
+                resume,
+                screen,
+            }) => {
+                println!("Received client init message");
+                (tunnel_input, resume, screen)
             }
             Ok(message) => {
                 println!("Received message: {}", message);
@@ -67,47 +116,31 @@ impl Connection<ChaCha20Poly1305> for Client<ChaCha20Poly1305> {
             }
         };
 
-        // generate pub key
-        // TODO: should sign this
-        let secret = EphemeralSecret::random_from_rng(OsRng);
-        let pub_key = PublicKey::from(&secret);
-
-        transport
-            .send_message(Message::ExchangePubKey { pub_key })
-            .await?;
-        println!("Sent pub key to client");
-
-        let client_pub_key = match transport.receive_message().await {
-            Ok(Message::ExchangePubKey { pub_key }) => {
-                println!("Received public key from client");
-                pub_key
+        let resumed = resume.and_then(|request| {
+            let session = tickets.open(&request.ticket);
+            if session.is_none() {
+                println!("Client presented an invalid or expired ticket");
             }
-            Ok(message) => {
-                println!("Received message: {}", message);
-                return Err(ClientConnectionError::InvalidMessageError);
-            }
-            Err(err) => {
-                println!("Did not receive pub key message");
-                return Err(err.into());
-            }
-        };
+            session.map(|session| (session, request.nonce))
+        });
+        let (id, cipher) = match resumed {
+            Some((session, client_nonce)) => {
+                println!("Resuming session for client {}", session.client_id);
+                let mut server_nonce = [0; 32];
+                OsRng.fill_bytes(&mut server_nonce);
+                transport
+                    .send_message(Message::ResumeAccepted {
+                        nonce: server_nonce,
+                    })
+                    .await?;
 
-        transport
-            .send_message(Message::ExchangePubKeyResponse)
-            .await?;
-        println!("Sent ack to client");
-
-        let cipher = {
-            let shared_secret = secret.diffie_hellman(&client_pub_key);
-            if !shared_secret.was_contributory() {
-                return Err(ClientConnectionError::DHContributionError);
+                let key = kdf::resumption_key(&session.secret, &client_nonce, &server_nonce);
+                let cipher = ChaCha20Poly1305::new_from_slice(&key)
+                    .expect("Could not generate cipher from resumption secret");
+                (session.client_id, cipher)
             }
-
-            Ok::<_, ClientConnectionError>(
-                ChaCha20Poly1305::new_from_slice(&shared_secret.to_bytes())
-                    .expect("Could not generate cipher from shared secret"),
-            )
-        }?;
+            None => (Uuid::new_v4(), exchange_keys(transport).await?),
+        };
 
         transport.set_key(cipher.clone());
 
@@ -121,65 +154,487 @@ impl Connection<ChaCha20Poly1305> for Client<ChaCha20Poly1305> {
             return Err(ClientConnectionError::InvalidMessageError);
         };
 
-        println!("Successfully connected to client at address {:?}", addr);
+        // a fresh ticket each session, since a ticket is only accepted once
+        let IssuedTicket { ticket, secret } = tickets.issue(id);
+        transport
+            .send_message(Message::SessionTicket {
+                client_id: id,
+                ticket,
+                secret,
+            })
+            .await?;
+
+        println!("Successfully connected to client");
 
         Ok(Client {
-            id: Uuid::new_v4(),
+            id,
+            generation: next_generation(),
             connected: true,
             key: cipher,
-            address: addr,
+            address: None,
+            tunnelled,
+            screen,
+            announced_gamepads: HashSet::new(),
             message_sender,
-            pending_target_change_responses: 0,
-            pending_messages: VecDeque::with_capacity(RING_BUFFER_LEN),
+            target_change_acks: TargetChangeAcks::new(),
+            disconnected_at: None,
+            control_link: LinkStats::new(),
+            input_link: LinkStats::new(),
+            last_udp_hello_seq: None,
+            pending_messages: PendingMessages::default(),
+            input_sender: None,
+            unsent_input: VecDeque::new(),
         })
     }
 }
 
+/// Performs the full Diffie-Hellman key exchange for a client without a usable ticket.
+async fn exchange_keys<Tr: SplitTransport<ChaCha20Poly1305>>(
+    transport: &mut Tr,
+) -> Result<ChaCha20Poly1305, ClientConnectionError> {
+    // generate pub key
+    // TODO: should sign this
+    let secret = EphemeralSecret::random_from_rng(OsRng);
+    let pub_key = PublicKey::from(&secret);
+
+    transport
+        .send_message(Message::ExchangePubKey { pub_key })
+        .await?;
+    println!("Sent pub key to client");
+
+    let client_pub_key = match transport.receive_message().await {
+        Ok(Message::ExchangePubKey { pub_key }) => {
+            println!("Received public key from client");
+            pub_key
+        }
+        Ok(message) => {
+            println!("Received message: {}", message);
+            return Err(ClientConnectionError::InvalidMessageError);
+        }
+        Err(err) => {
+            println!("Did not receive pub key message");
+            return Err(err.into());
+        }
+    };
+
+    transport
+        .send_message(Message::ExchangePubKeyResponse)
+        .await?;
+    println!("Sent ack to client");
+
+    let shared_secret = secret.diffie_hellman(&client_pub_key);
+    if !shared_secret.was_contributory() {
+        return Err(ClientConnectionError::DHContributionError);
+    }
+
+    Ok(ChaCha20Poly1305::new_from_slice(&shared_secret.to_bytes())
+        .expect("Could not generate cipher from shared secret"))
+}
+
 impl<T: Crypto> Client<T> {
-    // TODO: update to send batches of messages
-    pub async fn flush_pending_messages(
+    pub fn flush_pending_messages(
         &mut self,
-        transport: &mut InputEventTransport,
-    ) -> Result<(), ClientConnectionError> {
-        if !self.can_receive() {
+        transport: &InputEventTransport,
+    ) -> Result<(), ClientConnectionError>
+    where
+        T: 'static,
+    {
+        if !self.is_reachable() || !self.target_change_acks.is_empty() {
             return Err(ClientConnectionError::NotReady);
         }
         while let Some(message) = self.pending_messages.pop_front() {
-            transport
-                .send_message_to(message, self.address, Some(self.key.clone()))
-                .await?;
+            self.send_input_message(message, transport)?;
         }
         Ok(())
     }
 
+    /// Queues an input event for the client's input sender task, which sends it over UDP, or
+    /// through the control connection for tunnelled clients.
+    ///
+    /// The task is (re)started whenever the route to the client changes.
+    pub fn send_input_message(
+        &mut self,
+        message: Message,
+        transport: &InputEventTransport,
+    ) -> Result<(), ClientConnectionError>
+    where
+        T: 'static,
+    {
+        let route = match self.address {
+            _ if self.tunnelled => InputRoute::Tunnel,
+            Some(address) => InputRoute::Udp(address),
+            None => return Err(ClientConnectionError::NotReady),
+        };
+        if self.input_sender.as_ref().map(InputSender::route) != Some(route) {
+            // the connection is the same, so everything the previous route still owed is sent
+            if let Some(previous) = self.input_sender.take() {
+                self.unsent_input.extend(previous.take_queued());
+            }
+            let input_sender = InputSender::spawn(
+                self.id,
+                route,
+                transport.sender(),
+                self.key.clone(),
+                self.message_sender.clone(),
+                DEFAULT_INPUT_QUEUE_LEN,
+            );
+            for message in self.unsent_input.drain(..) {
+                input_sender.send(message);
+            }
+            self.input_sender = Some(input_sender);
+        }
+        if let Some(input_sender) = &self.input_sender {
+            input_sender.send(message);
+        }
+        Ok(())
+    }
+
+    /// Input events dropped by the current input sender task under backpressure.
+    pub fn dropped_input(&self) -> u64 {
+        self.input_sender.as_ref().map_or(0, InputSender::dropped)
+    }
+
+    /// Moves a new connection from the same client into this entry, keeping everything that
+    /// is not tied to the previous connection.
+    pub fn resume(&mut self, session: Client<T>) {
+        self.generation = session.generation;
+        self.connected = session.connected;
+        self.address = session.address;
+        self.tunnelled = session.tunnelled;
+        self.screen = session.screen;
+        // the client may have restarted and lost the controllers it made
+        self.announced_gamepads = session.announced_gamepads;
+        self.key = session.key;
+        // dropping the old sender lets the previous connection's handler shut down
+        self.message_sender = session.message_sender;
+        // responses owed by the previous connection will never arrive
+        self.target_change_acks = session.target_change_acks;
+        self.disconnected_at = session.disconnected_at;
+        self.control_link = session.control_link;
+        self.input_link = session.input_link;
+        self.last_udp_hello_seq = session.last_udp_hello_seq;
+        self.pending_messages = session.pending_messages;
+        // the sender task holds the previous connection's key and control channel
+        self.retire_input_sender();
+    }
+
+    pub fn mark_disconnected(&mut self, now: Instant) {
+        if self.connected {
+            self.disconnected_at = Some(now);
+        }
+        self.connected = false;
+        self.retire_input_sender();
+    }
+
+    /// Stops the input sender task of a connection that has ended. The client released every key
+    /// as that session ended, so only the releases the task had not sent are kept for the next
+    /// connection; a press would leave a key stuck down.
+    fn retire_input_sender(&mut self) {
+        if let Some(input_sender) = self.input_sender.take() {
+            let releases = input_sender.take_queued().into_iter().filter(is_release);
+            self.unsent_input.extend(releases);
+        }
+    }
+
+    /// Scales absolute pointer positions onto the client's screen. Without a known screen they
+    /// are left for the client to place.
+    pub fn place_on_screen(&self, message: Message) -> Message {
+        match (message, self.screen) {
+            (
+                Message::InputEvent {
+                    event: InputEvent::Tablet(event),
+                },
+                Some(screen),
+            ) => Message::InputEvent {
+                event: InputEvent::Tablet(screen.place(event)),
+            },
+            (message, _) => message,
+        }
+    }
+
     pub fn buffer_message(&mut self, message: Message) {
-        self.pending_messages.push_back(message);
+        self.pending_messages.push(message);
+    }
+
+    pub fn link_quality(&self) -> ConnectionQuality {
+        ConnectionQuality {
+            control: self.control_link.quality(),
+            input: self.input_link.quality(),
+        }
+    }
+
+    /// Returns true once the client has a path for input events.
+    pub fn is_reachable(&self) -> bool {
+        self.tunnelled || self.address.is_some()
     }
 
     pub fn can_receive(&self) -> bool {
-        self.pending_target_change_responses == 0 && self.pending_messages.is_empty()
+        self.is_reachable()
+            && self.target_change_acks.is_empty()
+            && self.pending_messages.is_empty()
+    }
+
+    /// Records the source address of an authenticated UDP hello as the client's input address.
+    ///
+    /// Hellos with a sequence number that is not newer than the last accepted one are ignored
+    /// so that a replayed datagram cannot redirect input to another host.
+    /// Returns `true` if the hello was accepted.
+    pub fn learn_address(&mut self, seq: u64, addr: SocketAddr) -> bool {
+        if self.last_udp_hello_seq.is_some_and(|last| seq <= last) {
+            return false;
+        }
+        self.last_udp_hello_seq = Some(seq);
+
+        if self.address != Some(addr) {
+            println!("Learned UDP address {} for client {}", addr, self.id);
+            self.address = Some(addr);
+        }
+        true
     }
 }
 
 #[cfg(test)]
 pub mod test {
-    use network::Message;
+    use network::{link_stats::LinkStats, Message};
     use tokio::sync::mpsc;
 
     use chacha20poly1305::{ChaCha20Poly1305, KeyInit};
     use uuid::Uuid;
 
-    use super::Client;
+    use super::{next_generation, Client, PendingMessages, TargetChangeAcks};
 
     pub fn test_client_fixture(message_sender: mpsc::Sender<Message>) -> Client<ChaCha20Poly1305> {
         Client {
             id: Uuid::new_v4(),
+            generation: next_generation(),
             connected: true,
-            address: "127.0.0.1:34567".parse().unwrap(),
+            address: Some("127.0.0.1:34567".parse().unwrap()),
+            tunnelled: false,
+            screen: None,
+            announced_gamepads: Default::default(),
             key: ChaCha20Poly1305::new_from_slice(&[0; 32]).unwrap(),
             message_sender,
-            pending_target_change_responses: 0,
-            pending_messages: Vec::new().into(),
+            target_change_acks: TargetChangeAcks::new(),
+            disconnected_at: None,
+            control_link: LinkStats::new(),
+            input_link: LinkStats::new(),
+            last_udp_hello_seq: None,
+            pending_messages: PendingMessages::default(),
+            input_sender: None,
+            unsent_input: Default::default(),
+        }
+    }
+
+    mod send_input_message {
+        use std::time::Instant;
+
+        use input_event::{InputEvent, Key, KeyboardEvent, KeyboardEventType};
+        use network::{input_event::InputEventTransport, Message};
+        use tokio::{net::UdpSocket, sync::mpsc};
+
+        use super::test_client_fixture;
+
+        fn key(event_type: KeyboardEventType) -> Message {
+            Message::InputEvent {
+                event: InputEvent::Keyboard(KeyboardEvent {
+                    event_type,
+                    key: Key::KEY_A,
+                }),
+            }
+        }
+
+        #[tokio::test]
+        async fn given_route_change_should_hand_queued_input_to_new_sender() {
+            // Given
+            let (message_sender, _message_receiver) = mpsc::channel(10);
+            let mut client = test_client_fixture(message_sender);
+            let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+            let transport = InputEventTransport::new(socket);
+            client
+                .send_input_message(key(KeyboardEventType::KeyPressed), &transport)
+                .unwrap();
+
+            // When
+            client.address = Some("127.0.0.1:34568".parse().unwrap());
+            client
+                .send_input_message(key(KeyboardEventType::KeyReleased), &transport)
+                .unwrap();
+
+            // Then
+            let queued: Vec<_> = client.input_sender.unwrap().take_queued().into();
+            assert_eq!(
+                queued,
+                vec![
+                    key(KeyboardEventType::KeyPressed),
+                    key(KeyboardEventType::KeyReleased)
+                ]
+            );
+        }
+
+        #[tokio::test]
+        async fn given_disconnect_should_keep_only_unsent_releases_for_next_connection() {
+            // Given
+            let (message_sender, _message_receiver) = mpsc::channel(10);
+            let mut client = test_client_fixture(message_sender);
+            let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+            let transport = InputEventTransport::new(socket);
+            for event_type in [
+                KeyboardEventType::KeyReleased,
+                KeyboardEventType::KeyPressed,
+            ] {
+                client
+                    .send_input_message(key(event_type), &transport)
+                    .unwrap();
+            }
+
+            // When
+            client.mark_disconnected(Instant::now());
+
+            // Then
+            assert!(client.input_sender.is_none());
+            assert_eq!(
+                Vec::from(client.unsent_input),
+                vec![key(KeyboardEventType::KeyReleased)]
+            );
+        }
+
+        #[tokio::test]
+        async fn given_tunnelled_client_should_send_over_control_channel() {
+            // Given
+            let (message_sender, mut message_receiver) = mpsc::channel(10);
+            let mut client = test_client_fixture(message_sender);
+            client.address = None;
+            client.tunnelled = true;
+            let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+            let transport = InputEventTransport::new(socket);
+            let message = Message::InputEvent {
+                event: InputEvent::Keyboard(KeyboardEvent {
+                    event_type: KeyboardEventType::KeyPressed,
+                    key: Key::KEY_A,
+                }),
+            };
+
+            // When
+            let response = client.send_input_message(message, &transport);
+
+            // Then
+            assert!(response.is_ok());
+            assert!(matches!(
+                message_receiver.recv().await,
+                Some(Message::InputEvent { .. })
+            ));
+        }
+    }
+
+    mod learn_address {
+        use tokio::sync::mpsc;
+
+        use super::test_client_fixture;
+
+        #[test]
+        fn given_newer_hello_should_update_address() {
+            // Given
+            let (message_sender, _message_receiver) = mpsc::channel(10);
+            let mut client = test_client_fixture(message_sender);
+            let new_addr = "10.0.0.2:40000".parse().unwrap();
+            client.learn_address(0, "10.0.0.1:40000".parse().unwrap());
+
+            // When
+            let accepted = client.learn_address(1, new_addr);
+
+            // Then
+            assert!(accepted);
+            assert_eq!(client.address, Some(new_addr));
+        }
+
+        #[test]
+        fn given_replayed_hello_should_keep_address() {
+            // Given
+            let (message_sender, _message_receiver) = mpsc::channel(10);
+            let mut client = test_client_fixture(message_sender);
+            let addr = "10.0.0.1:40000".parse().unwrap();
+            client.learn_address(5, addr);
+
+            // When
+            let accepted = client.learn_address(5, "10.0.0.3:40000".parse().unwrap());
+
+            // Then
+            assert!(!accepted);
+            assert_eq!(client.address, Some(addr));
+        }
+    }
+
+    mod place_on_screen {
+        use input_event::{InputEvent, PointerAxis, ScreenGeometry, TabletEvent, ABSOLUTE_MAX};
+        use network::Message;
+        use tokio::sync::mpsc;
+
+        use super::test_client_fixture;
+
+        fn tablet(event: TabletEvent) -> Message {
+            Message::InputEvent {
+                event: InputEvent::Tablet(event),
+            }
+        }
+
+        #[test]
+        fn given_known_screen_should_scale_positions_to_pixels() {
+            // Given
+            let (message_sender, _message_receiver) = mpsc::channel(10);
+            let mut client = test_client_fixture(message_sender);
+            client.screen = Some(ScreenGeometry {
+                width: 1921,
+                height: 1081,
+            });
+
+            // When
+            let placed: Vec<_> = [
+                TabletEvent::Position {
+                    axis: PointerAxis::Horizontal,
+                    value: ABSOLUTE_MAX / 2 + 1,
+                },
+                TabletEvent::Position {
+                    axis: PointerAxis::Vertical,
+                    value: ABSOLUTE_MAX,
+                },
+                TabletEvent::Pressure { value: 100 },
+            ]
+            .into_iter()
+            .map(|event| client.place_on_screen(tablet(event)))
+            .collect();
+
+            // Then
+            assert_eq!(
+                placed,
+                vec![
+                    tablet(TabletEvent::ScreenPosition {
+                        axis: PointerAxis::Horizontal,
+                        pixel: 960,
+                    }),
+                    tablet(TabletEvent::ScreenPosition {
+                        axis: PointerAxis::Vertical,
+                        pixel: 1080,
+                    }),
+                    tablet(TabletEvent::Pressure { value: 100 }),
+                ]
+            );
+        }
+
+        #[test]
+        fn given_unknown_screen_should_leave_positions_for_the_client() {
+            // Given
+            let (message_sender, _message_receiver) = mpsc::channel(10);
+            let client = test_client_fixture(message_sender);
+            let position = TabletEvent::Position {
+                axis: PointerAxis::Horizontal,
+                value: 10,
+            };
+
+            // When
+            let placed = client.place_on_screen(tablet(position));
+
+            // Then
+            assert_eq!(placed, tablet(position));
         }
     }
 }
@@ -0,0 +1,532 @@
+//! Per-client tasks that deliver input events, so that encryption and slow sends to one client
+//! never hold up the state actor.
+//!
+//! The state actor pushes events onto a bounded queue that the client's task drains. When the queue
+//! is full, pointer motion is merged into the newest queued delta on the same axis or dropped, key
+//! repeats are dropped, an absolute pointer value, touch frame or controller axis replaces the
+//! newest queued one it makes obsolete, and a key or button transition makes room by evicting the
+//! oldest stale event. If the queue holds nothing but transitions, a key or button pressed and
+//! released within it is dropped as a pair, which leaves the client's keys as they would have
+//! ended up. Failing that the oldest press is dropped, since a missed press cannot leave a key
+//! stuck down, and only then the oldest event, so the queue never grows past its capacity.
+//!
+//! A task that is replaced hands back whatever it had not yet taken from its queue, so that the
+//! transitions in it can be passed on to the task that takes over.
+
+use std::{
+    collections::VecDeque,
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+};
+
+use crypto::Crypto;
+use input_event::{
+    Button, GamepadButton, GamepadEvent, GamepadId, GamepadInput, InputEvent, Key,
+    KeyboardEventType, MouseEvent,
+};
+use network::{input_event::InputEventSender, Message};
+use tokio::sync::{mpsc, Notify};
+use uuid::Uuid;
+
+pub const DEFAULT_INPUT_QUEUE_LEN: usize = 256;
+
+/// Where a client's input events are sent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InputRoute {
+    /// Encrypted datagrams to the client's input address.
+    Udp(SocketAddr),
+    /// Through the client's control connection.
+    Tunnel,
+}
+
+#[derive(Debug)]
+struct QueueState {
+    messages: VecDeque<Message>,
+    dropped: u64,
+    closed: bool,
+}
+
+impl QueueState {
+    /// Makes room for an event that must not be dropped.
+    fn make_room(&mut self) {
+        if self.evict_stale() || self.evict_pair() || self.evict_press() {
+            return;
+        }
+        self.messages.pop_front();
+        self.dropped += 1;
+    }
+
+    /// Drops the oldest event that a later one supersedes, if there is one.
+    fn evict_stale(&mut self) -> bool {
+        let stale = self
+            .messages
+            .iter()
+            .position(|queued| Staleness::of(queued) != Staleness::Transition);
+        self.evict(stale)
+    }
+
+    /// Drops the first press whose release is also queued, along with that release.
+    fn evict_pair(&mut self) -> bool {
+        let mut pressed: Vec<(Control, usize)> = Vec::new();
+        for (idx, queued) in self.messages.iter().enumerate() {
+            let Some((control, event_type)) = transition(queued) else {
+                continue;
+            };
+            let open = pressed.iter().position(|(held, _)| *held == control);
+            match (event_type, open) {
+                (KeyboardEventType::KeyPressed, Some(open)) => pressed[open].1 = idx,
+                (KeyboardEventType::KeyPressed, None) => pressed.push((control, idx)),
+                (KeyboardEventType::KeyReleased, Some(open)) => {
+                    let press = pressed[open].1;
+                    self.messages.remove(idx);
+                    self.messages.remove(press);
+                    self.dropped += 2;
+                    return true;
+                }
+                _ => {}
+            }
+        }
+        false
+    }
+
+    /// Drops the oldest press.
+    fn evict_press(&mut self) -> bool {
+        let press = self.messages.iter().position(|queued| {
+            transition(queued)
+                .is_some_and(|(_, event_type)| event_type == KeyboardEventType::KeyPressed)
+        });
+        self.evict(press)
+    }
+
+    fn evict(&mut self, idx: Option<usize>) -> bool {
+        let Some(idx) = idx else {
+            return false;
+        };
+        self.messages.remove(idx);
+        self.dropped += 1;
+        true
+    }
+}
+
+#[derive(Debug)]
+struct InputQueue {
+    state: Mutex<QueueState>,
+    capacity: usize,
+    notify: Notify,
+}
+
+impl InputQueue {
+    fn new(capacity: usize) -> Self {
+        InputQueue {
+            state: Mutex::new(QueueState {
+                messages: VecDeque::with_capacity(capacity),
+                dropped: 0,
+                closed: false,
+            }),
+            capacity,
+            notify: Notify::new(),
+        }
+    }
+
+    fn push(&self, message: Message) {
+        let mut state = self.state.lock().unwrap();
+        if state.messages.len() >= self.capacity {
+            match Staleness::of(&message) {
+                Staleness::Delta => {
+                    if !merge_into_last(&mut state.messages, &message) {
+                        state.dropped += 1;
+                    }
+                    return;
+                }
+                Staleness::Repeat => {
+                    state.dropped += 1;
+                    return;
+                }
+                Staleness::Absolute => {
+                    if replace_last(&mut state.messages, &message) {
+                        return;
+                    }
+                    // dropping it would leave the pointer short of where it ended up
+                    state.make_room();
+                }
+                Staleness::Transition => state.make_room(),
+            }
+        }
+        state.messages.push_back(message);
+        drop(state);
+        self.notify.notify_one();
+    }
+
+    /// Waits for queued messages and takes all of them, or returns `None` once closed.
+    async fn take(&self) -> Option<VecDeque<Message>> {
+        loop {
+            {
+                let mut state = self.state.lock().unwrap();
+                if state.closed {
+                    return None;
+                }
+                if !state.messages.is_empty() {
+                    return Some(std::mem::take(&mut state.messages));
+                }
+            }
+            self.notify.notified().await;
+        }
+    }
+
+    /// Takes the messages that have not been taken for sending yet.
+    fn drain(&self) -> VecDeque<Message> {
+        std::mem::take(&mut self.state.lock().unwrap().messages)
+    }
+
+    fn close(&self) {
+        self.state.lock().unwrap().closed = true;
+        self.notify.notify_one();
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Staleness {
+    /// Relative pointer movement, superseded by the next delta.
+    Delta,
+    /// An absolute pointer value, touch frame or controller axis, superseded by the next of the
+    /// same kind.
+    Absolute,
+    /// A key repeat, which only matters while the key is down anyway.
+    Repeat,
+    /// A press or release, or anything else that must be delivered.
+    Transition,
+}
+
+impl Staleness {
+    fn of(message: &Message) -> Self {
+        match message {
+            Message::InputEvent {
+                event:
+                    InputEvent::Mouse(
+                        MouseEvent::Motion { .. }
+                        | MouseEvent::Scroll { .. }
+                        | MouseEvent::HiResScroll { .. },
+                    ),
+            } => Staleness::Delta,
+            Message::InputEvent { event } if event.is_absolute() => Staleness::Absolute,
+            Message::InputEvent {
+                event: InputEvent::Keyboard(event),
+            } if event.event_type == KeyboardEventType::KeyHeld => Staleness::Repeat,
+            _ => Staleness::Transition,
+        }
+    }
+}
+
+/// Returns true for input that a later event supersedes, which may be dropped under load.
+pub fn is_stale(message: &Message) -> bool {
+    Staleness::of(message) != Staleness::Transition
+}
+
+/// Returns true for the release of a key or button, which is safe to deliver however late.
+pub fn is_release(message: &Message) -> bool {
+    transition(message).is_some_and(|(_, event_type)| event_type == KeyboardEventType::KeyReleased)
+}
+
+/// A key or button that is pressed and released.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Control {
+    Key(Key),
+    Button(Button),
+    Gamepad(GamepadId, GamepadButton),
+}
+
+fn transition(message: &Message) -> Option<(Control, KeyboardEventType)> {
+    let Message::InputEvent { event } = message else {
+        return None;
+    };
+    match event {
+        InputEvent::Keyboard(event) => Some((Control::Key(event.key), event.event_type)),
+        InputEvent::Mouse(MouseEvent::Button { event_type, button }) => {
+            Some((Control::Button(*button), *event_type))
+        }
+        InputEvent::Gamepad(GamepadEvent {
+            pad,
+            input: GamepadInput::Button { event_type, button },
+        }) => Some((Control::Gamepad(*pad, *button), *event_type)),
+        _ => None,
+    }
+}
+
+/// Adds a motion or scroll delta to the newest queued event if it moves along the same axis.
+fn merge_into_last(messages: &mut VecDeque<Message>, message: &Message) -> bool {
+    let Some(Message::InputEvent {
+        event: InputEvent::Mouse(last),
+    }) = messages.back_mut()
+    else {
+        return false;
+    };
+    let Message::InputEvent {
+        event: InputEvent::Mouse(new),
+    } = message
+    else {
+        return false;
+    };
+    match (last, new) {
+        (
+            MouseEvent::Motion { axis, diff },
+            MouseEvent::Motion {
+                axis: new_axis,
+                diff: new_diff,
+            },
+        )
+        | (
+            MouseEvent::Scroll { axis, diff },
+            MouseEvent::Scroll {
+                axis: new_axis,
+                diff: new_diff,
+            },
+        )
+        | (
+            MouseEvent::HiResScroll { axis, diff },
+            MouseEvent::HiResScroll {
+                axis: new_axis,
+                diff: new_diff,
+            },
+        ) if *axis == *new_axis => {
+            *diff = diff.saturating_add(*new_diff);
+            true
+        }
+        _ => false,
+    }
+}
+
+/// Overwrites the newest queued event that an absolute pointer value or touch frame makes
+/// obsolete. Only the run of absolute events at the back of the queue is searched, so that a
+/// value never moves to before a button press that came ahead of it.
+fn replace_last(messages: &mut VecDeque<Message>, message: &Message) -> bool {
+    let Message::InputEvent { event: new } = message else {
+        return false;
+    };
+    for queued in messages.iter_mut().rev() {
+        let Message::InputEvent { event } = queued else {
+            return false;
+        };
+        if !event.is_absolute() {
+            return false;
+        }
+        if new.supersedes(event) {
+            *event = new.clone();
+            return true;
+        }
+    }
+    false
+}
+
+/// Handle to a client's input sender task. Dropping it stops the task once it has sent what it
+/// already took from the queue.
+#[derive(Debug)]
+pub struct InputSender {
+    route: InputRoute,
+    queue: Arc<InputQueue>,
+}
+
+impl InputSender {
+    /// Spawns a task sending to `route`. UDP sends use `sender` and `key`, tunnelled input goes
+    /// through `message_sender`.
+    pub fn spawn<T: Crypto + Clone + Send + 'static>(
+        id: Uuid,
+        route: InputRoute,
+        mut sender: InputEventSender,
+        key: T,
+        message_sender: mpsc::Sender<Message>,
+        capacity: usize,
+    ) -> Self {
+        let queue = Arc::new(InputQueue::new(capacity));
+        let task_queue = queue.clone();
+        tokio::spawn(async move {
+            while let Some(messages) = task_queue.take().await {
+                for message in messages {
+                    let result = match route {
+                        InputRoute::Udp(address) => sender
+                            .send_message_to(message, address, Some(key.clone()))
+                            .await
+                            .map_err(|err| err.to_string()),
+                        InputRoute::Tunnel => message_sender
+                            .send(message)
+                            .await
+                            .map_err(|err| err.to_string()),
+                    };
+                    if let Err(err) = result {
+                        eprintln!("Could not send input to client {}: {}", id, err);
+                    }
+                }
+            }
+        });
+        InputSender { route, queue }
+    }
+
+    pub fn route(&self) -> InputRoute {
+        self.route
+    }
+
+    /// Queues a message without waiting, applying the backpressure policy if the queue is full.
+    pub fn send(&self, message: Message) {
+        self.queue.push(message);
+    }
+
+    /// Takes back the messages the task has not started sending, so that they can be handed to
+    /// the task replacing this one.
+    pub fn take_queued(&self) -> VecDeque<Message> {
+        self.queue.drain()
+    }
+
+    /// Events discarded because the client's task could not keep up.
+    pub fn dropped(&self) -> u64 {
+        self.queue.state.lock().unwrap().dropped
+    }
+}
+
+impl Drop for InputSender {
+    fn drop(&mut self) {
+        self.queue.close();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use input_event::{
+        InputEvent, Key, KeyboardEvent, KeyboardEventType, MouseEvent, PointerAxis, TabletEvent,
+    };
+    use network::Message;
+
+    use super::InputQueue;
+
+    fn motion(diff: i32) -> Message {
+        Message::InputEvent {
+            event: InputEvent::Mouse(MouseEvent::Motion {
+                axis: PointerAxis::Horizontal,
+                diff,
+            }),
+        }
+    }
+
+    fn key(event_type: KeyboardEventType) -> Message {
+        Message::InputEvent {
+            event: InputEvent::Keyboard(KeyboardEvent {
+                event_type,
+                key: Key::KEY_A,
+            }),
+        }
+    }
+
+    #[tokio::test]
+    async fn given_full_queue_should_merge_motion_and_drop_repeats() {
+        // Given
+        let queue = InputQueue::new(2);
+        queue.push(key(KeyboardEventType::KeyPressed));
+        queue.push(motion(1));
+
+        // When
+        queue.push(motion(2));
+        queue.push(key(KeyboardEventType::KeyHeld));
+
+        // Then
+        let messages: Vec<_> = queue.take().await.unwrap().into();
+        assert_eq!(
+            messages,
+            vec![key(KeyboardEventType::KeyPressed), motion(3)]
+        );
+        assert_eq!(queue.state.lock().unwrap().dropped, 1);
+    }
+
+    #[tokio::test]
+    async fn given_full_queue_should_evict_stale_event_for_key_transition() {
+        // Given
+        let queue = InputQueue::new(2);
+        queue.push(motion(1));
+        queue.push(key(KeyboardEventType::KeyPressed));
+
+        // When
+        queue.push(key(KeyboardEventType::KeyReleased));
+
+        // Then
+        let messages: Vec<_> = queue.take().await.unwrap().into();
+        assert_eq!(
+            messages,
+            vec![
+                key(KeyboardEventType::KeyPressed),
+                key(KeyboardEventType::KeyReleased),
+            ]
+        );
+        assert_eq!(queue.state.lock().unwrap().dropped, 1);
+    }
+
+    #[tokio::test]
+    async fn given_queue_full_of_transitions_should_drop_pressed_and_released_pair() {
+        // Given
+        let other = || Message::InputEvent {
+            event: InputEvent::Keyboard(KeyboardEvent {
+                event_type: KeyboardEventType::KeyPressed,
+                key: Key::KEY_B,
+            }),
+        };
+        let queue = InputQueue::new(3);
+        queue.push(key(KeyboardEventType::KeyPressed));
+        queue.push(other());
+        queue.push(key(KeyboardEventType::KeyReleased));
+
+        // When
+        queue.push(key(KeyboardEventType::KeyPressed));
+
+        // Then
+        let messages: Vec<_> = queue.take().await.unwrap().into();
+        assert_eq!(messages, vec![other(), key(KeyboardEventType::KeyPressed)]);
+        assert_eq!(queue.state.lock().unwrap().dropped, 2);
+    }
+
+    #[tokio::test]
+    async fn given_queue_full_of_presses_should_drop_oldest_press_not_release() {
+        // Given
+        let press = |key| Message::InputEvent {
+            event: InputEvent::Keyboard(KeyboardEvent {
+                event_type: KeyboardEventType::KeyPressed,
+                key,
+            }),
+        };
+        let queue = InputQueue::new(2);
+        queue.push(key(KeyboardEventType::KeyReleased));
+        queue.push(press(Key::KEY_B));
+
+        // When
+        queue.push(press(Key::KEY_C));
+
+        // Then
+        let messages: Vec<_> = queue.take().await.unwrap().into();
+        assert_eq!(
+            messages,
+            vec![key(KeyboardEventType::KeyReleased), press(Key::KEY_C)]
+        );
+        assert_eq!(queue.state.lock().unwrap().dropped, 1);
+    }
+
+    #[tokio::test]
+    async fn given_full_queue_should_replace_absolute_value_on_same_axis() {
+        // Given
+        let position = |value| Message::InputEvent {
+            event: InputEvent::Tablet(TabletEvent::Position {
+                axis: PointerAxis::Horizontal,
+                value,
+            }),
+        };
+        let queue = InputQueue::new(2);
+        queue.push(position(1));
+        queue.push(key(KeyboardEventType::KeyPressed));
+
+        // When
+        queue.push(position(2));
+        queue.push(position(3));
+
+        // Then
+        let messages: Vec<_> = queue.take().await.unwrap().into();
+        assert_eq!(
+            messages,
+            vec![key(KeyboardEventType::KeyPressed), position(3)]
+        );
+        assert_eq!(queue.state.lock().unwrap().dropped, 1);
+    }
+}
@@ -1,3 +1,6 @@
 pub mod actor;
 pub mod client;
+pub mod input_sender;
+pub mod pending;
 pub mod resource;
+pub mod target_change;
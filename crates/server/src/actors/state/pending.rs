@@ -0,0 +1,476 @@
+//! Input held back for a client until it acknowledges a target change.
+//!
+//! The buffer is bounded. Pointer motion and scrolling are merged into the preceding delta on the
+//! same axis, absolute pointer values, touch frames and controller axes replace the preceding one
+//! of the same kind, and repeated presses of a key that is already down are dropped, so a burst of
+//! input only takes a few slots. When the buffer is still full it is compacted to its net effect:
+//! releases of keys that were down before buffering started, the summed pointer movement, the last
+//! absolute pointer values and touch frame, and presses of keys that are still down. A key pressed
+//! and released entirely within the buffer is lost at that point, which is preferable to a key left
+//! stuck down. Only if the compacted buffer is still full is the oldest message dropped.
+
+use std::collections::VecDeque;
+
+use input_event::{
+    Button, InputEvent, Key, KeyboardEvent, KeyboardEventType, MouseEvent, PointerAxis,
+};
+use network::Message;
+
+pub const DEFAULT_PENDING_CAPACITY: usize = 1024;
+
+#[derive(Debug)]
+pub struct PendingMessages {
+    messages: VecDeque<Message>,
+    capacity: usize,
+    dropped: u64,
+}
+
+impl Default for PendingMessages {
+    fn default() -> Self {
+        PendingMessages::new(DEFAULT_PENDING_CAPACITY)
+    }
+}
+
+impl PendingMessages {
+    pub fn new(capacity: usize) -> Self {
+        assert!(capacity > 0, "Pending message capacity must not be zero");
+        PendingMessages {
+            messages: VecDeque::with_capacity(capacity),
+            capacity,
+            dropped: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.messages.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.messages.is_empty()
+    }
+
+    /// Messages discarded because the buffer was full even after compaction.
+    pub fn dropped(&self) -> u64 {
+        self.dropped
+    }
+
+    pub fn pop_front(&mut self) -> Option<Message> {
+        self.messages.pop_front()
+    }
+
+    pub fn push(&mut self, message: Message) {
+        if let Message::InputEvent { event } = &message {
+            if self.absorb(event) {
+                return;
+            }
+        }
+
+        if self.messages.len() >= self.capacity {
+            self.compact();
+        }
+        if self.messages.len() >= self.capacity {
+            self.messages.pop_front();
+            self.dropped += 1;
+        }
+        self.messages.push_back(message);
+    }
+
+    /// Folds `event` into an already buffered event, returning true if nothing needs adding.
+    fn absorb(&mut self, event: &InputEvent) -> bool {
+        match event {
+            InputEvent::Mouse(MouseEvent::Motion { axis, diff }) => {
+                self.add_to_trailing_delta(*axis, *diff, |event| match event {
+                    MouseEvent::Motion { axis, diff } => Some((*axis, diff)),
+                    _ => None,
+                })
+            }
+            InputEvent::Mouse(MouseEvent::Scroll { axis, diff }) => {
+                self.add_to_trailing_delta(*axis, *diff, |event| match event {
+                    MouseEvent::Scroll { axis, diff } => Some((*axis, diff)),
+                    _ => None,
+                })
+            }
+            InputEvent::Mouse(MouseEvent::HiResScroll { axis, diff }) => self
+                .add_to_trailing_delta(*axis, *diff, |event| match event {
+                    MouseEvent::HiResScroll { axis, diff } => Some((*axis, diff)),
+                    _ => None,
+                }),
+            event if event.is_absolute() => self.replace_trailing_value(event),
+            InputEvent::Keyboard(keyboard)
+                if keyboard.event_type != KeyboardEventType::KeyReleased =>
+            {
+                is_down(self.last_key_state(keyboard.key))
+            }
+            InputEvent::Mouse(MouseEvent::Button { event_type, button })
+                if *event_type != KeyboardEventType::KeyReleased =>
+            {
+                is_down(self.last_button_state(*button))
+            }
+            _ => false,
+        }
+    }
+
+    /// Adds `diff` to the delta for `axis` in the run of same-kind events at the back of the
+    /// buffer. The run holds at most one event per axis, since anything else would have merged.
+    fn add_to_trailing_delta(
+        &mut self,
+        axis: PointerAxis,
+        diff: i32,
+        delta: impl Fn(&mut MouseEvent) -> Option<(PointerAxis, &mut i32)>,
+    ) -> bool {
+        for message in self.messages.iter_mut().rev() {
+            let Message::InputEvent {
+                event: InputEvent::Mouse(event),
+            } = message
+            else {
+                return false;
+            };
+            match delta(event) {
+                Some((buffered_axis, buffered_diff)) if buffered_axis == axis => {
+                    *buffered_diff = buffered_diff.saturating_add(diff);
+                    return true;
+                }
+                Some(_) => continue,
+                None => return false,
+            }
+        }
+        false
+    }
+
+    /// Overwrites the event that `event` supersedes in the run of absolute events at the back of
+    /// the buffer, so that it never moves to before a button press that came ahead of it.
+    fn replace_trailing_value(&mut self, event: &InputEvent) -> bool {
+        for message in self.messages.iter_mut().rev() {
+            let Message::InputEvent { event: buffered } = message else {
+                return false;
+            };
+            if !buffered.is_absolute() {
+                return false;
+            }
+            if event.supersedes(buffered) {
+                *buffered = event.clone();
+                return true;
+            }
+        }
+        false
+    }
+
+    fn last_key_state(&self, key: Key) -> Option<KeyboardEventType> {
+        self.messages
+            .iter()
+            .rev()
+            .find_map(|message| match message {
+                Message::InputEvent {
+                    event: InputEvent::Keyboard(event),
+                } if event.key == key => Some(event.event_type),
+                _ => None,
+            })
+    }
+
+    fn last_button_state(&self, button: Button) -> Option<KeyboardEventType> {
+        self.messages
+            .iter()
+            .rev()
+            .find_map(|message| match message {
+                Message::InputEvent {
+                    event:
+                        InputEvent::Mouse(MouseEvent::Button {
+                            event_type,
+                            button: buffered,
+                        }),
+                } if *buffered == button => Some(*event_type),
+                _ => None,
+            })
+    }
+
+    /// Replaces the buffered input with its net effect.
+    fn compact(&mut self) {
+        let before = self.messages.len();
+        let mut others = Vec::new();
+        let mut keys: Vec<(Key, Transitions)> = Vec::new();
+        let mut buttons: Vec<(Button, Transitions)> = Vec::new();
+        let mut motion = [0i32; 2];
+        let mut scroll = [0i32; 2];
+        let mut hi_res_scroll = [0i32; 2];
+        let mut absolute: Vec<InputEvent> = Vec::new();
+
+        for message in self.messages.drain(..) {
+            match message {
+                Message::InputEvent {
+                    event: InputEvent::Keyboard(event),
+                } => record(&mut keys, event.key, event.event_type),
+                Message::InputEvent {
+                    event: InputEvent::Mouse(MouseEvent::Button { event_type, button }),
+                } => record(&mut buttons, button, event_type),
+                Message::InputEvent {
+                    event: InputEvent::Mouse(MouseEvent::Motion { axis, diff }),
+                } => motion[axis_idx(axis)] = motion[axis_idx(axis)].saturating_add(diff),
+                Message::InputEvent {
+                    event: InputEvent::Mouse(MouseEvent::Scroll { axis, diff }),
+                } => scroll[axis_idx(axis)] = scroll[axis_idx(axis)].saturating_add(diff),
+                Message::InputEvent {
+                    event: InputEvent::Mouse(MouseEvent::HiResScroll { axis, diff }),
+                } => {
+                    hi_res_scroll[axis_idx(axis)] =
+                        hi_res_scroll[axis_idx(axis)].saturating_add(diff)
+                }
+                Message::InputEvent { event } if event.is_absolute() => {
+                    absolute.retain(|kept| !event.supersedes(kept));
+                    absolute.push(event);
+                }
+                message => others.push(message),
+            }
+        }
+
+        let key_event = |key, event_type| InputEvent::Keyboard(KeyboardEvent { event_type, key });
+        let button_event =
+            |button, event_type| InputEvent::Mouse(MouseEvent::Button { event_type, button });
+
+        let mut events = Vec::new();
+        events.extend(releases(&keys).map(|key| key_event(key, KeyboardEventType::KeyReleased)));
+        events.extend(
+            releases(&buttons).map(|button| button_event(button, KeyboardEventType::KeyReleased)),
+        );
+        for (idx, axis) in [PointerAxis::Horizontal, PointerAxis::Vertical]
+            .into_iter()
+            .enumerate()
+        {
+            if motion[idx] != 0 {
+                events.push(InputEvent::Mouse(MouseEvent::Motion {
+                    axis,
+                    diff: motion[idx],
+                }));
+            }
+            if scroll[idx] != 0 {
+                events.push(InputEvent::Mouse(MouseEvent::Scroll {
+                    axis,
+                    diff: scroll[idx],
+                }));
+            }
+            if hi_res_scroll[idx] != 0 {
+                events.push(InputEvent::Mouse(MouseEvent::HiResScroll {
+                    axis,
+                    diff: hi_res_scroll[idx],
+                }));
+            }
+        }
+        events.extend(absolute);
+        events.extend(presses(&keys).map(|key| key_event(key, KeyboardEventType::KeyPressed)));
+        events.extend(
+            presses(&buttons).map(|button| button_event(button, KeyboardEventType::KeyPressed)),
+        );
+
+        self.messages.extend(others);
+        self.messages.extend(
+            events
+                .into_iter()
+                .map(|event| Message::InputEvent { event }),
+        );
+        eprintln!(
+            "Pending input buffer full, compacted {} messages to {}",
+            before,
+            self.messages.len()
+        );
+    }
+}
+
+/// The first and last buffered transition of a key or button.
+#[derive(Debug, Clone, Copy)]
+struct Transitions {
+    first_down: bool,
+    last_down: bool,
+}
+
+fn record<T: PartialEq>(
+    entries: &mut Vec<(T, Transitions)>,
+    item: T,
+    event_type: KeyboardEventType,
+) {
+    let down = event_type != KeyboardEventType::KeyReleased;
+    match entries.iter_mut().find(|(entry, _)| *entry == item) {
+        Some((_, transitions)) => transitions.last_down = down,
+        None => entries.push((
+            item,
+            Transitions {
+                first_down: down,
+                last_down: down,
+            },
+        )),
+    }
+}
+
+/// Items whose first buffered event released them, so they were down before buffering began.
+fn releases<T: Copy>(entries: &[(T, Transitions)]) -> impl Iterator<Item = T> + '_ {
+    entries
+        .iter()
+        .filter(|(_, transitions)| !transitions.first_down)
+        .map(|(item, _)| *item)
+}
+
+/// Items that are still down at the end of the buffer.
+fn presses<T: Copy>(entries: &[(T, Transitions)]) -> impl Iterator<Item = T> + '_ {
+    entries
+        .iter()
+        .filter(|(_, transitions)| transitions.last_down)
+        .map(|(item, _)| *item)
+}
+
+fn is_down(state: Option<KeyboardEventType>) -> bool {
+    state.is_some_and(|event_type| event_type != KeyboardEventType::KeyReleased)
+}
+
+fn axis_idx(axis: PointerAxis) -> usize {
+    match axis {
+        PointerAxis::Horizontal => 0,
+        PointerAxis::Vertical => 1,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use input_event::{
+        Button, InputEvent, Key, KeyboardEvent, KeyboardEventType, MouseEvent, PointerAxis,
+        TabletEvent,
+    };
+    use network::Message;
+
+    use super::PendingMessages;
+
+    fn motion(axis: PointerAxis, diff: i32) -> Message {
+        Message::InputEvent {
+            event: InputEvent::Mouse(MouseEvent::Motion { axis, diff }),
+        }
+    }
+
+    fn key(key: Key, event_type: KeyboardEventType) -> Message {
+        Message::InputEvent {
+            event: InputEvent::Keyboard(KeyboardEvent { event_type, key }),
+        }
+    }
+
+    fn drain(pending: &mut PendingMessages) -> Vec<Message> {
+        std::iter::from_fn(|| pending.pop_front()).collect()
+    }
+
+    #[test]
+    fn given_consecutive_motion_should_merge_per_axis() {
+        // Given
+        let mut pending = PendingMessages::default();
+
+        // When
+        pending.push(motion(PointerAxis::Horizontal, 3));
+        pending.push(motion(PointerAxis::Vertical, -1));
+        pending.push(motion(PointerAxis::Horizontal, 4));
+        pending.push(key(Key::KEY_A, KeyboardEventType::KeyPressed));
+        pending.push(motion(PointerAxis::Vertical, 2));
+
+        // Then
+        assert_eq!(
+            drain(&mut pending),
+            vec![
+                motion(PointerAxis::Horizontal, 7),
+                motion(PointerAxis::Vertical, -1),
+                key(Key::KEY_A, KeyboardEventType::KeyPressed),
+                motion(PointerAxis::Vertical, 2),
+            ]
+        );
+    }
+
+    #[test]
+    fn given_repeats_of_held_key_should_keep_single_press() {
+        // Given
+        let mut pending = PendingMessages::default();
+
+        // When
+        pending.push(key(Key::KEY_A, KeyboardEventType::KeyPressed));
+        pending.push(key(Key::KEY_A, KeyboardEventType::KeyHeld));
+        pending.push(key(Key::KEY_A, KeyboardEventType::KeyHeld));
+        pending.push(key(Key::KEY_A, KeyboardEventType::KeyReleased));
+
+        // Then
+        assert_eq!(
+            drain(&mut pending),
+            vec![
+                key(Key::KEY_A, KeyboardEventType::KeyPressed),
+                key(Key::KEY_A, KeyboardEventType::KeyReleased),
+            ]
+        );
+    }
+
+    #[test]
+    fn given_full_buffer_should_compact_to_net_key_state() {
+        // Given
+        let mut pending = PendingMessages::new(4);
+        pending.push(key(Key::KEY_LEFTSHIFT, KeyboardEventType::KeyReleased));
+        pending.push(key(Key::KEY_A, KeyboardEventType::KeyPressed));
+        pending.push(key(Key::KEY_A, KeyboardEventType::KeyReleased));
+        pending.push(key(Key::KEY_B, KeyboardEventType::KeyPressed));
+
+        // When
+        pending.push(motion(PointerAxis::Horizontal, 5));
+
+        // Then
+        assert_eq!(pending.dropped(), 0);
+        assert_eq!(
+            drain(&mut pending),
+            vec![
+                key(Key::KEY_LEFTSHIFT, KeyboardEventType::KeyReleased),
+                key(Key::KEY_B, KeyboardEventType::KeyPressed),
+                motion(PointerAxis::Horizontal, 5),
+            ]
+        );
+    }
+
+    #[test]
+    fn given_full_buffer_without_redundancy_should_drop_oldest() {
+        // Given
+        let mut pending = PendingMessages::new(2);
+        pending.push(key(Key::KEY_A, KeyboardEventType::KeyPressed));
+        pending.push(key(Key::KEY_B, KeyboardEventType::KeyPressed));
+
+        // When
+        pending.push(key(Key::KEY_C, KeyboardEventType::KeyPressed));
+
+        // Then
+        assert_eq!(pending.dropped(), 1);
+        assert_eq!(
+            drain(&mut pending),
+            vec![
+                key(Key::KEY_B, KeyboardEventType::KeyPressed),
+                key(Key::KEY_C, KeyboardEventType::KeyPressed),
+            ]
+        );
+    }
+
+    #[test]
+    fn given_full_buffer_should_keep_last_absolute_values() {
+        // Given
+        let position = |value| Message::InputEvent {
+            event: InputEvent::Tablet(TabletEvent::Position {
+                axis: PointerAxis::Horizontal,
+                value,
+            }),
+        };
+        let touch = || Message::InputEvent {
+            event: InputEvent::Mouse(MouseEvent::Button {
+                event_type: KeyboardEventType::KeyPressed,
+                button: Button::BTN_TOUCH,
+            }),
+        };
+        let mut pending = PendingMessages::new(3);
+        pending.push(position(1));
+        pending.push(touch());
+        pending.push(position(2));
+
+        // When
+        pending.push(position(3));
+        pending.push(motion(PointerAxis::Vertical, 5));
+
+        // Then
+        assert_eq!(pending.dropped(), 0);
+        assert_eq!(
+            drain(&mut pending),
+            vec![position(3), touch(), motion(PointerAxis::Vertical, 5)]
+        );
+    }
+}
@@ -1,10 +1,22 @@
+use std::{
+    collections::BTreeMap,
+    net::SocketAddr,
+    time::{Duration, Instant},
+};
+
 use crypto::Crypto;
-use network::{input_event::InputEventTransport, Message};
+use input_event::{GamepadCapabilities, GamepadEvent, GamepadId, GamepadInput, InputEvent};
+use network::{
+    input_event::InputEventTransport, transport::decrypt_and_deserialise_message, Message,
+};
 use thiserror::Error;
-use tokio::sync::broadcast;
+use tokio::sync::{mpsc::error::TrySendError, watch};
 use uuid::Uuid;
 
-use super::client::{Client, ClientConnectionError};
+use super::{
+    client::{Client, ClientConnectionError},
+    target_change::{Expired, DEFAULT_TARGET_CHANGE_RETRIES, DEFAULT_TARGET_CHANGE_TIMEOUT},
+};
 
 #[derive(Debug, Error)]
 pub enum StateHandlerError {
@@ -13,17 +25,24 @@ pub enum StateHandlerError {
     #[error("Client not found")]
     NotFound,
     #[error("Grab request channel send error: {0}")]
-    GrabSendError(#[from] tokio::sync::broadcast::error::SendError<bool>),
-    #[error("Grab request channel send error: {0}")]
     MessageSendError(#[from] tokio::sync::mpsc::error::SendError<Message>),
     #[error("Client error: {0}")]
     ClientError(#[from] ClientConnectionError),
 }
 
+/// How long a disconnected client keeps its place before it is forgotten.
+pub const DEFAULT_DISCONNECTED_GRACE_PERIOD: Duration = Duration::from_secs(10 * 60);
+
 pub struct StateResource<T: Crypto> {
+    // kept in cycle order; clients are referred to by id so entries can be removed
     clients: Vec<Client<T>>,
     pub clipboard_contents: Option<String>,
-    target_idx: Option<usize>,
+    target: Option<Uuid>,
+    /// What each controller plugged into the server reports, to tell targets about it.
+    gamepads: BTreeMap<GamepadId, GamepadCapabilities>,
+    disconnected_grace_period: Duration,
+    target_change_timeout: Duration,
+    target_change_retries: u32,
 }
 
 impl<T: Crypto> Default for StateResource<T> {
@@ -31,48 +50,112 @@ impl<T: Crypto> Default for StateResource<T> {
         StateResource {
             clients: Vec::new(),
             clipboard_contents: None,
-            target_idx: None,
+            target: None,
+            gamepads: BTreeMap::new(),
+            disconnected_grace_period: DEFAULT_DISCONNECTED_GRACE_PERIOD,
+            target_change_timeout: DEFAULT_TARGET_CHANGE_TIMEOUT,
+            target_change_retries: DEFAULT_TARGET_CHANGE_RETRIES,
         }
     }
 }
 
 impl<T: Crypto> StateResource<T> {
-    pub fn add_client(&mut self, client: Client<T>) -> usize {
-        self.clients.push(client);
-        self.clients.len() - 1
+    pub fn with_disconnected_grace_period(mut self, grace_period: Duration) -> Self {
+        self.disconnected_grace_period = grace_period;
+        self
+    }
+
+    /// Sets how long to wait for a client to answer a target change notification, and how
+    /// many times to resend it before sending input to the client regardless.
+    pub fn with_target_change_timeout(mut self, timeout: Duration, retries: u32) -> Self {
+        self.target_change_timeout = timeout;
+        self.target_change_retries = retries;
+        self
+    }
+
+    /// Adds a newly connected client, or reuses the entry of a client resuming its session so
+    /// that its place in the cycle order is kept.
+    pub fn add_client(&mut self, client: Client<T>) -> Uuid {
+        let id = client.id;
+        if let Some(existing) = self.get_client_by_id_mut(id) {
+            println!("Client {} resumed its session", id);
+            existing.resume(client);
+        } else {
+            self.clients.push(client);
+        }
+        id
+    }
+
+    /// Keeps track of the controllers plugged into the server, and returns the `Connected` that
+    /// the target needs ahead of `message` if it has not been told about that controller yet,
+    /// such as when it became the target after the controller was plugged in.
+    pub fn announce_gamepad(&mut self, message: &Message) -> Option<Message> {
+        let Message::InputEvent {
+            event: InputEvent::Gamepad(event),
+        } = message
+        else {
+            return None;
+        };
+        match &event.input {
+            GamepadInput::Connected(capabilities) => {
+                self.gamepads.insert(event.pad, capabilities.clone());
+            }
+            GamepadInput::Disconnected => {
+                self.gamepads.remove(&event.pad);
+                for client in self.clients.iter_mut() {
+                    client.announced_gamepads.remove(&event.pad);
+                }
+                return None;
+            }
+            _ => {}
+        }
+        let target = self.target?;
+        let client = self.clients.iter_mut().find(|client| client.id == target)?;
+        let announced = !client.announced_gamepads.insert(event.pad);
+        if announced || matches!(event.input, GamepadInput::Connected(_)) {
+            return None;
+        }
+        let capabilities = self.gamepads.get(&event.pad)?.clone();
+        Some(Message::InputEvent {
+            event: InputEvent::Gamepad(GamepadEvent {
+                pad: event.pad,
+                input: GamepadInput::Connected(capabilities),
+            }),
+        })
+    }
+
+    /// Removes a client entirely. The caller is responsible for moving the target away first.
+    pub fn remove_client(&mut self, id: Uuid) -> Option<Client<T>> {
+        let position = self.position(id)?;
+        if self.target == Some(id) {
+            self.target = None;
+        }
+        Some(self.clients.remove(position))
     }
 
     pub fn get_target(&self) -> Option<&Client<T>> {
-        self.target_idx.map(|idx| &self.clients[idx])
+        self.target.and_then(|id| self.get_client_by_id(id))
     }
 
     pub fn get_target_mut(&mut self) -> Option<&mut Client<T>> {
-        self.target_idx.map(|idx| &mut self.clients[idx])
+        self.target.and_then(|id| self.get_client_by_id_mut(id))
+    }
+
+    pub fn get_target_id(&self) -> Option<Uuid> {
+        self.target
     }
 
+    /// The target's position in the cycle order.
     pub fn get_target_idx(&self) -> Option<usize> {
-        self.target_idx
+        self.target.and_then(|id| self.position(id))
     }
 
     pub fn get_num_clients(&self) -> usize {
         self.clients.len()
     }
 
-    pub fn get_client(&self, client_idx: usize) -> Result<&Client<T>, StateHandlerError> {
-        if client_idx >= self.clients.len() {
-            return Err(StateHandlerError::NotFound);
-        }
-        Ok(&self.clients[client_idx])
-    }
-
-    pub fn get_client_mut(
-        &mut self,
-        client_idx: usize,
-    ) -> Result<&mut Client<T>, StateHandlerError> {
-        if client_idx >= self.clients.len() {
-            return Err(StateHandlerError::NotFound);
-        }
-        Ok(&mut self.clients[client_idx])
+    pub fn clients(&self) -> impl Iterator<Item = &Client<T>> {
+        self.clients.iter()
     }
 
     pub fn get_client_by_id(&self, id: Uuid) -> Option<&Client<T>> {
@@ -83,59 +166,62 @@ impl<T: Crypto> StateResource<T> {
         self.clients.iter_mut().find(|client| client.id == id)
     }
 
-    pub async fn update_client(
-        &mut self,
-        client_idx: usize,
-        new_client: Client<T>,
-    ) -> Result<(), StateHandlerError> {
-        if client_idx >= self.clients.len() {
-            return Err(StateHandlerError::NotFound);
-        }
-        self.clients[client_idx] = new_client;
-        Ok(())
+    fn position(&self, id: Uuid) -> Option<usize> {
+        self.clients.iter().position(|client| client.id == id)
     }
 
-    pub fn set_target(&mut self, idx: Option<usize>) -> Result<(), StateHandlerError> {
-        if let Some(i) = idx {
-            if i >= self.clients.len() {
+    pub fn set_target(&mut self, id: Option<Uuid>) -> Result<(), StateHandlerError> {
+        if let Some(id) = id {
+            if self.position(id).is_none() {
                 return Err(StateHandlerError::NotFound);
             }
         }
-        self.target_idx = idx;
-        Ok(())
-    }
-
-    pub async fn mark_disconnected(&mut self, client_idx: usize) -> Result<(), StateHandlerError> {
-        if client_idx >= self.clients.len() {
-            return Err(StateHandlerError::NotFound);
-        }
-        self.clients[client_idx].connected = false;
+        self.target = id;
         Ok(())
     }
 
     pub async fn mark_disconnected_by_id(&mut self, id: Uuid) -> Result<(), StateHandlerError> {
         if let Some(client) = self.get_client_by_id_mut(id) {
-            client.connected = false;
+            client.mark_disconnected(Instant::now());
             Ok(())
         } else {
             Err(StateHandlerError::NotFound)
         }
     }
+
+    /// Removes clients that have been disconnected for longer than the grace period and
+    /// returns their ids. The current target is never removed.
+    pub fn collect_disconnected_clients(&mut self, now: Instant) -> Vec<Uuid> {
+        let grace_period = self.disconnected_grace_period;
+        let target = self.target;
+        let mut removed = Vec::new();
+        self.clients.retain(|client| {
+            let expired = Some(client.id) != target
+                && client
+                    .disconnected_at
+                    .is_some_and(|at| now.duration_since(at) >= grace_period);
+            if expired {
+                removed.push(client.id);
+            }
+            !expired
+        });
+        removed
+    }
 }
 
 // TODO: maybe make a trait for this
-impl<T: Crypto + Clone> StateResource<T> {
+impl<T: Crypto + Clone + 'static> StateResource<T> {
     pub async fn change_target(
         &mut self,
-        new_idx: Option<usize>,
-        grab_request_sender: &mut broadcast::Sender<bool>,
+        new_target: Option<Uuid>,
+        grab_request_sender: &mut watch::Sender<bool>,
     ) -> Result<(), StateHandlerError> {
-        println!("Changing target index to {:?}", new_idx);
+        println!("Changing target to {:?}", new_target);
         let prev = self.get_target().is_none();
-        let prev_idx = self.get_target_idx();
-        self.set_target(new_idx)?;
-        if let Some(idx) = prev_idx {
-            match self.send_change_target_notification(idx).await {
+        let prev_target = self.get_target_id();
+        self.set_target(new_target)?;
+        if let Some(id) = prev_target {
+            match self.send_change_target_notification(id) {
                 Ok(()) | Err(StateHandlerError::ClientDisconnected) => {}
                 Err(err) => return Err(err),
             }
@@ -143,86 +229,370 @@ impl<T: Crypto + Clone> StateResource<T> {
         let curr = self.get_target().is_none();
         if prev && !curr {
             // should grab
-            grab_request_sender.send(true)?;
+            grab_request_sender.send_replace(true);
         }
         if !prev && curr {
             // should ungrab
-            grab_request_sender.send(false)?;
+            grab_request_sender.send_replace(false);
         }
         Ok(())
     }
 
     pub async fn cycle_target(
         &mut self,
-        grab_request_sender: &mut broadcast::Sender<bool>,
+        grab_request_sender: &mut watch::Sender<bool>,
     ) -> Result<(), StateHandlerError> {
         let len = self.get_num_clients();
         let prev_idx = self.get_target_idx().unwrap_or(len);
 
         let target_idx = (0..=len)
             .map(|i| (prev_idx + i + 1) % (len + 1))
-            .find(|&idx| {
-                idx == len
-                    || self
-                        .get_client(idx)
-                        .map(|client| client.connected)
-                        .unwrap_or(false)
-            })
-            .ok_or(StateHandlerError::NotFound)
-            .map(|idx| if idx == len { None } else { Some(idx) })?;
+            .find(|&idx| idx == len || self.clients[idx].connected)
+            .ok_or(StateHandlerError::NotFound)?;
+        let target = self.clients.get(target_idx).map(|client| client.id);
 
-        self.change_target(target_idx, grab_request_sender).await
+        self.change_target(target, grab_request_sender).await
     }
 
-    async fn send_change_target_notification(
-        &mut self,
-        idx: usize,
-    ) -> Result<(), StateHandlerError> {
-        let client = self.get_client_mut(idx)?;
+    /// Tells a client it is no longer the target. A client whose queue is full is not waited
+    /// for: the notification stays unanswered, so it is resent at its deadline and input resumes
+    /// without it once the retries run out.
+    fn send_change_target_notification(&mut self, id: Uuid) -> Result<(), StateHandlerError> {
+        let timeout = self.target_change_timeout;
+        let client = self
+            .get_client_by_id_mut(id)
+            .ok_or(StateHandlerError::NotFound)?;
 
         if !client.connected {
             return Err(StateHandlerError::ClientDisconnected);
         }
 
-        println!("Sending target change notif to client at index {}", idx);
-        client.pending_target_change_responses += 1;
-        client
+        println!("Sending target change notif to client {}", id);
+        let seq = client.target_change_acks.notify(Instant::now(), timeout);
+        match client
             .message_sender
-            .send(Message::TargetChangeNotification)
-            .await?;
-        Ok(())
+            .try_send(Message::TargetChangeNotification { seq })
+        {
+            Ok(()) => Ok(()),
+            Err(TrySendError::Full(_)) => {
+                eprintln!(
+                    "Could not send target change {} to client {}: queue is full",
+                    seq, id
+                );
+                Ok(())
+            }
+            // the connection is gone, and its disconnect is on the way
+            Err(TrySendError::Closed(_)) => {
+                client.target_change_acks.acknowledge(seq);
+                Err(StateHandlerError::ClientDisconnected)
+            }
+        }
     }
 
     pub async fn handle_change_target_response(
         &mut self,
         id: Uuid,
+        seq: u32,
         transport: &mut InputEventTransport,
     ) -> Result<(), StateHandlerError> {
         let client = self
             .get_client_by_id_mut(id)
             .ok_or(StateHandlerError::NotFound)?;
-        client.pending_target_change_responses -= 1;
-        if client.pending_target_change_responses == 0 {
-            client.flush_pending_messages(transport).await?;
+        if !client.target_change_acks.acknowledge(seq) {
+            println!(
+                "Ignoring stale target change response {} from client {}",
+                seq, id
+            );
+            return Ok(());
+        }
+        if client.target_change_acks.is_empty() && client.is_reachable() {
+            client.flush_pending_messages(transport)?;
+        }
+        Ok(())
+    }
+
+    /// Resends target change notifications that were not answered in time, and stops waiting
+    /// for those that have run out of retries so that their client receives input again.
+    pub fn expire_target_change_acks(&mut self, now: Instant, transport: &mut InputEventTransport) {
+        let (timeout, retries) = (self.target_change_timeout, self.target_change_retries);
+        for client in self.clients.iter_mut().filter(|client| client.connected) {
+            let expired = client.target_change_acks.expire(now, timeout, retries);
+            if expired.is_empty() {
+                continue;
+            }
+            for expired in expired {
+                match expired {
+                    Expired::Retry { seq, attempt } => {
+                        eprintln!(
+                            "Client {} did not answer target change {} in {:?}, resending (attempt {}/{})",
+                            client.id, seq, timeout, attempt, retries
+                        );
+                        // a client that cannot take the resend is tried again at its next deadline
+                        if let Err(err) = client
+                            .message_sender
+                            .try_send(Message::TargetChangeNotification { seq })
+                        {
+                            eprintln!(
+                                "Could not resend target change {} to client {}: {}",
+                                seq, client.id, err
+                            );
+                        }
+                    }
+                    Expired::ForceRelease { seq } => {
+                        eprintln!(
+                            "Client {} never answered target change {}, resuming input without it",
+                            client.id, seq
+                        );
+                    }
+                }
+            }
+            if client.target_change_acks.is_empty() && client.is_reachable() {
+                if let Err(err) = client.flush_pending_messages(transport) {
+                    eprintln!(
+                        "Could not flush pending input to client {}: {}",
+                        client.id, err
+                    );
+                }
+            }
+        }
+    }
+
+    /// Unplugs a controller that has gone away from the earlier targets that were told about it,
+    /// since only the target is sent the input that unplugs it.
+    pub fn unplug_gamepad_from_others(&mut self, pad: GamepadId, transport: &InputEventTransport) {
+        let target = self.target;
+        for client in self
+            .clients
+            .iter_mut()
+            .filter(|client| Some(client.id) != target && client.announced_gamepads.contains(&pad))
+        {
+            let message = Message::InputEvent {
+                event: InputEvent::Gamepad(GamepadEvent {
+                    pad,
+                    input: GamepadInput::Disconnected,
+                }),
+            };
+            if !client.can_receive() {
+                client.buffer_message(message);
+                continue;
+            }
+            if let Err(err) = client.send_input_message(message, transport) {
+                eprintln!(
+                    "Could not unplug controller {} from client {}: {}",
+                    pad, client.id, err
+                );
+            }
+        }
+    }
+
+    /// Handles a datagram received on the input event socket.
+    ///
+    /// The sending client is identified by the key that successfully authenticates the datagram,
+    /// and its source address becomes the address that input events are sent to.
+    pub async fn handle_udp_datagram(
+        &mut self,
+        bytes: &[u8],
+        addr: SocketAddr,
+        transport: &mut InputEventTransport,
+    ) -> Result<(), StateHandlerError> {
+        let Some((client, message)) = self
+            .clients
+            .iter_mut()
+            .filter(|client| client.connected)
+            .find_map(|client| {
+                decrypt_and_deserialise_message(bytes, &Some(client.key.clone()))
+                    .ok()
+                    .map(|message| (client, message))
+            })
+        else {
+            eprintln!("Dropping unauthenticated datagram from {}", addr);
+            transport.record_corrupt();
+            return Ok(());
+        };
+
+        match message {
+            Message::UdpHello { seq } => {
+                if client.learn_address(seq, addr) && client.target_change_acks.is_empty() {
+                    client.flush_pending_messages(transport)?;
+                }
+            }
+            Message::Ping { seq, sent_at } => {
+                let key = Some(client.key.clone());
+                transport
+                    .send_message_to(Message::Pong { seq, sent_at }, addr, key)
+                    .await
+                    .map_err(ClientConnectionError::from)?;
+            }
+            Message::Pong { seq, sent_at } => {
+                client.input_link.record_pong(seq, sent_at);
+            }
+            message => {
+                eprintln!("Unexpected datagram from client {}: {}", client.id, message);
+            }
+        }
+        Ok(())
+    }
+
+    /// Answers a ping received on a client's control connection. A client that cannot take the
+    /// pong goes without it, so that one stalled client does not hold up the rest.
+    pub fn handle_ping(
+        &mut self,
+        id: Uuid,
+        seq: u32,
+        sent_at: u64,
+    ) -> Result<(), StateHandlerError> {
+        let client = self
+            .get_client_by_id(id)
+            .ok_or(StateHandlerError::NotFound)?;
+        if let Err(err) = client
+            .message_sender
+            .try_send(Message::Pong { seq, sent_at })
+        {
+            eprintln!("Could not answer ping from client {}: {}", id, err);
         }
         Ok(())
     }
 
+    pub fn handle_pong(
+        &mut self,
+        id: Uuid,
+        seq: u32,
+        sent_at: u64,
+    ) -> Result<(), StateHandlerError> {
+        let client = self
+            .get_client_by_id_mut(id)
+            .ok_or(StateHandlerError::NotFound)?;
+        client.control_link.record_pong(seq, sent_at);
+        Ok(())
+    }
+
+    /// Pings every connected client over its control connection, and over UDP once its input
+    /// address is known. A client whose ping cannot be sent is skipped until the next round, so
+    /// one stalled client does not hold up the rest.
+    pub async fn send_pings(&mut self, transport: &mut InputEventTransport) {
+        for client in self.clients.iter_mut().filter(|client| client.connected) {
+            let ping = client.control_link.ping();
+            if let Err(err) = client.message_sender.try_send(ping) {
+                eprintln!("Could not ping client {}: {}", client.id, err);
+            }
+
+            if let (false, Some(address)) = (client.tunnelled, client.address) {
+                let ping = client.input_link.ping();
+                if let Err(err) = transport
+                    .send_message_to(ping, address, Some(client.key.clone()))
+                    .await
+                {
+                    eprintln!("Could not ping client {} over UDP: {}", client.id, err);
+                }
+            }
+        }
+    }
+
+    pub fn log_link_quality(&self) {
+        for client in self.clients.iter().filter(|client| client.connected) {
+            println!(
+                "Link quality for client {}: {}",
+                client.id,
+                client.link_quality()
+            );
+            if client.dropped_input() > 0 {
+                println!(
+                    "Dropped {} input events for client {} under backpressure",
+                    client.dropped_input(),
+                    client.id
+                );
+            }
+        }
+    }
+
     pub async fn disconnect_client(
         &mut self,
         id: Uuid,
-        grab_request_sender: &mut broadcast::Sender<bool>,
+        grab_request_sender: &mut watch::Sender<bool>,
     ) -> Result<(), StateHandlerError> {
+        let Some(client) = self.get_client_by_id_mut(id) else {
+            // the client may already have been forgotten
+            println!("Ignoring disconnect of unknown client {}", id);
+            return Ok(());
+        };
         println!("Client {} disconnected", id);
-        self.get_client_by_id_mut(id)
-            .expect("Client with given id should exist")
-            .connected = false;
+        client.mark_disconnected(Instant::now());
         // swap target to server if target just disconnected
         if !self.get_target().map(|tgt| tgt.connected).unwrap_or(false) {
             self.change_target(None, grab_request_sender).await?;
         }
         Ok(())
     }
+
+    /// Disconnects a client whose connection numbered `generation` was lost, unless the client
+    /// has since resumed its session over a newer connection.
+    pub async fn disconnect_connection(
+        &mut self,
+        id: Uuid,
+        generation: u64,
+        grab_request_sender: &mut watch::Sender<bool>,
+    ) -> Result<(), StateHandlerError> {
+        if let Some(client) = self.get_client_by_id(id) {
+            if client.generation != generation {
+                println!(
+                    "Ignoring disconnect of a replaced connection to client {}",
+                    id
+                );
+                return Ok(());
+            }
+        }
+        self.disconnect_client(id, grab_request_sender).await
+    }
+
+    /// Forgets a client, closing its connection if it is still connected. A forgotten client
+    /// that reconnects is treated as new and goes to the end of the cycle order.
+    pub async fn forget_client(
+        &mut self,
+        id: Uuid,
+        grab_request_sender: &mut watch::Sender<bool>,
+    ) -> Result<(), StateHandlerError> {
+        if self.position(id).is_none() {
+            return Err(StateHandlerError::NotFound);
+        }
+        if self.target == Some(id) {
+            self.change_target(None, grab_request_sender).await?;
+        }
+        self.remove_client(id);
+        println!("Forgot client {}", id);
+        Ok(())
+    }
+
+    pub fn log_clients(&self) {
+        if self.clients.is_empty() {
+            println!("No clients");
+        }
+        for (idx, client) in self.clients.iter().enumerate() {
+            let target = if self.target == Some(client.id) {
+                " (target)"
+            } else {
+                ""
+            };
+            let status = if client.connected {
+                "connected"
+            } else {
+                "disconnected"
+            };
+            println!("{}: {} {}{}", idx, client.id, status, target);
+        }
+    }
+
+    /// Warns every connected client that the server is going away, so they release held keys
+    /// rather than waiting for their peer timeout.
+    pub async fn notify_shutdown(&mut self, reason: &str) {
+        for client in self.clients.iter().filter(|client| client.connected) {
+            let message = Message::ServerShuttingDown {
+                reason: reason.to_string(),
+            };
+            if let Err(err) = client.message_sender.send(message).await {
+                eprintln!("Could not notify client {} of shutdown: {}", client.id, err);
+            }
+        }
+    }
 }
 
 #[cfg(test)]
@@ -242,15 +612,16 @@ pub mod test {
             client_channels.into_iter().for_each(|channel| {
                 state.add_client(test_client_fixture(channel));
             });
-            state.target_idx = target_idx;
+            state.target = target_idx.map(|idx| state.clients[idx].id);
             state
         }
     }
 
+    #[allow(clippy::unnecessary_literal_unwrap)]
     mod change_target {
         use futures::FutureExt;
         use network::Message;
-        use tokio::sync::{broadcast, mpsc};
+        use tokio::sync::{mpsc, watch};
 
         use crate::actors::state::resource::test::fixtures::test_state_fixture;
 
@@ -261,12 +632,15 @@ pub mod test {
             let new_target_idx = Some(1);
             let (client_message_senders, _client_message_receivers): (Vec<_>, Vec<_>) =
                 (0..3).map(|_| mpsc::channel(10)).unzip();
-            let (mut grab_request_sender, _grab_request_receiver) = broadcast::channel(10);
+            let (mut grab_request_sender, _grab_request_receiver) = watch::channel(false);
             let mut state = test_state_fixture(client_message_senders, old_target_idx);
 
             // When
             let response = state
-                .change_target(new_target_idx, &mut grab_request_sender)
+                .change_target(
+                    new_target_idx.map(|idx| state.clients[idx].id),
+                    &mut grab_request_sender,
+                )
                 .await;
             tokio::task::yield_now().await;
 
@@ -282,12 +656,15 @@ pub mod test {
             let new_target_idx = Some(1);
             let (client_message_senders, mut client_message_receivers): (Vec<_>, Vec<_>) =
                 (0..3).map(|_| mpsc::channel(10)).unzip();
-            let (mut grab_request_sender, mut grab_request_receiver) = broadcast::channel(10);
+            let (mut grab_request_sender, mut grab_request_receiver) = watch::channel(false);
             let mut state = test_state_fixture(client_message_senders, old_target_idx);
 
             // When
             let response = state
-                .change_target(new_target_idx, &mut grab_request_sender)
+                .change_target(
+                    new_target_idx.map(|idx| state.clients[idx].id),
+                    &mut grab_request_sender,
+                )
                 .await;
             tokio::task::yield_now().await;
 
@@ -298,12 +675,8 @@ pub mod test {
                 let msg = rx.recv().now_or_never();
                 assert_eq!(msg, None);
             });
-            let grab_request = grab_request_receiver
-                .recv()
-                .now_or_never()
-                .expect("No grab request received")
-                .expect("Grab request channel was closed");
-            assert!(grab_request)
+            assert!(grab_request_receiver.has_changed().unwrap());
+            assert!(*grab_request_receiver.borrow_and_update());
         }
 
         #[tokio::test]
@@ -314,12 +687,15 @@ pub mod test {
             let new_target_idx = Some(2);
             let (client_message_senders, mut client_message_receivers): (Vec<_>, Vec<_>) =
                 (0..3).map(|_| mpsc::channel(10)).unzip();
-            let (mut grab_request_sender, mut grab_request_receiver) = broadcast::channel(10);
+            let (mut grab_request_sender, grab_request_receiver) = watch::channel(false);
             let mut state = test_state_fixture(client_message_senders, old_target_idx);
 
             // When
             let response = state
-                .change_target(new_target_idx, &mut grab_request_sender)
+                .change_target(
+                    new_target_idx.map(|idx| state.clients[idx].id),
+                    &mut grab_request_sender,
+                )
                 .await;
             tokio::task::yield_now().await;
 
@@ -331,9 +707,8 @@ pub mod test {
                 .now_or_never()
                 .expect("No client message received")
                 .expect("Client message channel was closed");
-            assert_eq!(client_notif, Message::TargetChangeNotification);
-            let grab_request = grab_request_receiver.recv().now_or_never();
-            assert_eq!(grab_request, None)
+            assert_eq!(client_notif, Message::TargetChangeNotification { seq: 0 });
+            assert!(!grab_request_receiver.has_changed().unwrap());
         }
 
         #[tokio::test]
@@ -341,15 +716,18 @@ pub mod test {
         ) {
             // Given
             let old_target_idx = Some(1);
-            let new_target_idx = None;
+            let new_target_idx: Option<usize> = None;
             let (client_message_senders, mut client_message_receivers): (Vec<_>, Vec<_>) =
                 (0..3).map(|_| mpsc::channel(10)).unzip();
-            let (mut grab_request_sender, mut grab_request_receiver) = broadcast::channel(10);
+            let (mut grab_request_sender, mut grab_request_receiver) = watch::channel(false);
             let mut state = test_state_fixture(client_message_senders, old_target_idx);
 
             // When
             let response = state
-                .change_target(new_target_idx, &mut grab_request_sender)
+                .change_target(
+                    new_target_idx.map(|idx| state.clients[idx].id),
+                    &mut grab_request_sender,
+                )
                 .await;
             tokio::task::yield_now().await;
 
@@ -361,19 +739,15 @@ pub mod test {
                 .now_or_never()
                 .expect("No client message received")
                 .expect("Client message channel was closed");
-            assert_eq!(client_notif, Message::TargetChangeNotification);
-            let grab_request = grab_request_receiver
-                .recv()
-                .now_or_never()
-                .expect("No grab request received")
-                .expect("Grab request channel was closed");
-            assert!(!grab_request);
+            assert_eq!(client_notif, Message::TargetChangeNotification { seq: 0 });
+            assert!(grab_request_receiver.has_changed().unwrap());
+            assert!(!*grab_request_receiver.borrow_and_update());
         }
     }
 
     mod cycle_target {
         use network::Message;
-        use tokio::sync::{broadcast, mpsc};
+        use tokio::sync::{mpsc, watch};
 
         use crate::actors::state::resource::test::fixtures::test_state_fixture;
 
@@ -382,7 +756,7 @@ pub mod test {
             // Given
             let (client_message_senders, _client_message_receivers): (Vec<_>, Vec<_>) =
                 (0..3).map(|_| mpsc::channel(10)).unzip();
-            let (mut grab_request_sender, _grab_request_receiver) = broadcast::channel(10);
+            let (mut grab_request_sender, _grab_request_receiver) = watch::channel(false);
             let target_idx = None;
             let mut state = test_state_fixture(client_message_senders, target_idx);
 
@@ -402,7 +776,7 @@ pub mod test {
             // Given
             let (client_message_senders, _client_message_receivers): (Vec<_>, Vec<_>) =
                 (0..3).map(|_| mpsc::channel(10)).unzip();
-            let (mut grab_request_sender, _grab_request_receiver) = broadcast::channel(10);
+            let (mut grab_request_sender, _grab_request_receiver) = watch::channel(false);
             let target_idx = Some(0);
             let mut state = test_state_fixture(client_message_senders, target_idx);
 
@@ -422,7 +796,7 @@ pub mod test {
             // Given
             let (client_message_senders, _client_message_receivers): (Vec<_>, Vec<_>) =
                 (0..3).map(|_| mpsc::channel(10)).unzip();
-            let (mut grab_request_sender, _grab_request_receiver) = broadcast::channel(10);
+            let (mut grab_request_sender, _grab_request_receiver) = watch::channel(false);
             let target_idx = Some(2);
             let mut state = test_state_fixture(client_message_senders, target_idx);
 
@@ -442,7 +816,7 @@ pub mod test {
             // Given
             let (client_message_senders, _client_message_receivers): (Vec<_>, Vec<Message>) =
                 (Vec::new(), Vec::new());
-            let (mut grab_request_sender, _grab_request_receiver) = broadcast::channel(10);
+            let (mut grab_request_sender, _grab_request_receiver) = watch::channel(false);
             let target_idx = None;
             let mut state = test_state_fixture(client_message_senders, target_idx);
 
@@ -458,7 +832,496 @@ pub mod test {
         }
     }
 
-    mod send_change_target_notification {}
+    mod send_change_target_notification {
+        use network::Message;
+        use tokio::sync::{mpsc, watch};
+
+        use crate::actors::state::resource::{
+            test::fixtures::test_state_fixture, StateHandlerError,
+        };
+
+        #[tokio::test]
+        async fn given_closed_connection_should_report_client_disconnected() {
+            // Given
+            let (sender, receiver) = mpsc::channel(10);
+            let mut state = test_state_fixture(vec![sender], Some(0));
+            let id = state.clients[0].id;
+            drop(receiver);
+
+            // When
+            let response = state.send_change_target_notification(id);
+
+            // Then
+            assert!(matches!(
+                response,
+                Err(StateHandlerError::ClientDisconnected)
+            ));
+            assert!(state.clients[0].target_change_acks.is_empty());
+        }
+
+        #[tokio::test]
+        async fn given_full_queue_should_leave_notification_waiting_for_retry() {
+            // Given
+            let (sender, _receiver) = mpsc::channel(1);
+            sender.try_send(Message::Heartbeat).unwrap();
+            let mut state = test_state_fixture(vec![sender], Some(0));
+            let id = state.clients[0].id;
+
+            // When
+            let response = state.send_change_target_notification(id);
+
+            // Then
+            assert!(response.is_ok());
+            assert!(!state.clients[0].target_change_acks.is_empty());
+        }
+
+        #[tokio::test]
+        async fn given_closed_old_target_should_still_change_target() {
+            // Given
+            let (closed_sender, closed_receiver) = mpsc::channel(10);
+            let (sender, _receiver) = mpsc::channel(10);
+            let mut state = test_state_fixture(vec![closed_sender, sender], Some(0));
+            let (mut grab_request_sender, _grab_request_receiver) = watch::channel(true);
+            drop(closed_receiver);
+
+            // When
+            let response = state.cycle_target(&mut grab_request_sender).await;
+
+            // Then
+            assert!(response.is_ok());
+            assert_eq!(state.get_target_idx(), Some(1));
+        }
+    }
+
+    mod handle_change_target_response {
+        use std::time::{Duration, Instant};
+
+        use network::{input_event::InputEventTransport, Message};
+        use tokio::{net::UdpSocket, sync::mpsc};
+
+        use crate::actors::state::resource::test::fixtures::test_state_fixture;
+
+        #[tokio::test]
+        async fn given_stale_or_duplicate_response_should_keep_waiting() {
+            // Given
+            let (sender, _receiver) = mpsc::channel(10);
+            let mut state = test_state_fixture(vec![sender], None);
+            let id = state.clients[0].id;
+            let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+            let mut transport = InputEventTransport::new(socket);
+            state.send_change_target_notification(id).unwrap();
+            state.send_change_target_notification(id).unwrap();
+
+            // When
+            state
+                .handle_change_target_response(id, 0, &mut transport)
+                .await
+                .unwrap();
+            state
+                .handle_change_target_response(id, 0, &mut transport)
+                .await
+                .unwrap();
+            let waiting = !state.clients[0].can_receive();
+            state
+                .handle_change_target_response(id, 1, &mut transport)
+                .await
+                .unwrap();
+
+            // Then
+            assert!(waiting);
+            assert!(state.clients[0].can_receive());
+        }
+
+        #[tokio::test]
+        async fn given_unanswered_notification_should_retry_then_force_release() {
+            // Given
+            let (sender, mut receiver) = mpsc::channel(10);
+            let timeout = Duration::from_secs(1);
+            let mut state =
+                test_state_fixture(vec![sender], None).with_target_change_timeout(timeout, 1);
+            let id = state.clients[0].id;
+            let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+            let mut transport = InputEventTransport::new(socket);
+            state.send_change_target_notification(id).unwrap();
+            receiver.recv().await.unwrap();
+            let now = Instant::now();
+
+            // When
+            state.expire_target_change_acks(now + timeout, &mut transport);
+            let resent = receiver.try_recv();
+            state.expire_target_change_acks(now + timeout * 2, &mut transport);
+
+            // Then
+            assert_eq!(resent, Ok(Message::TargetChangeNotification { seq: 0 }));
+            assert!(state.clients[0].can_receive());
+        }
+
+        #[tokio::test]
+        async fn given_client_with_full_queue_should_still_resend_to_the_others() {
+            // Given
+            let (full_sender, mut full_receiver) = mpsc::channel(1);
+            let (sender, mut receiver) = mpsc::channel(10);
+            let timeout = Duration::from_secs(1);
+            let mut state = test_state_fixture(vec![full_sender.clone(), sender], None)
+                .with_target_change_timeout(timeout, 1);
+            let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+            let mut transport = InputEventTransport::new(socket);
+            for id in [state.clients[0].id, state.clients[1].id] {
+                state.send_change_target_notification(id).unwrap();
+            }
+            full_receiver.recv().await.unwrap();
+            receiver.recv().await.unwrap();
+            full_sender.try_send(Message::Heartbeat).unwrap();
+
+            // When
+            state.expire_target_change_acks(Instant::now() + timeout, &mut transport);
+
+            // Then
+            assert_eq!(
+                receiver.try_recv(),
+                Ok(Message::TargetChangeNotification { seq: 0 })
+            );
+        }
+    }
+
+    mod link_quality {
+        use network::{input_event::InputEventTransport, link_stats::timestamp_micros, Message};
+        use tokio::{net::UdpSocket, sync::mpsc};
+
+        use crate::actors::state::resource::test::fixtures::test_state_fixture;
+
+        #[tokio::test]
+        async fn given_ping_should_echo_pong_to_client() {
+            // Given
+            let (sender, mut receiver) = mpsc::channel(10);
+            let mut state = test_state_fixture(vec![sender], None);
+            let id = state.clients[0].id;
+
+            // When
+            let response = state.handle_ping(id, 7, 1234);
+
+            // Then
+            assert!(response.is_ok());
+            assert_eq!(
+                receiver.recv().await,
+                Some(Message::Pong {
+                    seq: 7,
+                    sent_at: 1234
+                })
+            );
+        }
+
+        #[tokio::test]
+        async fn given_ping_from_client_with_full_queue_should_not_wait_for_room() {
+            // Given
+            let (sender, _receiver) = mpsc::channel(1);
+            sender.try_send(Message::Heartbeat).unwrap();
+            let mut state = test_state_fixture(vec![sender], None);
+            let id = state.clients[0].id;
+
+            // When
+            let response = state.handle_ping(id, 7, 1234);
+
+            // Then
+            assert!(response.is_ok());
+        }
+
+        #[tokio::test]
+        async fn given_client_with_full_queue_should_still_ping_the_others() {
+            // Given
+            let (full_sender, _full_receiver) = mpsc::channel(1);
+            full_sender.try_send(Message::Heartbeat).unwrap();
+            let (sender, mut receiver) = mpsc::channel(10);
+            let mut state = test_state_fixture(vec![full_sender, sender], None);
+            let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+            let mut transport = InputEventTransport::new(socket);
+
+            // When
+            state.send_pings(&mut transport).await;
+
+            // Then
+            assert!(matches!(
+                receiver.try_recv(),
+                Ok(Message::Ping { seq: 0, .. })
+            ));
+        }
+
+        #[tokio::test]
+        async fn given_pong_should_update_control_link_quality() {
+            // Given
+            let (sender, _receiver) = mpsc::channel(10);
+            let mut state = test_state_fixture(vec![sender], None);
+            let id = state.clients[0].id;
+            state.clients[0].control_link.ping();
+
+            // When
+            let response = state.handle_pong(id, 0, timestamp_micros());
+
+            // Then
+            assert!(response.is_ok());
+            let quality = state.clients[0].link_quality().control;
+            assert_eq!(quality.pongs_received, 1);
+            assert!(quality.smoothed_rtt.is_some());
+        }
+    }
+
+    mod notify_shutdown {
+        use futures::FutureExt;
+        use network::Message;
+        use tokio::sync::mpsc;
+
+        use crate::actors::state::resource::test::fixtures::test_state_fixture;
+
+        #[tokio::test]
+        async fn given_shutdown_should_notify_only_connected_clients() {
+            // Given
+            let (client_message_senders, mut client_message_receivers): (Vec<_>, Vec<_>) =
+                (0..2).map(|_| mpsc::channel(10)).unzip();
+            let mut state = test_state_fixture(client_message_senders, None);
+            state.clients[1].connected = false;
+
+            // When
+            state.notify_shutdown("Restarting").await;
+
+            // Then
+            assert_eq!(
+                client_message_receivers[0].recv().now_or_never(),
+                Some(Some(Message::ServerShuttingDown {
+                    reason: "Restarting".to_string()
+                }))
+            );
+            assert_eq!(client_message_receivers[1].recv().now_or_never(), None);
+        }
+    }
+
+    mod add_client {
+        use tokio::sync::mpsc;
+
+        use crate::actors::state::{
+            client::test::test_client_fixture, resource::test::fixtures::test_state_fixture,
+        };
+
+        #[tokio::test]
+        async fn given_resumed_session_should_reuse_client_slot() {
+            // Given
+            let (client_message_senders, _client_message_receivers): (Vec<_>, Vec<_>) =
+                (0..2).map(|_| mpsc::channel(10)).unzip();
+            let mut state = test_state_fixture(client_message_senders, None);
+            state.clients[0].connected = false;
+            let (sender, _receiver) = mpsc::channel(10);
+            let mut resumed = test_client_fixture(sender);
+            resumed.id = state.clients[0].id;
+
+            // When
+            let id = state.add_client(resumed);
+
+            // Then
+            assert_eq!(state.clients[0].id, id);
+            assert_eq!(state.get_num_clients(), 2);
+            assert!(state.clients[0].connected);
+        }
+    }
+
+    mod disconnect_connection {
+        use tokio::sync::{mpsc, watch};
+
+        use crate::actors::state::{
+            client::test::test_client_fixture, resource::test::fixtures::test_state_fixture,
+        };
+
+        #[tokio::test]
+        async fn given_resumed_session_should_ignore_disconnect_of_replaced_connection() {
+            // Given
+            let (sender, _receiver) = mpsc::channel(10);
+            let (mut grab_request_sender, _grab_request_receiver) = watch::channel(false);
+            let mut state = test_state_fixture(vec![sender], None);
+            let id = state.clients[0].id;
+            let replaced = state.clients[0].generation;
+            let (sender, _receiver) = mpsc::channel(10);
+            let mut resumed = test_client_fixture(sender);
+            resumed.id = id;
+            let current = resumed.generation;
+            state.add_client(resumed);
+
+            // When
+            let stale = state
+                .disconnect_connection(id, replaced, &mut grab_request_sender)
+                .await;
+            let connected_after_stale = state.clients[0].connected;
+            let response = state
+                .disconnect_connection(id, current, &mut grab_request_sender)
+                .await;
+
+            // Then
+            assert!(stale.is_ok());
+            assert!(connected_after_stale);
+            assert!(response.is_ok());
+            assert!(!state.clients[0].connected);
+        }
+    }
+
+    mod collect_disconnected_clients {
+        use std::time::{Duration, Instant};
+
+        use tokio::sync::mpsc;
+
+        use crate::actors::state::resource::test::fixtures::test_state_fixture;
+
+        #[tokio::test]
+        async fn given_grace_period_elapsed_should_remove_client_and_keep_target() {
+            // Given
+            let (client_message_senders, _client_message_receivers): (Vec<_>, Vec<_>) =
+                (0..3).map(|_| mpsc::channel(10)).unzip();
+            let mut state = test_state_fixture(client_message_senders, Some(2))
+                .with_disconnected_grace_period(Duration::from_secs(60));
+            let now = Instant::now();
+            let expired = state.clients[0].id;
+            let target = state.clients[2].id;
+            state.clients[0].mark_disconnected(now);
+            state.clients[1].mark_disconnected(now + Duration::from_secs(30));
+
+            // When
+            let removed = state.collect_disconnected_clients(now + Duration::from_secs(60));
+
+            // Then
+            assert_eq!(removed, vec![expired]);
+            assert_eq!(state.get_num_clients(), 2);
+            assert_eq!(state.get_target_id(), Some(target));
+            assert_eq!(state.get_target_idx(), Some(1));
+        }
+    }
+
+    mod forget_client {
+        use tokio::sync::{mpsc, watch};
 
-    mod handle_change_target_response {}
+        use crate::actors::state::resource::test::fixtures::test_state_fixture;
+
+        #[tokio::test]
+        async fn given_target_should_ungrab_and_remove_client() {
+            // Given
+            let (client_message_senders, _client_message_receivers): (Vec<_>, Vec<_>) =
+                (0..2).map(|_| mpsc::channel(10)).unzip();
+            let (mut grab_request_sender, mut grab_request_receiver) = watch::channel(false);
+            let mut state = test_state_fixture(client_message_senders, Some(0));
+            let id = state.clients[0].id;
+
+            // When
+            let response = state.forget_client(id, &mut grab_request_sender).await;
+
+            // Then
+            assert!(response.is_ok());
+            assert!(state.get_client_by_id(id).is_none());
+            assert_eq!(state.get_target_id(), None);
+            assert!(grab_request_receiver.has_changed().unwrap());
+            assert!(!*grab_request_receiver.borrow_and_update());
+        }
+    }
+
+    mod announce_gamepad {
+        use input_event::{
+            GamepadAxis, GamepadCapabilities, GamepadEvent, GamepadInput, InputEvent,
+        };
+        use network::{input_event::InputEventTransport, Message};
+        use tokio::{net::UdpSocket, sync::mpsc};
+
+        use crate::actors::state::resource::test::fixtures::test_state_fixture;
+
+        fn gamepad(input: GamepadInput) -> Message {
+            Message::InputEvent {
+                event: InputEvent::Gamepad(GamepadEvent { pad: 3, input }),
+            }
+        }
+
+        fn axis() -> Message {
+            gamepad(GamepadInput::Axis {
+                axis: GamepadAxis::ABS_X,
+                value: 0,
+            })
+        }
+
+        #[test]
+        fn given_new_target_should_announce_controller_once() {
+            // Given
+            let (client_message_senders, _client_message_receivers): (Vec<_>, Vec<_>) =
+                (0..2).map(|_| mpsc::channel(10)).unzip();
+            let mut state = test_state_fixture(client_message_senders, Some(0));
+            let capabilities = GamepadCapabilities::standard();
+            let plugged_in =
+                state.announce_gamepad(&gamepad(GamepadInput::Connected(capabilities.clone())));
+            state.target = Some(state.clients[1].id);
+
+            // When
+            let first = state.announce_gamepad(&axis());
+            let second = state.announce_gamepad(&axis());
+
+            // Then
+            assert!(plugged_in.is_none());
+            let Some(Message::InputEvent {
+                event:
+                    InputEvent::Gamepad(GamepadEvent {
+                        pad: 3,
+                        input: GamepadInput::Connected(announced),
+                    }),
+            }) = first
+            else {
+                panic!("Expected the controller to be announced");
+            };
+            assert_eq!(announced, capabilities);
+            assert!(second.is_none());
+        }
+
+        #[test]
+        fn given_unplugged_controller_should_not_announce_it() {
+            // Given
+            let (client_message_senders, _client_message_receivers): (Vec<_>, Vec<_>) =
+                (0..2).map(|_| mpsc::channel(10)).unzip();
+            let mut state = test_state_fixture(client_message_senders, None);
+            state.announce_gamepad(&gamepad(GamepadInput::Connected(
+                GamepadCapabilities::standard(),
+            )));
+            state.announce_gamepad(&gamepad(GamepadInput::Disconnected));
+            state.target = Some(state.clients[0].id);
+
+            // When
+            let announcement = state.announce_gamepad(&axis());
+
+            // Then
+            assert!(announcement.is_none());
+        }
+
+        #[tokio::test]
+        async fn given_unplugged_controller_should_unplug_it_from_earlier_targets() {
+            // Given
+            let (client_message_senders, mut client_message_receivers): (Vec<_>, Vec<_>) =
+                (0..2).map(|_| mpsc::channel(10)).unzip();
+            let mut state = test_state_fixture(client_message_senders, Some(0));
+            state
+                .clients
+                .iter_mut()
+                .for_each(|client| client.tunnelled = true);
+            let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+            let transport = InputEventTransport::new(socket);
+            state.announce_gamepad(&gamepad(GamepadInput::Connected(
+                GamepadCapabilities::standard(),
+            )));
+            state.announce_gamepad(&axis());
+            state.target = Some(state.clients[1].id);
+            state.announce_gamepad(&axis());
+
+            // When
+            state.unplug_gamepad_from_others(3, &transport);
+            state.announce_gamepad(&gamepad(GamepadInput::Disconnected));
+
+            // Then
+            assert_eq!(
+                client_message_receivers[0].recv().await,
+                Some(gamepad(GamepadInput::Disconnected))
+            );
+            assert!(client_message_receivers[1].try_recv().is_err());
+            assert!(state
+                .clients
+                .iter()
+                .all(|client| client.announced_gamepads.is_empty()));
+        }
+    }
 }
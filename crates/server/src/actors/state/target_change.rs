@@ -0,0 +1,160 @@
+//! Deadlines for target change notifications.
+//!
+//! Each notification carries a sequence number that the client echoes in its response, so a
+//! response can only settle the notification it answers. A late or duplicated response is
+//! ignored rather than settling a newer notification, which keeps the outstanding count exact.
+
+use std::time::{Duration, Instant};
+
+pub const DEFAULT_TARGET_CHANGE_TIMEOUT: Duration = Duration::from_secs(2);
+/// Times a notification is resent before the server stops waiting for its response.
+pub const DEFAULT_TARGET_CHANGE_RETRIES: u32 = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Outstanding {
+    seq: u32,
+    deadline: Instant,
+    retries: u32,
+}
+
+/// What to do about a notification whose deadline has passed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Expired {
+    /// Send the notification with this sequence number again.
+    Retry { seq: u32, attempt: u32 },
+    /// Give up on the notification with this sequence number and resume sending input.
+    ForceRelease { seq: u32 },
+}
+
+#[derive(Debug, Default)]
+pub struct TargetChangeAcks {
+    next_seq: u32,
+    outstanding: Vec<Outstanding>,
+}
+
+impl TargetChangeAcks {
+    pub fn new() -> Self {
+        TargetChangeAcks::default()
+    }
+
+    /// Records a notification about to be sent and returns its sequence number.
+    pub fn notify(&mut self, now: Instant, timeout: Duration) -> u32 {
+        let seq = self.next_seq;
+        self.next_seq = self.next_seq.wrapping_add(1);
+        self.outstanding.push(Outstanding {
+            seq,
+            deadline: now + timeout,
+            retries: 0,
+        });
+        seq
+    }
+
+    /// Settles the notification with sequence number `seq`. Returns false if it is not
+    /// outstanding, for instance because it was already answered or force released.
+    pub fn acknowledge(&mut self, seq: u32) -> bool {
+        let before = self.outstanding.len();
+        self.outstanding
+            .retain(|outstanding| outstanding.seq != seq);
+        self.outstanding.len() != before
+    }
+
+    /// Collects notifications whose deadline has passed. Those with retries left get a new
+    /// deadline, the rest stop being outstanding.
+    pub fn expire(&mut self, now: Instant, timeout: Duration, max_retries: u32) -> Vec<Expired> {
+        let mut expired = Vec::new();
+        self.outstanding.retain_mut(|outstanding| {
+            if now < outstanding.deadline {
+                return true;
+            }
+            if outstanding.retries < max_retries {
+                outstanding.retries += 1;
+                outstanding.deadline = now + timeout;
+                expired.push(Expired::Retry {
+                    seq: outstanding.seq,
+                    attempt: outstanding.retries,
+                });
+                true
+            } else {
+                expired.push(Expired::ForceRelease {
+                    seq: outstanding.seq,
+                });
+                false
+            }
+        });
+        expired
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.outstanding.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.outstanding.len()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::{Duration, Instant};
+
+    use super::{Expired, TargetChangeAcks};
+
+    const TIMEOUT: Duration = Duration::from_secs(1);
+
+    #[test]
+    fn given_response_should_settle_only_its_notification() {
+        // Given
+        let now = Instant::now();
+        let mut acks = TargetChangeAcks::new();
+        let first = acks.notify(now, TIMEOUT);
+        let second = acks.notify(now, TIMEOUT);
+
+        // When
+        let settled = acks.acknowledge(first);
+        let duplicate = acks.acknowledge(first);
+
+        // Then
+        assert!(settled);
+        assert!(!duplicate);
+        assert_eq!(acks.len(), 1);
+        assert!(acks.acknowledge(second));
+        assert!(acks.is_empty());
+    }
+
+    #[test]
+    fn given_missed_deadline_should_retry_then_force_release() {
+        // Given
+        let now = Instant::now();
+        let mut acks = TargetChangeAcks::new();
+        let seq = acks.notify(now, TIMEOUT);
+
+        // When
+        let early = acks.expire(now, TIMEOUT, 1);
+        let first = acks.expire(now + TIMEOUT, TIMEOUT, 1);
+        let second = acks.expire(now + TIMEOUT * 2, TIMEOUT, 1);
+
+        // Then
+        assert!(early.is_empty());
+        assert_eq!(first, vec![Expired::Retry { seq, attempt: 1 }]);
+        assert_eq!(second, vec![Expired::ForceRelease { seq }]);
+        assert!(acks.is_empty());
+    }
+
+    #[test]
+    fn given_late_response_after_force_release_should_be_ignored() {
+        // Given
+        let now = Instant::now();
+        let mut acks = TargetChangeAcks::new();
+        let released = acks.notify(now, TIMEOUT);
+        acks.expire(now + TIMEOUT, TIMEOUT, 0);
+        let current = acks.notify(now + TIMEOUT, TIMEOUT);
+
+        // When
+        let settled = acks.acknowledge(released);
+
+        // Then
+        assert!(!settled);
+        assert_eq!(acks.len(), 1);
+        assert!(acks.acknowledge(current));
+    }
+}
@@ -0,0 +1,102 @@
+//! Line based admin commands read from the server's standard input.
+
+use thiserror::Error;
+use tokio::{
+    io::{AsyncBufReadExt, BufReader},
+    sync::mpsc::Sender,
+};
+use tokio_util::sync::CancellationToken;
+use uuid::Uuid;
+
+use crate::{InternalMessage, ServerMessage};
+
+const USAGE: &str = "Commands: 'clients' lists clients, 'forget <client id>' forgets a client";
+
+#[derive(Debug, Error)]
+pub enum AdminCommandError {
+    #[error("Unknown command '{0}'")]
+    UnknownCommand(String),
+    #[error("Expected a client id")]
+    MissingClientId,
+    #[error("Invalid client id: {0}")]
+    InvalidClientId(#[from] uuid::Error),
+}
+
+pub fn parse_command(line: &str) -> Result<ServerMessage, AdminCommandError> {
+    let mut words = line.split_whitespace();
+    match words.next() {
+        Some("clients") => Ok(ServerMessage::ListClients),
+        Some("forget") => {
+            let id = words.next().ok_or(AdminCommandError::MissingClientId)?;
+            Ok(ServerMessage::ForgetClient {
+                id: Uuid::parse_str(id)?,
+            })
+        }
+        command => Err(AdminCommandError::UnknownCommand(
+            command.unwrap_or_default().to_string(),
+        )),
+    }
+}
+
+/// Forwards commands typed on standard input to the state actor until stdin closes.
+pub async fn admin_console(
+    message_sender: Sender<InternalMessage>,
+    cancellation_token: CancellationToken,
+) {
+    let mut lines = BufReader::new(tokio::io::stdin()).lines();
+    loop {
+        let line = tokio::select! {
+            line = lines.next_line() => line,
+            _ = cancellation_token.cancelled() => return,
+        };
+        let line = match line {
+            Ok(Some(line)) if line.trim().is_empty() => continue,
+            Ok(Some(line)) => line,
+            Ok(None) => return,
+            Err(err) => {
+                eprintln!("Could not read admin command: {}", err);
+                return;
+            }
+        };
+
+        match parse_command(&line) {
+            Ok(message) => {
+                let message = InternalMessage::LocalMessage { message };
+                if message_sender.send(message).await.is_err() {
+                    return;
+                }
+            }
+            Err(err) => eprintln!("{}. {}", err, USAGE),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use uuid::Uuid;
+
+    use crate::ServerMessage;
+
+    use super::{parse_command, AdminCommandError};
+
+    #[test]
+    fn given_forget_command_should_parse_client_id() {
+        // Given
+        let id = Uuid::new_v4();
+
+        // When
+        let response = parse_command(&format!("forget {}", id));
+
+        // Then
+        assert!(matches!(response, Ok(ServerMessage::ForgetClient { id: parsed }) if parsed == id));
+    }
+
+    #[test]
+    fn given_forget_without_id_should_reject() {
+        // When
+        let response = parse_command("forget");
+
+        // Then
+        assert!(matches!(response, Err(AdminCommandError::MissingClientId)));
+    }
+}
@@ -1,6 +1,10 @@
+use std::collections::HashMap;
+
 use evdev::{EvdevEnum, Key};
+use input_event::{InputEvent, KeyboardEvent, KeyboardEventType, MouseEvent};
 
-const NUM_KEYS: usize = 0x2e7 - 1;
+// every Linux key code, up to KEY_MAX
+const NUM_KEYS: usize = 0x300;
 
 // TODO: move to input-capture crate... should probably include as method in exposed trait
 pub struct KeyboardState {
@@ -23,6 +27,14 @@ impl KeyboardState {
     pub fn is_combination_pressed(&self, combination: Vec<Key>) -> bool {
         combination.into_iter().all(|key| self.is_key_pressed(key))
     }
+
+    pub fn pressed_keys(&self) -> impl Iterator<Item = Key> + '_ {
+        self.mapping
+            .iter()
+            .enumerate()
+            .filter(|(_, &pressed)| pressed)
+            .map(|(index, _)| Key::from_index(index))
+    }
 }
 
 impl Default for KeyboardState {
@@ -34,3 +46,134 @@ impl Default for KeyboardState {
 }
 
 pub const CYCLE_TARGET: [Key; 3] = [Key::KEY_LEFTCTRL, Key::KEY_LEFTSHIFT, Key::KEY_H];
+
+/// The keys and mouse buttons held on each of several devices. A key counts as pressed while any
+/// device holds it, so releasing it on one keyboard does not release it on another, and a
+/// combination can be spread across keyboards.
+#[derive(Default)]
+pub struct DevicesKeyboardState {
+    devices: HashMap<usize, KeyboardState>,
+}
+
+impl DevicesKeyboardState {
+    pub fn press_key(&mut self, device: usize, key: Key) {
+        self.devices.entry(device).or_default().press_key(key)
+    }
+
+    /// Releases `key` on `device` and returns true if no other device still holds it.
+    pub fn release_key(&mut self, device: usize, key: Key) -> bool {
+        if let Some(state) = self.devices.get_mut(&device) {
+            state.release_key(key)
+        }
+        !self.is_key_pressed(key)
+    }
+
+    pub fn is_key_pressed(&self, key: Key) -> bool {
+        self.devices.values().any(|state| state.is_key_pressed(key))
+    }
+
+    /// Drops a device that has gone away and returns the keys that were only held on it, which
+    /// are no longer pressed.
+    pub fn forget_device(&mut self, device: usize) -> Vec<Key> {
+        let Some(state) = self.devices.remove(&device) else {
+            return Vec::new();
+        };
+        state
+            .pressed_keys()
+            .filter(|&key| !self.is_key_pressed(key))
+            .collect()
+    }
+
+    pub fn is_combination_pressed(&self, combination: Vec<Key>) -> bool {
+        combination.into_iter().all(|key| self.is_key_pressed(key))
+    }
+}
+
+/// The event that releases `key`, which is a mouse button release for a button code. `None` for
+/// a code with neither a key nor a button.
+pub fn release_event(key: Key) -> Option<InputEvent> {
+    let event_type = KeyboardEventType::KeyReleased;
+    if let Ok(button) = key.try_into() {
+        return Some(InputEvent::Mouse(MouseEvent::Button { event_type, button }));
+    }
+    let key = key.try_into().ok()?;
+    Some(InputEvent::Keyboard(KeyboardEvent { event_type, key }))
+}
+
+#[cfg(test)]
+mod test {
+    use evdev::Key;
+    use input_event::{Button, InputEvent, KeyboardEventType, MouseEvent};
+
+    use super::{release_event, DevicesKeyboardState, CYCLE_TARGET};
+
+    #[test]
+    fn given_key_held_on_two_devices_should_stay_pressed_until_both_release() {
+        // Given
+        let mut state = DevicesKeyboardState::default();
+        state.press_key(0, Key::KEY_A);
+        state.press_key(1, Key::KEY_A);
+
+        // When
+        let released_first = state.release_key(0, Key::KEY_A);
+
+        // Then
+        assert!(!released_first);
+        assert!(state.is_key_pressed(Key::KEY_A));
+        assert!(state.release_key(1, Key::KEY_A));
+        assert!(!state.is_key_pressed(Key::KEY_A));
+    }
+
+    #[test]
+    fn given_combination_spread_across_devices_should_be_pressed() {
+        // Given
+        let mut state = DevicesKeyboardState::default();
+
+        // When
+        for (device, key) in CYCLE_TARGET.into_iter().enumerate() {
+            state.press_key(device, key);
+        }
+
+        // Then
+        assert!(state.is_combination_pressed(CYCLE_TARGET.to_vec()));
+    }
+
+    #[test]
+    fn given_device_forgotten_should_release_only_keys_no_other_device_holds() {
+        // Given
+        let mut state = DevicesKeyboardState::default();
+        state.press_key(0, Key::KEY_A);
+        state.press_key(0, Key::KEY_B);
+        state.press_key(1, Key::KEY_B);
+
+        // When
+        let released = state.forget_device(0);
+
+        // Then
+        assert_eq!(released, vec![Key::KEY_A]);
+        assert!(state.is_key_pressed(Key::KEY_B));
+    }
+
+    #[test]
+    fn given_device_forgotten_with_button_held_should_release_the_button() {
+        // Given
+        let mut state = DevicesKeyboardState::default();
+        state.press_key(0, Key::BTN_LEFT);
+
+        // When
+        let released: Vec<_> = state
+            .forget_device(0)
+            .into_iter()
+            .filter_map(release_event)
+            .collect();
+
+        // Then
+        assert_eq!(
+            released,
+            vec![InputEvent::Mouse(MouseEvent::Button {
+                event_type: KeyboardEventType::KeyReleased,
+                button: Button::BTN_LEFT,
+            })]
+        );
+    }
+}
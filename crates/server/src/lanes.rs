@@ -0,0 +1,242 @@
+//! Prioritised channels into the state actor.
+//!
+//! Internal messages travel on one of two lanes. The control lane carries hotkeys, client
+//! control messages and disconnects, and is always drained first so that a burst of input
+//! cannot delay a target switch. The data lane carries input events. When it is full, stale
+//! input such as pointer motion is dropped instead of waiting, while key transitions wait for
+//! room so that none are lost.
+
+use std::{
+    fmt,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+};
+
+use network::Message;
+use tokio::sync::mpsc::{self, error::SendError, error::TrySendError};
+
+use crate::{actors::state::input_sender::is_stale, InternalMessage};
+
+pub const DEFAULT_CONTROL_LANE_LEN: usize = 64;
+pub const DEFAULT_DATA_LANE_LEN: usize = 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Lane {
+    Control,
+    Data,
+}
+
+impl Lane {
+    pub fn of(message: &InternalMessage) -> Self {
+        match message {
+            InternalMessage::ClientMessage {
+                message: Message::InputEvent { .. },
+                ..
+            } => Lane::Data,
+            _ => Lane::Control,
+        }
+    }
+}
+
+/// Occupancy of a single lane.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct QueueDepth {
+    pub len: usize,
+    pub capacity: usize,
+    /// The most messages seen queued at once.
+    pub peak: usize,
+}
+
+impl fmt::Display for QueueDepth {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{} (peak {})", self.len, self.capacity, self.peak)
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct LaneDepths {
+    pub control: QueueDepth,
+    pub data: QueueDepth,
+    /// Stale input events dropped because the data lane was full.
+    pub dropped: u64,
+}
+
+impl fmt::Display for LaneDepths {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "control: {}; data: {}; dropped: {}",
+            self.control, self.data, self.dropped
+        )
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct LaneSender {
+    control: mpsc::Sender<InternalMessage>,
+    data: mpsc::Sender<InternalMessage>,
+    dropped: Arc<AtomicU64>,
+}
+
+impl LaneSender {
+    /// Sends `message` on its lane, dropping it if it is stale input and the data lane is full.
+    pub async fn send(&self, message: InternalMessage) -> Result<(), SendError<InternalMessage>> {
+        match Lane::of(&message) {
+            Lane::Control => self.control.send(message).await,
+            Lane::Data => match self.data.try_send(message) {
+                Ok(()) => Ok(()),
+                Err(TrySendError::Full(message)) => match &message {
+                    InternalMessage::ClientMessage { message: inner, .. } if is_stale(inner) => {
+                        self.dropped.fetch_add(1, Ordering::Relaxed);
+                        Ok(())
+                    }
+                    _ => self.data.send(message).await,
+                },
+                Err(TrySendError::Closed(message)) => Err(SendError(message)),
+            },
+        }
+    }
+
+    /// The control lane on its own, for producers that never send input events.
+    pub fn control(&self) -> mpsc::Sender<InternalMessage> {
+        self.control.clone()
+    }
+}
+
+#[derive(Debug)]
+pub struct LaneReceiver {
+    control: mpsc::Receiver<InternalMessage>,
+    data: mpsc::Receiver<InternalMessage>,
+    dropped: Arc<AtomicU64>,
+    control_peak: usize,
+    data_peak: usize,
+}
+
+impl LaneReceiver {
+    /// Receives the next message, preferring the control lane. Returns `None` once both lanes
+    /// are closed and empty.
+    pub async fn recv(&mut self) -> Option<InternalMessage> {
+        self.control_peak = self.control_peak.max(self.control.len());
+        self.data_peak = self.data_peak.max(self.data.len());
+
+        tokio::select! {
+            biased;
+            Some(message) = self.control.recv() => Some(message),
+            Some(message) = self.data.recv() => Some(message),
+            else => None,
+        }
+    }
+
+    pub fn depths(&self) -> LaneDepths {
+        LaneDepths {
+            control: QueueDepth {
+                len: self.control.len(),
+                capacity: self.control.max_capacity(),
+                peak: self.control_peak.max(self.control.len()),
+            },
+            data: QueueDepth {
+                len: self.data.len(),
+                capacity: self.data.max_capacity(),
+                peak: self.data_peak.max(self.data.len()),
+            },
+            dropped: self.dropped.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Creates the lanes into the state actor.
+pub fn lanes(control_len: usize, data_len: usize) -> (LaneSender, LaneReceiver) {
+    let (control_tx, control_rx) = mpsc::channel(control_len);
+    let (data_tx, data_rx) = mpsc::channel(data_len);
+    let dropped = Arc::new(AtomicU64::new(0));
+    (
+        LaneSender {
+            control: control_tx,
+            data: data_tx,
+            dropped: dropped.clone(),
+        },
+        LaneReceiver {
+            control: control_rx,
+            data: data_rx,
+            dropped,
+            control_peak: 0,
+            data_peak: 0,
+        },
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use input_event::{InputEvent, Key, KeyboardEvent, KeyboardEventType, MouseEvent, PointerAxis};
+    use network::Message;
+
+    use crate::{InternalMessage, ServerMessage};
+
+    use super::lanes;
+
+    fn input(event: InputEvent) -> InternalMessage {
+        InternalMessage::ClientMessage {
+            message: Message::InputEvent { event },
+            sender: None,
+        }
+    }
+
+    fn motion() -> InternalMessage {
+        input(InputEvent::Mouse(MouseEvent::Motion {
+            axis: PointerAxis::Horizontal,
+            diff: 1,
+        }))
+    }
+
+    #[tokio::test]
+    async fn given_queued_input_should_receive_control_message_first() {
+        // Given
+        let (sender, mut receiver) = lanes(4, 4);
+        sender.send(motion()).await.unwrap();
+        sender
+            .send(InternalMessage::LocalMessage {
+                message: ServerMessage::Cycle,
+            })
+            .await
+            .unwrap();
+
+        // When
+        let first = receiver.recv().await;
+
+        // Then
+        assert!(matches!(
+            first,
+            Some(InternalMessage::LocalMessage {
+                message: ServerMessage::Cycle
+            })
+        ));
+        assert_eq!(receiver.depths().data.len, 1);
+    }
+
+    #[tokio::test]
+    async fn given_full_data_lane_should_drop_motion_but_wait_for_keys() {
+        // Given
+        let (sender, mut receiver) = lanes(4, 1);
+        sender.send(motion()).await.unwrap();
+
+        // When
+        sender.send(motion()).await.unwrap();
+        let key = sender.send(input(InputEvent::Keyboard(KeyboardEvent {
+            event_type: KeyboardEventType::KeyPressed,
+            key: Key::KEY_A,
+        })));
+        tokio::pin!(key);
+        let waiting = futures::poll!(&mut key).is_pending();
+        receiver.recv().await;
+        key.await.unwrap();
+
+        // Then
+        assert!(waiting);
+        let depths = receiver.depths();
+        assert_eq!(depths.dropped, 1);
+        assert_eq!(depths.data.len, 1);
+        assert_eq!(depths.data.peak, 1);
+    }
+}
@@ -2,13 +2,28 @@ use network::Message;
 use uuid::Uuid;
 
 pub mod actors;
+pub mod admin;
 pub mod keyboard_state;
+pub mod lanes;
 pub mod server_loop;
+pub mod session_ticket;
+pub mod supervisor;
 
 #[derive(Debug)]
 pub enum ServerMessage {
     Cycle,
-    ClientDisconnect { id: Uuid },
+    /// The connection numbered `generation` to the client was lost.
+    ClientDisconnect {
+        id: Uuid,
+        generation: u64,
+    },
+    Shutdown {
+        reason: String,
+    },
+    ListClients,
+    ForgetClient {
+        id: Uuid,
+    },
 }
 
 #[derive(Debug)]
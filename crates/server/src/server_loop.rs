@@ -1,72 +1,156 @@
-use std::net::SocketAddr;
+use std::{sync::Arc, time::Duration};
 
-use tokio::sync::{broadcast, mpsc};
+use chacha20poly1305::ChaCha20Poly1305;
+use network::ServerAddress;
+use tokio::sync::{mpsc, watch, Mutex};
 use tokio_util::sync::CancellationToken;
 
-use crate::actors::{
-    device::resource::DeviceResource, server::resource::ServerResource,
-    state::resource::StateResource,
+use crate::{
+    actors::{
+        device::resource::{DeviceConfig, DeviceResource},
+        server::resource::ServerResource,
+        state::{actor::StateEndpoints, resource::StateResource},
+    },
+    admin::admin_console,
+    lanes::{lanes, DEFAULT_CONTROL_LANE_LEN, DEFAULT_DATA_LANE_LEN},
+    session_ticket::SessionTickets,
+    supervisor::{supervise, RestartPolicy, SupervisorError},
+    InternalMessage, ServerMessage,
 };
 
-pub async fn run(server_addr: SocketAddr) {
-    let (event_tx1, event_rx) = mpsc::channel(32);
+// time for shutdown notices to reach clients before their connections are torn down
+const SHUTDOWN_GRACE_PERIOD: Duration = Duration::from_millis(500);
+
+pub async fn run(
+    server_addr: ServerAddress,
+    state: StateResource<ChaCha20Poly1305>,
+    devices: DeviceConfig,
+) {
+    if let Err(err) = devices.discover() {
+        eprintln!("{}", err);
+        return;
+    }
+
+    let (lane_tx, lane_rx) = lanes(DEFAULT_CONTROL_LANE_LEN, DEFAULT_DATA_LANE_LEN);
+    // clients, the admin console and shutdown only ever send control messages
+    let client_message_tx = lane_tx.control();
     let (client_tx, client_rx) = mpsc::channel(32);
-    let (client_message_tx, client_message_rx) = mpsc::channel(32);
-    let (grab_request_tx, grab_request_rx1) = broadcast::channel(32);
+    let (grab_request_tx, grab_request_rx) = watch::channel(false);
     let cancellation_token = CancellationToken::new();
 
+    // TODO: remove unwrap
+    let endpoints = StateEndpoints::bind(&server_addr, lane_rx, client_rx, grab_request_tx.clone())
+        .await
+        .unwrap();
+    // a tokio mutex is not poisoned by a panic, so clients and tickets survive a restart
+    let state = Arc::new(Mutex::new((state, endpoints)));
     let cancellation_token_clone = cancellation_token.clone();
-    let event_processor = tokio::spawn(async move {
-        let state = StateResource::default();
-        state
-            .process(
-                server_addr,
-                event_rx,
-                client_message_rx,
-                client_rx,
-                grab_request_tx,
-                cancellation_token_clone,
-            )
-            .await
-    });
+    let ungrab_tx = grab_request_tx.clone();
+    let event_processor = supervise(
+        "Event processor",
+        RestartPolicy::default(),
+        cancellation_token.clone(),
+        move || {
+            let state = state.clone();
+            let cancellation_token = cancellation_token_clone.clone();
+            async move {
+                let mut state = state.lock().await;
+                let (state, endpoints) = &mut *state;
+                state.process(endpoints, cancellation_token).await
+            }
+        },
+        // input has nowhere to go until the processor is back
+        move || {
+            ungrab_tx.send_replace(false);
+        },
+    );
 
-    let devices = DeviceResource::new();
     let cancellation_token_clone = cancellation_token.clone();
-    let device_listener = tokio::spawn(async move {
-        devices
-            .start_device_listener(event_tx1, grab_request_rx1, cancellation_token_clone)
-            .await
-    });
+    let device_listener = supervise(
+        "Device listener",
+        RestartPolicy::default(),
+        cancellation_token.clone(),
+        move || {
+            let devices = devices.clone();
+            let lane_tx = lane_tx.clone();
+            let mut grab_request_rx = grab_request_rx.clone();
+            // apply the current grab state as soon as the listener starts
+            grab_request_rx.mark_changed();
+            let cancellation_token = cancellation_token_clone.clone();
+            async move {
+                // closing the devices on failure releases any grab on them
+                DeviceResource::open(&devices)?
+                    .start_device_listener(lane_tx, grab_request_rx, cancellation_token)
+                    .await
+            }
+        },
+        || {},
+    );
 
-    let server = ServerResource::new(server_addr).await;
-    let client_tx_clone = client_tx.clone();
+    let tickets = SessionTickets::new();
+    let shutdown_tx = client_message_tx.clone();
+    let admin_tx = client_message_tx.clone();
     let cancellation_token_clone = cancellation_token.clone();
-    let server_actor =
-        server.start_listening(client_tx_clone, client_message_tx, cancellation_token_clone);
+    let server_actor = supervise(
+        "Server listener",
+        RestartPolicy::default(),
+        cancellation_token.clone(),
+        move || {
+            let server_addr = server_addr.clone();
+            let tickets = tickets.clone();
+            let client_tx = client_tx.clone();
+            let client_message_tx = client_message_tx.clone();
+            let cancellation_token = cancellation_token_clone.clone();
+            async move {
+                ServerResource::new(&server_addr)
+                    .await
+                    .with_tickets(tickets)
+                    .start_listening(client_tx, client_message_tx, cancellation_token)
+                    .await
+            }
+        },
+        || {},
+    );
+
+    tokio::spawn(admin_console(admin_tx, cancellation_token.clone()));
 
     println!("Initialised server");
-    tokio::select! {
+    let reason = tokio::select! {
         result = server_actor => {
-            match result {
-                Ok(()) => println!("Server closed gracefully"),
-                Err(err) => eprintln!("Server exited with error: {}", err),
-            }
+            log_exit("Server listener", result);
+            "Server listener stopped"
         },
         result = device_listener => {
-            match result {
-                Ok(Ok(())) => println!("Device listener closed gracefully"),
-                Ok(Err(err)) => eprintln!("Device listener exited with error: {}", err),
-                Err(err) => eprintln!("Device listener panicked: {}", err),
-            }
+            log_exit("Device listener", result);
+            "Device listener stopped"
         },
         result = event_processor => {
-            match result {
-                Ok(Ok(())) => println!("Event processor closed gracefully"),
-                Ok(Err(err)) => eprintln!("Event processor exited with error: {}", err),
-                Err(err) => eprintln!("Event processor panicked: {}", err),
-            }
+            log_exit("Event processor", result);
+            "Event processor stopped"
+        },
+        _ = tokio::signal::ctrl_c() => {
+            "Server stopped by user"
         },
+    };
+    println!("Shutting down server: {}", reason);
+
+    let message = ServerMessage::Shutdown {
+        reason: reason.to_string(),
+    };
+    // the event processor may be the actor that stopped, in which case no one can be told
+    if shutdown_tx
+        .send(InternalMessage::LocalMessage { message })
+        .await
+        .is_ok()
+    {
+        tokio::time::sleep(SHUTDOWN_GRACE_PERIOD).await;
     }
-    println!("Shutting down server");
     cancellation_token.cancel();
 }
+
+fn log_exit(name: &str, result: Result<(), SupervisorError>) {
+    match result {
+        Ok(()) => println!("{} closed gracefully", name),
+        Err(err) => eprintln!("{}", err),
+    }
+}
@@ -0,0 +1,209 @@
+//! Stateless session tickets.
+//!
+//! At the end of a handshake the server hands the client a ticket, sealed with a key that only
+//! the server knows, holding the client's id and a fresh resumption secret. A reconnecting
+//! client presents the ticket instead of repeating the key exchange and both sides derive the
+//! new session key from that secret. Tickets do not survive a server restart since the sealing
+//! key is generated at startup; clients then fall back to a full key exchange.
+//!
+//! Each ticket can be used once. The server remembers the tickets it has accepted until they
+//! expire, so a ticket captured from an earlier resumption cannot be replayed.
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use chacha20poly1305::{
+    aead::{rand_core::RngCore, OsRng},
+    ChaCha20Poly1305, KeyInit, Nonce,
+};
+use crypto::{kdf::KEY_LEN, Decryptor, Encryptor};
+use uuid::Uuid;
+
+pub const DEFAULT_TICKET_LIFETIME: Duration = Duration::from_secs(24 * 60 * 60);
+const NONCE_LEN: usize = 12;
+// client id, resumption secret and issue time
+const CONTENTS_LEN: usize = 16 + KEY_LEN + 8;
+
+/// A ticket to hand to the client along with the secret it must keep to use it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IssuedTicket {
+    pub ticket: Vec<u8>,
+    pub secret: [u8; KEY_LEN],
+}
+
+/// The session a valid ticket resumes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResumedSession {
+    pub client_id: Uuid,
+    pub secret: [u8; KEY_LEN],
+}
+
+#[derive(Clone)]
+pub struct SessionTickets {
+    key: ChaCha20Poly1305,
+    lifetime: Duration,
+    /// When each accepted ticket was issued, keyed by its nonce, kept until it would expire.
+    redeemed: Arc<Mutex<HashMap<[u8; NONCE_LEN], u64>>>,
+}
+
+impl Default for SessionTickets {
+    fn default() -> Self {
+        SessionTickets::new()
+    }
+}
+
+impl SessionTickets {
+    pub fn new() -> Self {
+        SessionTickets {
+            key: ChaCha20Poly1305::new(&ChaCha20Poly1305::generate_key(&mut OsRng)),
+            lifetime: DEFAULT_TICKET_LIFETIME,
+            redeemed: Arc::default(),
+        }
+    }
+
+    pub fn with_lifetime(mut self, lifetime: Duration) -> Self {
+        self.lifetime = lifetime;
+        self
+    }
+
+    pub fn issue(&self, client_id: Uuid) -> IssuedTicket {
+        self.issue_at(client_id, SystemTime::now())
+    }
+
+    /// Returns the session the ticket belongs to, or `None` if it was not issued by this server,
+    /// has expired or has already been used.
+    pub fn open(&self, ticket: &[u8]) -> Option<ResumedSession> {
+        self.open_at(ticket, SystemTime::now())
+    }
+
+    fn issue_at(&self, client_id: Uuid, now: SystemTime) -> IssuedTicket {
+        let mut secret = [0; KEY_LEN];
+        OsRng.fill_bytes(&mut secret);
+
+        let mut contents = Vec::with_capacity(CONTENTS_LEN);
+        contents.extend_from_slice(client_id.as_bytes());
+        contents.extend_from_slice(&secret);
+        contents.extend_from_slice(&unix_secs(now).to_le_bytes());
+
+        let (sealed, nonce) = self
+            .key
+            .encrypt(contents)
+            .expect("Sealing a session ticket should not fail");
+        let mut ticket = nonce.to_vec();
+        ticket.extend_from_slice(&sealed);
+
+        IssuedTicket { ticket, secret }
+    }
+
+    fn open_at(&self, ticket: &[u8], now: SystemTime) -> Option<ResumedSession> {
+        if ticket.len() < NONCE_LEN {
+            return None;
+        }
+        let (nonce, sealed) = ticket.split_at(NONCE_LEN);
+        let contents = self
+            .key
+            .decrypt(sealed.to_vec(), *Nonce::from_slice(nonce))
+            .ok()?;
+        if contents.len() != CONTENTS_LEN {
+            return None;
+        }
+
+        let client_id = Uuid::from_slice(&contents[..16]).ok()?;
+        let secret: [u8; KEY_LEN] = contents[16..16 + KEY_LEN].try_into().ok()?;
+        let issued_at = u64::from_le_bytes(contents[16 + KEY_LEN..].try_into().ok()?);
+        let lifetime = self.lifetime.as_secs();
+        let now = unix_secs(now);
+        if now.saturating_sub(issued_at) > lifetime {
+            return None;
+        }
+
+        let mut redeemed = self.redeemed.lock().unwrap();
+        redeemed.retain(|_, issued_at| now.saturating_sub(*issued_at) <= lifetime);
+        let nonce: [u8; NONCE_LEN] = nonce.try_into().ok()?;
+        if redeemed.insert(nonce, issued_at).is_some() {
+            return None;
+        }
+
+        Some(ResumedSession { client_id, secret })
+    }
+}
+
+fn unix_secs(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::{Duration, SystemTime};
+
+    use uuid::Uuid;
+
+    use super::SessionTickets;
+
+    #[test]
+    fn given_issued_ticket_should_resume_same_client() {
+        // Given
+        let tickets = SessionTickets::new();
+        let client_id = Uuid::new_v4();
+        let issued = tickets.issue(client_id);
+
+        // When
+        let resumed = tickets.open(&issued.ticket).unwrap();
+
+        // Then
+        assert_eq!(resumed.client_id, client_id);
+        assert_eq!(resumed.secret, issued.secret);
+    }
+
+    #[test]
+    fn given_tampered_or_foreign_ticket_should_reject() {
+        // Given
+        let tickets = SessionTickets::new();
+        let mut issued = tickets.issue(Uuid::new_v4());
+        let foreign = SessionTickets::new().issue(Uuid::new_v4());
+        let last = issued.ticket.len() - 1;
+        issued.ticket[last] ^= 1;
+
+        // When
+        let tampered = tickets.open(&issued.ticket);
+        let foreign = tickets.open(&foreign.ticket);
+
+        // Then
+        assert!(tampered.is_none());
+        assert!(foreign.is_none());
+    }
+
+    #[test]
+    fn given_used_ticket_should_reject_replay() {
+        // Given
+        let tickets = SessionTickets::new();
+        let issued = tickets.issue(Uuid::new_v4());
+        let restarted_listener = tickets.clone();
+        tickets.open(&issued.ticket).unwrap();
+
+        // When
+        let response = restarted_listener.open(&issued.ticket);
+
+        // Then
+        assert!(response.is_none());
+    }
+
+    #[test]
+    fn given_expired_ticket_should_reject() {
+        // Given
+        let tickets = SessionTickets::new().with_lifetime(Duration::from_secs(60));
+        let now = SystemTime::now();
+        let issued = tickets.issue_at(Uuid::new_v4(), now);
+
+        // When
+        let response = tickets.open_at(&issued.ticket, now + Duration::from_secs(61));
+
+        // Then
+        assert!(response.is_none());
+    }
+}
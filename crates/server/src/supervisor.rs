@@ -0,0 +1,206 @@
+//! Restarts actors that fail, so that one faulty actor does not take the whole server down.
+//!
+//! An actor that returns an error or panics is started again after a short delay. An actor that
+//! keeps failing is given up on once it has failed too often within a window, at which point the
+//! server shuts down as it did before supervision.
+
+use std::{
+    collections::VecDeque,
+    fmt::Display,
+    future::Future,
+    time::{Duration, Instant},
+};
+
+use thiserror::Error;
+use tokio_util::sync::CancellationToken;
+
+pub const DEFAULT_MAX_RESTARTS: usize = 5;
+pub const DEFAULT_RESTART_WINDOW: Duration = Duration::from_secs(60);
+pub const DEFAULT_RESTART_DELAY: Duration = Duration::from_millis(500);
+
+#[derive(Debug, Error)]
+pub enum SupervisorError {
+    #[error("{name} failed {restarts} times within {window:?}, giving up")]
+    TooManyRestarts {
+        name: &'static str,
+        restarts: usize,
+        window: Duration,
+    },
+}
+
+#[derive(Debug, Clone)]
+pub struct RestartPolicy {
+    max_restarts: usize,
+    window: Duration,
+    delay: Duration,
+    restarts: VecDeque<Instant>,
+}
+
+impl Default for RestartPolicy {
+    fn default() -> Self {
+        RestartPolicy::new(
+            DEFAULT_MAX_RESTARTS,
+            DEFAULT_RESTART_WINDOW,
+            DEFAULT_RESTART_DELAY,
+        )
+    }
+}
+
+impl RestartPolicy {
+    pub fn new(max_restarts: usize, window: Duration, delay: Duration) -> Self {
+        RestartPolicy {
+            max_restarts,
+            window,
+            delay,
+            restarts: VecDeque::new(),
+        }
+    }
+
+    /// Records a failure at `now` and returns how long to wait before restarting, or `None`
+    /// if the actor has used up its restarts for the current window.
+    pub fn on_failure(&mut self, now: Instant) -> Option<Duration> {
+        while self
+            .restarts
+            .front()
+            .is_some_and(|&at| now.duration_since(at) >= self.window)
+        {
+            self.restarts.pop_front();
+        }
+        if self.restarts.len() >= self.max_restarts {
+            return None;
+        }
+        self.restarts.push_back(now);
+        Some(self.delay)
+    }
+}
+
+/// Runs the actor built by `start` until it finishes cleanly or the server is cancelled,
+/// restarting it whenever it fails. `on_failure` runs as soon as the actor has stopped, before
+/// the restart delay, to put anything the actor held into a safe state.
+pub async fn supervise<S, Fut, E>(
+    name: &'static str,
+    mut policy: RestartPolicy,
+    cancellation_token: CancellationToken,
+    mut start: S,
+    mut on_failure: impl FnMut(),
+) -> Result<(), SupervisorError>
+where
+    S: FnMut() -> Fut,
+    Fut: Future<Output = Result<(), E>> + Send + 'static,
+    E: Display + Send + 'static,
+{
+    loop {
+        match tokio::spawn(start()).await {
+            Ok(Ok(())) => return Ok(()),
+            Ok(Err(err)) => eprintln!("{} exited with error: {}", name, err),
+            Err(err) => eprintln!("{} panicked: {}", name, err),
+        }
+        on_failure();
+        if cancellation_token.is_cancelled() {
+            return Ok(());
+        }
+
+        let Some(delay) = policy.on_failure(Instant::now()) else {
+            return Err(SupervisorError::TooManyRestarts {
+                name,
+                restarts: policy.max_restarts,
+                window: policy.window,
+            });
+        };
+        println!("Restarting {} in {:?}", name, delay);
+        tokio::select! {
+            _ = tokio::time::sleep(delay) => {},
+            _ = cancellation_token.cancelled() => return Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+        time::{Duration, Instant},
+    };
+
+    use tokio_util::sync::CancellationToken;
+
+    use super::{supervise, RestartPolicy, SupervisorError};
+
+    #[test]
+    fn given_failures_outside_window_should_keep_restarting() {
+        // Given
+        let window = Duration::from_secs(10);
+        let mut policy = RestartPolicy::new(1, window, Duration::ZERO);
+        let now = Instant::now();
+
+        // When
+        let first = policy.on_failure(now);
+        let second = policy.on_failure(now + window / 2);
+        let third = policy.on_failure(now + window);
+
+        // Then
+        assert_eq!(first, Some(Duration::ZERO));
+        assert_eq!(second, None);
+        assert_eq!(third, Some(Duration::ZERO));
+    }
+
+    #[tokio::test]
+    async fn given_panicking_actor_should_restart_until_it_succeeds() {
+        // Given
+        let starts = Arc::new(AtomicUsize::new(0));
+        let failures = Arc::new(AtomicUsize::new(0));
+        let policy = RestartPolicy::new(5, Duration::from_secs(60), Duration::ZERO);
+        let starts_clone = starts.clone();
+        let failures_clone = failures.clone();
+
+        // When
+        let result = supervise(
+            "Test actor",
+            policy,
+            CancellationToken::new(),
+            move || {
+                let starts = starts_clone.clone();
+                async move {
+                    if starts.fetch_add(1, Ordering::SeqCst) < 2 {
+                        panic!("actor failed");
+                    }
+                    Ok::<(), String>(())
+                }
+            },
+            move || {
+                failures_clone.fetch_add(1, Ordering::SeqCst);
+            },
+        )
+        .await;
+
+        // Then
+        assert!(result.is_ok());
+        assert_eq!(starts.load(Ordering::SeqCst), 3);
+        assert_eq!(failures.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn given_actor_that_keeps_failing_should_give_up() {
+        // Given
+        let policy = RestartPolicy::new(2, Duration::from_secs(60), Duration::ZERO);
+
+        // When
+        let result = supervise(
+            "Test actor",
+            policy,
+            CancellationToken::new(),
+            || async { Err::<(), _>("always fails") },
+            || {},
+        )
+        .await;
+
+        // Then
+        assert!(matches!(
+            result,
+            Err(SupervisorError::TooManyRestarts { restarts: 2, .. })
+        ));
+    }
+}
@@ -1,9 +1,18 @@
-use std::{io::Write, net::SocketAddr};
+use std::{io::Write, net::SocketAddr, time::Duration};
 
-use client::client_loop::{self, ClientError};
-use server::server_loop;
+use client::{
+    client_loop::{self, ClientError},
+    connection::Connection,
+    session::default_session_path,
+};
+use server::{
+    actors::{device::resource::DeviceConfig, state::resource::StateResource},
+    server_loop,
+};
 use thiserror::Error;
 
+const LINK_QUALITY_DISPLAY_INTERVAL: Duration = Duration::from_secs(10);
+
 #[derive(Debug, Error)]
 pub enum UIError {
     #[error("Error in client loop: {0}")]
@@ -30,7 +39,12 @@ pub async fn ui() -> Result<(), UIError> {
                 .as_str()
                 .parse()
                 .expect("Should provide a valid socket address");
-            server_loop::run(server_addr).await;
+            server_loop::run(
+                server_addr.into(),
+                StateResource::default(),
+                DeviceConfig::default(),
+            )
+            .await;
         }
         "client" => {
             print!("Server Address (ip:port): ");
@@ -38,12 +52,23 @@ pub async fn ui() -> Result<(), UIError> {
                 .as_str()
                 .parse()
                 .expect("Should provide a valid socket address");
-            print!("Client Address (ip:port): ");
-            let client_addr: SocketAddr = get_input()
-                .as_str()
-                .parse()
-                .expect("Should provide a valid socket address");
-            client_loop::run(server_addr, client_addr).await?;
+            let mut connection = Connection::default();
+            if let Some(path) = default_session_path() {
+                connection = connection.with_session_file(path);
+            }
+            let link_quality = connection.link_quality_handle();
+            tokio::spawn(async move {
+                let mut interval = tokio::time::interval(LINK_QUALITY_DISPLAY_INTERVAL);
+                loop {
+                    interval.tick().await;
+                    let quality = link_quality.quality();
+                    // nothing has been measured until a session is up
+                    if quality.control.pings_sent > 0 {
+                        println!("Link quality: {}", quality);
+                    }
+                }
+            });
+            client_loop::run(server_addr.into(), connection).await?;
         }
         _ => {
             println!("Response was '{}'", chosen);
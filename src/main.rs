@@ -1,4 +1,14 @@
-use std::{error::Error, net::SocketAddr};
+use std::{error::Error, path::PathBuf, time::Duration};
+
+use client::{
+    connection::{Connection, TransportKind},
+    session::default_session_path,
+};
+use network::ServerAddress;
+use server::actors::{
+    device::resource::{DeviceConfig, DeviceSelector},
+    state::resource::StateResource,
+};
 
 const WELCOME_STRING: &str = r#"
 ====================================================================
@@ -15,11 +25,35 @@ async fn main() -> Result<(), Box<dyn Error>> {
     let args: Vec<String> = std::env::args().collect();
 
     if args.contains(&"--server".to_string()) {
+        let mut state = StateResource::default();
+        if let Some(grace_period) = parse_seconds(&args, "--client-grace-period")? {
+            state = state.with_disconnected_grace_period(grace_period);
+        }
+        let mut devices = DeviceConfig::default();
+        if let Some(keyboard) = parse_device(&args, "--keyboard")? {
+            devices = devices.with_keyboard(keyboard);
+        }
+        if let Some(mouse) = parse_device(&args, "--mouse")? {
+            devices = devices.with_mouse(mouse);
+        }
+        if let Some(tablet) = parse_device(&args, "--tablet")? {
+            devices = devices.with_tablet(tablet);
+        }
+        if let Some(gamepad) = parse_device(&args, "--gamepad")? {
+            devices = devices.with_gamepad(gamepad);
+        }
         let server_addr = parse_server_args(args)?;
-        server::server_loop::run(server_addr).await;
+        server::server_loop::run(server_addr, state, devices).await;
     } else if args.contains(&"--client".to_string()) {
-        let (server_addr, client_addr) = parse_client_args(args)?;
-        client::client_loop::run(server_addr, client_addr).await?;
+        let mut connection = Connection::new(parse_transport_kind(&args));
+        if let Some(path) = default_session_path() {
+            connection = connection.with_session_file(path);
+        }
+        if let Some(peer_timeout) = parse_seconds(&args, "--peer-timeout")? {
+            connection = connection.with_peer_timeout(peer_timeout);
+        }
+        let server_addr = parse_client_args(args)?;
+        client::client_loop::run(server_addr, connection).await?;
     } else {
         ui::ui().await?;
     }
@@ -27,18 +61,71 @@ async fn main() -> Result<(), Box<dyn Error>> {
     Ok(())
 }
 
-pub fn parse_client_args(args: Vec<String>) -> Result<(SocketAddr, SocketAddr), Box<dyn Error>> {
-    if args.len() < 4 {
-        panic!("Not enough arguments. Please provide a server address and client address followed by a flag");
+pub fn parse_client_args(args: Vec<String>) -> Result<ServerAddress, Box<dyn Error>> {
+    if args.len() < 3 {
+        panic!("Not enough arguments. Please provide a server address followed by a flag");
+    }
+    if args.contains(&"--unix".to_string()) && args.contains(&"--websocket".to_string()) {
+        return Err("--unix and --websocket cannot be used together".into());
     }
 
-    Ok((args[1].parse()?, args[2].parse()?))
+    parse_server_address(&args)
+}
+
+pub fn parse_transport_kind(args: &[String]) -> TransportKind {
+    #[cfg(feature = "websocket")]
+    if args.contains(&"--websocket".to_string()) {
+        return TransportKind::WebSocket;
+    }
+    #[cfg(not(feature = "websocket"))]
+    if args.contains(&"--websocket".to_string()) {
+        panic!("WebSocket transport requires building with the 'websocket' feature");
+    }
+
+    TransportKind::Tcp
+}
+
+/// Reads a `<flag> <seconds>` pair, such as `--peer-timeout 10`.
+pub fn parse_seconds(args: &[String], flag: &str) -> Result<Option<Duration>, Box<dyn Error>> {
+    let Some(position) = args.iter().position(|arg| arg == flag) else {
+        return Ok(None);
+    };
+    let seconds: u64 = args
+        .get(position + 1)
+        .ok_or_else(|| format!("{} requires a number of seconds", flag))?
+        .parse()?;
+
+    Ok(Some(Duration::from_secs(seconds)))
+}
+
+/// Reads a `<flag> <selector>` pair, such as `--mouse 046d:c52b` or
+/// `--keyboard /dev/input/by-id/usb-Logitech-event-kbd`.
+pub fn parse_device(args: &[String], flag: &str) -> Result<Option<DeviceSelector>, Box<dyn Error>> {
+    let Some(position) = args.iter().position(|arg| arg == flag) else {
+        return Ok(None);
+    };
+    let selector = args
+        .get(position + 1)
+        .ok_or_else(|| format!("{} requires a device name, path or vendor:product ID", flag))?
+        .parse()?;
+
+    Ok(Some(selector))
 }
 
-pub fn parse_server_args(args: Vec<String>) -> Result<SocketAddr, Box<dyn Error>> {
+pub fn parse_server_args(args: Vec<String>) -> Result<ServerAddress, Box<dyn Error>> {
     if args.len() < 3 {
-        panic!("Not enough arguments. Please provide a server address and client address followed by a flag");
+        panic!("Not enough arguments. Please provide a server address followed by a flag");
+    }
+
+    parse_server_address(&args)
+}
+
+/// Reads the address in `args[1]`, which is a socket path when `--unix` is given, such as
+/// `/run/kvm.sock --server --unix`.
+fn parse_server_address(args: &[String]) -> Result<ServerAddress, Box<dyn Error>> {
+    if args.contains(&"--unix".to_string()) {
+        return Ok(ServerAddress::Unix(PathBuf::from(&args[1])));
     }
 
-    Ok(args[1].parse()?)
+    Ok(ServerAddress::Inet(args[1].parse()?))
 }
@@ -1,26 +1,32 @@
 use std::{error::Error, net::SocketAddr, time::Duration};
 
 use client::connection::Connection;
+use network::ServerAddress;
 use server::actors::server::resource::ServerResource;
 use tokio::sync::mpsc;
 use tokio_util::sync::CancellationToken;
 
-async fn connect_client(
-    client_addr: SocketAddr,
+#[cfg(feature = "websocket")]
+async fn connect_websocket_client(
     server_addr: SocketAddr,
 ) -> Result<bool, Box<dyn Error + Send + Sync>> {
+    let mut conn = Connection::new(client::connection::TransportKind::WebSocket);
+    conn.connect_websocket(server_addr).await?;
+    Ok(conn.is_connected)
+}
+
+async fn connect_client(server_addr: SocketAddr) -> Result<bool, Box<dyn Error + Send + Sync>> {
     let mut conn = Connection::default();
-    conn.connect(client_addr, server_addr).await?;
+    conn.connect(server_addr).await?;
     Ok(conn.is_connected)
 }
 
 #[tokio::test]
 async fn given_matching_ip_addresses_should_successfully_form_a_connection() {
     // Given
-    let client_addr: SocketAddr = "127.0.0.1:15342".parse().unwrap();
     let server_addr: SocketAddr = "127.0.0.1:15343".parse().unwrap();
 
-    let server = ServerResource::new(server_addr).await;
+    let server = ServerResource::new(&server_addr.into()).await;
 
     let (client_sender, _rx1) = mpsc::channel(10);
     let (client_message_sender, _rx2) = mpsc::channel(10);
@@ -34,9 +40,98 @@ async fn given_matching_ip_addresses_should_successfully_form_a_connection() {
             .await
     });
     tokio::time::sleep(Duration::from_millis(100)).await;
-    let response = connect_client(client_addr, server_addr).await;
+    let response = connect_client(server_addr).await;
 
     // Then
     assert!(response.is_ok());
     assert!(response.unwrap()); // is connected
 }
+
+#[cfg(feature = "websocket")]
+#[tokio::test]
+async fn given_websocket_client_should_connect_on_the_same_address() {
+    // Given
+    let server_addr: SocketAddr = "127.0.0.1:15344".parse().unwrap();
+
+    let server = ServerResource::new(&server_addr.into()).await;
+
+    let (client_sender, mut client_receiver) = mpsc::channel(10);
+    let (client_message_sender, _rx2) = mpsc::channel(10);
+
+    let cancellation_token = CancellationToken::new();
+
+    // When
+    tokio::spawn(async move {
+        server
+            .start_listening(client_sender, client_message_sender, cancellation_token)
+            .await
+    });
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    let response = connect_websocket_client(server_addr).await;
+
+    // Then
+    assert!(response.is_ok());
+    assert!(response.unwrap()); // is connected
+    assert!(client_receiver.recv().await.unwrap().tunnelled);
+}
+
+#[tokio::test]
+async fn given_reconnecting_client_should_resume_with_the_same_id() {
+    // Given
+    let server_addr: SocketAddr = "127.0.0.1:15345".parse().unwrap();
+
+    let server = ServerResource::new(&server_addr.into()).await;
+
+    let (client_sender, mut client_receiver) = mpsc::channel(10);
+    let (client_message_sender, _rx2) = mpsc::channel(10);
+
+    let cancellation_token = CancellationToken::new();
+
+    tokio::spawn(async move {
+        server
+            .start_listening(client_sender, client_message_sender, cancellation_token)
+            .await
+    });
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    let mut conn = Connection::default();
+    conn.connect(server_addr).await.unwrap();
+    let first = client_receiver.recv().await.unwrap();
+
+    // When
+    let response = conn.connect(server_addr).await;
+    let second = client_receiver.recv().await.unwrap();
+
+    // Then
+    assert!(response.is_ok());
+    assert_eq!(second.id, first.id);
+    assert_eq!(conn.client_id(), Some(first.id));
+}
+
+#[tokio::test]
+async fn given_unix_socket_path_should_successfully_form_a_connection() {
+    // Given
+    let server_path =
+        std::env::temp_dir().join(format!("test-connection-{}.sock", std::process::id()));
+
+    let server = ServerResource::new(&ServerAddress::Unix(server_path.clone())).await;
+
+    let (client_sender, mut client_receiver) = mpsc::channel(10);
+    let (client_message_sender, _rx2) = mpsc::channel(10);
+
+    let cancellation_token = CancellationToken::new();
+
+    // When
+    tokio::spawn(async move {
+        server
+            .start_listening(client_sender, client_message_sender, cancellation_token)
+            .await
+    });
+    let mut conn = Connection::default();
+    let response = conn.connect_unix(&server_path).await;
+
+    // Then
+    assert!(response.is_ok());
+    assert!(conn.is_connected);
+    assert!(!client_receiver.recv().await.unwrap().tunnelled);
+    std::fs::remove_file(server_path).unwrap();
+}